@@ -2,15 +2,27 @@
 // Handles caching of formula data and downloads
 
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
 use super::error::{Result, SpsError};
 use crate::Config;
 
 /// Define how long cache entries are considered valid
 const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60); // 24 hours
 
+/// Directory in the cache where `sps fetch` records the artifacts it fetched.
+pub const FETCH_MANIFEST_DIR: &str = "fetch-manifest";
+
+/// Starts every parsed index file. Bump it whenever a type stored in one changes shape,
+/// so indexes written by an older sps are re-parsed instead of misread.
+const INDEX_MAGIC: &[u8] = b"sps-index-v2\n";
+
 /// Cache struct to manage cache operations
 pub struct Cache {
     cache_dir: PathBuf,
@@ -20,10 +32,7 @@ pub struct Cache {
 impl Cache {
     /// Create a new Cache using the config's cache_dir
     pub fn new(config: &Config) -> Result<Self> {
-        let cache_dir = config.cache_dir();
-        if !cache_dir.exists() {
-            fs::create_dir_all(&cache_dir)?;
-        }
+        let cache_dir = config.ensure_cache_dir()?;
 
         Ok(Self {
             cache_dir,
@@ -58,6 +67,62 @@ impl Cache {
         fs::read_to_string(&path).map_err(|e| SpsError::Cache(format!("IO error: {e}")))
     }
 
+    /// Loads a cached JSON file parsed as `T`. See [`Cache::load_parsed_with`].
+    pub fn load_parsed<T>(&self, filename: &str) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        self.load_parsed_with(filename, |raw| {
+            serde_json::from_str(raw)
+                .map_err(|e| SpsError::Cache(format!("Failed to parse cached {filename}: {e}")))
+        })
+    }
+
+    /// Loads a cached file parsed by `parse`, reusing the result of an earlier parse when
+    /// the file has not changed since.
+    ///
+    /// Parsing the full formula and cask indexes takes far longer than reading them, so
+    /// the parsed value is kept in `<filename>.index` as CBOR together with the SHA-256
+    /// of the raw file it came from. When the hash no longer matches, e.g. after
+    /// `sps update`, or the index cannot be decoded, the file is parsed again and the
+    /// index rewritten. `T` has to round-trip through its own `Serialize` and
+    /// `Deserialize`.
+    pub fn load_parsed_with<T, F>(&self, filename: &str, parse: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&str) -> Result<T>,
+    {
+        let raw = self.load_raw(filename)?;
+        let digest = Sha256::digest(raw.as_bytes());
+        let index_path = self.index_path(filename);
+
+        if let Ok(index) = fs::read(&index_path) {
+            let payload = index
+                .strip_prefix(INDEX_MAGIC)
+                .and_then(|rest| rest.strip_prefix(digest.as_slice()));
+            match payload.map(ciborium::from_reader::<T, _>) {
+                Some(Ok(value)) => {
+                    tracing::debug!("Loaded parsed {} from {:?}", filename, index_path);
+                    return Ok(value);
+                }
+                Some(Err(e)) => {
+                    tracing::debug!("Discarding unreadable index {:?}: {}", index_path, e)
+                }
+                None => tracing::debug!("Index {:?} is stale, re-parsing {}", index_path, filename),
+            }
+        }
+
+        let value = parse(&raw)?;
+        if let Err(e) = write_index(&index_path, &digest, &value) {
+            tracing::warn!("Failed to write parsed index {:?}: {}", index_path, e);
+        }
+        Ok(value)
+    }
+
+    fn index_path(&self, filename: &str) -> PathBuf {
+        self.cache_dir.join(format!("{filename}.index"))
+    }
+
     /// Checks if a cache file exists and is valid (within TTL)
     pub fn is_cache_valid(&self, filename: &str) -> Result<bool> {
         let path = self.cache_dir.join(filename);
@@ -80,6 +145,10 @@ impl Cache {
         if path.exists() {
             fs::remove_file(&path)?;
         }
+        let index_path = self.index_path(filename);
+        if index_path.exists() {
+            fs::remove_file(&index_path)?;
+        }
         Ok(())
     }
 
@@ -97,3 +166,22 @@ impl Cache {
         &self._config
     }
 }
+
+/// Writes through a temporary file so a concurrent reader never sees a partial index.
+fn write_index<T: Serialize>(path: &Path, digest: &[u8], value: &T) -> Result<()> {
+    let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    let result = (|| {
+        let mut writer = BufWriter::new(fs::File::create(&tmp_path)?);
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_all(digest)?;
+        ciborium::into_writer(value, &mut writer)
+            .map_err(|e| SpsError::Cache(format!("Failed to encode index: {e}")))?;
+        writer.flush()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
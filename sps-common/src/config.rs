@@ -1,66 +1,848 @@
 // sps-common/src/config.rs
-use std::env;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fs};
 
 use directories::UserDirs; // Ensure this crate is in sps-common/Cargo.toml
-use tracing::debug;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use super::error::Result; // Assuming SpsResult is Result from super::error
+use super::error::{Result, SpsError}; // Assuming SpsResult is Result from super::error
 
-// This constant will serve as a fallback if HOMEBREW_PREFIX is not set or is empty.
+// This constant will serve as a fallback if no prefix is set, see `resolve_sps_root`.
 const DEFAULT_FALLBACK_SPS_ROOT: &str = "/opt/homebrew";
 const SPS_ROOT_MARKER_FILENAME: &str = ".sps_root_v1";
 
+const MAX_DOWNLOADS_ENV_VAR: &str = "SPS_MAX_DOWNLOADS";
+const JOBS_ENV_VAR: &str = "SPS_JOBS";
+const MAX_WORKERS_ENV_VAR: &str = "SPS_MAX_WORKERS";
+const BUILD_JOBS_ENV_VAR: &str = "SPS_BUILD_JOBS";
+const HOMEBREW_MAKE_JOBS_ENV_VAR: &str = "HOMEBREW_MAKE_JOBS";
+const WORKER_QUEUE_SIZE_ENV_VAR: &str = "SPS_WORKER_QUEUE_SIZE";
+const PROFILE_ENV_VAR: &str = "SPS_PROFILE";
+const CASK_USER_SCOPE_ENV_VAR: &str = "SPS_CASK_USER_SCOPE";
+const VERIFY_SIGNATURE_ENV_VAR: &str = "SPS_VERIFY_SIGNATURE";
+const NO_QUARANTINE_ENV_VAR: &str = "SPS_NO_QUARANTINE";
+const FORCE_QUARANTINE_ENV_VAR: &str = "SPS_FORCE_QUARANTINE";
+const BUILD_GENERATOR_ENV_VAR: &str = "SPS_BUILD_GENERATOR";
+const BUILD_TIMEOUT_ENV_VAR: &str = "SPS_BUILD_TIMEOUT";
+const OFFLINE_ENV_VAR: &str = "SPS_OFFLINE";
+const SKIP_RELOCATION_ENV_VAR: &str = "SPS_SKIP_RELOCATION";
+const LOCK_TIMEOUT_ENV_VAR: &str = "SPS_LOCK_TIMEOUT";
+const API_BASE_URL_ENV_VAR: &str = "SPS_API_BASE_URL";
+const BOTTLE_DOMAIN_ENV_VAR: &str = "SPS_BOTTLE_DOMAIN";
+const OCI_REGISTRY_ENV_VAR: &str = "SPS_OCI_REGISTRY";
+const PREFIX_ENV_VAR: &str = "SPS_PREFIX";
+const CACHE_DIR_ENV_VAR: &str = "SPS_CACHE_DIR";
+const BOTTLE_TAG_ENV_VAR: &str = "SPS_BOTTLE_TAG";
+const FORCE_ARCH_ENV_VAR: &str = "SPS_FORCE_ARCH";
+const QUIET_ENV_VAR: &str = "SPS_QUIET";
+
+const CONFIG_FILENAME: &str = "config.json";
+const PROFILES_DIRNAME: &str = "profiles";
+
+const DEFAULT_MAX_DOWNLOADS: usize = 4;
+const DEFAULT_WORKER_QUEUE_SIZE: usize = 100;
+const DEFAULT_BUILD_TIMEOUT_SECS: u64 = 3600;
+pub const DEFAULT_API_BASE_URL: &str = "https://formulae.brew.sh/api";
+
+/// `path` made absolute against the current directory; `None` if it is empty.
+fn absolute_path(path: PathBuf) -> Option<PathBuf> {
+    if path.as_os_str().is_empty() {
+        return None;
+    }
+    if path.is_absolute() {
+        return Some(path);
+    }
+    Some(
+        env::current_dir()
+            .map(|cwd| cwd.join(&path))
+            .unwrap_or(path),
+    )
+}
+
+/// The cache directory `SPS_CACHE_DIR` selects instead of the one in the prefix.
+fn cache_dir_from_env() -> Option<PathBuf> {
+    let cache_dir = absolute_path(PathBuf::from(env::var_os(CACHE_DIR_ENV_VAR)?))?;
+    debug!(
+        "Using cache directory {} from {}",
+        cache_dir.display(),
+        CACHE_DIR_ENV_VAR
+    );
+    Some(cache_dir)
+}
+
+/// The prefix to use: `prefix` (from `--prefix`), then `SPS_PREFIX`, then
+/// `HOMEBREW_PREFIX`, then the default.
+fn resolve_sps_root(prefix: Option<PathBuf>) -> PathBuf {
+    if let Some(prefix) = prefix.and_then(absolute_path) {
+        debug!("Using prefix {} from --prefix", prefix.display());
+        return prefix;
+    }
+    for var in [PREFIX_ENV_VAR, "HOMEBREW_PREFIX"] {
+        if let Some(prefix) = env::var_os(var).filter(|v| !v.is_empty()) {
+            debug!("Using prefix from {}", var);
+            return PathBuf::from(prefix);
+        }
+    }
+    debug!(
+        "Neither {} nor HOMEBREW_PREFIX is set, falling back to default: {}",
+        PREFIX_ENV_VAR, DEFAULT_FALLBACK_SPS_ROOT
+    );
+    PathBuf::from(DEFAULT_FALLBACK_SPS_ROOT)
+}
+
+/// Concurrency limits shared by the install pipeline and source builds.
+///
+/// Precedence, highest first: CLI flags (applied by the caller via
+/// [`ConcurrencyLimits::with_overrides`]), environment variables, then defaults derived
+/// from the host CPU count. Every limit is at least 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    /// Downloads that may run at once (`SPS_MAX_DOWNLOADS`, default 4).
+    pub max_downloads: usize,
+    /// Install/build jobs the worker pool runs at once (`SPS_JOBS`, then
+    /// `SPS_MAX_WORKERS`, default logical cores).
+    pub max_workers: usize,
+    /// `make -j` value for source builds (`SPS_BUILD_JOBS`, then `HOMEBREW_MAKE_JOBS`,
+    /// default logical cores).
+    pub build_jobs: usize,
+    /// Jobs that may wait for a free worker before the scheduler blocks
+    /// (`SPS_WORKER_QUEUE_SIZE`, default 100).
+    pub worker_queue_size: usize,
+}
+
+impl ConcurrencyLimits {
+    /// Defaults for the current host, ignoring the environment.
+    pub fn platform_defaults() -> Self {
+        Self {
+            max_downloads: DEFAULT_MAX_DOWNLOADS,
+            max_workers: num_cpus::get().max(1),
+            build_jobs: num_cpus::get().max(1),
+            worker_queue_size: DEFAULT_WORKER_QUEUE_SIZE,
+        }
+    }
+
+    /// Platform defaults with any environment overrides applied.
+    pub fn from_env() -> Self {
+        Self::platform_defaults().with_env()
+    }
+
+    /// Applies environment overrides on top of these limits.
+    pub fn with_env(self) -> Self {
+        Self {
+            max_downloads: env_limit(MAX_DOWNLOADS_ENV_VAR).unwrap_or(self.max_downloads),
+            max_workers: env_limit(JOBS_ENV_VAR)
+                .or_else(|| env_limit(MAX_WORKERS_ENV_VAR))
+                .unwrap_or(self.max_workers),
+            build_jobs: env_limit(BUILD_JOBS_ENV_VAR)
+                .or_else(|| env_limit(HOMEBREW_MAKE_JOBS_ENV_VAR))
+                .unwrap_or(self.build_jobs),
+            worker_queue_size: env_limit(WORKER_QUEUE_SIZE_ENV_VAR)
+                .unwrap_or(self.worker_queue_size),
+        }
+    }
+
+    /// Applies command-line values on top of these limits. `None` keeps the current value.
+    pub fn with_overrides(
+        self,
+        max_downloads: Option<usize>,
+        max_workers: Option<usize>,
+        build_jobs: Option<usize>,
+    ) -> Self {
+        Self {
+            max_downloads: max_downloads.unwrap_or(self.max_downloads).max(1),
+            max_workers: max_workers.unwrap_or(self.max_workers).max(1),
+            build_jobs: build_jobs.unwrap_or(self.build_jobs).max(1),
+            worker_queue_size: self.worker_queue_size,
+        }
+    }
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Reads a positive integer limit from `var`, warning about and ignoring bad values.
+fn env_limit(var: &str) -> Option<usize> {
+    let raw = env::var(var).ok().filter(|s| !s.trim().is_empty())?;
+    match raw.trim().parse::<usize>() {
+        Ok(value) if value > 0 => Some(value),
+        _ => {
+            warn!("Ignoring invalid {}='{}', using default.", var, raw);
+            None
+        }
+    }
+}
+
+/// Settings stored in a config file: `state/config.json` for the base config, or
+/// `state/profiles/<name>.json` for a profile. Every field is optional; unset
+/// fields fall through to the next layer.
+///
+/// Precedence, highest first: CLI flags, environment variables, the active
+/// profile, the base config file, built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottle_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oci_registry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker_registry_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker_registry_basic_auth: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_api_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_downloads: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_workers: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_jobs: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cask_user_scope: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go_sumdb: Option<String>,
+}
+
+impl ConfigFile {
+    /// Keys accepted by [`ConfigFile::get`], [`ConfigFile::set`] and [`ConfigFile::unset`].
+    pub const KEYS: &'static [&'static str] = &[
+        "api_base_url",
+        "artifact_domain",
+        "bottle_domain",
+        "oci_registry",
+        "docker_registry_token",
+        "docker_registry_basic_auth",
+        "github_api_token",
+        "max_downloads",
+        "max_workers",
+        "build_jobs",
+        "cask_user_scope",
+        "go_proxy",
+        "go_sumdb",
+    ];
+
+    /// Reads a config file. A missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(SpsError::Config(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        serde_json::from_str(&raw)
+            .map_err(|e| SpsError::Config(format!("Invalid config file {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Layers `other` on top of `self`: fields set in `other` win.
+    pub fn merged_with(self, other: Self) -> Self {
+        Self {
+            api_base_url: other.api_base_url.or(self.api_base_url),
+            artifact_domain: other.artifact_domain.or(self.artifact_domain),
+            bottle_domain: other.bottle_domain.or(self.bottle_domain),
+            oci_registry: other.oci_registry.or(self.oci_registry),
+            docker_registry_token: other.docker_registry_token.or(self.docker_registry_token),
+            docker_registry_basic_auth: other
+                .docker_registry_basic_auth
+                .or(self.docker_registry_basic_auth),
+            github_api_token: other.github_api_token.or(self.github_api_token),
+            max_downloads: other.max_downloads.or(self.max_downloads),
+            max_workers: other.max_workers.or(self.max_workers),
+            build_jobs: other.build_jobs.or(self.build_jobs),
+            cask_user_scope: other.cask_user_scope.or(self.cask_user_scope),
+            go_proxy: other.go_proxy.or(self.go_proxy),
+            go_sumdb: other.go_sumdb.or(self.go_sumdb),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match key {
+            "api_base_url" => self.api_base_url.clone(),
+            "artifact_domain" => self.artifact_domain.clone(),
+            "bottle_domain" => self.bottle_domain.clone(),
+            "oci_registry" => self.oci_registry.clone(),
+            "docker_registry_token" => self.docker_registry_token.clone(),
+            "docker_registry_basic_auth" => self.docker_registry_basic_auth.clone(),
+            "github_api_token" => self.github_api_token.clone(),
+            "max_downloads" => self.max_downloads.map(|v| v.to_string()),
+            "max_workers" => self.max_workers.map(|v| v.to_string()),
+            "build_jobs" => self.build_jobs.map(|v| v.to_string()),
+            "cask_user_scope" => self.cask_user_scope.map(|v| v.to_string()),
+            "go_proxy" => self.go_proxy.clone(),
+            "go_sumdb" => self.go_sumdb.clone(),
+            _ => return Err(unknown_key(key)),
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let limit = || -> Result<Option<usize>> {
+            match value.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Some(n)),
+                _ => Err(SpsError::Config(format!(
+                    "{key} must be a positive number, got '{value}'"
+                ))),
+            }
+        };
+        match key {
+            "api_base_url" => self.api_base_url = Some(value.to_string()),
+            "artifact_domain" => self.artifact_domain = Some(value.to_string()),
+            "bottle_domain" => self.bottle_domain = Some(value.to_string()),
+            "oci_registry" => self.oci_registry = Some(value.to_string()),
+            "docker_registry_token" => self.docker_registry_token = Some(value.to_string()),
+            "docker_registry_basic_auth" => {
+                self.docker_registry_basic_auth = Some(value.to_string())
+            }
+            "github_api_token" => self.github_api_token = Some(value.to_string()),
+            "max_downloads" => self.max_downloads = limit()?,
+            "max_workers" => self.max_workers = limit()?,
+            "build_jobs" => self.build_jobs = limit()?,
+            "cask_user_scope" => {
+                self.cask_user_scope = Some(parse_bool(value).ok_or_else(|| {
+                    SpsError::Config(format!("{key} must be true or false, got '{value}'"))
+                })?)
+            }
+            "go_proxy" => self.go_proxy = Some(value.to_string()),
+            "go_sumdb" => self.go_sumdb = Some(value.to_string()),
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    pub fn unset(&mut self, key: &str) -> Result<()> {
+        match key {
+            "api_base_url" => self.api_base_url = None,
+            "artifact_domain" => self.artifact_domain = None,
+            "bottle_domain" => self.bottle_domain = None,
+            "oci_registry" => self.oci_registry = None,
+            "docker_registry_token" => self.docker_registry_token = None,
+            "docker_registry_basic_auth" => self.docker_registry_basic_auth = None,
+            "github_api_token" => self.github_api_token = None,
+            "max_downloads" => self.max_downloads = None,
+            "max_workers" => self.max_workers = None,
+            "build_jobs" => self.build_jobs = None,
+            "cask_user_scope" => self.cask_user_scope = None,
+            "go_proxy" => self.go_proxy = None,
+            "go_sumdb" => self.go_sumdb = None,
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+}
+
+/// `SPS_LOCK_TIMEOUT` in seconds; an invalid value means not to wait.
+fn lock_timeout_from_env() -> Duration {
+    let Ok(value) = env::var(LOCK_TIMEOUT_ENV_VAR) else {
+        return Duration::ZERO;
+    };
+    value.trim().parse::<u64>().map_or_else(
+        |_| {
+            warn!("Ignoring invalid {}='{}'", LOCK_TIMEOUT_ENV_VAR, value);
+            Duration::ZERO
+        },
+        Duration::from_secs,
+    )
+}
+
+/// `SPS_BUILD_TIMEOUT` in seconds; `0` disables the limit and an invalid value keeps
+/// the default.
+fn build_timeout_from_env() -> Option<Duration> {
+    let secs = match env::var(BUILD_TIMEOUT_ENV_VAR) {
+        Ok(value) => value.trim().parse::<u64>().unwrap_or_else(|_| {
+            warn!(
+                "Ignoring invalid {}='{}', using {}s",
+                BUILD_TIMEOUT_ENV_VAR, value, DEFAULT_BUILD_TIMEOUT_SECS
+            );
+            DEFAULT_BUILD_TIMEOUT_SECS
+        }),
+        Err(_) => DEFAULT_BUILD_TIMEOUT_SECS,
+    };
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// The CPU architecture of this machine, spelled the way bottle tags spell it.
+pub fn hardware_arch() -> &'static str {
+    match env::consts::ARCH {
+        "aarch64" => "arm64",
+        arch => arch,
+    }
+}
+
+/// Parses an `--arch` or `SPS_FORCE_ARCH` value; `None` if it is empty.
+///
+/// Fails for an unknown architecture, and for `arm64` on Intel hardware, which cannot run
+/// it.
+pub fn parse_bottle_arch(arch: &str) -> Result<Option<&'static str>> {
+    let arch = match arch.trim() {
+        "" => return Ok(None),
+        "x86_64" | "intel" => "x86_64",
+        "arm64" | "aarch64" => "arm64",
+        other => {
+            return Err(SpsError::ValidationError(format!(
+                "Unknown architecture '{other}': use x86_64 or arm64"
+            )))
+        }
+    };
+    if arch == "arm64" && hardware_arch() != "arm64" {
+        return Err(SpsError::ValidationError(format!(
+            "Cannot install arm64 bottles on {} hardware",
+            hardware_arch()
+        )));
+    }
+    Ok(Some(arch))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn unknown_key(key: &str) -> SpsError {
+    SpsError::Config(format!(
+        "Unknown config key '{}'. Known keys: {}",
+        key,
+        ConfigFile::KEYS.join(", ")
+    ))
+}
+
+/// Profile names become file names, so keep them to a safe character set.
+fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(SpsError::Config(format!(
+            "Invalid profile name '{name}': use letters, digits, '-' and '_' only"
+        )))
+    }
+}
+
+/// Which installed software gets the `com.apple.quarantine` attribute, so that
+/// Gatekeeper checks it on first launch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuarantinePolicy {
+    /// Quarantine cask downloads and apps, but not formulae (Homebrew's behavior).
+    #[default]
+    Casks,
+    /// Quarantine nothing (`--no-quarantine` or `SPS_NO_QUARANTINE`).
+    Never,
+    /// Also quarantine the executables of installed formulae (`--force-quarantine` or
+    /// `SPS_FORCE_QUARANTINE`).
+    Always,
+}
+
+impl QuarantinePolicy {
+    pub fn quarantine_casks(self) -> bool {
+        self != Self::Never
+    }
+
+    pub fn quarantine_formulae(self) -> bool {
+        self == Self::Always
+    }
+
+    /// Applies the per-command flags; `--no-quarantine` wins over everything else.
+    pub fn with_flags(self, no_quarantine: bool, force_quarantine: bool) -> Self {
+        if no_quarantine {
+            Self::Never
+        } else if force_quarantine {
+            Self::Always
+        } else {
+            self
+        }
+    }
+
+    fn from_env() -> Self {
+        let flag = |var: &str| env::var(var).ok().and_then(|v| parse_bool(&v)) == Some(true);
+        Self::default().with_flags(flag(NO_QUARANTINE_ENV_VAR), flag(FORCE_QUARANTINE_ENV_VAR))
+    }
+}
+
+/// The build tool CMake and Meson source builds generate files for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildGenerator {
+    Ninja,
+    Make,
+}
+
+impl FromStr for BuildGenerator {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ninja" => Ok(Self::Ninja),
+            "make" => Ok(Self::Make),
+            other => Err(format!(
+                "unknown build generator '{other}' (expected 'ninja' or 'make')"
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub sps_root: PathBuf, // Public for direct construction in main for init if needed
+    /// Homebrew JSON API root (`SPS_API_BASE_URL` or `api_base_url`), without a
+    /// trailing slash.
     pub api_base_url: String,
     pub artifact_domain: Option<String>,
+    /// Replaces `https://ghcr.io/v2/homebrew/core` in bottle URLs, so bottles are
+    /// downloaded from a plain HTTP mirror (`SPS_BOTTLE_DOMAIN` or `bottle_domain`).
+    pub bottle_domain: Option<String>,
+    /// Host of an OCI registry mirroring ghcr.io, e.g. `registry.internal:5000`
+    /// (`SPS_OCI_REGISTRY` or `oci_registry`).
+    pub oci_registry: Option<String>,
     pub docker_registry_token: Option<String>,
     pub docker_registry_basic_auth: Option<String>,
     pub github_api_token: Option<String>,
+    pub limits: ConcurrencyLimits,
+    /// The profile selected via `--profile` or `SPS_PROFILE`, if any.
+    pub profile: Option<String>,
+    /// Install cask artifacts such as fonts and plugins into `~/Library` (the
+    /// default). When off, they go to the system `/Library` if it is writable.
+    pub cask_user_scope: bool,
+    /// Check the code signature of cask app bundles before installing them
+    /// (`--verify-signature` or `SPS_VERIFY_SIGNATURE`).
+    pub verify_signature: bool,
+    pub quarantine: QuarantinePolicy,
+    /// Forces the CMake/Meson build tool (`--generator` or `SPS_BUILD_GENERATOR`);
+    /// `None` uses Ninja when it is on the build PATH and Make otherwise.
+    pub build_generator: Option<BuildGenerator>,
+    /// How long a single source build command, pkg installer or cask installer script
+    /// may run before it is killed (`SPS_BUILD_TIMEOUT` in seconds, default one hour, `0`
+    /// for no limit).
+    pub build_timeout: Option<Duration>,
+    /// Build Go modules from their `vendor/` directory without network access
+    /// (`--offline` or `SPS_OFFLINE`). Other build systems are not affected.
+    pub offline_build: bool,
+    /// Pour bottles without rewriting the paths compiled into them (`--skip-relocation`
+    /// or `SPS_SKIP_RELOCATION`).
+    pub skip_relocation: bool,
+    /// Let linking replace prefix entries that belong to another formula or were not
+    /// created by sps, instead of failing (`install --overwrite`).
+    pub overwrite_links: bool,
+    /// `GOPROXY` and `GOSUMDB` for Go source builds, from the environment or the
+    /// `go_proxy`/`go_sumdb` config keys.
+    pub go_proxy: Option<String>,
+    pub go_sumdb: Option<String>,
+    /// How long a command that modifies the prefix waits for another sps process to
+    /// finish (`SPS_LOCK_TIMEOUT` in seconds, default `0`: fail right away).
+    pub lock_timeout: Duration,
+    /// Where downloads and API data are cached instead of `sps_cache` in the prefix
+    /// (`--cache-dir` or `SPS_CACHE_DIR`).
+    pub cache_dir_override: Option<PathBuf>,
+    /// Platform tag bottles are selected by instead of the host's, e.g. `sonoma` on a
+    /// macOS release sps does not know yet (`--bottle-tag` or `SPS_BOTTLE_TAG`).
+    pub bottle_tag: Option<String>,
+    /// CPU architecture bottles are selected for instead of the hardware's: `x86_64` on
+    /// Apple Silicon installs Intel bottles that run under Rosetta 2 (`--arch` or
+    /// `SPS_FORCE_ARCH`).
+    pub arch_override: Option<&'static str>,
+    /// Stream pipeline events to stdout as newline-delimited JSON instead of drawing the
+    /// status table (`--output json`).
+    pub json_output: bool,
+    /// Print only errors (`--quiet` or `SPS_QUIET=1`).
+    pub quiet: bool,
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
-        debug!("Loading sps configuration");
+        Self::load_with_profile(None)
+    }
 
-        // Try to get SPS_ROOT from HOMEBREW_PREFIX environment variable.
-        // Fallback to DEFAULT_FALLBACK_SPS_ROOT if not set or empty.
-        let sps_root_str = env::var("HOMEBREW_PREFIX").ok().filter(|s| !s.is_empty())
-            .unwrap_or_else(|| {
-                debug!(
-                    "HOMEBREW_PREFIX environment variable not set or empty, falling back to default: {}",
-                    DEFAULT_FALLBACK_SPS_ROOT
-                );
-                DEFAULT_FALLBACK_SPS_ROOT.to_string()
-            });
+    /// Loads the configuration with `profile` (or, if `None`, `SPS_PROFILE`) layered
+    /// over the base config file. Selecting a profile that has no file is an error.
+    pub fn load_with_profile(profile: Option<&str>) -> Result<Self> {
+        Self::load_with_prefix(None, profile)
+    }
+
+    /// Like [`Config::load_with_profile`], but with `prefix` (`--prefix`) taking
+    /// precedence over `SPS_PREFIX` and `HOMEBREW_PREFIX`. A relative path is taken
+    /// relative to the current directory.
+    pub fn load_with_prefix(prefix: Option<PathBuf>, profile: Option<&str>) -> Result<Self> {
+        debug!("Loading sps configuration");
 
-        let sps_root_path = PathBuf::from(&sps_root_str);
+        let sps_root_path = resolve_sps_root(prefix);
         debug!("Effective SPS_ROOT set to: {}", sps_root_path.display());
 
-        let api_base_url = "https://formulae.brew.sh/api".to_string();
+        let profile = profile
+            .map(String::from)
+            .or_else(|| env::var(PROFILE_ENV_VAR).ok())
+            .filter(|p| !p.is_empty());
+        let state_dir = sps_root_path.join("state");
+        let mut file = ConfigFile::load(&state_dir.join(CONFIG_FILENAME))?;
+        if let Some(name) = &profile {
+            validate_profile_name(name)?;
+            let profile_path = profile_path_in(&state_dir, name);
+            if !profile_path.is_file() {
+                return Err(SpsError::Config(format!(
+                    "Profile '{}' does not exist (expected {})",
+                    name,
+                    profile_path.display()
+                )));
+            }
+            debug!("Using config profile '{}'", name);
+            file = file.merged_with(ConfigFile::load(&profile_path)?);
+        }
 
-        let artifact_domain = env::var("HOMEBREW_ARTIFACT_DOMAIN").ok();
-        let docker_registry_token = env::var("HOMEBREW_DOCKER_REGISTRY_TOKEN").ok();
-        let docker_registry_basic_auth = env::var("HOMEBREW_DOCKER_REGISTRY_BASIC_AUTH_TOKEN").ok();
-        let github_api_token = env::var("HOMEBREW_GITHUB_API_TOKEN").ok();
+        let env_or_file = |var: &str, value: Option<String>| env::var(var).ok().or(value);
+        let api_base_url = env_or_file(API_BASE_URL_ENV_VAR, file.api_base_url)
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_API_BASE_URL.to_string());
+        let artifact_domain = env_or_file("HOMEBREW_ARTIFACT_DOMAIN", file.artifact_domain);
+        let bottle_domain = env_or_file(BOTTLE_DOMAIN_ENV_VAR, file.bottle_domain)
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| url.trim().trim_end_matches('/').to_string());
+        let oci_registry = env_or_file(OCI_REGISTRY_ENV_VAR, file.oci_registry)
+            .map(|host| {
+                let host = host.trim();
+                host.strip_prefix("https://")
+                    .unwrap_or(host)
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .filter(|host| !host.is_empty());
+        if api_base_url != DEFAULT_API_BASE_URL || bottle_domain.is_some() || oci_registry.is_some()
+        {
+            debug!(
+                "Using mirrors: api={} bottles={:?} registry={:?}",
+                api_base_url, bottle_domain, oci_registry
+            );
+        }
+        let docker_registry_token =
+            env_or_file("HOMEBREW_DOCKER_REGISTRY_TOKEN", file.docker_registry_token);
+        let docker_registry_basic_auth = env_or_file(
+            "HOMEBREW_DOCKER_REGISTRY_BASIC_AUTH_TOKEN",
+            file.docker_registry_basic_auth,
+        );
+        let github_api_token = env_or_file("HOMEBREW_GITHUB_API_TOKEN", file.github_api_token);
+        let limits = ConcurrencyLimits::platform_defaults()
+            .with_overrides(file.max_downloads, file.max_workers, file.build_jobs)
+            .with_env();
+        debug!("Concurrency limits: {:?}", limits);
+        let cask_user_scope = env::var(CASK_USER_SCOPE_ENV_VAR)
+            .ok()
+            .and_then(|v| parse_bool(&v))
+            .or(file.cask_user_scope)
+            .unwrap_or(true);
+        let verify_signature = env::var(VERIFY_SIGNATURE_ENV_VAR)
+            .ok()
+            .and_then(|v| parse_bool(&v))
+            .unwrap_or(false);
+        let quarantine = QuarantinePolicy::from_env();
+        let build_generator = env::var(BUILD_GENERATOR_ENV_VAR)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .and_then(|v| {
+                v.parse()
+                    .map_err(|e| warn!("Ignoring {}: {}", BUILD_GENERATOR_ENV_VAR, e))
+                    .ok()
+            });
+        if quarantine != QuarantinePolicy::default() {
+            debug!("Quarantine policy: {:?}", quarantine);
+        }
+        let build_timeout = build_timeout_from_env();
+        let offline_build = env::var(OFFLINE_ENV_VAR)
+            .ok()
+            .and_then(|v| parse_bool(&v))
+            .unwrap_or(false);
+        let skip_relocation = env::var(SKIP_RELOCATION_ENV_VAR)
+            .ok()
+            .and_then(|v| parse_bool(&v))
+            .unwrap_or(false);
+        let go_proxy = env_or_file("GOPROXY", file.go_proxy);
+        let go_sumdb = env_or_file("GOSUMDB", file.go_sumdb);
+        let lock_timeout = lock_timeout_from_env();
+        let cache_dir_override = cache_dir_from_env();
+        let bottle_tag = env::var(BOTTLE_TAG_ENV_VAR).ok().filter(|t| !t.is_empty());
+        let quiet = env::var(QUIET_ENV_VAR).is_ok_and(|v| v == "1");
+        let arch_override = env::var(FORCE_ARCH_ENV_VAR).ok().and_then(|v| {
+            parse_bottle_arch(&v)
+                .map_err(|e| warn!("Ignoring {}: {}", FORCE_ARCH_ENV_VAR, e))
+                .ok()
+                .flatten()
+        });
 
         debug!("Configuration loaded successfully.");
         Ok(Self {
             sps_root: sps_root_path,
             api_base_url,
             artifact_domain,
+            bottle_domain,
+            oci_registry,
             docker_registry_token,
             docker_registry_basic_auth,
             github_api_token,
+            limits,
+            profile,
+            cask_user_scope,
+            verify_signature,
+            quarantine,
+            build_generator,
+            build_timeout,
+            offline_build,
+            skip_relocation,
+            overwrite_links: false,
+            go_proxy,
+            go_sumdb,
+            lock_timeout,
+            cache_dir_override,
+            bottle_tag,
+            arch_override,
+            json_output: false,
+            quiet,
         })
     }
 
+    /// The built-in defaults with `sps_root` as the prefix, ignoring the environment
+    /// and config files, so tests do not pick up the host's `SPS_*` settings.
+    #[doc(hidden)]
+    pub fn for_tests(sps_root: &Path) -> Self {
+        Self {
+            sps_root: sps_root.to_path_buf(),
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+            artifact_domain: None,
+            bottle_domain: None,
+            oci_registry: None,
+            docker_registry_token: None,
+            docker_registry_basic_auth: None,
+            github_api_token: None,
+            limits: ConcurrencyLimits::platform_defaults(),
+            profile: None,
+            cask_user_scope: true,
+            verify_signature: false,
+            quarantine: QuarantinePolicy::default(),
+            build_generator: None,
+            build_timeout: None,
+            offline_build: false,
+            skip_relocation: false,
+            overwrite_links: false,
+            go_proxy: None,
+            go_sumdb: None,
+            lock_timeout: Duration::ZERO,
+            cache_dir_override: None,
+            bottle_tag: None,
+            arch_override: None,
+            json_output: false,
+            quiet: false,
+        }
+    }
+
+    /// Keeps downloads and API data in `cache_dir` (`--cache-dir`), ahead of
+    /// `SPS_CACHE_DIR`. A relative path is taken relative to the current directory; an
+    /// empty one is ignored.
+    pub fn set_cache_dir_override(&mut self, cache_dir: PathBuf) {
+        if let Some(cache_dir) = absolute_path(cache_dir) {
+            debug!(
+                "Using cache directory {} from --cache-dir",
+                cache_dir.display()
+            );
+            self.cache_dir_override = Some(cache_dir);
+        }
+    }
+
+    /// The architecture bottles are selected for: the override, or the hardware's.
+    pub fn bottle_arch(&self) -> &'static str {
+        self.arch_override.unwrap_or_else(hardware_arch)
+    }
+
+    /// The base config file, shared by all profiles.
+    pub fn config_file_path(&self) -> PathBuf {
+        self.state_dir().join(CONFIG_FILENAME)
+    }
+
+    pub fn profiles_dir(&self) -> PathBuf {
+        self.state_dir().join(PROFILES_DIRNAME)
+    }
+
+    pub fn profile_config_path(&self, name: &str) -> Result<PathBuf> {
+        validate_profile_name(name)?;
+        Ok(profile_path_in(&self.state_dir(), name))
+    }
+
+    /// The file `sps config` edits: the active profile's, or the base config.
+    pub fn active_config_path(&self) -> Result<PathBuf> {
+        match &self.profile {
+            Some(name) => self.profile_config_path(name),
+            None => Ok(self.config_file_path()),
+        }
+    }
+
     pub fn sps_root(&self) -> &Path {
         &self.sps_root
     }
 
+    /// Checks that the prefix exists and that this user can write to it, so that a
+    /// command fails up front with a clear message instead of halfway through.
+    pub fn check_prefix_writable(&self) -> Result<()> {
+        let root = self.sps_root();
+        if !root.is_dir() {
+            return Err(SpsError::Config(format!(
+                "Prefix {} does not exist; run `{}` to create it",
+                root.display(),
+                self.init_command()
+            )));
+        }
+        let probe = root.join(format!(".sps-write-check-{}", std::process::id()));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+        {
+            Ok(_) => {
+                let _ = fs::remove_file(&probe);
+                Ok(())
+            }
+            Err(e) => Err(SpsError::Config(format!(
+                "Prefix {} is not writable by the current user ({e}); fix its ownership or \
+                 run `{} --force`",
+                root.display(),
+                self.init_command()
+            ))),
+        }
+    }
+
+    /// The `sps init` command line that sets up this prefix, with `--prefix` spelled out
+    /// unless it is the default one.
+    pub fn init_command(&self) -> String {
+        if self.sps_root == Path::new(DEFAULT_FALLBACK_SPS_ROOT) {
+            "sps init".to_string()
+        } else {
+            format!("sps --prefix {} init", self.sps_root.display())
+        }
+    }
+
     pub fn bin_dir(&self) -> PathBuf {
         self.sps_root.join("bin")
     }
@@ -86,13 +868,44 @@ impl Config {
     }
 
     pub fn cache_dir(&self) -> PathBuf {
-        self.sps_root.join("sps_cache")
+        self.cache_dir_override
+            .clone()
+            .unwrap_or_else(|| self.sps_root.join("sps_cache"))
+    }
+
+    /// The cache directory, created if it does not exist yet.
+    pub fn ensure_cache_dir(&self) -> Result<PathBuf> {
+        let cache_dir = self.cache_dir();
+        if cache_dir.is_dir() {
+            return Ok(cache_dir);
+        }
+        fs::create_dir_all(&cache_dir).map_err(|e| {
+            let hint = if e.kind() == std::io::ErrorKind::PermissionDenied {
+                format!(
+                    "; the current user cannot write there, so fix its permissions or point \
+                     {CACHE_DIR_ENV_VAR} or --cache-dir at a writable directory"
+                )
+            } else {
+                String::new()
+            };
+            SpsError::Cache(format!(
+                "Could not create cache directory {}: {e}{hint}",
+                cache_dir.display()
+            ))
+        })?;
+        debug!("Created cache directory {}", cache_dir.display());
+        Ok(cache_dir)
     }
 
     pub fn logs_dir(&self) -> PathBuf {
         self.sps_root.join("sps_logs")
     }
 
+    /// The transcript of the latest source build of a formula.
+    pub fn build_log_path(&self, formula_name: &str) -> PathBuf {
+        self.logs_dir().join(format!("{formula_name}-build.log"))
+    }
+
     pub fn tmp_dir(&self) -> PathBuf {
         self.sps_root.join("tmp")
     }
@@ -101,6 +914,20 @@ impl Config {
         self.sps_root.join("state")
     }
 
+    /// Held by commands that modify the prefix, see [`crate::lock::SpsLock`].
+    pub fn lock_file_path(&self) -> PathBuf {
+        self.state_dir().join("sps.lock")
+    }
+
+    /// The JSON-lines journal of install, upgrade and reinstall jobs.
+    pub fn install_journal_path(&self) -> PathBuf {
+        self.state_dir().join("install_journal.jsonl")
+    }
+
+    pub fn pinned_file_path(&self) -> PathBuf {
+        self.state_dir().join("pinned")
+    }
+
     pub fn man_base_dir(&self) -> PathBuf {
         self.sps_root.join("share").join("man")
     }
@@ -190,6 +1017,12 @@ impl Config {
     }
 }
 
+fn profile_path_in(state_dir: &Path, name: &str) -> PathBuf {
+    state_dir
+        .join(PROFILES_DIRNAME)
+        .join(format!("{name}.json"))
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::load().expect("Failed to load default configuration")
@@ -199,3 +1032,71 @@ impl Default for Config {
 pub fn load_config() -> Result<Config> {
     Config::load()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_count_defaults_to_cpus_and_cli_overrides_win() {
+        let defaults = ConcurrencyLimits::platform_defaults();
+        assert_eq!(defaults.max_workers, num_cpus::get().max(1));
+
+        let limits = defaults.with_overrides(None, Some(3), Some(0));
+        assert_eq!(limits.max_downloads, defaults.max_downloads);
+        assert_eq!(limits.max_workers, 3);
+        assert_eq!(limits.build_jobs, 1);
+    }
+
+    #[test]
+    fn bottle_arch_accepts_aliases_and_rejects_unknown_names() {
+        assert_eq!(parse_bottle_arch("").unwrap(), None);
+        assert_eq!(parse_bottle_arch("intel").unwrap(), Some("x86_64"));
+        assert_eq!(parse_bottle_arch(" x86_64 ").unwrap(), Some("x86_64"));
+        assert!(parse_bottle_arch("ppc").is_err());
+        assert_eq!(
+            parse_bottle_arch("aarch64").is_ok(),
+            hardware_arch() == "arm64"
+        );
+    }
+
+    #[test]
+    fn prefix_flag_is_made_absolute_and_ignored_when_empty() {
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(
+            resolve_sps_root(Some(PathBuf::from("pfx"))),
+            cwd.join("pfx")
+        );
+        assert_eq!(
+            resolve_sps_root(Some(PathBuf::from("/opt/sps"))),
+            PathBuf::from("/opt/sps")
+        );
+        assert_eq!(
+            resolve_sps_root(Some(PathBuf::new())),
+            resolve_sps_root(None)
+        );
+    }
+
+    #[test]
+    fn cache_dir_flag_replaces_the_configured_one() {
+        let mut config = Config::for_tests(Path::new("/opt/sps"));
+        config.cache_dir_override = Some(PathBuf::from("/var/cache/sps"));
+
+        config.set_cache_dir_override(PathBuf::new());
+        assert_eq!(config.cache_dir(), PathBuf::from("/var/cache/sps"));
+
+        config.set_cache_dir_override(PathBuf::from("cache"));
+        assert_eq!(
+            config.cache_dir(),
+            env::current_dir().unwrap().join("cache")
+        );
+    }
+
+    #[test]
+    fn init_command_names_a_custom_prefix() {
+        let mut config = Config::for_tests(Path::new(DEFAULT_FALLBACK_SPS_ROOT));
+        assert_eq!(config.init_command(), "sps init");
+        config.sps_root = PathBuf::from("/opt/sps");
+        assert_eq!(config.init_command(), "sps --prefix /opt/sps init");
+    }
+}
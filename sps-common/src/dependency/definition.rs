@@ -4,6 +4,9 @@ use std::fmt;
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
+use crate::error::{Result, SpsError};
+use crate::model::version::Version;
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
     pub struct DependencyTag: u8 {
@@ -27,11 +30,96 @@ impl fmt::Display for DependencyTag {
     }
 }
 
+/// A version a dependency has to be at: `>= 1.2` for a minimum or `= 1.2` (or just
+/// `1.2`) for an exact version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum VersionConstraint {
+    AtLeast(String),
+    Exact(String),
+}
+
+impl VersionConstraint {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (constraint, version): (fn(String) -> Self, &str) =
+            if let Some(version) = s.strip_prefix(">=") {
+                (Self::AtLeast, version)
+            } else if let Some(version) = s.strip_prefix("==").or_else(|| s.strip_prefix('=')) {
+                (Self::Exact, version)
+            } else {
+                (Self::Exact, s)
+            };
+        let version = version.trim();
+        if version.is_empty() || version.contains(char::is_whitespace) {
+            return Err(SpsError::VersionError(format!(
+                "Invalid version requirement '{s}': expected '>= <version>' or '= <version>'"
+            )));
+        }
+        Ok(constraint(version.to_string()))
+    }
+
+    /// Whether `version` (a formula or keg version, revision suffix allowed) meets the
+    /// constraint. Versions that cannot be compared numerically only meet an exact
+    /// constraint when they are spelled the same.
+    pub fn is_satisfied_by(&self, version: &str) -> bool {
+        let version = version.split('_').next().unwrap_or(version);
+        match self {
+            Self::Exact(required) => {
+                version == required
+                    || matches!(
+                        (Version::parse(version), Version::parse(required)),
+                        (Ok(a), Ok(b)) if a == b
+                    )
+            }
+            Self::AtLeast(minimum) => match (Version::parse(version), Version::parse(minimum)) {
+                (Ok(a), Ok(b)) => a >= b,
+                _ => version == minimum,
+            },
+        }
+    }
+
+    /// Whether some version meets both constraints.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::AtLeast(_), Self::AtLeast(_)) => true,
+            (Self::Exact(version), constraint) | (constraint, Self::Exact(version)) => {
+                constraint.is_satisfied_by(version)
+            }
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtLeast(version) => write!(f, ">= {version}"),
+            Self::Exact(version) => write!(f, "= {version}"),
+        }
+    }
+}
+
+impl TryFrom<String> for VersionConstraint {
+    type Error = SpsError;
+    fn try_from(s: String) -> Result<Self> {
+        Self::parse(&s)
+    }
+}
+
+impl From<VersionConstraint> for String {
+    fn from(constraint: VersionConstraint) -> Self {
+        constraint.to_string()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Dependency {
     pub name: String,
     #[serde(default)]
     pub tags: DependencyTag,
+    /// The version the dependency has to be at, if the formula restricts it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<VersionConstraint>,
 }
 
 impl Dependency {
@@ -39,6 +127,7 @@ impl Dependency {
         Self {
             name: name.into(),
             tags: DependencyTag::RUNTIME,
+            version: None,
         }
     }
 
@@ -46,8 +135,14 @@ impl Dependency {
         Self {
             name: name.into(),
             tags,
+            version: None,
         }
     }
+
+    pub fn with_version(mut self, version: Option<VersionConstraint>) -> Self {
+        self.version = version;
+        self
+    }
 }
 
 pub trait DependencyExt {
@@ -80,3 +175,41 @@ impl DependencyExt for Vec<Dependency> {
         self.filter_by_tags(DependencyTag::BUILD, DependencyTag::empty())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_constraints_parse_and_round_trip() {
+        let at_least = VersionConstraint::parse(" >= 1.2 ").unwrap();
+        assert_eq!(at_least, VersionConstraint::AtLeast("1.2".to_string()));
+        assert_eq!(
+            VersionConstraint::parse(&at_least.to_string()).unwrap(),
+            at_least
+        );
+        for exact in ["1.2", "= 1.2", "==1.2"] {
+            assert_eq!(
+                VersionConstraint::parse(exact).unwrap(),
+                VersionConstraint::Exact("1.2".to_string())
+            );
+        }
+        for invalid in ["", ">=", "= ", ">= 1 2"] {
+            assert!(VersionConstraint::parse(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn version_constraints_compare_numerically_and_ignore_revisions() {
+        let at_least = VersionConstraint::AtLeast("1.10".to_string());
+        assert!(at_least.is_satisfied_by("1.10_1"));
+        assert!(at_least.is_satisfied_by("2.0"));
+        assert!(!at_least.is_satisfied_by("1.9"));
+
+        let exact = VersionConstraint::Exact("3.0".to_string());
+        assert!(exact.is_satisfied_by("3.0_2"));
+        assert!(!exact.is_satisfied_by("3.1"));
+        assert!(exact.is_compatible_with(&VersionConstraint::AtLeast("2.0".to_string())));
+        assert!(!exact.is_compatible_with(&VersionConstraint::Exact("3.1".to_string())));
+    }
+}
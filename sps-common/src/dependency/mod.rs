@@ -3,7 +3,7 @@ pub mod requirement;
 pub mod resolver;
 
 // Re-export key types for easier access
-pub use definition::{Dependency, DependencyExt, DependencyTag}; // Updated source module
+pub use definition::{Dependency, DependencyExt, DependencyTag, VersionConstraint}; // Updated source module
 pub use requirement::Requirement;
 pub use resolver::{
     DependencyResolver, ResolutionContext, ResolutionStatus, ResolvedDependency, ResolvedGraph,
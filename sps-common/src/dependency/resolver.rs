@@ -5,7 +5,8 @@ use std::sync::Arc;
 
 use tracing::{debug, error, warn};
 
-use crate::dependency::{Dependency, DependencyTag};
+use crate::config::Config;
+use crate::dependency::{Dependency, DependencyTag, VersionConstraint};
 use crate::error::{Result, SpsError};
 use crate::formulary::Formulary;
 use crate::keg::KegRegistry;
@@ -34,7 +35,7 @@ pub struct ResolutionContext<'a> {
     pub initial_target_preferences: &'a PerTargetInstallPreferences,
     pub build_all_from_source: bool,
     pub cascade_source_preference_to_dependencies: bool,
-    pub has_bottle_for_current_platform: fn(&Formula) -> bool,
+    pub has_bottle_for_current_platform: fn(&Formula, &Config) -> bool,
     pub initial_target_actions: &'a HashMap<String, crate::pipeline::JobAction>,
 }
 
@@ -80,6 +81,9 @@ pub struct DependencyResolver<'a> {
     visiting: HashSet<String>,
     resolution_details: HashMap<String, ResolvedDependency>,
     errors: HashMap<String, Arc<SpsError>>,
+    /// Version requirements on followed edges: dependency name to the requiring
+    /// formula and its constraint.
+    version_constraints: HashMap<String, Vec<(String, VersionConstraint)>>,
 }
 
 impl<'a> DependencyResolver<'a> {
@@ -90,9 +94,16 @@ impl<'a> DependencyResolver<'a> {
             visiting: HashSet::new(),
             resolution_details: HashMap::new(),
             errors: HashMap::new(),
+            version_constraints: HashMap::new(),
         }
     }
 
+    /// The context this resolver was built with, e.g. for re-checking which
+    /// edges it followed after `resolve_targets` returns.
+    pub fn context(&self) -> &ResolutionContext<'a> {
+        &self.context
+    }
+
     fn determine_node_install_strategy(
         &self,
         formula_name: &str,
@@ -100,23 +111,24 @@ impl<'a> DependencyResolver<'a> {
         is_initial_target: bool,
         requesting_parent_strategy: Option<NodeInstallStrategy>,
     ) -> NodeInstallStrategy {
-        if is_initial_target {
-            if self
-                .context
-                .initial_target_preferences
-                .force_source_build_targets
-                .contains(formula_name)
-            {
-                return NodeInstallStrategy::SourceOnly;
-            }
-            if self
+        // Forcing a source build applies to dependencies too, so a single dependency
+        // can be rebuilt without rebuilding its dependents.
+        if self
+            .context
+            .initial_target_preferences
+            .force_source_build_targets
+            .contains(formula_name)
+        {
+            return NodeInstallStrategy::SourceOnly;
+        }
+        if is_initial_target
+            && self
                 .context
                 .initial_target_preferences
                 .force_bottle_only_targets
                 .contains(formula_name)
-            {
-                return NodeInstallStrategy::BottleOrFail;
-            }
+        {
+            return NodeInstallStrategy::BottleOrFail;
         }
 
         if self.context.build_all_from_source {
@@ -138,7 +150,10 @@ impl<'a> DependencyResolver<'a> {
             return NodeInstallStrategy::BottleOrFail;
         }
 
-        let strategy = if (self.context.has_bottle_for_current_platform)(formula_arc) {
+        let strategy = if (self.context.has_bottle_for_current_platform)(
+            formula_arc,
+            self.context.formulary.config(),
+        ) {
             NodeInstallStrategy::BottlePreferred
         } else {
             NodeInstallStrategy::SourceOnly
@@ -148,7 +163,7 @@ impl<'a> DependencyResolver<'a> {
             "Install strategy for '{formula_name}': {:?} (initial_target={is_initial_target}, parent={:?}, bottle_available={})",
             strategy,
             requesting_parent_strategy,
-            (self.context.has_bottle_for_current_platform)(formula_arc)
+            (self.context.has_bottle_for_current_platform)(formula_arc, self.context.formulary.config())
         );
         strategy
     }
@@ -158,6 +173,7 @@ impl<'a> DependencyResolver<'a> {
         self.visiting.clear();
         self.resolution_details.clear();
         self.errors.clear();
+        self.version_constraints.clear();
 
         for target_name in targets {
             if let Err(e) = self.resolve_recursive(target_name, DependencyTag::RUNTIME, true, None)
@@ -178,6 +194,8 @@ impl<'a> DependencyResolver<'a> {
                 .collect::<Vec<_>>()
         );
 
+        self.check_version_constraints()?;
+
         let sorted_list = match self.topological_sort() {
             Ok(list) => list,
             Err(e @ SpsError::DependencyError(_)) => {
@@ -219,11 +237,8 @@ impl<'a> DependencyResolver<'a> {
                         debug!("Adding build dep path: {}", opt_path.display());
                         build_paths.push(opt_path.clone());
                     }
-                    if dep.accumulated_tags.intersects(
-                        DependencyTag::RUNTIME
-                            | DependencyTag::RECOMMENDED
-                            | DependencyTag::OPTIONAL,
-                    ) && seen_runtime_paths.insert(opt_path.clone())
+                    if dep.accumulated_tags.intersects(RUNTIME_TAGS)
+                        && seen_runtime_paths.insert(opt_path.clone())
                     {
                         debug!("Adding runtime dep path: {}", opt_path.display());
                         runtime_paths.push(opt_path.clone());
@@ -523,7 +538,20 @@ impl<'a> DependencyResolver<'a> {
                 parent_name, parent_strategy, dep_name, dep_tags
             );
 
-            if let Err(e) = self.resolve_recursive(dep_name, dep_tags, false, Some(parent_strategy))
+            if let Some(constraint) = &dep.version {
+                let requirement = (parent_name.to_string(), constraint.clone());
+                let recorded = self
+                    .version_constraints
+                    .entry(dep_name.clone())
+                    .or_default();
+                if !recorded.contains(&requirement) {
+                    recorded.push(requirement);
+                }
+            }
+
+            let child_tags = inherited_tags(dep_snapshot.accumulated_tags, dep_tags);
+            if let Err(e) =
+                self.resolve_recursive(dep_name, child_tags, false, Some(parent_strategy))
             {
                 // Log the error but don't necessarily stop all resolution for this branch yet
                 warn!(
@@ -541,6 +569,70 @@ impl<'a> DependencyResolver<'a> {
         Ok(())
     }
 
+    /// Checks the version requirements of the followed edges against each other and
+    /// against the version each dependency resolved to: the installed keg if it is used
+    /// as is, otherwise the version the formula would install.
+    fn check_version_constraints(&self) -> Result<()> {
+        let mut names: Vec<&String> = self.version_constraints.keys().collect();
+        names.sort();
+        let mut problems = Vec::new();
+        for name in names {
+            let requirements = &self.version_constraints[name];
+            for (i, (first_parent, first)) in requirements.iter().enumerate() {
+                for (second_parent, second) in &requirements[i + 1..] {
+                    if !first.is_compatible_with(second) {
+                        problems.push(format!(
+                            "'{first_parent}' requires {name} {first} but '{second_parent}' \
+                             requires {name} {second}"
+                        ));
+                    }
+                }
+            }
+
+            let Some(resolved) = self.resolution_details.get(name.as_str()) else {
+                continue;
+            };
+            let available = resolved.formula.version_str_full();
+            let installed = match (resolved.status, &resolved.keg_path) {
+                (ResolutionStatus::Installed, Some(keg_path)) => keg_path
+                    .file_name()
+                    .map(|version| version.to_string_lossy().into_owned()),
+                (ResolutionStatus::NotFound | ResolutionStatus::Failed, _) => continue,
+                _ => None,
+            };
+            for (parent, constraint) in requirements {
+                match &installed {
+                    Some(version) if !constraint.is_satisfied_by(version) => {
+                        let hint = if constraint.is_satisfied_by(&available) {
+                            format!(" (run `sps upgrade {name}` to get {available})")
+                        } else {
+                            String::new()
+                        };
+                        problems.push(format!(
+                            "'{parent}' requires {name} {constraint}, but {version} is \
+                             installed{hint}"
+                        ));
+                    }
+                    None if !constraint.is_satisfied_by(&available) => {
+                        problems.push(format!(
+                            "'{parent}' requires {name} {constraint}, but only {available} is \
+                             available"
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        error!("Unsatisfiable version requirements: {:?}", problems);
+        Err(SpsError::DependencyError(format!(
+            "Unsatisfiable version requirements: {}",
+            problems.join("; ")
+        )))
+    }
+
     fn topological_sort(&self) -> Result<Vec<ResolvedDependency>> {
         let mut in_degree: HashMap<String, usize> = HashMap::new();
         let mut adj: HashMap<String, HashSet<String>> = HashMap::new();
@@ -613,7 +705,7 @@ impl<'a> DependencyResolver<'a> {
         // Check for cycles: if sorted_list's length doesn't match relevant_nodes_map's length
         // (excluding already installed, skipped optional if not included, etc.)
         // A more direct check is if in_degree still contains non-zero values for relevant nodes.
-        let mut cycle_detected = false;
+        let mut cycle_nodes = Vec::new();
         for (name, &degree) in &in_degree {
             if degree > 0 && relevant_nodes_map.contains_key(name) {
                 // Further check if this node should have been processed (not skipped globally)
@@ -623,7 +715,7 @@ impl<'a> DependencyResolver<'a> {
                         .should_consider_edge_globally(detail.accumulated_tags)
                     {
                         error!("Cycle detected or unresolved dependency: Node '{}' still has in-degree {}. Tags: {:?}", name, degree, detail.accumulated_tags);
-                        cycle_detected = true;
+                        cycle_nodes.push(name.clone());
                     } else {
                         debug!("Node '{}' has in-degree {} but was globally skipped. Tags: {:?}. Not a cycle error.", name, degree, detail.accumulated_tags);
                     }
@@ -631,28 +723,36 @@ impl<'a> DependencyResolver<'a> {
             }
         }
 
-        if cycle_detected {
-            return Err(SpsError::DependencyError(
-                "Circular dependency detected or graph resolution incomplete".to_string(),
-            ));
+        if !cycle_nodes.is_empty() {
+            cycle_nodes.sort();
+            return Err(SpsError::DependencyError(format!(
+                "Circular dependency detected involving: {}",
+                cycle_nodes.join(", ")
+            )));
         }
 
         Ok(sorted_list) // Return the full sorted list of relevant nodes
     }
 
     fn should_consider_dependency(&self, dep: &Dependency) -> bool {
-        let tags = dep.tags;
-        if tags.contains(DependencyTag::TEST) && !self.context.include_test {
-            return false;
-        }
-        if tags.contains(DependencyTag::OPTIONAL) && !self.context.include_optional {
-            return false;
-        }
-        if tags.contains(DependencyTag::RECOMMENDED) && self.context.skip_recommended {
-            return false;
-        }
-        true
+        self.context.should_consider_edge_globally(dep.tags)
+    }
+}
+
+/// Tags that put a dependency in the runtime closure.
+const RUNTIME_TAGS: DependencyTag = DependencyTag::RUNTIME
+    .union(DependencyTag::RECOMMENDED)
+    .union(DependencyTag::OPTIONAL);
+
+/// The tags a child is resolved with. Whatever its edge says, a dependency of something
+/// that is only needed to build or test is itself only needed for that, so it stays
+/// out of the runtime closure.
+fn inherited_tags(parent_tags: DependencyTag, edge_tags: DependencyTag) -> DependencyTag {
+    let build_or_test = DependencyTag::BUILD | DependencyTag::TEST;
+    if parent_tags.intersects(RUNTIME_TAGS) || !parent_tags.intersects(build_or_test) {
+        return edge_tags;
     }
+    edge_tags.difference(RUNTIME_TAGS) | (parent_tags & build_or_test)
 }
 
 impl Formula {
@@ -671,6 +771,8 @@ impl Formula {
             dependencies: Vec::new(),
             requirements: Vec::new(),
             resources: Vec::new(),
+            keg_only: false,
+            keg_only_reason: None,
             install_keg_path: None,
         }
     }
@@ -693,12 +795,8 @@ impl<'a> ResolutionContext<'a> {
 
         match parent_node_determined_strategy {
             NodeInstallStrategy::BottlePreferred | NodeInstallStrategy::BottleOrFail => {
-                let is_purely_build_dependency = edge_tags.contains(DependencyTag::BUILD)
-                    && !edge_tags.intersects(
-                        DependencyTag::RUNTIME
-                            | DependencyTag::RECOMMENDED
-                            | DependencyTag::OPTIONAL,
-                    );
+                let is_purely_build_dependency =
+                    edge_tags.contains(DependencyTag::BUILD) && !edge_tags.intersects(RUNTIME_TAGS);
                 if is_purely_build_dependency {
                     debug!("Edge with tags {:?} SKIPPED: Pure BUILD dependency of a bottle-installed parent '{}'.", edge_tags, parent_formula_for_logging.name());
                     return false;
@@ -84,6 +84,14 @@ pub enum SpsError {
 
     #[error("Codesign Error: {0}")]
     CodesignError(String),
+
+    /// Several failures that all share `category`'s exit code, combined into one
+    /// message.
+    #[error("{message}")]
+    Aggregated {
+        message: String,
+        category: Box<SpsError>,
+    },
 }
 
 impl From<std::io::Error> for SpsError {
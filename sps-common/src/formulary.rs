@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use semver::Version;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use super::cache::Cache;
 use super::config::Config;
+use super::dependency::{Dependency, Requirement};
 use super::error::{Result, SpsError};
-use super::model::formula::Formula;
+use super::model::formula::{BottleSpec, Formula, ResourceSpec};
+use super::model::tap::{self, DefinitionKind};
 
 #[derive()]
 pub struct Formulary {
@@ -25,6 +29,19 @@ impl Formulary {
         }
     }
 
+    pub fn config(&self) -> &Config {
+        self.cache.config()
+    }
+
+    /// Makes `formula` loadable by name without consulting the API cache, e.g. one
+    /// read from a local file. It shadows any cached definition of the same name.
+    pub fn register(&self, formula: Formula) {
+        self.parsed_cache
+            .lock()
+            .unwrap()
+            .insert(formula.name.clone(), Arc::new(formula));
+    }
+
     pub fn load_formula(&self, name: &str) -> Result<Formula> {
         let mut parsed_cache_guard = self.parsed_cache.lock().unwrap();
         if let Some(formula_arc) = parsed_cache_guard.get(name) {
@@ -33,9 +50,7 @@ impl Formulary {
         }
         drop(parsed_cache_guard);
 
-        let raw_data = self.cache.load_raw("formula.json")?;
-        let all_formulas: Vec<Formula> = serde_json::from_str(&raw_data)
-            .map_err(|e| SpsError::Cache(format!("Failed to parse cached formula data: {e}")))?;
+        let all_formulas = load_formula_index(&self.cache)?;
         debug!("Parsed {} formulas.", all_formulas.len());
 
         let mut found_formula: Option<Formula> = None;
@@ -67,10 +82,175 @@ impl Formulary {
                     "Formula '{}' not found within the cached formula data.",
                     name
                 );
-                Err(SpsError::Generic(format!(
-                    "Formula '{name}' not found in cache."
-                )))
+                let Some(tapped) = load_tapped_formula(self.cache.config(), name)? else {
+                    let variants = match name.split_once('@') {
+                        Some((base, _)) => {
+                            let parsed = self.parsed_cache.lock().unwrap();
+                            format!(" {}", describe_variants(base, &parsed))
+                        }
+                        None => String::new(),
+                    };
+                    return Err(SpsError::Generic(format!(
+                        "Formula '{name}' not found in cache.{variants}"
+                    )));
+                };
+                self.register(tapped.clone());
+                Ok(tapped)
             }
         }
     }
 }
+
+/// Resolves an install target written as `name@version` to the formula to install:
+/// the formula of exactly that name if there is one (Homebrew's versioned formulae such
+/// as `node@18`), otherwise `name` or one of its `name@` variants whose version is
+/// `version` or starts with it (`node@18.20` matches `node@18` at 18.20.4). Names
+/// without `@` are returned unchanged.
+pub fn resolve_versioned_name(
+    requested: &str,
+    formulae: &HashMap<String, Arc<Formula>>,
+) -> Result<String> {
+    if formulae.contains_key(requested) {
+        return Ok(requested.to_string());
+    }
+    let Some((base, version)) = requested.split_once('@') else {
+        return Ok(requested.to_string());
+    };
+    let variants = versioned_variants(base, formulae);
+    let matching = variants.iter().find(|formula| {
+        let available = formula.version_str_full();
+        available == version
+            || formula.stable_version_str == version
+            || formula
+                .stable_version_str
+                .starts_with(&format!("{version}."))
+    });
+    match matching {
+        Some(formula) => {
+            debug!("Resolved '{}' to formula '{}'", requested, formula.name);
+            Ok(formula.name.clone())
+        }
+        None => Err(SpsError::NotFound(format!(
+            "Formula '{requested}' is not published. {}",
+            describe_variants(base, formulae)
+        ))),
+    }
+}
+
+/// `base` and its `base@...` formulae, sorted by name.
+fn versioned_variants<'a>(
+    base: &str,
+    formulae: &'a HashMap<String, Arc<Formula>>,
+) -> Vec<&'a Formula> {
+    let prefix = format!("{base}@");
+    let mut variants: Vec<&Formula> = formulae
+        .values()
+        .filter(|formula| formula.name == base || formula.name.starts_with(&prefix))
+        .map(|formula| formula.as_ref())
+        .collect();
+    variants.sort_by(|a, b| a.name.cmp(&b.name));
+    variants
+}
+
+/// Lists the available versions of `base` for an error message.
+fn describe_variants(base: &str, formulae: &HashMap<String, Arc<Formula>>) -> String {
+    let variants = versioned_variants(base, formulae);
+    if variants.is_empty() {
+        return format!("No versions of '{base}' are available.");
+    }
+    let listed: Vec<String> = variants
+        .iter()
+        .map(|formula| format!("{} ({})", formula.name, formula.version_str_full()))
+        .collect();
+    format!("Available versions of '{base}': {}", listed.join(", "))
+}
+
+/// The formula `name` from an installed tap, for names the core index does not have.
+pub fn load_tapped_formula(config: &Config, name: &str) -> Result<Option<Formula>> {
+    let Some(tapped) = tap::find_tapped_definition(config, DefinitionKind::Formula, name) else {
+        return Ok(None);
+    };
+    serde_json::from_value(tapped.value).map(Some).map_err(|e| {
+        SpsError::Generic(format!(
+            "Invalid formula '{name}' in tap {}: {e}",
+            tapped.tap
+        ))
+    })
+}
+
+/// Loads every formula in the cached `formula.json`, reusing the parsed index when the
+/// file is unchanged (see [`Cache::load_parsed_with`]).
+pub fn load_formula_index(cache: &Cache) -> Result<Vec<Formula>> {
+    let records: Vec<FormulaRecord> = cache.load_parsed_with("formula.json", |raw| {
+        let formulas: Vec<Formula> = serde_json::from_str(raw)
+            .map_err(|e| SpsError::Cache(format!("Failed to parse cached formula data: {e}")))?;
+        Ok(formulas.into_iter().map(FormulaRecord::from).collect())
+    })?;
+    Ok(records.into_iter().map(Formula::from).collect())
+}
+
+/// A [`Formula`] as stored in the parsed index. `Formula`'s `Deserialize` reads the API's
+/// JSON shape rather than what its `Serialize` writes, so it cannot be stored directly.
+#[derive(Serialize, Deserialize)]
+struct FormulaRecord {
+    name: String,
+    stable_version_str: String,
+    version_semver: Version,
+    revision: u32,
+    desc: Option<String>,
+    homepage: Option<String>,
+    url: String,
+    sha256: String,
+    mirrors: Vec<String>,
+    bottle: BottleSpec,
+    dependencies: Vec<Dependency>,
+    requirements: Vec<Requirement>,
+    resources: Vec<ResourceSpec>,
+    keg_only: bool,
+    keg_only_reason: Option<String>,
+}
+
+impl From<Formula> for FormulaRecord {
+    fn from(f: Formula) -> Self {
+        Self {
+            name: f.name,
+            stable_version_str: f.stable_version_str,
+            version_semver: f.version_semver,
+            revision: f.revision,
+            desc: f.desc,
+            homepage: f.homepage,
+            url: f.url,
+            sha256: f.sha256,
+            mirrors: f.mirrors,
+            bottle: f.bottle,
+            dependencies: f.dependencies,
+            requirements: f.requirements,
+            resources: f.resources,
+            keg_only: f.keg_only,
+            keg_only_reason: f.keg_only_reason,
+        }
+    }
+}
+
+impl From<FormulaRecord> for Formula {
+    fn from(r: FormulaRecord) -> Self {
+        Self {
+            name: r.name,
+            stable_version_str: r.stable_version_str,
+            version_semver: r.version_semver,
+            revision: r.revision,
+            desc: r.desc,
+            homepage: r.homepage,
+            url: r.url,
+            sha256: r.sha256,
+            mirrors: r.mirrors,
+            bottle: r.bottle,
+            dependencies: r.dependencies,
+            requirements: r.requirements,
+            resources: r.resources,
+            keg_only: r.keg_only,
+            keg_only_reason: r.keg_only_reason,
+            install_keg_path: None,
+        }
+    }
+}
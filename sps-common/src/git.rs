@@ -0,0 +1,299 @@
+// sps-common/src/git.rs
+//! Blocking git clone, fetch and fast-forward with progress reporting and cancellation.
+//!
+//! libgit2 reports transfer progress through a callback, and that callback is also
+//! the only point where a running transfer can be stopped: returning `false` makes
+//! libgit2 abort. Callers on an async runtime should run these in `spawn_blocking`.
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{FetchOptions, Oid, RemoteCallbacks, Repository};
+use tracing::{debug, info, warn};
+
+use crate::error::{Result, SpsError};
+use crate::pipeline::GitProgressFn;
+
+/// Clones `url` into `dest`, which must not exist yet.
+///
+/// If the clone fails or is cancelled through `cancel`, whatever was written to
+/// `dest` is removed so a later attempt starts from a clean slate.
+pub fn clone_repo(
+    url: &str,
+    dest: &Path,
+    progress: Option<&GitProgressFn>,
+    cancel: Option<&AtomicBool>,
+) -> Result<Repository> {
+    if dest.exists() {
+        return Err(SpsError::Generic(format!(
+            "Cannot clone {url}: {} already exists",
+            dest.display()
+        )));
+    }
+    debug!("Cloning {} into {}", url, dest.display());
+    let result = RepoBuilder::new()
+        .fetch_options(fetch_options(progress, cancel))
+        .clone(url, dest);
+    result.map_err(|e| {
+        if dest.exists() {
+            debug!("Removing partial clone at {}", dest.display());
+            if let Err(rm_err) = fs::remove_dir_all(dest) {
+                warn!(
+                    "Failed to remove partial clone at {}: {}",
+                    dest.display(),
+                    rm_err
+                );
+            }
+        }
+        transfer_error("clone", url, e, cancel)
+    })
+}
+
+/// Fetches `refspecs` from `remote_name` into `repo`. A cancelled fetch leaves the
+/// repository as it was; libgit2 only updates refs once the pack is complete.
+pub fn fetch_remote(
+    repo: &Repository,
+    remote_name: &str,
+    refspecs: &[&str],
+    progress: Option<&GitProgressFn>,
+    cancel: Option<&AtomicBool>,
+) -> Result<()> {
+    let mut remote = repo
+        .find_remote(remote_name)
+        .map_err(|e| SpsError::Generic(format!("Failed to find remote '{remote_name}': {e}")))?;
+    let url = remote.url().unwrap_or(remote_name).to_string();
+    debug!("Fetching {:?} from {}", refspecs, url);
+    remote
+        .fetch(refspecs, Some(&mut fetch_options(progress, cancel)), None)
+        .map_err(|e| transfer_error("fetch", &url, e, cancel))
+}
+
+/// What [`update_repo`] did to bring a clone up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepoUpdate {
+    /// There was no usable clone, so a fresh one was made.
+    Cloned,
+    UpToDate,
+    /// The checked-out branch was fast-forwarded by this many commits.
+    FastForwarded {
+        commits: usize,
+    },
+}
+
+/// Brings the clone of `url` at `dest` up to date: fetches the checked-out branch from
+/// `origin` and fast-forwards to it. Clones from scratch only if `dest` is missing or
+/// is not a usable repository; a branch that has diverged from `origin` is an error.
+pub fn update_repo(
+    url: &str,
+    dest: &Path,
+    progress: Option<&GitProgressFn>,
+    cancel: Option<&AtomicBool>,
+) -> Result<RepoUpdate> {
+    let repo = match Repository::open(dest) {
+        Ok(repo) if repo.head().is_ok() => Some(repo),
+        Ok(_) => {
+            warn!(
+                "{} has no checked-out branch; cloning it again",
+                dest.display()
+            );
+            None
+        }
+        Err(e) if dest.exists() => {
+            warn!(
+                "{} is not a usable git repository ({}); cloning it again",
+                dest.display(),
+                e
+            );
+            None
+        }
+        Err(_) => None,
+    };
+    let Some(repo) = repo else {
+        if dest.exists() {
+            fs::remove_dir_all(dest)?;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        clone_repo(url, dest, progress, cancel)?;
+        return Ok(RepoUpdate::Cloned);
+    };
+
+    let head = repo
+        .head()
+        .map_err(|e| SpsError::Generic(format!("Failed to read HEAD of {url}: {e}")))?;
+    let branch = match head.shorthand() {
+        Some(branch) if head.is_branch() => branch.to_string(),
+        _ => {
+            return Err(SpsError::Generic(format!(
+                "Cannot update {}: HEAD is not on a branch",
+                dest.display()
+            )))
+        }
+    };
+    let old_id = head
+        .target()
+        .ok_or_else(|| SpsError::Generic(format!("HEAD of {} has no commit", dest.display())))?;
+    let tracking_ref = format!("refs/remotes/origin/{branch}");
+    fetch_remote(
+        &repo,
+        "origin",
+        &[&format!("+refs/heads/{branch}:{tracking_ref}")],
+        progress,
+        cancel,
+    )?;
+    let new_id = repo
+        .refname_to_id(&tracking_ref)
+        .map_err(|e| SpsError::Generic(format!("Failed to resolve {tracking_ref}: {e}")))?;
+    if new_id == old_id || repo.graph_descendant_of(old_id, new_id).unwrap_or(false) {
+        debug!("{} is up to date", dest.display());
+        return Ok(RepoUpdate::UpToDate);
+    }
+    if !repo.graph_descendant_of(new_id, old_id).unwrap_or(false) {
+        return Err(SpsError::Generic(format!(
+            "Cannot fast-forward {} to origin/{branch}: the branch has diverged",
+            dest.display()
+        )));
+    }
+
+    let commits = count_commits(&repo, old_id, new_id)?;
+    fast_forward(&repo, head.name().unwrap_or("HEAD"), new_id)?;
+    info!(
+        "Updated {} by {} commit(s) from origin/{}",
+        dest.display(),
+        commits,
+        branch
+    );
+    Ok(RepoUpdate::FastForwarded { commits })
+}
+
+/// The number of commits reachable from `new_id` but not from `old_id`.
+fn count_commits(repo: &Repository, old_id: Oid, new_id: Oid) -> Result<usize> {
+    let git_error = |e: git2::Error| SpsError::Generic(format!("Failed to walk history: {e}"));
+    let mut walk = repo.revwalk().map_err(git_error)?;
+    walk.push(new_id).map_err(git_error)?;
+    walk.hide(old_id).map_err(git_error)?;
+    Ok(walk.count())
+}
+
+/// Checks out `target` and moves the branch `refname` to it.
+fn fast_forward(repo: &Repository, refname: &str, target: Oid) -> Result<()> {
+    let git_error = |e: git2::Error| SpsError::Generic(format!("Failed to fast-forward: {e}"));
+    let commit = repo.find_commit(target).map_err(git_error)?;
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+        .map_err(git_error)?;
+    repo.find_reference(refname)
+        .and_then(|mut reference| reference.set_target(target, "sps: fast-forward"))
+        .map_err(git_error)?;
+    Ok(())
+}
+
+fn fetch_options<'a>(
+    progress: Option<&'a GitProgressFn>,
+    cancel: Option<&'a AtomicBool>,
+) -> FetchOptions<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.transfer_progress(move |stats| {
+        if is_cancelled(cancel) {
+            return false;
+        }
+        if let Some(progress) = progress {
+            progress(
+                stats.received_objects(),
+                stats.total_objects(),
+                stats.received_bytes(),
+            );
+        }
+        true
+    });
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks);
+    options
+}
+
+fn is_cancelled(cancel: Option<&AtomicBool>) -> bool {
+    cancel.is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
+
+fn transfer_error(
+    operation: &str,
+    url: &str,
+    error: git2::Error,
+    cancel: Option<&AtomicBool>,
+) -> SpsError {
+    if is_cancelled(cancel) {
+        SpsError::Generic(format!("Git {operation} of {url} was cancelled"))
+    } else {
+        SpsError::Generic(format!("Failed to {operation} {url}: {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A scratch directory under the system temp dir, removed when dropped.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("sps-git-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn source_repo(dir: &Path) -> String {
+        let repo = Repository::init(dir).unwrap();
+        fs::write(dir.join("README"), "tap").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("README")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("sps", "sps@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        format!("file://{}", dir.display())
+    }
+
+    #[test]
+    fn cancelled_clone_leaves_nothing_behind() {
+        let scratch = Scratch::new("cancel");
+        let url = source_repo(&scratch.0.join("source"));
+        let dest = scratch.0.join("clone");
+
+        let cancel = AtomicBool::new(true);
+        let result = clone_repo(&url, &dest, None, Some(&cancel));
+
+        assert!(result.is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn clone_reports_progress() {
+        let scratch = Scratch::new("progress");
+        let url = source_repo(&scratch.0.join("source"));
+        let dest = scratch.0.join("clone");
+
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let progress: GitProgressFn = {
+            let calls = std::sync::Arc::clone(&calls);
+            std::sync::Arc::new(move |_, _, _| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        clone_repo(&url, &dest, Some(&progress), None).unwrap();
+
+        assert!(dest.join("README").is_file());
+        assert!(calls.load(Ordering::Relaxed) > 0);
+    }
+}
@@ -1,6 +1,6 @@
 // sps-common/src/keg.rs
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Corrected tracing imports: added error, removed unused debug
 use tracing::{debug, error, warn};
@@ -16,6 +16,61 @@ pub struct InstalledKeg {
     pub path: PathBuf,
 }
 
+/// The receipt key recording whether a keg was installed by name rather than pulled in
+/// as a dependency.
+pub const INSTALLED_ON_REQUEST_KEY: &str = "installed_on_request";
+
+/// The receipt key listing the runtime dependencies the keg was installed with.
+pub const RUNTIME_DEPENDENCIES_KEY: &str = "runtime_dependencies";
+
+impl InstalledKeg {
+    /// Whether this keg was installed by name rather than as a dependency, as recorded in
+    /// its receipt; `None` if the receipt is missing or predates the record.
+    pub fn installed_on_request(&self) -> Option<bool> {
+        installed_on_request(&self.path)
+    }
+
+    /// The names of the runtime dependencies recorded in this keg's receipt; `None` if
+    /// the receipt is missing or predates the record.
+    pub fn runtime_dependencies(&self) -> Option<Vec<String>> {
+        read_receipt_value(&self.path, RUNTIME_DEPENDENCIES_KEY)?
+            .as_array()?
+            .iter()
+            .map(|name| name.as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+/// Reads the on-request record from the receipt of the keg at `keg_path`.
+pub fn installed_on_request(keg_path: &Path) -> Option<bool> {
+    read_receipt_value(keg_path, INSTALLED_ON_REQUEST_KEY)?.as_bool()
+}
+
+/// Records in the receipt of the keg at `keg_path` that it was installed on request.
+/// Returns whether the receipt changed; receipts that are missing or predate the record
+/// already count as requested and are left alone.
+pub fn mark_installed_on_request(keg_path: &Path) -> Result<bool> {
+    if installed_on_request(keg_path) != Some(false) {
+        return Ok(false);
+    }
+    let receipt_path = keg_path.join("INSTALL_RECEIPT.json");
+    let mut receipt: serde_json::Value = serde_json::from_str(&fs::read_to_string(&receipt_path)?)
+        .map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+    receipt[INSTALLED_ON_REQUEST_KEY] = serde_json::Value::Bool(true);
+    let json = serde_json::to_string_pretty(&receipt)
+        .map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+    fs::write(&receipt_path, json)?;
+    Ok(true)
+}
+
+fn read_receipt_value(keg_path: &Path, key: &str) -> Option<serde_json::Value> {
+    let receipt = fs::read_to_string(keg_path.join("INSTALL_RECEIPT.json")).ok()?;
+    serde_json::from_str::<serde_json::Value>(&receipt)
+        .ok()?
+        .get_mut(key)
+        .map(serde_json::Value::take)
+}
+
 /// Manages querying installed packages in the Cellar.
 #[derive(Debug)]
 pub struct KegRegistry {
@@ -4,7 +4,9 @@ pub mod config;
 pub mod dependency;
 pub mod error;
 pub mod formulary;
+pub mod git;
 pub mod keg;
+pub mod lock;
 pub mod model;
 pub mod pipeline;
 // Optional: pub mod dependency_def;
@@ -0,0 +1,123 @@
+// sps-common/src/lock.rs
+//! An advisory lock that keeps concurrent sps processes from modifying the prefix at
+//! the same time.
+//!
+//! The lock is an OS file lock on `state/sps.lock`, so it disappears with the process
+//! however that ends: on exit, on error and when killed by a signal. A stale lock file
+//! left behind does not block anyone. The file holds the PID of the current holder,
+//! which is only used to make the error message more helpful.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use crate::config::Config;
+use crate::error::{Result, SpsError};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Holds the sps process lock until dropped.
+#[derive(Debug)]
+pub struct SpsLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl SpsLock {
+    /// Acquires the lock, waiting up to `config.lock_timeout` for another sps process
+    /// to release it.
+    pub fn acquire(config: &Config) -> Result<Self> {
+        let path = config.lock_file_path();
+        let deadline = Instant::now() + config.lock_timeout;
+        let mut announced = false;
+        loop {
+            if let Some(lock) = Self::try_acquire(config)? {
+                return Ok(lock);
+            }
+            if Instant::now() >= deadline {
+                return Err(SpsError::Generic(format!(
+                    "Another sps process{} is running and holds {}; wait for it to finish \
+                     or set SPS_LOCK_TIMEOUT to wait for it",
+                    holder_pid(&path)
+                        .map(|pid| format!(" (pid {pid})"))
+                        .unwrap_or_default(),
+                    path.display()
+                )));
+            }
+            if !announced && !config.quiet && !config.json_output {
+                eprintln!("Waiting for another sps process to finish...");
+                announced = true;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Acquires the lock if no other sps process holds it, without waiting.
+    pub fn try_acquire(config: &Config) -> Result<Option<Self>> {
+        let path = config.lock_file_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| {
+                SpsError::Generic(format!("Could not open lock file {}: {e}", path.display()))
+            })?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => {
+                return Err(SpsError::Generic(format!(
+                    "Could not lock {}: {e}",
+                    path.display()
+                )))
+            }
+        }
+        // Best effort: the PID only improves the message other processes print.
+        let _ = file
+            .set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| write!(file, "{}", std::process::id()));
+        debug!("Acquired {}", path.display());
+        Ok(Some(Self { file, path }))
+    }
+
+    /// Whether a process other than this one holds the lock: `None` if not, otherwise
+    /// the holder's PID when it is known. Never takes the lock itself.
+    pub fn other_holder(config: &Config) -> Option<Option<u32>> {
+        let path = config.lock_file_path();
+        let file = File::open(&path).ok()?;
+        match file.try_lock_shared() {
+            Ok(()) => {
+                let _ = file.unlock();
+                None
+            }
+            Err(_) => match holder_pid(&path) {
+                Some(pid) if pid == std::process::id() => None,
+                pid => Some(pid),
+            },
+        }
+    }
+}
+
+impl Drop for SpsLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+        debug!("Released {}", self.path.display());
+    }
+}
+
+fn holder_pid(path: &Path) -> Option<u32> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
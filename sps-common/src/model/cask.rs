@@ -1,10 +1,12 @@
 // ===== sps-common/src/model/cask.rs ===== // Corrected path
 use std::collections::HashMap;
-use std::fs;
+use std::{fmt, fs};
 
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
+use crate::error::{Result, SpsError};
+use crate::model::version::Version;
 
 pub type Artifact = serde_json::Value;
 
@@ -79,6 +81,151 @@ pub enum MacOSReq {
     Map(HashMap<String, Vec<String>>),
 }
 
+/// macOS release symbols as used in cask definitions, mapped to their versions.
+const MACOS_RELEASES: &[(&str, &str)] = &[
+    ("tahoe", "26"),
+    ("sequoia", "15"),
+    ("sonoma", "14"),
+    ("ventura", "13"),
+    ("monterey", "12"),
+    ("big_sur", "11"),
+    ("catalina", "10.15"),
+    ("mojave", "10.14"),
+    ("high_sierra", "10.13"),
+    ("sierra", "10.12"),
+    ("el_capitan", "10.11"),
+    ("yosemite", "10.10"),
+];
+
+/// Inclusive range of macOS releases a cask supports. A missing bound is open.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MacOSBounds {
+    pub min: Option<Version>,
+    pub max: Option<Version>,
+}
+
+impl MacOSBounds {
+    /// Whether `host` (e.g. "14.5" or "10.15.7") falls inside the range. Versions
+    /// are compared per release, so 11.7 satisfies `<= :big_sur`.
+    pub fn contains(&self, host: &str) -> Result<bool> {
+        let host = macos_release(host)?;
+        Ok(self.min.as_ref().is_none_or(|min| &host >= min)
+            && self.max.as_ref().is_none_or(|max| &host <= max))
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
+impl fmt::Display for MacOSBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) if min == max => write!(f, "macOS {}", release_str(min)),
+            (Some(min), Some(max)) => {
+                write!(f, "macOS {} through {}", release_str(min), release_str(max))
+            }
+            (Some(min), None) => write!(f, "macOS {} or newer", release_str(min)),
+            (None, Some(max)) => write!(f, "macOS {} or older", release_str(max)),
+            (None, None) => write!(f, "any macOS"),
+        }
+    }
+}
+
+impl MacOSReq {
+    /// Collapses the requirement into the inclusive range of supported releases.
+    /// A list of releases is treated as "any of", i.e. its lowest to its highest.
+    pub fn bounds(&self) -> Result<MacOSBounds> {
+        let mut bounds = MacOSBounds::default();
+        match self {
+            MacOSReq::Symbol(s) | MacOSReq::Comparison(s) => {
+                apply_macos_constraint(&mut bounds, s)?
+            }
+            MacOSReq::Symbols(list) => {
+                for s in list {
+                    apply_macos_constraint(&mut bounds, s)?;
+                }
+            }
+            MacOSReq::Map(map) => {
+                for (op, values) in map {
+                    for value in values {
+                        apply_macos_constraint(&mut bounds, &format!("{op} {value}"))?;
+                    }
+                }
+            }
+        }
+        Ok(bounds)
+    }
+}
+
+/// Narrows (for comparisons) or widens (for bare releases, which are alternatives)
+/// `bounds` by one constraint such as ">= :big_sur", "<= 12" or ":catalina".
+fn apply_macos_constraint(bounds: &mut MacOSBounds, constraint: &str) -> Result<()> {
+    let constraint = constraint.trim();
+    let (op, release) = match constraint.find(|c: char| c == ':' || c.is_ascii_digit()) {
+        Some(idx) => (constraint[..idx].trim(), &constraint[idx..]),
+        None => {
+            return Err(SpsError::VersionError(format!(
+                "Unrecognised macOS requirement '{constraint}'"
+            )))
+        }
+    };
+    let version = macos_release(release)?;
+    match op {
+        ">=" => bounds.min = bounds.min.take().max(Some(version)),
+        "<=" => {
+            bounds.max = Some(match bounds.max.take() {
+                Some(max) => max.min(version),
+                None => version,
+            })
+        }
+        "" | "==" => {
+            bounds.min = Some(match bounds.min.take() {
+                Some(min) => min.min(version.clone()),
+                None => version.clone(),
+            });
+            bounds.max = bounds.max.take().max(Some(version));
+        }
+        _ => {
+            return Err(SpsError::VersionError(format!(
+                "Unsupported macOS comparison '{op}' in '{constraint}'"
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Parses a release symbol (":big_sur") or version ("11.7", "10.15.7") into the
+/// release it belongs to: the major version from 11 on, major.minor before that.
+fn macos_release(value: &str) -> Result<Version> {
+    let value = value.trim();
+    let version = match value.strip_prefix(':') {
+        Some(symbol) => MACOS_RELEASES
+            .iter()
+            .find(|(name, _)| *name == symbol)
+            .map(|(_, version)| *version)
+            .ok_or_else(|| SpsError::VersionError(format!("Unknown macOS release ':{symbol}'")))?,
+        None => value,
+    };
+    let mut parts = version.split('.');
+    let major = parts.next().unwrap_or_default();
+    let release = match major {
+        "10" => format!("10.{}", parts.next().unwrap_or("0")),
+        _ => major.to_string(),
+    };
+    Version::parse(&release)
+}
+
+/// Formats a release the way Apple names it: "11", "10.15".
+fn release_str(version: &Version) -> String {
+    let v: semver::Version = version.clone().into();
+    if v.major == 10 {
+        format!("{}.{}", v.major, v.minor)
+    } else {
+        v.major.to_string()
+    }
+}
+
 /// Helper to coerce string-or-list into Vec<String>
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -10,7 +10,7 @@ use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use tracing::{debug, error};
 
-use crate::dependency::{Dependency, DependencyTag, Requirement};
+use crate::dependency::{Dependency, DependencyTag, Requirement, VersionConstraint};
 use crate::error::Result; // <-- Import only Result // Use log crate imports
 
 // --- Resource Spec Struct ---
@@ -20,6 +20,10 @@ pub struct ResourceSpec {
     pub name: String,
     pub url: String,
     pub sha256: String,
+    /// Expected size in bytes, when the source publishes one.
+    pub size: Option<u64>,
+    /// Alternative URLs serving the same file, tried in order after `url`.
+    pub mirrors: Vec<String>,
     // Add other potential fields like version if needed later
 }
 
@@ -28,6 +32,13 @@ pub struct ResourceSpec {
 pub struct BottleFileSpec {
     pub url: String,
     pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The cellar the bottle was built in: `:any` or `:any_skip_relocation` for
+    /// relocatable bottles, or an absolute path such as `/usr/local/Cellar` when the
+    /// bottle has that path compiled in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cellar: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -79,6 +90,12 @@ pub struct Formula {
     pub requirements: Vec<Requirement>,
     #[serde(skip_deserializing)] // Skip direct deserialization for this field
     pub resources: Vec<ResourceSpec>, // Stores parsed resources
+    /// Keg-only formulae are installed into the Cellar and `opt` but not linked into
+    /// the prefix, usually because they would shadow a system library.
+    #[serde(default)]
+    pub keg_only: bool,
+    #[serde(default)]
+    pub keg_only_reason: Option<String>,
     #[serde(skip)]
     pub install_keg_path: Option<PathBuf>,
 }
@@ -89,6 +106,18 @@ impl<'de> Deserialize<'de> for Formula {
     where
         D: Deserializer<'de>,
     {
+        /// A dependency list entry: a name, or `{"name": ..., "version": ">= 1.2"}` to
+        /// restrict the dependency's version.
+        #[derive(Deserialize, Debug)]
+        #[serde(untagged)]
+        enum RawDependency {
+            Name(String),
+            Constrained {
+                name: String,
+                version: VersionConstraint,
+            },
+        }
+
         // Temporary struct reflecting the JSON structure more closely
         // *** Added 'resources' field to capture raw JSON Value ***
         #[derive(Deserialize, Debug)]
@@ -108,21 +137,25 @@ impl<'de> Deserialize<'de> for Formula {
             #[serde(default)]
             bottle: BottleSpec,
             #[serde(default)]
-            dependencies: Vec<String>,
+            dependencies: Vec<RawDependency>,
             #[serde(default)]
-            build_dependencies: Vec<String>,
+            build_dependencies: Vec<RawDependency>,
             #[serde(default)]
-            test_dependencies: Vec<String>,
+            test_dependencies: Vec<RawDependency>,
             #[serde(default)]
-            recommended_dependencies: Vec<String>,
+            recommended_dependencies: Vec<RawDependency>,
             #[serde(default)]
-            optional_dependencies: Vec<String>,
+            optional_dependencies: Vec<RawDependency>,
             #[serde(default, deserialize_with = "deserialize_requirements")]
             requirements: Vec<Requirement>,
             #[serde(default)]
             resources: Vec<Value>, // Capture resources as generic Value first
             #[serde(default)]
             urls: Option<Value>,
+            #[serde(default)]
+            keg_only: bool,
+            #[serde(default)]
+            keg_only_reason: Option<Value>,
         }
 
         let raw: RawFormulaData = RawFormulaData::deserialize(deserializer)?;
@@ -216,8 +249,18 @@ impl<'de> Deserialize<'de> for Formula {
         // --- Dependency Processing (Original logic) ---
         let mut combined_dependencies: Vec<Dependency> = Vec::new();
         let mut seen_deps: HashMap<String, DependencyTag> = HashMap::new();
-        let mut process_list = |deps: &[String], tag: DependencyTag| {
-            for name in deps {
+        let mut dep_versions: HashMap<String, VersionConstraint> = HashMap::new();
+        let mut process_list = |deps: &[RawDependency], tag: DependencyTag| {
+            for dep in deps {
+                let name = match dep {
+                    RawDependency::Name(name) => name,
+                    RawDependency::Constrained { name, version } => {
+                        dep_versions
+                            .entry(name.clone())
+                            .or_insert_with(|| version.clone());
+                        name
+                    }
+                };
                 *seen_deps
                     .entry(name.clone())
                     .or_insert(DependencyTag::empty()) |= tag;
@@ -235,7 +278,8 @@ impl<'de> Deserialize<'de> for Formula {
             DependencyTag::OPTIONAL | DependencyTag::RUNTIME,
         );
         for (name, tags) in seen_deps {
-            combined_dependencies.push(Dependency::new_with_tags(name, tags));
+            let version = dep_versions.remove(&name);
+            combined_dependencies.push(Dependency::new_with_tags(name, tags).with_version(version));
         }
 
         // --- Resource Processing ---
@@ -308,11 +352,34 @@ impl<'de> Deserialize<'de> for Formula {
             dependencies: combined_dependencies,
             requirements: raw.requirements,
             resources: combined_resources, // Assign parsed resources
+            keg_only: raw.keg_only,
+            keg_only_reason: raw.keg_only_reason.as_ref().and_then(keg_only_reason_text),
             install_keg_path: None,
         })
     }
 }
 
+/// The API gives the reason as `{"reason": ":provided_by_macos", "explanation": ""}`;
+/// a local formula file may use a plain string.
+fn keg_only_reason_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Object(map) => {
+            let explanation = map.get("explanation").and_then(Value::as_str).unwrap_or("");
+            if explanation.trim().is_empty() {
+                map.get("reason")
+                    .and_then(Value::as_str)?
+                    .trim_start_matches(':')
+                    .replace('_', " ")
+            } else {
+                explanation.trim().to_string()
+            }
+        }
+        _ => return None,
+    };
+    Some(text).filter(|t| !t.is_empty())
+}
+
 // --- Formula impl Methods ---
 impl Formula {
     // dependencies() and requirements() are unchanged
@@ -463,6 +530,10 @@ impl<'de> Deserialize<'de> for ResourceSpec {
             name: String, // name is often the key, not in the value
             url: String,
             sha256: String,
+            #[serde(default)]
+            size: Option<u64>,
+            #[serde(default)]
+            mirrors: Vec<String>,
         }
         let helper = Helper::deserialize(deserializer)?;
         // Note: The actual resource name comes from the key in the map during Formula
@@ -471,6 +542,8 @@ impl<'de> Deserialize<'de> for ResourceSpec {
             name: helper.name,
             url: helper.url,
             sha256: helper.sha256,
+            size: helper.size,
+            mirrors: helper.mirrors,
         })
     }
 }
@@ -1,10 +1,25 @@
 // tap/tap.rs - Basic tap functionality // Should probably be in model module
+//! Third-party taps: git repositories of extra formula and cask definitions, cloned
+//! into the taps directory.
+//!
+//! sps reads definitions in the API's JSON format only, from `Formula/<name>.json` and
+//! `Casks/<token>.json` in the tap. Core definitions always win; a tapped definition is
+//! only used for names core does not know.
 
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 
-use tracing::debug;
+use serde_json::Value;
+use tracing::{debug, warn};
 
+use crate::config::Config;
 use crate::error::{Result, SpsError};
+use crate::git;
+use crate::pipeline::GitProgressFn;
+
+/// Tap repositories are named `homebrew-<repo>` on GitHub and on disk.
+const REPO_PREFIX: &str = "homebrew-";
 
 /// Represents a source of packages (formulas and casks)
 pub struct Tap {
@@ -18,86 +33,85 @@ pub struct Tap {
     pub path: PathBuf,
 }
 
-impl Tap {
-    /// Create a new tap from user/repo format
-    pub fn new(name: &str) -> Result<Self> {
-        let parts: Vec<&str> = name.split('/').collect();
-        if parts.len() != 2 {
-            return Err(SpsError::Generic(format!("Invalid tap name: {name}")));
+/// The two kinds of definitions a tap can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Formula,
+    Cask,
+}
+
+impl DefinitionKind {
+    /// The directory of the tap that holds definitions of this kind.
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::Formula => "Formula",
+            Self::Cask => "Casks",
         }
-        let user = parts[0].to_string();
-        let repo = parts[1].to_string();
-        let prefix = if cfg!(target_arch = "aarch64") {
-            PathBuf::from("/opt/homebrew")
-        } else {
-            PathBuf::from("/usr/local")
-        };
-        let path = prefix
-            .join("Library/Taps")
+    }
+
+    /// The key that holds the definition's name.
+    fn name_key(self) -> &'static str {
+        match self {
+            Self::Formula => "name",
+            Self::Cask => "token",
+        }
+    }
+}
+
+/// A formula or cask definition read from a tap.
+#[derive(Debug, Clone)]
+pub struct TappedDefinition {
+    /// The tap it came from, as `user/repo`.
+    pub tap: String,
+    pub name: String,
+    pub value: Value,
+}
+
+impl Tap {
+    /// Create a new tap from user/repo format. `user/homebrew-repo` is accepted too.
+    pub fn new(name: &str, config: &Config) -> Result<Self> {
+        let (user, repo) = parse_tap_name(name)?;
+        let path = config
+            .taps_dir()
             .join(&user)
-            .join(format!("homebrew-{repo}"));
+            .join(format!("{REPO_PREFIX}{repo}"));
         Ok(Self { user, repo, path })
     }
 
-    /// Update this tap by pulling latest changes
-    pub fn update(&self) -> Result<()> {
-        use git2::{FetchOptions, Repository};
-
-        let repo = Repository::open(&self.path)
-            .map_err(|e| SpsError::Generic(format!("Failed to open tap repository: {e}")))?;
-
-        // Fetch updates from origin
-        let mut remote = repo
-            .find_remote("origin")
-            .map_err(|e| SpsError::Generic(format!("Failed to find remote 'origin': {e}")))?;
-
-        let mut fetch_options = FetchOptions::new();
-        remote
-            .fetch(
-                &["refs/heads/*:refs/heads/*"],
-                Some(&mut fetch_options),
-                None,
-            )
-            .map_err(|e| SpsError::Generic(format!("Failed to fetch updates: {e}")))?;
-
-        // Merge changes
-        let fetch_head = repo
-            .find_reference("FETCH_HEAD")
-            .map_err(|e| SpsError::Generic(format!("Failed to find FETCH_HEAD: {e}")))?;
-
-        let fetch_commit = repo
-            .reference_to_annotated_commit(&fetch_head)
-            .map_err(|e| SpsError::Generic(format!("Failed to get commit from FETCH_HEAD: {e}")))?;
-
-        let analysis = repo
-            .merge_analysis(&[&fetch_commit])
-            .map_err(|e| SpsError::Generic(format!("Failed to analyze merge: {e}")))?;
-
-        if analysis.0.is_up_to_date() {
-            debug!("Already up-to-date");
-            return Ok(());
-        }
+    /// The GitHub URL this tap is cloned from
+    pub fn remote_url(&self) -> String {
+        format!("https://github.com/{}/homebrew-{}", self.user, self.repo)
+    }
 
-        if analysis.0.is_fast_forward() {
-            let mut reference = repo
-                .find_reference("refs/heads/master")
-                .map_err(|e| SpsError::Generic(format!("Failed to find master branch: {e}")))?;
-            reference
-                .set_target(fetch_commit.id(), "Fast-forward")
-                .map_err(|e| SpsError::Generic(format!("Failed to fast-forward: {e}")))?;
-            repo.set_head("refs/heads/master")
-                .map_err(|e| SpsError::Generic(format!("Failed to set HEAD: {e}")))?;
-            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
-                .map_err(|e| SpsError::Generic(format!("Failed to checkout: {e}")))?;
-        } else {
-            return Err(SpsError::Generic(
-                "Tap requires merge but automatic merging is not implemented".to_string(),
-            ));
+    /// Clone this tap. A cancelled or failed clone leaves no directory behind.
+    pub fn install(
+        &self,
+        progress: Option<&GitProgressFn>,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<()> {
+        if self.is_installed() {
+            return Err(SpsError::Generic(format!(
+                "Tap {} is already installed",
+                self.full_name()
+            )));
         }
-
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        git::clone_repo(&self.remote_url(), &self.path, progress, cancel)?;
         Ok(())
     }
 
+    /// Update this tap by fetching and fast-forwarding its clone, cloning it again if
+    /// the clone is missing or broken.
+    pub fn update(
+        &self,
+        progress: Option<&GitProgressFn>,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<git::RepoUpdate> {
+        git::update_repo(&self.remote_url(), &self.path, progress, cancel)
+    }
+
     /// Remove this tap by deleting its local repository
     pub fn remove(&self) -> Result<()> {
         if !self.path.exists() {
@@ -109,7 +123,12 @@ impl Tap {
         debug!("Removing tap {}", self.full_name());
         std::fs::remove_dir_all(&self.path).map_err(|e| {
             SpsError::Generic(format!("Failed to remove tap {}: {}", self.full_name(), e))
-        })
+        })?;
+        // Drop the user directory once its last tap is gone.
+        if let Some(user_dir) = self.path.parent() {
+            let _ = fs::remove_dir(user_dir);
+        }
+        Ok(())
     }
 
     /// Get the full name of the tap (user/repo)
@@ -121,4 +140,147 @@ impl Tap {
     pub fn is_installed(&self) -> bool {
         self.path.exists()
     }
+
+    /// The definition of `name` in this tap, if it has one. A file that cannot be read
+    /// or parsed is logged and treated as missing.
+    pub fn find_definition(&self, kind: DefinitionKind, name: &str) -> Option<TappedDefinition> {
+        if !is_valid_component(name) {
+            return None;
+        }
+        let path = self.path.join(kind.dir_name()).join(format!("{name}.json"));
+        if !path.is_file() {
+            return None;
+        }
+        self.read_definition(kind, &path)
+    }
+
+    /// Every definition of `kind` in this tap, sorted by name.
+    pub fn definitions(&self, kind: DefinitionKind) -> Vec<TappedDefinition> {
+        let Ok(entries) = fs::read_dir(self.path.join(kind.dir_name())) else {
+            return Vec::new();
+        };
+        let mut definitions: Vec<TappedDefinition> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| self.read_definition(kind, &path))
+            .collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    fn read_definition(&self, kind: DefinitionKind, path: &Path) -> Option<TappedDefinition> {
+        let value = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).map_err(|e| e.to_string()));
+        let value = match value {
+            Ok(value) => value,
+            Err(e) => {
+                warn!(
+                    "Skipping {} in tap {}: {}",
+                    path.display(),
+                    self.full_name(),
+                    e
+                );
+                return None;
+            }
+        };
+        let name = value
+            .get(kind.name_key())
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))?;
+        Some(TappedDefinition {
+            tap: self.full_name(),
+            name,
+            value,
+        })
+    }
+}
+
+/// The taps cloned into the taps directory, sorted by name.
+pub fn installed_taps(config: &Config) -> Vec<Tap> {
+    let mut taps = Vec::new();
+    let Ok(users) = fs::read_dir(config.taps_dir()) else {
+        return taps;
+    };
+    for user_dir in users.flatten().filter(|entry| entry.path().is_dir()) {
+        let user = user_dir.file_name().to_string_lossy().into_owned();
+        let Ok(repos) = fs::read_dir(user_dir.path()) else {
+            continue;
+        };
+        for repo_dir in repos.flatten().filter(|entry| entry.path().is_dir()) {
+            let dir_name = repo_dir.file_name().to_string_lossy().into_owned();
+            if let Some(repo) = dir_name.strip_prefix(REPO_PREFIX) {
+                taps.push(Tap {
+                    user: user.clone(),
+                    repo: repo.to_string(),
+                    path: repo_dir.path(),
+                });
+            }
+        }
+    }
+    taps.sort_by_key(Tap::full_name);
+    taps
+}
+
+/// Looks `name` up in every installed tap. If several taps define it, the first in
+/// name order is used and the conflict is reported.
+pub fn find_tapped_definition(
+    config: &Config,
+    kind: DefinitionKind,
+    name: &str,
+) -> Option<TappedDefinition> {
+    let mut found = installed_taps(config)
+        .iter()
+        .filter_map(|tap| tap.find_definition(kind, name))
+        .collect::<Vec<_>>()
+        .into_iter();
+    let first = found.next()?;
+    let others: Vec<String> = found.map(|definition| definition.tap).collect();
+    if !others.is_empty() {
+        warn!(
+            "'{}' is defined by several taps; using {} over {}",
+            name,
+            first.tap,
+            others.join(", ")
+        );
+    }
+    debug!("Using '{}' from tap {}", name, first.tap);
+    Some(first)
+}
+
+/// Every definition of `kind` in the installed taps. A name defined by several taps
+/// appears once, from the first tap in name order.
+pub fn tapped_definitions(config: &Config, kind: DefinitionKind) -> Vec<TappedDefinition> {
+    let mut seen = std::collections::HashSet::new();
+    installed_taps(config)
+        .iter()
+        .flat_map(|tap| tap.definitions(kind))
+        .filter(|definition| seen.insert(definition.name.clone()))
+        .collect()
+}
+
+/// Splits `user/repo` (or `user/homebrew-repo`) into its lowercased parts.
+fn parse_tap_name(name: &str) -> Result<(String, String)> {
+    let invalid = || {
+        SpsError::ValidationError(format!(
+            "Invalid tap name '{name}': expected <user>/<repo>, e.g. `sps tap acme/tools`"
+        ))
+    };
+    let (user, repo) = name.split_once('/').ok_or_else(invalid)?;
+    let repo = repo.strip_prefix(REPO_PREFIX).unwrap_or(repo);
+    if !is_valid_component(user) || !is_valid_component(repo) {
+        return Err(invalid());
+    }
+    Ok((user.to_lowercase(), repo.to_lowercase()))
+}
+
+/// Whether `s` is a single, safe path component: ASCII letters, digits and `-_.@+`,
+/// not starting with `.` or `-`.
+fn is_valid_component(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(['.', '-'])
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | '+'))
 }
@@ -1,6 +1,7 @@
 // sps-common/src/pipeline.rs
 use std::path::PathBuf;
-use std::sync::Arc; // Required for Arc<SpsError> in JobProcessingState
+use std::sync::{Arc, Mutex}; // Required for Arc<SpsError> in JobProcessingState
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
@@ -36,6 +37,9 @@ pub struct PlannedJob {
     pub action: JobAction,
     pub is_source_build: bool,
     pub use_private_store_source: Option<PathBuf>,
+    /// Whether the package was asked for by name rather than pulled in as a dependency.
+    /// Upgrades and reinstalls keep what the existing keg recorded.
+    pub installed_on_request: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -46,7 +50,11 @@ pub struct WorkerJob {
     pub is_source_from_private_store: bool,
 }
 
+/// Serialized with an `event` tag naming the variant in snake_case, e.g.
+/// `{"event":"download_progress","target_id":"wget",...}`; `sps --output json` streams
+/// events in this form.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum PipelineEvent {
     PipelineStarted {
         total_jobs: usize,
@@ -69,6 +77,11 @@ pub enum PipelineEvent {
         target_id: String,
         url: String,
     },
+    DownloadProgress {
+        target_id: String,
+        bytes_done: u64,
+        total_bytes: Option<u64>, // None when the server sent no Content-Length
+    },
     DownloadFinished {
         target_id: String,
         path: PathBuf,
@@ -102,6 +115,23 @@ pub enum PipelineEvent {
         target_id: String,
         pkg_type: PipelinePackageType,
     },
+    ExtractProgress {
+        target_id: String,
+        files_done: usize,
+        total_files: Option<usize>, // None for streamed archives (tar) with no index
+    },
+    GitProgress {
+        target_id: String,
+        objects_received: usize,
+        total_objects: usize, // 0 until the remote has announced the pack size
+        bytes_received: usize,
+    },
+    /// The latest line a cask's pkg installer or installer script printed; pkg
+    /// installers report their percentage this way.
+    InstallerOutput {
+        target_id: String,
+        line: String,
+    },
     LinkStarted {
         target_id: String,
         pkg_type: PipelinePackageType,
@@ -147,6 +177,54 @@ impl PipelineEvent {
     }
 }
 
+// --- Progress Reporting ---
+
+/// Progress callback for downloads, called with `(bytes_done, total_bytes)`.
+pub type DownloadProgressFn = Arc<dyn Fn(u64, Option<u64>) + Send + Sync>;
+
+/// Progress callback for archive extraction, called with `(files_done, total_files)`.
+pub type ExtractProgressFn = Arc<dyn Fn(usize, Option<usize>) + Send + Sync>;
+
+/// Progress callback for git transfers, called with
+/// `(objects_received, total_objects, bytes_received)`.
+pub type GitProgressFn = Arc<dyn Fn(usize, usize, usize) + Send + Sync>;
+
+/// Receives the lines a cask's pkg installer or installer script prints, one at a time.
+pub type InstallerOutputFn = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Minimum time between two progress events for the same job.
+pub const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Rate-limits progress events so that a fast download or extraction cannot fill
+/// the bounded event channel and make receivers lag past job results.
+#[derive(Debug, Default)]
+pub struct ProgressThrottle {
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl ProgressThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if a progress event should be sent now. `is_final` always passes
+    /// so the last update (e.g. 100%) is never dropped.
+    pub fn should_send(&self, is_final: bool) -> bool {
+        let now = Instant::now();
+        let mut last_sent = match self.last_sent.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let due = last_sent.is_none_or(|last| now.duration_since(last) >= PROGRESS_EVENT_INTERVAL);
+        if due || is_final {
+            *last_sent = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
 // --- New Structs and Enums for Refactored Runner ---
 
 /// Represents the current processing state of a job in the pipeline.
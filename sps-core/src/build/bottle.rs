@@ -0,0 +1,262 @@
+// sps-core/src/build/bottle.rs
+//! Builds a formula from source and packages it as a bottle, for
+//! `sps install --build-bottle`.
+//!
+//! The build installs into a staging prefix under the temp dir rather than the Cellar,
+//! so a failed build leaves nothing behind. The staged keg is then relocated to the
+//! keg path it would have in the Cellar, as if it had been built there.
+//!
+//! The keg is copied to a staging directory laid out as `<name>/<version>/`, the same
+//! layout poured bottles have, and the prefix and Cellar paths in its text files are
+//! replaced with the `@@HOMEBREW_PREFIX@@`/`@@HOMEBREW_CELLAR@@` placeholders that
+//! pouring substitutes back. Binaries cannot be rewritten that way: if one still
+//! contains the Cellar or prefix path, the bottle records the absolute Cellar it was
+//! built for instead of `:any`, as a poured bottle's `cellar` field does.
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::formula::{Formula, FormulaDependencies};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::build::compile;
+use crate::install::bottle::{exec, get_current_platform};
+
+/// Machine-specific files that must not end up in a bottle.
+const EXCLUDED_FILES: &[&str] = &["INSTALL_MANIFEST.json"];
+
+/// The result of packaging a keg. Also written next to the archive as JSON, which holds
+/// what a formula's bottle block needs for this file.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuiltBottle {
+    pub name: String,
+    pub version: String,
+    /// Platform tag, e.g. `arm64_sonoma`.
+    pub tag: String,
+    pub path: PathBuf,
+    pub sha256: String,
+    /// `:any`, or the absolute Cellar if binaries contain build paths.
+    pub cellar: String,
+}
+
+/// Builds `formula` from `source` (its downloaded source archive when `None`) in a
+/// staging prefix and packages the result with [`create_bottle`]. The formula's
+/// dependencies must already be installed.
+pub async fn build_bottle(
+    formula: &Formula,
+    source: Option<&Path>,
+    config: &Config,
+    output_dir: &Path,
+) -> Result<BuiltBottle> {
+    let source = match source {
+        Some(path) => path.to_path_buf(),
+        None => compile::download_source(formula, config, None).await?,
+    };
+
+    fs::create_dir_all(config.tmp_dir())?;
+    let staging_prefix = tempfile::Builder::new()
+        .prefix(&format!("{}-bottle-build-", formula.name()))
+        .tempdir_in(config.tmp_dir())
+        .map_err(|e| SpsError::IoError(format!("Failed to create bottle build prefix: {e}")))?;
+    let staging_cellar = staging_prefix.path().join("Cellar");
+    let staged_keg = formula.install_prefix(&staging_cellar)?;
+    debug!(
+        "Building {} for bottling in {}",
+        formula.name(),
+        staged_keg.display()
+    );
+    compile::build_from_source_into(&source, formula, config, &[], true, staged_keg.clone())
+        .await?;
+
+    let relocations = HashMap::from([(
+        staging_cellar.display().to_string(),
+        config.cellar_dir().display().to_string(),
+    )]);
+    exec::relocate_keg_paths(formula, &staged_keg, config, relocations)?;
+    create_bottle(formula, &staged_keg, config, output_dir)
+}
+
+/// Packages `keg_path` into `<output_dir>/<name>--<version>.<tag>.bottle.tar.gz` and
+/// writes a `.json` file with the tag, checksum and cellar next to it. The tag is the
+/// configured one (`--bottle-tag`), or the host's.
+pub fn create_bottle(
+    formula: &Formula,
+    keg_path: &Path,
+    config: &Config,
+    output_dir: &Path,
+) -> Result<BuiltBottle> {
+    let name = formula.name().to_string();
+    let version = formula.version_str_full();
+    let tag = config
+        .bottle_tag
+        .clone()
+        .unwrap_or_else(|| get_current_platform(config));
+    if tag.contains("unknown") {
+        return Err(SpsError::Generic(format!(
+            "Cannot determine the bottle platform tag for this system ('{tag}')"
+        )));
+    }
+
+    fs::create_dir_all(config.tmp_dir())?;
+    let staging = tempfile::Builder::new()
+        .prefix(&format!("{name}-bottle-"))
+        .tempdir_in(config.tmp_dir())
+        .map_err(|e| SpsError::IoError(format!("Failed to create bottle staging dir: {e}")))?;
+    let staged_keg = staging.path().join(&name).join(&version);
+    debug!(
+        "Staging {} for bottling in {}",
+        keg_path.display(),
+        staged_keg.display()
+    );
+    copy_keg(keg_path, &staged_keg)?;
+    let relocatable = replace_build_paths(&staged_keg, config)?;
+
+    fs::create_dir_all(output_dir)?;
+    let file_name = format!("{name}--{version}.{tag}.bottle.tar.gz");
+    let path = output_dir.join(&file_name);
+    write_archive(staging.path(), &name, &path)?;
+
+    let bottle = BuiltBottle {
+        name,
+        version,
+        tag,
+        sha256: sha256_file(&path)?,
+        cellar: if relocatable {
+            ":any".to_string()
+        } else {
+            config.cellar_dir().display().to_string()
+        },
+        path,
+    };
+    let json = serde_json::to_string_pretty(&bottle)
+        .map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+    fs::write(output_dir.join(format!("{file_name}.json")), json)?;
+    Ok(bottle)
+}
+
+/// Copies the keg, keeping symlinks as symlinks.
+fn copy_keg(keg_path: &Path, destination: &Path) -> Result<()> {
+    for entry in WalkDir::new(keg_path) {
+        let entry = entry.map_err(io::Error::from)?;
+        let relative = entry.path().strip_prefix(keg_path).unwrap_or(entry.path());
+        if entry.depth() == 1 && EXCLUDED_FILES.iter().any(|f| relative == Path::new(f)) {
+            continue;
+        }
+        let target = destination.join(relative);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts placeholders in for the Cellar and prefix paths in text files. Returns false if
+/// a binary file still refers to either, i.e. the bottle only works in this prefix.
+fn replace_build_paths(staged_keg: &Path, config: &Config) -> Result<bool> {
+    let cellar = config.cellar_dir().display().to_string();
+    let prefix = config.sps_root().display().to_string();
+    let mut relocatable = true;
+    for entry in WalkDir::new(staged_keg).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let mut contents = Vec::new();
+        File::open(path)?.read_to_end(&mut contents)?;
+        let mentions = |needle: &str| {
+            contents
+                .windows(needle.len())
+                .any(|window| window == needle.as_bytes())
+        };
+        if !mentions(&prefix) && !mentions(&cellar) {
+            continue;
+        }
+        match String::from_utf8(contents) {
+            Ok(text) if !text.contains('\0') => {
+                // The Cellar lies inside the prefix, so it has to be replaced first.
+                let replaced = text
+                    .replace(&cellar, "@@HOMEBREW_CELLAR@@")
+                    .replace(&prefix, "@@HOMEBREW_PREFIX@@");
+                let permissions = fs::metadata(path)?.permissions();
+                fs::write(path, replaced)?;
+                fs::set_permissions(path, permissions)?;
+                debug!("Replaced build paths in {}", path.display());
+            }
+            _ => {
+                debug!(
+                    "{} contains build paths but is binary; bottle will not be relocatable",
+                    path.display()
+                );
+                relocatable = false;
+            }
+        }
+    }
+    Ok(relocatable)
+}
+
+fn write_archive(staging_root: &Path, name: &str, archive_path: &Path) -> Result<()> {
+    let file = File::create(archive_path)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(name, staging_root.join(name))?;
+    builder.into_inner()?.finish()?;
+    debug!("Wrote bottle {}", archive_path.display());
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bottle_builds_stay_out_of_the_cellar() {
+        let root = tempfile::tempdir().unwrap();
+        let mut config = Config::for_tests(root.path());
+        config.bottle_tag = Some("arm64_sonoma".to_string());
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "hello",
+            "versions": { "stable": "1.0" },
+        }))
+        .unwrap();
+        let source = root.path().join("hello.txt");
+        fs::write(&source, format!("{}/share\n", config.sps_root().display())).unwrap();
+        let output_dir = root.path().join("bottles");
+
+        let bottle = build_bottle(&formula, Some(&source), &config, &output_dir)
+            .await
+            .unwrap();
+
+        assert!(bottle.path.exists());
+        assert_eq!(bottle.tag, "arm64_sonoma");
+        assert_eq!(bottle.cellar, ":any");
+        assert!(!formula
+            .install_prefix(&config.cellar_dir())
+            .unwrap()
+            .exists());
+        let leftovers = fs::read_dir(config.tmp_dir())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains("bottle-build"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -4,10 +4,11 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+use sps_common::config::BuildGenerator;
 use sps_common::error::{Result, SpsError};
 use tracing::debug;
 
-use crate::build::compile::run_command_in_dir;
+use crate::build::compile::{resolve_generator, run_command_in_dir};
 use crate::build::env::BuildEnvironment;
 
 pub fn cmake_build(
@@ -15,6 +16,7 @@ pub fn cmake_build(
     build_dir: &Path,
     install_dir: &Path,
     build_env: &BuildEnvironment,
+    generator: Option<BuildGenerator>,
 ) -> Result<()> {
     debug!("Building with CMake in {}", build_dir.display());
     let cmake_build_subdir_name = "sps-cmake-build";
@@ -28,10 +30,17 @@ pub fn cmake_build(
             )
         })?;
 
+    let (generator, build_tool_exe) = resolve_generator(generator, build_env, build_dir)?;
+    let (generator_name, build_tool) = match generator {
+        BuildGenerator::Ninja => ("Ninja", "ninja"),
+        BuildGenerator::Make => ("Unix Makefiles", "make"),
+    };
+
     debug!(
-        "Running cmake configuration (source: {}, build: {})",
+        "Running cmake configuration (source: {}, build: {}, generator: {})",
         build_dir.join(source_subdir).display(),
-        cmake_build_dir.display()
+        cmake_build_dir.display(),
+        generator_name
     );
 
     let mut cmd_configure = Command::new(cmake_exe);
@@ -40,9 +49,9 @@ pub fn cmake_build(
         .arg(format!("-DCMAKE_INSTALL_PREFIX={}", install_dir.display()))
         .arg("-DCMAKE_POLICY_VERSION_MINIMUM=3.5")
         .arg("-DCMAKE_BUILD_TYPE=Release")
+        .args(["-G", generator_name])
+        .arg(format!("-DCMAKE_MAKE_PROGRAM={}", build_tool_exe.display()))
         .args([
-            "-G",
-            "Ninja",
             "-DCMAKE_FIND_FRAMEWORK=LAST",
             "-DCMAKE_VERBOSE_MAKEFILE=ON",
             "-Wno-dev",
@@ -63,24 +72,22 @@ pub fn cmake_build(
         String::from_utf8_lossy(&configure_output.stderr)
     );
 
-    debug!("Running ninja install in {}", cmake_build_dir.display());
-    let ninja_exe = which::which_in("ninja", build_env.get_path_string(), &cmake_build_dir)
-        .map_err(|_| {
-            SpsError::BuildEnvError(
-                "ninja command not found in build environment PATH.".to_string(),
-            )
-        })?;
-
-    let mut cmd_install = Command::new(ninja_exe);
+    // Make picks up the job count from MAKEFLAGS; ninja is parallel by default.
+    debug!(
+        "Running {} install in {}",
+        build_tool,
+        cmake_build_dir.display()
+    );
+    let mut cmd_install = Command::new(build_tool_exe);
     cmd_install.arg("install");
 
     run_command_in_dir(
         &mut cmd_install,
         &cmake_build_dir,
         build_env,
-        "ninja install",
+        &format!("{build_tool} install"),
     )?;
-    debug!("Ninja install completed successfully.");
+    debug!("{} install completed successfully.", build_tool);
 
     Ok(())
 }
@@ -5,7 +5,7 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use sps_common::error::{Result, SpsError};
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::build::compile::run_command_in_dir;
 use crate::build::env::BuildEnvironment;
@@ -60,16 +60,28 @@ pub fn go_build(
         package_to_build
     );
 
+    let go_env = go_environment(source_dir, build_env);
+    let use_vendor = build_env.is_offline() && source_dir.join("vendor").is_dir();
+    if build_env.is_offline() && !use_vendor {
+        warn!(
+            "Offline Go build of {} has no vendor/ directory; module downloads will fail",
+            formula_name
+        );
+    }
+
     let mut cmd = Command::new(go_exe);
+    cmd.arg("build");
+    if use_vendor {
+        cmd.arg("-mod=vendor");
+    }
     #[allow(clippy::suspicious_command_arg_space)]
-    cmd.arg("build")
-        .arg("-o")
+    cmd.arg("-o")
         .arg(&output_binary_path)
         .arg("-ldflags")
         .arg("-s -w")
         .arg(&package_to_build);
 
-    let build_output = run_command_in_dir(&mut cmd, source_dir, build_env, "go build")?;
+    let build_output = run_command_in_dir(&mut cmd, source_dir, &go_env, "go build")?;
 
     debug!(
         "Go build stdout:\n{}",
@@ -86,3 +98,27 @@ pub fn go_build(
 
     Ok(())
 }
+
+/// The build environment with Go's module and build caches inside the source
+/// tree's temporary directory, so builds neither read from nor write to the
+/// user's `~/go` and `~/Library/Caches/go-build`. Offline builds also get
+/// `GOPROXY=off` and never download a newer toolchain.
+fn go_environment(source_dir: &Path, build_env: &BuildEnvironment) -> BuildEnvironment {
+    let go_home = source_dir.join(".sps-go");
+    let mut go_env = build_env.clone();
+    go_env.set_var("GOPATH", go_home.join("path").to_string_lossy());
+    go_env.set_var("GOCACHE", go_home.join("cache").to_string_lossy());
+    // The module cache is read-only by default, which would stop the temporary
+    // build directory from being deleted afterwards.
+    let mut goflags = build_env.get_var("GOFLAGS").unwrap_or_default().to_string();
+    if !goflags.split_whitespace().any(|flag| flag == "-modcacherw") {
+        goflags = format!("{goflags} -modcacherw").trim().to_string();
+    }
+    go_env.set_var("GOFLAGS", goflags);
+    if build_env.is_offline() {
+        go_env.set_var("GOPROXY", "off");
+        go_env.set_var("GOTOOLCHAIN", "local");
+    }
+    debug!("Go build caches in {}", go_home.display());
+    go_env
+}
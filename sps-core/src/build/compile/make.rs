@@ -4,12 +4,12 @@ use std::fs;
 use std::io::Read;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output};
 
 use sps_common::error::{Result, SpsError};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
-use crate::build::compile::run_command_in_dir;
+use crate::build::compile::{command_output_in_dir, ensure_success, run_command_in_dir};
 use crate::build::env::BuildEnvironment;
 
 fn is_gnu_autotools_configure(script_path: &Path) -> bool {
@@ -50,6 +50,17 @@ fn is_gnu_autotools_configure(script_path: &Path) -> bool {
     }
 }
 
+/// Subdirectory of the source tree that out-of-tree Autotools builds run in.
+const VPATH_BUILD_DIR: &str = "sps-build";
+
+/// Runs `configure`, `make` and `make install`.
+///
+/// Autoconf-generated scripts are run from a separate build directory as
+/// `../configure` (a VPATH build), which keeps the source tree clean and is
+/// required by packages that refuse in-source builds. Other configure scripts,
+/// and source trees that were already configured in place are built in-tree. A
+/// VPATH configure that fails falls back to an in-tree build only when the package
+/// rejects out-of-tree builds; any other failure is reported as is.
 pub fn configure_and_make(
     source_dir: &Path,
     install_dir: &Path,
@@ -66,47 +77,112 @@ pub fn configure_and_make(
     }
 
     let is_autotools = is_gnu_autotools_configure(&configure_script_path);
-
-    debug!("Running ./configure --prefix={}", install_dir.display());
     if is_autotools {
         debug!("(Detected Autotools flags)");
     }
 
-    let mut cmd_configure = Command::new("./configure");
-    cmd_configure.arg(format!("--prefix={}", install_dir.display()));
-    if is_autotools {
-        cmd_configure.args(["--disable-dependency-tracking", "--disable-silent-rules"]);
-    }
-
-    let configure_output =
-        run_command_in_dir(&mut cmd_configure, source_dir, build_env, "configure")?;
-    debug!(
-        "Configure stdout:\n{}",
-        String::from_utf8_lossy(&configure_output.stdout)
-    );
-    debug!(
-        "Configure stderr:\n{}",
-        String::from_utf8_lossy(&configure_output.stderr)
-    );
+    // Autoconf refuses to configure out of tree once the source dir has a config.status.
+    let vpath_dir = source_dir.join(VPATH_BUILD_DIR);
+    let build_dir = if is_autotools && !source_dir.join("config.status").exists() {
+        fs::create_dir_all(&vpath_dir).map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
+        let mut cmd_configure = configure_command("../configure", install_dir, is_autotools);
+        let output = command_output_in_dir(&mut cmd_configure, &vpath_dir, build_env, "configure")?;
+        if output.status.success() {
+            log_configure_output(&output);
+            vpath_dir
+        } else if rejects_vpath_build(&output) {
+            warn!(
+                "Package does not support out-of-tree builds; configuring in the source directory"
+            );
+            let _ = fs::remove_dir_all(&vpath_dir);
+            run_configure(
+                "./configure",
+                source_dir,
+                install_dir,
+                is_autotools,
+                build_env,
+            )?;
+            source_dir.to_path_buf()
+        } else {
+            ensure_success(output, &vpath_dir, build_env, "configure")?;
+            vpath_dir
+        }
+    } else {
+        run_configure(
+            "./configure",
+            source_dir,
+            install_dir,
+            is_autotools,
+            build_env,
+        )?;
+        source_dir.to_path_buf()
+    };
 
-    let make_exe = which::which_in("make", build_env.get_path_string(), source_dir)
+    let make_exe = which::which_in("make", build_env.get_path_string(), &build_dir)
         .or_else(|_| which::which("make"))
         .map_err(|_| SpsError::BuildEnvError("make command not found.".to_string()))?;
 
-    debug!("Running make");
+    debug!("Running make in {}", build_dir.display());
     let mut cmd_make = Command::new(make_exe.clone());
-    run_command_in_dir(&mut cmd_make, source_dir, build_env, "make")?;
+    run_command_in_dir(&mut cmd_make, &build_dir, build_env, "make")?;
     debug!("Make completed successfully.");
 
     debug!("Running make install");
     let mut cmd_install = Command::new(make_exe);
     cmd_install.arg("install");
-    run_command_in_dir(&mut cmd_install, source_dir, build_env, "make install")?;
+    run_command_in_dir(&mut cmd_install, &build_dir, build_env, "make install")?;
     debug!("Make install completed successfully.");
 
     Ok(())
 }
 
+/// Runs `script` (`./configure`, or `../configure` for a VPATH build) in `dir`.
+fn run_configure(
+    script: &str,
+    dir: &Path,
+    install_dir: &Path,
+    is_autotools: bool,
+    build_env: &BuildEnvironment,
+) -> Result<()> {
+    let mut cmd_configure = configure_command(script, install_dir, is_autotools);
+    let configure_output = run_command_in_dir(&mut cmd_configure, dir, build_env, "configure")?;
+    log_configure_output(&configure_output);
+    Ok(())
+}
+
+fn configure_command(script: &str, install_dir: &Path, is_autotools: bool) -> Command {
+    debug!("Running {} --prefix={}", script, install_dir.display());
+    let mut cmd_configure = Command::new(script);
+    cmd_configure.arg(format!("--prefix={}", install_dir.display()));
+    if is_autotools {
+        cmd_configure.args(["--disable-dependency-tracking", "--disable-silent-rules"]);
+    }
+    cmd_configure
+}
+
+fn log_configure_output(output: &Output) {
+    debug!(
+        "Configure stdout:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    debug!(
+        "Configure stderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Whether a failed configure run refused to build outside the source directory,
+/// as opposed to failing for a reason an in-tree build would hit as well.
+fn rejects_vpath_build(output: &Output) -> bool {
+    const VPATH_ERRORS: &[&str] = &["source directory already configured", "VPATH"];
+    [&output.stderr, &output.stdout].into_iter().any(|stream| {
+        String::from_utf8_lossy(stream)
+            .lines()
+            .filter(|line| line.contains("error"))
+            .any(|line| VPATH_ERRORS.iter().any(|marker| line.contains(marker)))
+    })
+}
+
 pub fn simple_make(
     source_dir: &Path,
     install_dir: &Path,
@@ -249,3 +325,29 @@ fn extract_output_from_exec_error(msg: &str) -> Option<std::process::Output> {
     let _ = msg; // Avoid unused warning
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use super::*;
+
+    fn failed(stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn only_vpath_refusals_fall_back_in_tree() {
+        assert!(rejects_vpath_build(&failed(
+            "configure: error: source directory already configured; run \"make distclean\" there first"
+        )));
+        assert!(!rejects_vpath_build(&failed(
+            "configure: error: C compiler cannot create executables"
+        )));
+    }
+}
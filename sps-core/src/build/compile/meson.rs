@@ -3,10 +3,11 @@
 use std::path::Path;
 use std::process::Command;
 
+use sps_common::config::BuildGenerator;
 use sps_common::error::{Result, SpsError};
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::build::compile::run_command_in_dir;
+use crate::build::compile::{resolve_generator, run_command_in_dir};
 use crate::build::env::BuildEnvironment;
 
 pub fn meson_build(
@@ -14,6 +15,7 @@ pub fn meson_build(
     build_dir: &Path,
     install_dir: &Path,
     build_env: &BuildEnvironment,
+    generator: Option<BuildGenerator>,
 ) -> Result<()> {
     debug!("Building with Meson in {}", build_dir.display());
     let meson_build_subdir_name = "sps-meson-build";
@@ -27,6 +29,12 @@ pub fn meson_build(
             )
         })?;
 
+    // Meson only generates for ninja, so a forced Make generator cannot apply here.
+    if generator == Some(BuildGenerator::Make) {
+        warn!("Meson has no Make backend; building with ninja despite --generator make");
+    }
+    resolve_generator(Some(BuildGenerator::Ninja), build_env, build_dir)?;
+
     debug!(
         "Running meson setup (source: {}, build: {})",
         source_root_abs.display(),
@@ -54,8 +62,6 @@ pub fn meson_build(
     );
 
     debug!("Running meson install -C {}", meson_build_dir.display());
-    let _ninja_exe = which::which_in("ninja", build_env.get_path_string(), build_dir)
-        .map_err(|_| SpsError::BuildEnvError("ninja command not found.".to_string()))?;
 
     let mut cmd_install = Command::new(&meson_exe);
     cmd_install.arg("install").arg("-C").arg(&meson_build_dir);
@@ -2,18 +2,21 @@
 
 use std::collections::HashMap;
 use std::fs::{self};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
 
 use futures::future::try_join_all;
 use infer;
-use sps_common::config::Config;
+use sps_common::config::{BuildGenerator, Config};
 use sps_common::error::{Result, SpsError};
 use sps_common::model::formula::{Formula, FormulaDependencies, ResourceSpec};
+use sps_common::pipeline::DownloadProgressFn;
 use sps_net::http as http_fetch;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use super::env::BuildEnvironment;
+use super::process;
 use crate::install::{bottle, extract};
 
 mod cargo;
@@ -32,11 +35,16 @@ pub use meson::meson_build;
 pub use perl::perl_build;
 pub use python::python_build;
 
-const SUPPORTED_ARCHIVE_EXTENSIONS: [&str; 5] = ["gz", "bz2", "xz", "tar", "zip"];
-pub(crate) const RECOGNISED_SINGLE_FILE_EXTENSIONS: [&str; 9] =
-    ["tar", "gz", "tgz", "bz2", "tbz", "tbz2", "xz", "txz", "zip"];
+const SUPPORTED_ARCHIVE_EXTENSIONS: [&str; 7] = ["gz", "bz2", "xz", "lz", "tar", "zip", "7z"];
+pub(crate) const RECOGNISED_SINGLE_FILE_EXTENSIONS: [&str; 11] = [
+    "tar", "gz", "tgz", "bz2", "tbz", "tbz2", "xz", "txz", "lz", "zip", "7z",
+];
 
-pub async fn download_source(formula: &Formula, config: &Config) -> Result<PathBuf> {
+pub async fn download_source(
+    formula: &Formula,
+    config: &Config,
+    progress: Option<DownloadProgressFn>,
+) -> Result<PathBuf> {
     let url = if !formula.url.is_empty() {
         formula.url.clone()
     } else if let Some(homepage) = &formula.homepage {
@@ -65,11 +73,28 @@ pub async fn download_source(formula: &Formula, config: &Config) -> Result<PathB
         &url,
         &formula.sha256,
         &formula.mirrors,
+        None,
         config,
+        progress,
     )
     .await
 }
 
+/// Downloads the formula's resources into the cache, or reuses cached copies, and
+/// returns each resource's name with its archive.
+pub async fn download_resources(
+    formula: &Formula,
+    config: &Config,
+) -> Result<Vec<(String, PathBuf)>> {
+    let resources = formula.resources()?;
+    let download_futures = resources.iter().map(|resource| async move {
+        debug!(" --> Downloading resource: {}", resource.name);
+        let path = http_fetch::fetch_resource(&formula.name, resource, config).await?;
+        Ok::<_, SpsError>((resource.name.clone(), path))
+    });
+    try_join_all(download_futures).await
+}
+
 fn create_dir_all_with_context(path: &Path, context: &str) -> Result<()> {
     fs::create_dir_all(path).map_err(|e| {
         SpsError::Io(std::sync::Arc::new(std::io::Error::new(
@@ -147,12 +172,59 @@ fn check_and_run_autoreconf(source_dir: &Path, build_env: &BuildEnvironment) ->
     Ok(())
 }
 
+/// Picks the build tool CMake and Meson generate for: `forced` if given, otherwise
+/// Ninja when it is on the build PATH and Make when it is not. Returns the tool
+/// together with its executable.
+fn resolve_generator(
+    forced: Option<BuildGenerator>,
+    build_env: &BuildEnvironment,
+    cwd: &Path,
+) -> Result<(BuildGenerator, PathBuf)> {
+    let find = |tool: &str| which::which_in(tool, build_env.get_path_string(), cwd).ok();
+    let (generator, exe) = match forced {
+        Some(BuildGenerator::Ninja) => (
+            BuildGenerator::Ninja,
+            find("ninja").ok_or_else(|| {
+                SpsError::BuildEnvError(
+                    "ninja was requested as the build generator but is not on the build PATH."
+                        .to_string(),
+                )
+            })?,
+        ),
+        Some(BuildGenerator::Make) => (
+            BuildGenerator::Make,
+            find("make")
+                .or_else(|| which::which("make").ok())
+                .ok_or_else(|| SpsError::BuildEnvError("make command not found.".to_string()))?,
+        ),
+        None => match find("ninja") {
+            Some(ninja) => (BuildGenerator::Ninja, ninja),
+            None => {
+                debug!("ninja not found on the build PATH; falling back to make");
+                return resolve_generator(Some(BuildGenerator::Make), build_env, cwd);
+            }
+        },
+    };
+    debug!(
+        "Using build generator {:?} ({}){}",
+        generator,
+        exe.display(),
+        if forced.is_some() {
+            ", as requested"
+        } else {
+            ""
+        }
+    );
+    Ok((generator, exe))
+}
+
 fn detect_and_build(
     build_dir: &Path,
     source_subdir: &Path,
     install_dir: &Path,
     build_env: &BuildEnvironment,
     all_installed_paths: &[PathBuf],
+    generator: Option<BuildGenerator>,
 ) -> Result<()> {
     let source_root_abs = build_dir.join(source_subdir);
     debug!(
@@ -164,10 +236,10 @@ fn detect_and_build(
 
     if source_root_abs.join("CMakeLists.txt").exists() {
         debug!("Detected build system: CMake");
-        cmake::cmake_build(source_subdir, build_dir, install_dir, build_env)?;
+        cmake::cmake_build(source_subdir, build_dir, install_dir, build_env, generator)?;
     } else if source_root_abs.join("meson.build").exists() {
         debug!("Detected build system: Meson");
-        meson::meson_build(source_subdir, build_dir, install_dir, build_env)?;
+        meson::meson_build(source_subdir, build_dir, install_dir, build_env, generator)?;
     } else if source_root_abs.join("configure").exists() {
         debug!("Detected build system: Autotools (configure script)");
         make::configure_and_make(&source_root_abs, install_dir, build_env)?;
@@ -285,8 +357,30 @@ pub async fn build_from_source(
     formula: &Formula,
     config: &Config,
     all_installed_paths: &[PathBuf],
+    installed_on_request: bool,
 ) -> Result<PathBuf> {
     let install_dir = formula.install_prefix(config.cellar_dir().as_path())?;
+    build_from_source_into(
+        source_path,
+        formula,
+        config,
+        all_installed_paths,
+        installed_on_request,
+        install_dir,
+    )
+    .await
+}
+
+/// Like [`build_from_source`], but installs into `install_dir` instead of the formula's
+/// keg in the Cellar.
+pub async fn build_from_source_into(
+    source_path: &Path,
+    formula: &Formula,
+    config: &Config,
+    all_installed_paths: &[PathBuf],
+    installed_on_request: bool,
+    install_dir: PathBuf,
+) -> Result<PathBuf> {
     let formula_name = formula.name();
 
     let source_extension = source_path
@@ -298,7 +392,15 @@ pub async fn build_from_source(
         debug!("Installing single file formula: {}", formula_name);
         create_dir_all_with_context(&install_dir, "install directory")?;
         install_single_file(source_path, formula, &install_dir)?;
-        bottle::write_receipt(formula, &install_dir, "source")?;
+        let wrappers = bottle::link::generate_libexec_wrappers(&install_dir)?;
+        bottle::write_receipt(
+            formula,
+            &install_dir,
+            "source",
+            installed_on_request,
+            &wrappers,
+            config,
+        )?;
         return Ok(install_dir);
     }
 
@@ -362,17 +464,7 @@ pub async fn build_from_source(
         let resource_staging_base = build_dir.join(".sps-resources");
         create_dir_all_with_context(&resource_staging_base, "resource staging base")?;
 
-        let download_futures = resources.iter().map(|resource| {
-            let formula_name_clone = formula_name.to_string();
-            let config_clone = config.clone();
-            async move {
-                debug!(" --> Downloading resource: {}", resource.name);
-                let path = http_fetch::fetch_resource(&formula_name_clone, resource, &config_clone)
-                    .await?;
-                Ok::<_, SpsError>((resource.name.clone(), path))
-            }
-        });
-        let download_results = try_join_all(download_futures).await?;
+        let download_results = download_resources(formula, config).await?;
 
         for (res_name, resource_archive_path) in download_results {
             let stage_path = resource_staging_base.join(&res_name);
@@ -401,12 +493,22 @@ pub async fn build_from_source(
     );
 
     debug!("Setting up build environment");
-    let build_env = BuildEnvironment::new(
+    let mut build_env = BuildEnvironment::new(
         formula,
         config.sps_root(),
         config.cellar_dir().as_path(),
         all_installed_paths,
-    )?;
+        config.limits.build_jobs,
+    )?
+    .with_command_timeout(config.build_timeout)
+    .with_log_file(start_build_log(formula, config))
+    .with_offline(config.offline_build);
+    if let Some(proxy) = &config.go_proxy {
+        build_env.set_var("GOPROXY", proxy.as_str());
+    }
+    if let Some(sumdb) = &config.go_sumdb {
+        build_env.set_var("GOSUMDB", sumdb.as_str());
+    }
 
     if !resources.is_empty() {
         debug!("Installing {} resources into libexec", resources.len());
@@ -435,6 +537,7 @@ pub async fn build_from_source(
         &install_dir,
         &build_env,
         all_installed_paths,
+        config.build_generator,
     )?;
 
     if !install_dir.exists() {
@@ -451,7 +554,15 @@ pub async fn build_from_source(
             install_dir.display()
         );
     }
-    crate::install::bottle::write_receipt(formula, &install_dir, "source")?;
+    let wrappers = bottle::link::generate_libexec_wrappers(&install_dir)?;
+    bottle::write_receipt(
+        formula,
+        &install_dir,
+        "source",
+        installed_on_request,
+        &wrappers,
+        config,
+    )?;
     debug!(
         "Build completed, temporary directory {} will be cleaned up.",
         build_dir.display()
@@ -627,11 +738,84 @@ fn install_single_file(source_path: &Path, formula: &Formula, install_dir: &Path
     Ok(())
 }
 
+/// Starts a fresh build log for `formula`, replacing the previous one. Returns
+/// `None`, and builds run without a log, if the file cannot be created.
+fn start_build_log(formula: &Formula, config: &Config) -> Option<PathBuf> {
+    let path = config.build_log_path(formula.name());
+    let header = format!(
+        "Build log for {} {}\nStarted: {}\n",
+        formula.name(),
+        formula.version_str_full(),
+        chrono::Local::now().to_rfc3339()
+    );
+    let result = fs::create_dir_all(config.logs_dir()).and_then(|_| fs::write(&path, header));
+    match result {
+        Ok(()) => {
+            debug!("Writing build log to {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            warn!("Cannot create build log {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Appends one build stage to the log: the command, then its output and exit status,
+/// or the error if it did not run to completion.
+fn append_build_log(
+    log_path: &Path,
+    context: &str,
+    cwd: &Path,
+    cmd: &Command,
+    outcome: std::result::Result<&Output, &SpsError>,
+) {
+    let mut entry = format!("\n==> {} (in {})\n{:?}\n", context, cwd.display(), cmd);
+    match outcome {
+        Ok(output) => {
+            entry.push_str(&String::from_utf8_lossy(&output.stdout));
+            if !output.stderr.is_empty() {
+                entry.push_str("--- stderr ---\n");
+                entry.push_str(&String::from_utf8_lossy(&output.stderr));
+            }
+            entry.push_str(&format!("==> {}: {}\n", context, output.status));
+        }
+        Err(e) => entry.push_str(&format!("==> {context}: {e}\n")),
+    }
+    let result = fs::OpenOptions::new()
+        .append(true)
+        .open(log_path)
+        .and_then(|mut file| file.write_all(entry.as_bytes()));
+    if let Err(e) = result {
+        warn!("Cannot write build log {}: {}", log_path.display(), e);
+    }
+}
+
+/// Points a build failure at the full transcript, when there is one.
+fn with_log_hint(message: String, build_env: &BuildEnvironment) -> String {
+    match build_env.log_file() {
+        Some(path) => format!("{message}. Full build log: {}", path.display()),
+        None => message,
+    }
+}
+
 fn run_command_in_dir(
     cmd: &mut Command,
     cwd: &Path,
     build_env: &BuildEnvironment,
     context: &str,
+) -> Result<Output> {
+    let output = command_output_in_dir(cmd, cwd, build_env, context)?;
+    ensure_success(output, cwd, build_env, context)
+}
+
+/// Runs `cmd` in `cwd` like [`run_command_in_dir`], but returns its output without
+/// checking the exit status.
+fn command_output_in_dir(
+    cmd: &mut Command,
+    cwd: &Path,
+    build_env: &BuildEnvironment,
+    context: &str,
 ) -> Result<Output> {
     build_env.apply_to_command(cmd);
     cmd.current_dir(cwd);
@@ -644,15 +828,26 @@ fn run_command_in_dir(
         cmd
     );
 
-    let output = cmd.output().map_err(|e| {
-        SpsError::CommandExecError(format!(
-            "Failed to execute command for {} in {}: {}",
-            context,
-            cwd.display(),
-            e
-        ))
-    })?;
+    let result = process::output_with_timeout(cmd, build_env.command_timeout(), context);
+    if let Some(log_path) = build_env.log_file() {
+        append_build_log(log_path, context, cwd, cmd, result.as_ref());
+    }
+    result.map_err(|e| match e {
+        SpsError::CommandExecError(message) => {
+            SpsError::CommandExecError(with_log_hint(message, build_env))
+        }
+        other => other,
+    })
+}
 
+/// Checks the exit status of a command run by [`command_output_in_dir`], printing its
+/// output (and any configure log) when it failed.
+fn ensure_success(
+    output: Output,
+    cwd: &Path,
+    build_env: &BuildEnvironment,
+    context: &str,
+) -> Result<Output> {
     if !output.status.success() {
         error!(
             "Command failed for {} in [{}]. Status: {}",
@@ -686,11 +881,14 @@ fn run_command_in_dir(
             }
         }
 
-        Err(SpsError::CommandExecError(format!(
-            "Command failed during {} stage in [{}]. Status: {}",
-            context,
-            cwd.display(),
-            output.status
+        Err(SpsError::CommandExecError(with_log_hint(
+            format!(
+                "Command failed during {} stage in [{}]. Status: {}",
+                context,
+                cwd.display(),
+                output.status
+            ),
+            build_env,
         )))
     } else {
         debug!("Command successful for {} in [{}]", context, cwd.display());
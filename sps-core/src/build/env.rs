@@ -4,6 +4,7 @@
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use sps_common::error::{Result, SpsError};
 use sps_common::model::formula::FormulaDependencies;
@@ -134,6 +135,13 @@ const ENV_VARS_TO_KEEP: &[&str] = &[
     "DISPLAY",
     "XAUTHORITY",
     "TZ",
+    "GOFLAGS",
+    "GOPROXY",
+    "GOSUMDB",
+    "GOPRIVATE",
+    "GONOPROXY",
+    "GONOSUMDB",
+    "GOINSECURE",
 ];
 
 /// Represents the sanitized build environment, mimicking Homebrew's "superenv".
@@ -159,6 +167,12 @@ pub struct BuildEnvironment {
     /// Resolved path to the macOS SDK (or "/" if not applicable).
     #[allow(dead_code)]
     sdk_path: PathBuf,
+    /// How long a single build command may run before it is killed.
+    command_timeout: Option<Duration>,
+    /// File every build command's output is appended to.
+    log_file: Option<PathBuf>,
+    /// Build tools should not reach the network (`--offline`).
+    offline: bool,
 }
 
 impl BuildEnvironment {
@@ -168,6 +182,7 @@ impl BuildEnvironment {
         sps_prefix: &Path,
         cellar_path: &Path,
         all_installed_opt_paths: &[PathBuf],
+        build_jobs: usize,
     ) -> Result<Self> {
         debug!(
             "Creating BuildEnvironment for formula '{}'...",
@@ -362,9 +377,8 @@ impl BuildEnvironment {
         vars.insert("LDFLAGS".to_string(), ldflags.clone());
         debug!("Set LDFLAGS={}", ldflags);
 
-        let jobs = num_cpus::get().to_string();
-        vars.insert("MAKEFLAGS".to_string(), format!("-j{jobs}"));
-        debug!("Set MAKEFLAGS=-j{}", jobs);
+        vars.insert("MAKEFLAGS".to_string(), format!("-j{build_jobs}"));
+        debug!("Set MAKEFLAGS=-j{}", build_jobs);
 
         Self::set_path_list_var(&mut vars, "PKG_CONFIG_PATH", &pkgconfig_paths)?;
         Self::set_path_list_var(&mut vars, "PKG_CONFIG_LIBDIR", &pkgconfig_paths)?;
@@ -396,9 +410,46 @@ impl BuildEnvironment {
             cc,
             cxx,
             sdk_path,
+            command_timeout: None,
+            log_file: None,
+            offline: false,
         })
     }
 
+    /// Limits how long each build command may run; `None` lets them run forever.
+    pub fn with_command_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    pub fn command_timeout(&self) -> Option<Duration> {
+        self.command_timeout
+    }
+
+    /// Appends the output of every build command to `path`.
+    pub fn with_log_file(mut self, path: Option<PathBuf>) -> Self {
+        self.log_file = path;
+        self
+    }
+
+    pub fn log_file(&self) -> Option<&Path> {
+        self.log_file.as_deref()
+    }
+
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Sets or replaces one variable of the environment.
+    pub fn set_var(&mut self, key: &str, value: impl Into<String>) {
+        self.vars.insert(key.to_string(), value.into());
+    }
+
     // is_controlled_homebrew_var remains unchanged
     fn is_controlled_homebrew_var(key: &str) -> bool {
         matches!(
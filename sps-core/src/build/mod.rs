@@ -1,2 +1,4 @@
+pub mod bottle;
 pub mod compile;
 pub mod env;
+pub mod process;
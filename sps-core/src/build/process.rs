@@ -0,0 +1,206 @@
+// sps-core/src/build/process.rs
+//! Runs build commands, and the installers of cask artifacts, with a time limit and
+//! lets them be cancelled.
+//!
+//! Each command runs in its own process group, so that killing it also takes down
+//! whatever it spawned (compilers, test binaries, nested makes). Being in a separate
+//! group also means the terminal's Ctrl-C no longer reaches the build directly; the
+//! CLI forwards it through [`cancel_running_builds`] instead.
+
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use sps_common::error::{Result, SpsError};
+use sps_common::pipeline::InstallerOutputFn;
+use tracing::{debug, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a process group gets to exit after SIGTERM before it is sent SIGKILL.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Set once the user asked to stop; no new build commands are started afterwards.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Process group IDs of the build commands currently running.
+static RUNNING_GROUPS: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Terminates every running build command and cask installer and refuses to start new
+/// ones. Blocks for at most [`KILL_GRACE`] while the commands shut down.
+pub fn cancel_running_builds() {
+    CANCELLED.store(true, Ordering::SeqCst);
+    let groups: Vec<i32> = RUNNING_GROUPS
+        .lock()
+        .map(|groups| groups.iter().copied().collect())
+        .unwrap_or_default();
+    if !groups.is_empty() {
+        debug!("Cancelling {} running build command(s)", groups.len());
+    }
+    terminate_groups(&groups);
+}
+
+/// Runs `cmd` to completion like [`Command::output`], but kills its process group
+/// if it runs longer than `timeout` or the build is cancelled. `context` names the
+/// build stage in the returned error.
+pub(crate) fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    context: &str,
+) -> Result<Output> {
+    run_with_timeout(cmd, timeout, context, None)
+}
+
+/// Like [`output_with_timeout`], but also hands each line the command writes to stdout
+/// to `on_line` as it arrives, for installers that report their progress there.
+pub(crate) fn output_with_timeout_streaming(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    context: &str,
+    on_line: InstallerOutputFn,
+) -> Result<Output> {
+    run_with_timeout(cmd, timeout, context, Some(on_line))
+}
+
+fn run_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    context: &str,
+    on_line: Option<InstallerOutputFn>,
+) -> Result<Output> {
+    if CANCELLED.load(Ordering::SeqCst) {
+        return Err(cancelled_error(context));
+    }
+    cmd.process_group(0)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn().map_err(|e| {
+        SpsError::CommandExecError(format!("Failed to execute command for {context}: {e}"))
+    })?;
+    let pgid = child.id() as i32;
+    if let Ok(mut groups) = RUNNING_GROUPS.lock() {
+        groups.insert(pgid);
+    }
+    let stdout = read_in_background(child.stdout.take(), on_line);
+    let stderr = read_in_background(child.stderr.take(), None);
+
+    let result = wait_with_deadline(&mut child, pgid, timeout, context);
+    if let Ok(mut groups) = RUNNING_GROUPS.lock() {
+        groups.remove(&pgid);
+    }
+    // On failure the readers are left to finish on their own: a process that escaped
+    // the group could keep the pipes open indefinitely.
+    let status = result?;
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn wait_with_deadline(
+    child: &mut Child,
+    pgid: i32,
+    timeout: Option<Duration>,
+    context: &str,
+) -> Result<ExitStatus> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if CANCELLED.load(Ordering::SeqCst) {
+            terminate_child(child, pgid);
+            return Err(cancelled_error(context));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let limit = timeout.unwrap_or_default().as_secs();
+            warn!(
+                "{} exceeded the build timeout of {}s; killing it",
+                context, limit
+            );
+            terminate_child(child, pgid);
+            return Err(SpsError::CommandExecError(format!(
+                "{context} timed out after {limit}s (set SPS_BUILD_TIMEOUT to change the limit)"
+            )));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Like [`terminate_groups`] for a single command, reaping the group leader while
+/// waiting so that it does not linger as a zombie until the grace period ends.
+fn terminate_child(child: &mut Child, pgid: i32) {
+    signal_group(pgid, libc::SIGTERM);
+    let deadline = Instant::now() + KILL_GRACE;
+    while Instant::now() < deadline {
+        if matches!(child.try_wait(), Ok(Some(_))) && !group_alive(pgid) {
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    signal_group(pgid, libc::SIGKILL);
+    let _ = child.wait();
+}
+
+/// Sends SIGTERM to each group, waits up to [`KILL_GRACE`] for them to exit, then
+/// sends SIGKILL to any that are left.
+fn terminate_groups(groups: &[i32]) {
+    for &pgid in groups {
+        signal_group(pgid, libc::SIGTERM);
+    }
+    let deadline = Instant::now() + KILL_GRACE;
+    while Instant::now() < deadline && groups.iter().any(|&pgid| group_alive(pgid)) {
+        thread::sleep(POLL_INTERVAL);
+    }
+    for &pgid in groups.iter().filter(|&&pgid| group_alive(pgid)) {
+        signal_group(pgid, libc::SIGKILL);
+    }
+}
+
+fn signal_group(pgid: i32, signal: libc::c_int) {
+    // SAFETY: kill(2) with a negative pid only signals that process group.
+    unsafe {
+        libc::kill(-pgid, signal);
+    }
+}
+
+fn group_alive(pgid: i32) -> bool {
+    // SAFETY: signal 0 performs the existence check without delivering anything.
+    unsafe { libc::kill(-pgid, 0) == 0 }
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    on_line: Option<InstallerOutputFn>,
+) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let Some(mut pipe) = pipe else {
+            return buf;
+        };
+        let Some(on_line) = on_line else {
+            let _ = pipe.read_to_end(&mut buf);
+            return buf;
+        };
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end();
+            if !text.is_empty() {
+                on_line(text);
+            }
+            buf.append(&mut line);
+        }
+        buf
+    })
+}
+
+fn cancelled_error(context: &str) -> SpsError {
+    SpsError::CommandExecError(format!("{context} was cancelled"))
+}
@@ -0,0 +1,121 @@
+// sps-core/src/check/diff.rs
+//! Records which files an installed package consists of, and compares two records.
+//!
+//! A snapshot maps every file under the package's install roots to a SHA-256 of its
+//! contents, keyed by the path relative to its root so that two versions of the same
+//! package line up. Taking one before a reinstall or upgrade and one afterwards shows
+//! what the operation changed on disk.
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use sps_common::config::Config;
+use sps_common::error::Result;
+use tracing::debug;
+use walkdir::WalkDir;
+
+use super::installed::{InstalledPackageInfo, PackageType};
+
+/// sps's own bookkeeping files; they are rewritten on every install, so listing them
+/// as changed would only add noise.
+const BOOKKEEPING_FILES: &[&str] = &[
+    "INSTALL_RECEIPT.json",
+    "INSTALL_MANIFEST.json",
+    "CASK_INSTALL_MANIFEST.json",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct InstallSnapshot {
+    pub version: String,
+    /// Relative path -> hex SHA-256 of the contents (or of the target, for symlinks).
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+}
+
+impl InstallDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Hashes every file of an installed package: the keg for a formula, the Caskroom
+/// entry and the private store copy for a cask.
+pub fn snapshot(info: &InstalledPackageInfo, config: &Config) -> Result<InstallSnapshot> {
+    let mut roots = vec![info.path.clone()];
+    if info.pkg_type == PackageType::Cask {
+        roots.push(config.cask_store_version_path(&info.name, &info.version));
+    }
+
+    let mut files = BTreeMap::new();
+    for root in roots.iter().filter(|root| root.is_dir()) {
+        debug!("Snapshotting {} for {}", root.display(), info.name);
+        files.extend(hash_tree(root)?);
+    }
+    Ok(InstallSnapshot {
+        version: info.version.clone(),
+        files,
+    })
+}
+
+/// Lists files only in `after` (added), only in `before` (removed), and in both with
+/// different contents (changed), each sorted by path.
+pub fn diff_snapshots(before: &InstallSnapshot, after: &InstallSnapshot) -> InstallDiff {
+    let mut diff = InstallDiff::default();
+    for (path, old_hash) in &before.files {
+        match after.files.get(path) {
+            None => diff.removed.push(path.clone()),
+            Some(new_hash) if new_hash != old_hash => diff.changed.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.added = after
+        .files
+        .keys()
+        .filter(|path| !before.files.contains_key(*path))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Hashes every file and symlink under `root` except sps's bookkeeping files, keyed by
+/// the path relative to `root`.
+pub fn hash_tree(root: &Path) -> Result<BTreeMap<PathBuf, String>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(root).min_depth(1) {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if entry.depth() == 1 && BOOKKEEPING_FILES.contains(&&*name) {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(entry.path())
+            .to_path_buf();
+        files.insert(relative, hash_entry(entry.path(), entry.path_is_symlink())?);
+    }
+    Ok(files)
+}
+
+pub(crate) fn hash_entry(path: &Path, is_symlink: bool) -> Result<String> {
+    let mut hasher = Sha256::new();
+    if is_symlink {
+        // Compare where the link points; a target inside a root is hashed on its own.
+        hasher.update(b"symlink:");
+        hasher.update(fs::read_link(path)?.to_string_lossy().as_bytes());
+    } else {
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,676 @@
+// sps-core/src/check/doctor.rs
+//! Detects, and optionally repairs, inconsistencies in the sps prefix.
+//!
+//! `diagnose` only reads; `fix` repairs one issue at a time and appends what it did
+//! to `doctor.log` in the logs directory so each change can be traced or undone.
+//!
+//! `run_checks` covers the environment around the prefix: problems that need the
+//! user to act (install a tool, change PATH, fix ownership) rather than an automatic
+//! repair. Each check is a plain function listed in `ENVIRONMENT_CHECKS`.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::formulary::Formulary;
+use sps_common::keg::KegRegistry;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::install::bottle::link;
+use crate::install::cask::CaskInstallManifest;
+use crate::pipeline::journal::{self, JournalRecord};
+use crate::uninstall;
+
+/// Prefix directories that hold links into the Cellar.
+const LINKED_PREFIX_DIRS: &[&str] = &[
+    "bin",
+    "sbin",
+    "lib",
+    "include",
+    "share",
+    "etc",
+    "Frameworks",
+];
+const CASK_MANIFEST_FILENAME: &str = "CASK_INSTALL_MANIFEST.json";
+const DOCTOR_LOG_FILENAME: &str = "doctor.log";
+const RECEIPT_FILENAME: &str = "INSTALL_RECEIPT.json";
+/// Tools a source build may call directly.
+const BUILD_TOOLS: &[&str] = &["cc", "make", "git"];
+const LARGE_CACHE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    /// How to resolve a warning or failure.
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+type EnvironmentCheck = fn(&Config) -> CheckResult;
+
+/// Run in order by `run_checks`; add new checks here.
+const ENVIRONMENT_CHECKS: &[EnvironmentCheck] = &[
+    check_prefix_writable,
+    check_prefix_convention,
+    check_bin_dir_on_path,
+    check_build_tools,
+    check_opt_links,
+    check_orphaned_kegs,
+    check_cache_size,
+];
+
+#[derive(Debug, Clone)]
+pub enum DoctorIssue {
+    /// A symlink in the prefix whose Cellar/opt target no longer exists.
+    BrokenLink { link: PathBuf, target: PathBuf },
+    /// A temporary `.download` file left behind by an interrupted download.
+    OrphanedDownload { path: PathBuf },
+    /// A cask manifest whose `cask_store_path` points at nothing.
+    StaleCaskStorePath {
+        manifest: PathBuf,
+        recorded: String,
+        replacement: Option<PathBuf>,
+    },
+    /// The newest installed keg of a formula has no `opt` link.
+    MissingLinks { name: String, keg: PathBuf },
+    /// A pipeline job that failed, or whose process ended before it committed, per the
+    /// install journal.
+    InterruptedInstall { record: JournalRecord },
+}
+
+impl fmt::Display for DoctorIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BrokenLink { link, target } => write!(
+                f,
+                "broken symlink {} -> {}",
+                link.display(),
+                target.display()
+            ),
+            Self::OrphanedDownload { path } => {
+                write!(f, "leftover partial download {}", path.display())
+            }
+            Self::StaleCaskStorePath {
+                manifest, recorded, ..
+            } => write!(
+                f,
+                "{} records missing cask_store_path {}",
+                manifest.display(),
+                recorded
+            ),
+            Self::MissingLinks { name, keg } => {
+                write!(
+                    f,
+                    "{} ({}) is installed but not linked",
+                    name,
+                    keg.display()
+                )
+            }
+            Self::InterruptedInstall { record } if record.owns_path => write!(
+                f,
+                "interrupted {} of {} {} (last step: {}) may have left {}",
+                record.action,
+                record.target,
+                record.version,
+                record.step,
+                record.path.display()
+            ),
+            Self::InterruptedInstall { record } => write!(
+                f,
+                "interrupted {} of {} {} (last step: {}) did not finish; {} existed before it started",
+                record.action,
+                record.target,
+                record.version,
+                record.step,
+                record.path.display()
+            ),
+        }
+    }
+}
+
+impl DoctorIssue {
+    /// What `fix` would do, phrased for a dry run.
+    pub fn fix_description(&self) -> String {
+        match self {
+            Self::BrokenLink { link, .. } => format!("remove {}", link.display()),
+            Self::OrphanedDownload { path } => format!("delete {}", path.display()),
+            Self::StaleCaskStorePath {
+                manifest,
+                replacement,
+                ..
+            } => match replacement {
+                Some(path) => format!("point {} at {}", manifest.display(), path.display()),
+                None => format!("clear cask_store_path in {}", manifest.display()),
+            },
+            Self::MissingLinks { name, .. } => format!("relink {name}"),
+            Self::InterruptedInstall { record } if record.owns_path => format!(
+                "remove the partial {} of {} at {}",
+                record.action,
+                record.target,
+                record.path.display()
+            ),
+            Self::InterruptedInstall { record } => format!(
+                "close the journal entry of {} and leave {} in place",
+                record.target,
+                record.path.display()
+            ),
+        }
+    }
+}
+
+/// Scans the prefix for every issue `fix` knows how to repair.
+pub fn diagnose(config: &Config) -> Result<Vec<DoctorIssue>> {
+    let mut issues = broken_links(config);
+    issues.extend(orphaned_downloads(config));
+    issues.extend(stale_cask_store_paths(config));
+    issues.extend(missing_links(config)?);
+    issues.extend(
+        journal::incomplete_transactions(config)
+            .into_iter()
+            .map(|record| DoctorIssue::InterruptedInstall { record }),
+    );
+    Ok(issues)
+}
+
+/// Runs every environment check. A `Fail` means sps cannot work properly until the
+/// user acts; a `Warn` only affects some operations.
+pub fn run_checks(config: &Config) -> Vec<CheckResult> {
+    ENVIRONMENT_CHECKS
+        .iter()
+        .map(|check| check(config))
+        .collect()
+}
+
+/// Repairs a single issue and records the change in the doctor log.
+pub fn fix(issue: &DoctorIssue, config: &Config) -> Result<()> {
+    match issue {
+        DoctorIssue::BrokenLink { link, target } => {
+            fs::remove_file(link)?;
+            log_fix(
+                config,
+                &format!(
+                    "removed broken link {} -> {} (undo: ln -s {} {})",
+                    link.display(),
+                    target.display(),
+                    target.display(),
+                    link.display()
+                ),
+            );
+        }
+        DoctorIssue::OrphanedDownload { path } => {
+            fs::remove_file(path)?;
+            log_fix(
+                config,
+                &format!("deleted partial download {}", path.display()),
+            );
+        }
+        DoctorIssue::StaleCaskStorePath {
+            manifest,
+            recorded,
+            replacement,
+        } => {
+            let raw = fs::read_to_string(manifest)?;
+            let mut parsed: CaskInstallManifest =
+                serde_json::from_str(&raw).map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+            let backup = manifest.with_extension("json.bak");
+            fs::write(&backup, &raw)?;
+            parsed.cask_store_path = replacement
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string());
+            let json = serde_json::to_string_pretty(&parsed)
+                .map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+            fs::write(manifest, json)?;
+            log_fix(
+                config,
+                &format!(
+                    "rewrote cask_store_path in {} from {} to {} (backup: {})",
+                    manifest.display(),
+                    recorded,
+                    replacement
+                        .as_ref()
+                        .map_or_else(|| "<none>".to_string(), |p| p.display().to_string()),
+                    backup.display()
+                ),
+            );
+        }
+        DoctorIssue::MissingLinks { name, keg } => {
+            let formula = Formulary::new(config.clone()).load_formula(name)?;
+            link::link_formula_artifacts(&formula, keg, config)?;
+            log_fix(
+                config,
+                &format!(
+                    "relinked {} from {} (links listed in {})",
+                    name,
+                    keg.display(),
+                    keg.join("INSTALL_MANIFEST.json").display()
+                ),
+            );
+        }
+        DoctorIssue::InterruptedInstall { record } if !record.owns_path => {
+            journal::mark_rolled_back(record, config)?;
+            log_fix(
+                config,
+                &format!(
+                    "closed the unfinished {} of {} {} (interrupted after: {}); left {} in place \
+                     because it existed before the {} started",
+                    record.action,
+                    record.target,
+                    record.version,
+                    record.step,
+                    record.path.display(),
+                    record.action
+                ),
+            );
+        }
+        DoctorIssue::InterruptedInstall { record } => {
+            remove_partial_install(record, config)?;
+            journal::mark_rolled_back(record, config)?;
+            log_fix(
+                config,
+                &format!(
+                    "removed partial {} of {} {} at {} (interrupted after: {}); reinstall it to restore",
+                    record.action,
+                    record.target,
+                    record.version,
+                    record.path.display(),
+                    record.step
+                ),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Removes what an interrupted job left in the prefix: a formula keg and the links into
+/// it, or a cask's recorded artifacts and Caskroom version directory. Only called for a
+/// path the job created itself.
+fn remove_partial_install(record: &JournalRecord, config: &Config) -> Result<()> {
+    if !record.owns_path {
+        return Err(SpsError::Generic(format!(
+            "Refusing to remove {}: it existed before the {} of {} started",
+            record.path.display(),
+            record.action,
+            record.target
+        )));
+    }
+    let allowed_root = if record.is_cask {
+        config.cask_room_dir()
+    } else {
+        config.cellar_dir()
+    };
+    // The path comes from a file on disk; never remove anything outside the Cellar or
+    // Caskroom, nor either of them as a whole.
+    if !record.path.starts_with(&allowed_root) || record.path.parent() == Some(&allowed_root) {
+        return Err(SpsError::Generic(format!(
+            "Refusing to remove {}: not a version directory in {}",
+            record.path.display(),
+            allowed_root.display()
+        )));
+    }
+    if record.is_cask {
+        let manifest = record.path.join(CASK_MANIFEST_FILENAME);
+        if let Some(parsed) = fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<CaskInstallManifest>(&raw).ok())
+        {
+            let failed = uninstall::rollback_cask_artifacts(&parsed.artifacts, config);
+            if !failed.is_empty() {
+                warn!(
+                    "Could not remove some artifacts of {}: {}",
+                    record.target,
+                    failed.join(", ")
+                );
+            }
+        }
+    } else {
+        link::unlink_formula_artifacts(&record.target, &record.version, config)?;
+    }
+    if record.path.symlink_metadata().is_ok() {
+        fs::remove_dir_all(&record.path)?;
+    }
+    Ok(())
+}
+
+fn broken_links(config: &Config) -> Vec<DoctorIssue> {
+    let cellar = config.cellar_dir();
+    let opt = config.opt_dir();
+    let mut roots = vec![opt.clone()];
+    roots.extend(LINKED_PREFIX_DIRS.iter().map(|d| config.sps_root().join(d)));
+
+    let mut issues = Vec::new();
+    for root in roots.iter().filter(|r| r.is_dir()) {
+        for entry in WalkDir::new(root).min_depth(1).into_iter().flatten() {
+            if !entry.path_is_symlink() || entry.path().exists() {
+                continue;
+            }
+            let Ok(raw_target) = fs::read_link(entry.path()) else {
+                continue;
+            };
+            let target = match entry.path().parent() {
+                Some(parent) if raw_target.is_relative() => parent.join(&raw_target),
+                _ => raw_target.clone(),
+            };
+            let target = normalize(&target);
+            // Only links into sps-managed trees; anything else is not ours to judge.
+            if target.starts_with(&cellar) || target.starts_with(&opt) {
+                issues.push(DoctorIssue::BrokenLink {
+                    link: entry.into_path(),
+                    target: raw_target,
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn orphaned_downloads(config: &Config) -> Vec<DoctorIssue> {
+    let cache_dir = config.cache_dir();
+    if !cache_dir.is_dir() {
+        return Vec::new();
+    }
+    WalkDir::new(cache_dir)
+        .min_depth(1)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            name.starts_with('.') && name.ends_with(".download")
+        })
+        .map(|entry| DoctorIssue::OrphanedDownload {
+            path: entry.into_path(),
+        })
+        .collect()
+}
+
+fn stale_cask_store_paths(config: &Config) -> Vec<DoctorIssue> {
+    let cask_room = config.cask_room_dir();
+    let mut issues = Vec::new();
+    for entry in WalkDir::new(&cask_room)
+        .min_depth(3)
+        .max_depth(3)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_name() == CASK_MANIFEST_FILENAME)
+    {
+        let manifest_path = entry.into_path();
+        let manifest: CaskInstallManifest = match fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+        {
+            Some(m) => m,
+            None => {
+                debug!("Skipping unreadable manifest {}", manifest_path.display());
+                continue;
+            }
+        };
+        let Some(recorded) = manifest.cask_store_path.clone() else {
+            continue;
+        };
+        if !manifest.is_installed || Path::new(&recorded).exists() {
+            continue;
+        }
+        let replacement = manifest
+            .primary_app_file_name
+            .as_deref()
+            .map(|app| config.cask_store_app_path(&manifest.token, &manifest.version, app))
+            .filter(|p| p.exists());
+        issues.push(DoctorIssue::StaleCaskStorePath {
+            manifest: manifest_path,
+            recorded,
+            replacement,
+        });
+    }
+    issues
+}
+
+fn missing_links(config: &Config) -> Result<Vec<DoctorIssue>> {
+    let registry = KegRegistry::new(config.clone());
+    let mut names: Vec<String> = registry
+        .list_installed_kegs()?
+        .into_iter()
+        .map(|keg| keg.name)
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let mut issues = Vec::new();
+    for name in names {
+        // A dangling opt link counts as missing; it is reported as broken too.
+        if config.formula_opt_path(&name).exists() {
+            continue;
+        }
+        if let Some(keg) = registry.get_installed_keg(&name)? {
+            issues.push(DoctorIssue::MissingLinks {
+                name,
+                keg: keg.path,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+fn check_prefix_writable(config: &Config) -> CheckResult {
+    const NAME: &str = "prefix";
+    let root = config.sps_root();
+    if !root.is_dir() {
+        return CheckResult::fail(
+            NAME,
+            format!("{} does not exist", root.display()),
+            format!("Run `{}` to create it", config.init_command()),
+        );
+    }
+    match tempfile::NamedTempFile::new_in(root) {
+        Ok(_) => CheckResult::pass(NAME, format!("{} is writable", root.display())),
+        Err(e) => CheckResult::fail(
+            NAME,
+            format!("{} is not writable: {}", root.display(), e),
+            format!(
+                "Fix its ownership or run `{} --force`",
+                config.init_command()
+            ),
+        ),
+    }
+}
+
+fn check_prefix_convention(config: &Config) -> CheckResult {
+    const NAME: &str = "prefix location";
+    match super::prefix::prefix_arch_mismatch(config) {
+        None => CheckResult::pass(NAME, "prefix matches the bottle build prefix"),
+        Some(mismatch) => CheckResult::warn(
+            NAME,
+            format!(
+                "{} is not the {} prefix {}",
+                mismatch.configured.display(),
+                mismatch.arch,
+                mismatch.expected
+            ),
+            format!(
+                "Bottles are relocated when poured here and some may break; set HOMEBREW_PREFIX to {}",
+                mismatch.expected
+            ),
+        ),
+    }
+}
+
+fn check_bin_dir_on_path(config: &Config) -> CheckResult {
+    const NAME: &str = "PATH";
+    let bin_dir = config.bin_dir();
+    let on_path = std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir == bin_dir));
+    if on_path {
+        CheckResult::pass(NAME, format!("{} is on PATH", bin_dir.display()))
+    } else {
+        CheckResult::warn(
+            NAME,
+            format!("{} is not on PATH", bin_dir.display()),
+            format!(
+                "Add `export PATH=\"{}:$PATH\"` to your shell profile",
+                bin_dir.display()
+            ),
+        )
+    }
+}
+
+fn check_build_tools(_config: &Config) -> CheckResult {
+    const NAME: &str = "build tools";
+    let missing: Vec<&str> = BUILD_TOOLS
+        .iter()
+        .copied()
+        .filter(|tool| which::which(tool).is_err())
+        .collect();
+    if missing.is_empty() {
+        return CheckResult::pass(NAME, format!("found {}", BUILD_TOOLS.join(", ")));
+    }
+    let hint = if cfg!(target_os = "macos") {
+        "Install the Xcode Command Line Tools with `xcode-select --install`"
+    } else {
+        "Install a C toolchain, make and git with your system package manager"
+    };
+    CheckResult::warn(
+        NAME,
+        format!("{} not found; source builds will fail", missing.join(", ")),
+        hint,
+    )
+}
+
+fn check_opt_links(config: &Config) -> CheckResult {
+    const NAME: &str = "opt links";
+    let opt = config.opt_dir();
+    let dangling = broken_links(config)
+        .into_iter()
+        .filter(
+            |issue| matches!(issue, DoctorIssue::BrokenLink { link, .. } if link.starts_with(&opt)),
+        )
+        .count();
+    if dangling == 0 {
+        CheckResult::pass(NAME, "no dangling links in opt")
+    } else {
+        CheckResult::fail(
+            NAME,
+            format!("{dangling} dangling link(s) in {}", opt.display()),
+            "Run `sps doctor --fix` to remove them, then reinstall the affected formulae",
+        )
+    }
+}
+
+fn check_orphaned_kegs(config: &Config) -> CheckResult {
+    const NAME: &str = "kegs";
+    let kegs = match KegRegistry::new(config.clone()).list_installed_kegs() {
+        Ok(kegs) => kegs,
+        Err(e) => {
+            return CheckResult::fail(
+                NAME,
+                format!("could not read the Cellar: {e}"),
+                format!("Check the permissions of {}", config.cellar_dir().display()),
+            )
+        }
+    };
+    let orphaned: Vec<String> = kegs
+        .iter()
+        .filter(|keg| !keg.path.join(RECEIPT_FILENAME).exists())
+        .map(|keg| format!("{} {}", keg.name, keg.version_str))
+        .collect();
+    if orphaned.is_empty() {
+        CheckResult::pass(NAME, format!("{} keg(s) with install receipts", kegs.len()))
+    } else {
+        CheckResult::warn(
+            NAME,
+            format!("no install receipt for {}", orphaned.join(", ")),
+            "These are likely left over from interrupted installs; `sps reinstall` or `sps uninstall` them",
+        )
+    }
+}
+
+fn check_cache_size(config: &Config) -> CheckResult {
+    const NAME: &str = "cache";
+    let cache_dir = config.cache_dir();
+    let bytes: u64 = WalkDir::new(&cache_dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|meta| meta.len())
+        .sum();
+    let mib = bytes / (1024 * 1024);
+    if bytes < LARGE_CACHE_BYTES {
+        CheckResult::pass(NAME, format!("{} uses {} MiB", cache_dir.display(), mib))
+    } else {
+        CheckResult::warn(
+            NAME,
+            format!("{} uses {} MiB", cache_dir.display(), mib),
+            "Run `sps cleanup` to remove old downloads",
+        )
+    }
+}
+
+/// Resolves `.` and `..` components without touching the filesystem, since the
+/// target of a broken link cannot be canonicalized.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                out.pop();
+            }
+            std::path::Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn log_fix(config: &Config, message: &str) {
+    debug!("doctor: {}", message);
+    let log_dir = config.logs_dir();
+    let result = fs::create_dir_all(&log_dir).and_then(|_| {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_dir.join(DOCTOR_LOG_FILENAME))?;
+        writeln!(file, "{} {}", chrono::Utc::now().to_rfc3339(), message)
+    });
+    if let Err(e) = result {
+        warn!("Could not write doctor log: {}", e);
+    }
+}
@@ -1,5 +1,10 @@
+pub mod diff;
+pub mod doctor;
 pub mod installed;
+pub mod pinned;
+pub mod prefix;
 pub mod update;
+pub mod verify;
 
 pub use installed::{InstalledPackageInfo, PackageType};
 pub use update::UpdateInfo;
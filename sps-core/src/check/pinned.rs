@@ -0,0 +1,76 @@
+// sps-core/src/check/pinned.rs
+//! Reads and writes the set of pinned package names.
+//!
+//! Pinned packages are stored one name per line in the `pinned` file under
+//! `config.state_dir()`. Pinned packages are skipped by `sps upgrade --all`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::sync::Arc;
+
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use tracing::debug;
+
+/// Loads the set of pinned package names. A missing pin file yields an empty set.
+pub fn get_pinned_packages(config: &Config) -> Result<BTreeSet<String>> {
+    let pinned_path = config.pinned_file_path();
+    if !pinned_path.is_file() {
+        debug!(
+            "No pinned file at {}, nothing is pinned.",
+            pinned_path.display()
+        );
+        return Ok(BTreeSet::new());
+    }
+    let content = fs::read_to_string(&pinned_path).map_err(|e| SpsError::Io(Arc::new(e)))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Returns true if `name` is currently pinned.
+pub fn is_pinned(name: &str, config: &Config) -> Result<bool> {
+    Ok(get_pinned_packages(config)?.contains(name))
+}
+
+/// Adds `name` to the pinned set. Returns false if it was already pinned.
+pub fn pin_package(name: &str, config: &Config) -> Result<bool> {
+    let mut pinned = get_pinned_packages(config)?;
+    if !pinned.insert(name.to_string()) {
+        return Ok(false);
+    }
+    write_pinned_packages(&pinned, config)?;
+    Ok(true)
+}
+
+/// Removes `name` from the pinned set. Returns false if it was not pinned.
+pub fn unpin_package(name: &str, config: &Config) -> Result<bool> {
+    let mut pinned = get_pinned_packages(config)?;
+    if !pinned.remove(name) {
+        return Ok(false);
+    }
+    write_pinned_packages(&pinned, config)?;
+    Ok(true)
+}
+
+fn write_pinned_packages(pinned: &BTreeSet<String>, config: &Config) -> Result<()> {
+    let pinned_path = config.pinned_file_path();
+    if let Some(parent) = pinned_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| SpsError::Io(Arc::new(e)))?;
+    }
+    let mut content = String::new();
+    for name in pinned {
+        content.push_str(name);
+        content.push('\n');
+    }
+    fs::write(&pinned_path, content).map_err(|e| SpsError::Io(Arc::new(e)))?;
+    debug!(
+        "Wrote {} pinned package(s) to {}",
+        pinned.len(),
+        pinned_path.display()
+    );
+    Ok(())
+}
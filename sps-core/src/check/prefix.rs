@@ -0,0 +1,80 @@
+// sps-core/src/check/prefix.rs
+//! Checks the configured prefix against the macOS convention for the CPU architecture.
+//!
+//! Bottles are built for `/opt/homebrew` on Apple Silicon and `/usr/local` on Intel.
+//! sps rewrites the placeholder prefix when pouring, but paths compiled into binaries
+//! cannot always be relocated, so a bottle poured elsewhere can fail at runtime.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use sps_common::config::Config;
+
+const ARM64_PREFIX: &str = "/opt/homebrew";
+const X86_64_PREFIX: &str = "/usr/local";
+
+#[derive(Debug, Clone)]
+pub struct PrefixMismatch {
+    pub configured: PathBuf,
+    pub expected: &'static str,
+    pub arch: &'static str,
+    /// The configured prefix is the other architecture's convention, which usually
+    /// means an Intel setup was migrated or sps runs under Rosetta.
+    pub other_arch_prefix: bool,
+}
+
+impl fmt::Display for PrefixMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sps prefix {} does not match the {} convention {}",
+            self.configured.display(),
+            self.arch,
+            self.expected
+        )?;
+        if self.other_arch_prefix {
+            write!(
+                f,
+                " (it is the prefix used on the other architecture; check whether this shell runs under Rosetta)"
+            )?;
+        }
+        write!(
+            f,
+            ". Bottles are built for {} and have to be relocated when poured elsewhere; \
+             binaries with hard-coded paths may then break. Set HOMEBREW_PREFIX to {} or \
+             use --build-from-source for affected formulae.",
+            self.expected, self.expected
+        )
+    }
+}
+
+/// Returns the mismatch if the prefix differs from the architecture's convention.
+/// Always `None` off macOS, where there is no such convention.
+pub fn prefix_arch_mismatch(config: &Config) -> Option<PrefixMismatch> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let (arch, expected, other) = if cfg!(target_arch = "aarch64") {
+        ("Apple Silicon", ARM64_PREFIX, X86_64_PREFIX)
+    } else {
+        ("Intel", X86_64_PREFIX, ARM64_PREFIX)
+    };
+    let configured = config.sps_root();
+    if same_path(configured, Path::new(expected)) {
+        return None;
+    }
+    Some(PrefixMismatch {
+        configured: configured.to_path_buf(),
+        expected,
+        arch,
+        other_arch_prefix: same_path(configured, Path::new(other)),
+    })
+}
+
+/// Compares through symlinks where possible, e.g. a prefix that links to /opt/homebrew.
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.components().eq(b.components()),
+    }
+}
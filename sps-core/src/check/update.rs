@@ -30,7 +30,7 @@ pub struct UpdateInfo {
 
 /// Ensures that the raw JSON data for formulas and casks exists in the main disk cache,
 /// fetching from the API if necessary.
-async fn ensure_api_data_cached(cache: &Cache) -> Result<()> {
+async fn ensure_api_data_cached(cache: &Cache, config: &Config) -> Result<()> {
     let formula_check = cache.load_raw("formula.json");
     let cask_check = cache.load_raw("cask.json");
 
@@ -53,12 +53,12 @@ async fn ensure_api_data_cached(cache: &Cache) -> Result<()> {
             tracing::debug!("Populating missing formula.json and cask.json from API...");
             let (formula_res, cask_res) = tokio::join!(
                 async {
-                    let data = api::fetch_all_formulas().await?;
+                    let data = api::fetch_all_formulas(config).await?;
                     cache.store_raw("formula.json", &data)?;
                     Ok::<(), SpsError>(())
                 },
                 async {
-                    let data = api::fetch_all_casks().await?;
+                    let data = api::fetch_all_casks(config).await?;
                     cache.store_raw("cask.json", &data)?;
                     Ok::<(), SpsError>(())
                 }
@@ -69,13 +69,13 @@ async fn ensure_api_data_cached(cache: &Cache) -> Result<()> {
         }
         (true, false) => {
             tracing::debug!("Populating missing formula.json from API...");
-            let data = api::fetch_all_formulas().await?;
+            let data = api::fetch_all_formulas(config).await?;
             cache.store_raw("formula.json", &data)?;
             tracing::debug!("Formula.json populated from API.");
         }
         (false, true) => {
             tracing::debug!("Populating missing cask.json from API...");
-            let data = api::fetch_all_casks().await?;
+            let data = api::fetch_all_casks(config).await?;
             cache.store_raw("cask.json", &data)?;
             tracing::debug!("Cask.json populated from API.");
         }
@@ -92,7 +92,7 @@ pub async fn check_for_updates(
     config: &Config,
 ) -> Result<Vec<UpdateInfo>> {
     // 1. Ensure the underlying JSON files in the main cache are populated.
-    ensure_api_data_cached(cache)
+    ensure_api_data_cached(cache, config)
         .await
         .map_err(|e| {
             tracing::error!(
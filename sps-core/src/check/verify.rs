@@ -0,0 +1,205 @@
+// sps-core/src/check/verify.rs
+//! Checks installed packages against what was recorded when they were installed.
+//!
+//! A keg's receipt lists every file of the keg with its SHA-256 (see
+//! [`crate::install::bottle::write_receipt`]) and its install manifest lists the links
+//! and wrappers put into the prefix for it; a cask's install manifest lists its app
+//! bundles and links. Anything recorded that has gone missing, a link whose target has
+//! gone, and, when asked for, a keg file whose contents changed, is a discrepancy.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sps_common::config::Config;
+use sps_common::error::Result;
+use sps_common::model::artifact::InstalledArtifact;
+use tracing::debug;
+
+use super::diff;
+use super::installed::{InstalledPackageInfo, PackageType};
+use crate::install::bottle::link;
+use crate::install::cask::CaskInstallManifest;
+
+/// The receipt key listing the keg's files and their SHA-256.
+pub const RECORDED_FILES_KEY: &str = "files";
+
+/// Something recorded at install time that no longer matches the disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// The receipt or install manifest itself is missing or unreadable.
+    MissingRecord(PathBuf),
+    /// A file recorded in the keg's receipt is gone.
+    MissingFile(PathBuf),
+    /// A keg file whose contents no longer match the recorded hash.
+    ModifiedFile(PathBuf),
+    /// A link or wrapper recorded for the package is gone.
+    MissingLink(PathBuf),
+    /// A link or wrapper whose target is gone.
+    BrokenLink { link: PathBuf, target: PathBuf },
+    /// An app bundle or other artifact recorded for a cask is gone.
+    MissingArtifact(PathBuf),
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingRecord(path) => write!(f, "missing or unreadable {}", path.display()),
+            Self::MissingFile(path) => write!(f, "missing file {}", path.display()),
+            Self::ModifiedFile(path) => write!(f, "modified file {}", path.display()),
+            Self::MissingLink(path) => write!(f, "missing link {}", path.display()),
+            Self::BrokenLink { link, target } => {
+                write!(f, "broken link {} -> {}", link.display(), target.display())
+            }
+            Self::MissingArtifact(path) => write!(f, "missing {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// How many recorded files, links and artifacts were checked.
+    pub checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
+    /// Whether the keg's receipt lists its files; kegs installed before sps recorded
+    /// them only get their links checked.
+    pub files_recorded: bool,
+    /// Whether the keg is the linked version of its formula; the links of an unlinked
+    /// keg are not expected to exist.
+    pub linked: bool,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Verifies an installed package. `checksums` re-hashes the keg files recorded in a
+/// formula's receipt; it has no effect on casks, whose files are not recorded.
+pub fn verify_package(
+    info: &InstalledPackageInfo,
+    config: &Config,
+    checksums: bool,
+) -> Result<VerifyReport> {
+    match info.pkg_type {
+        PackageType::Formula => verify_keg(info, config, checksums),
+        PackageType::Cask => Ok(verify_cask(info)),
+    }
+}
+
+fn verify_keg(
+    info: &InstalledPackageInfo,
+    config: &Config,
+    checksums: bool,
+) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    let keg = &info.path;
+
+    let receipt_path = keg.join("INSTALL_RECEIPT.json");
+    let receipt = fs::read_to_string(&receipt_path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+    let Some(receipt) = receipt else {
+        report
+            .discrepancies
+            .push(Discrepancy::MissingRecord(receipt_path));
+        return Ok(report);
+    };
+    let files: Option<BTreeMap<PathBuf, String>> = receipt
+        .get(RECORDED_FILES_KEY)
+        .and_then(|files| serde_json::from_value(files.clone()).ok());
+    if let Some(files) = files {
+        report.files_recorded = true;
+        debug!(
+            "Checking {} recorded file(s) of {}",
+            files.len(),
+            keg.display()
+        );
+        for (relative, recorded_hash) in files {
+            report.checked += 1;
+            let path = keg.join(&relative);
+            let Ok(metadata) = path.symlink_metadata() else {
+                report.discrepancies.push(Discrepancy::MissingFile(path));
+                continue;
+            };
+            if checksums {
+                let hash = diff::hash_entry(&path, metadata.file_type().is_symlink())?;
+                if hash != recorded_hash {
+                    report.discrepancies.push(Discrepancy::ModifiedFile(path));
+                }
+            }
+        }
+    }
+
+    report.linked = is_linked(info, config);
+    if report.linked {
+        for link_path in link::read_install_manifest(keg) {
+            report.checked += 1;
+            check_link(&link_path, &mut report.discrepancies);
+        }
+    }
+    Ok(report)
+}
+
+/// Whether the formula's `opt` link points into this keg.
+fn is_linked(info: &InstalledPackageInfo, config: &Config) -> bool {
+    let Ok(target) = fs::canonicalize(config.formula_opt_path(&info.name)) else {
+        return false;
+    };
+    fs::canonicalize(&info.path).is_ok_and(|keg| target.starts_with(keg))
+}
+
+fn verify_cask(info: &InstalledPackageInfo) -> VerifyReport {
+    let mut report = VerifyReport {
+        files_recorded: true,
+        linked: true,
+        ..VerifyReport::default()
+    };
+    let manifest_path = info.path.join("CASK_INSTALL_MANIFEST.json");
+    let Some(manifest) = fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<CaskInstallManifest>(&raw).ok())
+    else {
+        report
+            .discrepancies
+            .push(Discrepancy::MissingRecord(manifest_path));
+        return report;
+    };
+    for artifact in &manifest.artifacts {
+        match artifact {
+            InstalledArtifact::AppBundle { path } => {
+                report.checked += 1;
+                if path.symlink_metadata().is_err() {
+                    report
+                        .discrepancies
+                        .push(Discrepancy::MissingArtifact(path.clone()));
+                }
+            }
+            InstalledArtifact::BinaryLink { link_path, .. }
+            | InstalledArtifact::ManpageLink { link_path, .. }
+            | InstalledArtifact::CaskroomLink { link_path, .. } => {
+                report.checked += 1;
+                check_link(link_path, &mut report.discrepancies);
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+/// Records a discrepancy if the link or wrapper at `path` is gone or leads nowhere.
+fn check_link(path: &Path, discrepancies: &mut Vec<Discrepancy>) {
+    if path.symlink_metadata().is_err() {
+        discrepancies.push(Discrepancy::MissingLink(path.to_path_buf()));
+        return;
+    }
+    if let Some(target) = link::link_target(path) {
+        if !target.exists() {
+            discrepancies.push(Discrepancy::BrokenLink {
+                link: path.to_path_buf(),
+                target,
+            });
+        }
+    }
+}
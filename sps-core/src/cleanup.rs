@@ -0,0 +1,240 @@
+// sps-core/src/cleanup.rs
+//! Finds and removes stale cache downloads and superseded keg versions.
+//!
+//! Planning and removal are separate so callers can offer a dry run. Kegs that an
+//! `opt` symlink points at are never candidates, whatever the options.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sps_common::cache::FETCH_MANIFEST_DIR;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::keg::{InstalledKeg, KegRegistry};
+use sps_net::validation::{verified_sidecar_path, VERIFIED_SIDECAR_EXTENSION};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::uninstall::common::remove_filesystem_artifact;
+
+/// Metadata files in the cache root that are index data, not downloads.
+const PROTECTED_CACHE_FILES: &[&str] = &[
+    "formula.json",
+    "cask.json",
+    "formula.json.index",
+    "cask.json.index",
+];
+
+#[derive(Debug, Clone)]
+pub struct CleanupOptions {
+    /// Cached downloads last modified longer ago than this are removed.
+    pub max_age: Duration,
+    /// Remove every cached download regardless of age, and every keg that is not
+    /// the one linked under `opt`.
+    pub prune_all: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CleanupKind {
+    CacheFile,
+    Keg { name: String, version: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct CleanupItem {
+    pub path: PathBuf,
+    pub size: u64,
+    pub kind: CleanupKind,
+}
+
+/// Lists everything `options` allows to be removed, without touching the filesystem.
+pub fn plan_cleanup(config: &Config, options: &CleanupOptions) -> Result<Vec<CleanupItem>> {
+    let mut items = stale_cache_files(&config.cache_dir(), options)?;
+    items.extend(stale_kegs(config, options)?);
+    Ok(items)
+}
+
+/// Removes a single planned item, and the checksum sidecar of a cached file.
+pub fn remove_item(item: &CleanupItem) -> Result<()> {
+    let use_sudo = matches!(item.kind, CleanupKind::Keg { .. });
+    if remove_filesystem_artifact(&item.path, use_sudo) {
+        if item.kind == CleanupKind::CacheFile {
+            let _ = fs::remove_file(verified_sidecar_path(&item.path));
+        }
+        Ok(())
+    } else {
+        Err(SpsError::Generic(format!(
+            "Failed to remove {}",
+            item.path.display()
+        )))
+    }
+}
+
+fn stale_cache_files(cache_dir: &Path, options: &CleanupOptions) -> Result<Vec<CleanupItem>> {
+    let mut items = Vec::new();
+    if !cache_dir.is_dir() {
+        return Ok(items);
+    }
+    let now = SystemTime::now();
+    let manifest_dir = cache_dir.join(FETCH_MANIFEST_DIR);
+    let walker = WalkDir::new(cache_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| entry.path() != manifest_dir);
+    for entry in walker {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Error scanning cache directory: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        // Dot files include downloads still in progress (`.<name>.download`).
+        let name = entry.file_name().to_string_lossy();
+        if name.starts_with('.') || (entry.depth() == 1 && PROTECTED_CACHE_FILES.contains(&&*name))
+        {
+            continue;
+        }
+        // A checksum sidecar goes together with its file; an orphaned one is always stale.
+        let is_orphaned_sidecar = match verified_file_of(entry.path()) {
+            Some(file) if file.is_file() => continue,
+            Some(_) => true,
+            None => false,
+        };
+        let metadata = entry.metadata().map_err(|e| {
+            SpsError::Generic(format!(
+                "Failed to read metadata for {}: {}",
+                entry.path().display(),
+                e
+            ))
+        })?;
+        let is_stale = options.prune_all
+            || is_orphaned_sidecar
+            || metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > options.max_age);
+        if is_stale {
+            items.push(CleanupItem {
+                path: entry.into_path(),
+                size: metadata.len(),
+                kind: CleanupKind::CacheFile,
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// The cached file `path` records a verified checksum for, if `path` is such a sidecar.
+fn verified_file_of(path: &Path) -> Option<PathBuf> {
+    if path.extension()? != VERIFIED_SIDECAR_EXTENSION {
+        return None;
+    }
+    Some(path.with_extension(""))
+}
+
+fn stale_kegs(config: &Config, options: &CleanupOptions) -> Result<Vec<CleanupItem>> {
+    let registry = KegRegistry::new(config.clone());
+    let mut by_name: HashMap<String, Vec<InstalledKeg>> = HashMap::new();
+    for keg in registry.list_installed_kegs()? {
+        by_name.entry(keg.name.clone()).or_default().push(keg);
+    }
+
+    let linked = linked_keg_paths(&config.opt_dir());
+    let mut items = Vec::new();
+    for (name, kegs) in by_name {
+        let latest = registry.get_installed_keg(&name)?.map(|keg| keg.path);
+        let name_is_linked = kegs.iter().any(|keg| is_linked(&keg.path, &linked));
+        for keg in kegs {
+            if is_linked(&keg.path, &linked) {
+                continue;
+            }
+            // Without --prune=all the latest version stays; with it, the latest only
+            // stays when nothing of this formula is linked, so nothing is removed entirely.
+            let is_latest = latest.as_ref() == Some(&keg.path);
+            if is_latest && (!options.prune_all || !name_is_linked) {
+                continue;
+            }
+            items.push(CleanupItem {
+                size: dir_size(&keg.path),
+                path: keg.path,
+                kind: CleanupKind::Keg {
+                    name: name.clone(),
+                    version: keg.version_str,
+                },
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// Canonical targets of every symlink directly under `opt_dir`.
+fn linked_keg_paths(opt_dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = fs::read_dir(opt_dir) else {
+        return HashSet::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_symlink()))
+        .filter_map(|entry| fs::canonicalize(entry.path()).ok())
+        .collect()
+}
+
+fn is_linked(keg_path: &Path, linked: &HashSet<PathBuf>) -> bool {
+    match fs::canonicalize(keg_path) {
+        Ok(canonical) => linked.contains(&canonical),
+        Err(e) => {
+            // Err on the side of keeping kegs we cannot resolve.
+            debug!("Cannot resolve keg path {}: {}", keg_path.display(), e);
+            true
+        }
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_records_and_partial_downloads_are_never_stale() {
+        let cache = tempfile::tempdir().unwrap();
+        let nested = cache.path().join("sources");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(cache.path().join(FETCH_MANIFEST_DIR)).unwrap();
+        let manifest_record = cache
+            .path()
+            .join(FETCH_MANIFEST_DIR)
+            .join("bottle-foo.json");
+        let partial = nested.join(".foo-1.0.tar.gz.download");
+        let download = nested.join("foo-1.0.tar.gz");
+        for file in [&manifest_record, &partial, &download] {
+            fs::write(file, b"x").unwrap();
+        }
+
+        let options = CleanupOptions {
+            max_age: Duration::ZERO,
+            prune_all: true,
+        };
+        let stale: Vec<PathBuf> = stale_cache_files(cache.path(), &options)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.path)
+            .collect();
+        assert_eq!(stale, [download]);
+    }
+}
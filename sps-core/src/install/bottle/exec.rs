@@ -11,23 +11,29 @@ use semver;
 use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::formula::{BottleFileSpec, Formula, FormulaDependencies};
+use sps_common::pipeline::{DownloadProgressFn, ExtractProgressFn};
 use sps_net::oci;
-use sps_net::validation::verify_checksum;
+use sps_net::validation::{
+    error_page_note, verified_sidecar_path, verify_checksum, verify_content_type, verify_size,
+};
 use tempfile::NamedTempFile;
 use tracing::{debug, error, warn};
 use walkdir::WalkDir;
 
-use super::macho;
+use super::macho::{self, MachoPathKind};
 use crate::install::bottle::get_current_platform;
-use crate::install::extract::extract_archive;
+use crate::install::devtools::MACOS_CODENAMES;
+use crate::install::extract::extract_archive_with_link_root;
 
 pub async fn download_bottle(
     formula: &Formula,
     config: &Config,
     client: &Client,
+    progress: Option<DownloadProgressFn>,
+    skip_verify: bool,
 ) -> Result<PathBuf> {
     debug!("Attempting to download bottle for {}", formula.name);
-    let (platform_tag, bottle_file_spec) = get_bottle_for_platform(formula)?;
+    let (platform_tag, bottle_file_spec) = get_bottle_for_platform(formula, config)?;
     debug!(
         "Selected bottle spec for platform '{}': URL={}, SHA256={}",
         platform_tag, bottle_file_spec.url, bottle_file_spec.sha256
@@ -44,12 +50,27 @@ pub async fn download_bottle(
         "{}-{}.{}.bottle.tar.gz",
         formula.name, standard_version_str, platform_tag
     );
-    let cache_dir = config.cache_dir().join("bottles");
+    let cache_dir = config.ensure_cache_dir()?.join("bottles");
     fs::create_dir_all(&cache_dir).map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
     let bottle_cache_path = cache_dir.join(&filename);
+    if skip_verify {
+        warn!(
+            "Checksum verification DISABLED for bottle of {} (--no-verify).",
+            formula.name
+        );
+    }
     if bottle_cache_path.is_file() {
         debug!("Bottle found in cache: {}", bottle_cache_path.display());
-        if !bottle_file_spec.sha256.is_empty() {
+        if skip_verify {
+            verify_bottle_content(formula, &bottle_cache_path)?;
+            return Ok(bottle_cache_path);
+        } else if let Some(Err(e)) = bottle_file_spec
+            .size
+            .map(|size| verify_size(&bottle_cache_path, size))
+        {
+            debug!("Cached bottle is the wrong size: {}. Redownloading.", e);
+            let _ = fs::remove_file(&bottle_cache_path);
+        } else if !bottle_file_spec.sha256.is_empty() {
             match verify_checksum(&bottle_cache_path, &bottle_file_spec.sha256) {
                 Ok(_) => {
                     debug!("Using valid cached bottle: {}", bottle_cache_path.display());
@@ -69,16 +90,20 @@ pub async fn download_bottle(
                 "Using cached bottle without checksum verification (checksum not specified): {}",
                 bottle_cache_path.display()
             );
+            verify_bottle_content(formula, &bottle_cache_path)?;
             return Ok(bottle_cache_path);
         }
     } else {
         debug!("Bottle not found in cache.");
     }
-    let bottle_url_str = &bottle_file_spec.url;
+    let bottle_url = oci::mirrored_bottle_url(&bottle_file_spec.url, config);
+    let bottle_url_str = &bottle_url;
     let registry_domain = config
-        .artifact_domain
+        .oci_registry
         .as_deref()
+        .or(config.artifact_domain.as_deref())
         .unwrap_or(oci::DEFAULT_GHCR_DOMAIN);
+    // A `bottle_domain` mirror is a plain file server and is downloaded over HTTP.
     let is_oci_blob_url = (bottle_url_str.contains("://ghcr.io/")
         || bottle_url_str.contains(registry_domain))
         && bottle_url_str.contains("/blobs/sha256:");
@@ -103,7 +128,8 @@ pub async fn download_bottle(
             &bottle_cache_path,
             config,
             client,
-            expected_digest,
+            if skip_verify { "" } else { expected_digest },
+            progress,
         )
         .await
         {
@@ -113,6 +139,10 @@ pub async fn download_bottle(
                     bottle_cache_path.display()
                 );
             }
+            Err(e @ SpsError::ChecksumMismatch(_)) => {
+                error!("OCI blob from {} is corrupt: {}", bottle_url_str, e);
+                return Err(e);
+            }
             Err(e) => {
                 error!("Failed to download OCI blob from {}: {}", bottle_url_str, e);
                 let _ = fs::remove_file(&bottle_cache_path);
@@ -131,9 +161,15 @@ pub async fn download_bottle(
         match sps_net::http::fetch_formula_source_or_bottle(
             formula.name(),
             bottle_url_str,
-            &bottle_file_spec.sha256,
+            if skip_verify {
+                ""
+            } else {
+                &bottle_file_spec.sha256
+            },
             &[],
+            bottle_file_spec.size,
             config,
+            progress,
         )
         .await
         {
@@ -177,6 +213,10 @@ pub async fn download_bottle(
             bottle_cache_path.display()
         )));
     }
+    verify_bottle_content(formula, &bottle_cache_path)?;
+    if !skip_verify {
+        verify_downloaded_bottle(formula, &platform_tag, bottle_file_spec, &bottle_cache_path)?;
+    }
     debug!(
         "Bottle download successful: {}",
         bottle_cache_path.display()
@@ -184,7 +224,89 @@ pub async fn download_bottle(
     Ok(bottle_cache_path)
 }
 
-pub fn get_bottle_for_platform(formula: &Formula) -> Result<(String, &BottleFileSpec)> {
+/// Checks that a bottle is a gzip archive before anything else reads it, so an error page
+/// saved under the bottle's name fails here with a clear message instead of midway
+/// through extraction. A file that is not one is deleted from the cache.
+fn verify_bottle_content(formula: &Formula, bottle_path: &Path) -> Result<()> {
+    let Err(e) = verify_content_type(bottle_path, "gz") else {
+        return Ok(());
+    };
+    error!(
+        "Bottle for {} is not a gzip archive: {}. Deleting {}",
+        formula.name,
+        e,
+        bottle_path.display()
+    );
+    let reason = error_page_note(bottle_path).unwrap_or("it is not a gzip archive");
+    let _ = fs::remove_file(bottle_path);
+    let _ = fs::remove_file(verified_sidecar_path(bottle_path));
+    Err(SpsError::ValidationError(format!(
+        "The bottle downloaded for {} is unusable: {}. Try again, or check any proxy or \
+         bottle mirror in between.",
+        formula.name, reason
+    )))
+}
+
+/// Checks a freshly downloaded bottle against the size (when published) and sha256 in the
+/// formula's bottle spec for the selected platform tag. On mismatch the cached file is
+/// deleted so the next attempt downloads it again.
+fn verify_downloaded_bottle(
+    formula: &Formula,
+    platform_tag: &str,
+    bottle_file_spec: &BottleFileSpec,
+    bottle_path: &Path,
+) -> Result<()> {
+    if let Some(size) = bottle_file_spec.size {
+        if let Err(e) = verify_size(bottle_path, size) {
+            error!(
+                "Bottle for {} ({}) is truncated or oversized: {}. Deleting {}",
+                formula.name,
+                platform_tag,
+                e,
+                bottle_path.display()
+            );
+            let _ = fs::remove_file(bottle_path);
+            return Err(e);
+        }
+    }
+    if bottle_file_spec.sha256.is_empty() {
+        warn!(
+            "No sha256 in bottle spec for {} ({}); cannot verify {}",
+            formula.name,
+            platform_tag,
+            bottle_path.display()
+        );
+        return Ok(());
+    }
+    match verify_checksum(bottle_path, &bottle_file_spec.sha256) {
+        Ok(()) => {
+            debug!(
+                "Bottle checksum verified for {} ({})",
+                formula.name, platform_tag
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "Bottle checksum verification failed for {} ({}): {}. Deleting {}",
+                formula.name,
+                platform_tag,
+                e,
+                bottle_path.display()
+            );
+            let _ = fs::remove_file(bottle_path);
+            Err(SpsError::ChecksumError(format!(
+                "Bottle for {} ({}) does not match its expected sha256 {}",
+                formula.name, platform_tag, bottle_file_spec.sha256
+            )))
+        }
+    }
+}
+
+pub fn get_bottle_for_platform<'a>(
+    formula: &'a Formula,
+    config: &Config,
+) -> Result<(String, &'a BottleFileSpec)> {
     let stable_spec = formula.bottle.stable.as_ref().ok_or_else(|| {
         SpsError::Generic(format!(
             "Formula '{}' has no stable bottle specification.",
@@ -197,115 +319,98 @@ pub fn get_bottle_for_platform(formula: &Formula) -> Result<(String, &BottleFile
             formula.name
         )));
     }
-    let current_platform = get_current_platform();
+    if let Some(tag) = config.bottle_tag.as_deref() {
+        // A forced tag is used as given; falling back to another bottle would defeat it.
+        return match stable_spec.files.get(tag) {
+            Some(spec) => {
+                debug!("Using bottle tag '{}' from the override", tag);
+                Ok((tag.to_string(), spec))
+            }
+            None => {
+                let mut available: Vec<&str> =
+                    stable_spec.files.keys().map(String::as_str).collect();
+                available.sort_unstable();
+                Err(SpsError::NotFound(format!(
+                    "Formula '{}' has no bottle for the overridden tag '{}' (available: {})",
+                    formula.name,
+                    tag,
+                    available.join(", ")
+                )))
+            }
+        };
+    }
+    let current_platform = get_current_platform(config);
     if current_platform == "unknown" || current_platform.contains("unknown") {
         debug!(
             "Could not reliably determine current platform ('{}'). Bottle selection might be incorrect.",
             current_platform
         );
     }
-    debug!(
-        "Determining bottle for current platform: {}",
-        current_platform
-    );
     debug!(
         "Available bottle platforms in formula spec: {:?}",
         stable_spec.files.keys().cloned().collect::<Vec<_>>()
     );
-    if let Some(spec) = stable_spec.files.get(&current_platform) {
-        debug!(
-            "Found exact bottle match for platform: {}",
-            current_platform
-        );
-        return Ok((current_platform.clone(), spec));
-    }
-    debug!("No exact match found for {}", current_platform);
-    const ARM_MACOS_VERSIONS: &[&str] = &["sequoia", "sonoma", "ventura", "monterey", "big_sur"];
-    const INTEL_MACOS_VERSIONS: &[&str] = &[
-        "sequoia", "sonoma", "ventura", "monterey", "big_sur", "catalina", "mojave",
-    ];
-    if cfg!(target_os = "macos") {
-        if let Some(current_os_name) = current_platform
-            .strip_prefix("arm64_")
-            .or(Some(current_platform.as_str()))
-        {
-            let version_list = if current_platform.starts_with("arm64_") {
-                ARM_MACOS_VERSIONS
-            } else {
-                INTEL_MACOS_VERSIONS
-            };
-            if let Some(current_os_index) = version_list.iter().position(|&v| v == current_os_name)
-            {
-                for target_os_name in version_list.iter().skip(current_os_index) {
-                    let target_tag = if current_platform.starts_with("arm64_") {
-                        format!("arm64_{target_os_name}")
-                    } else {
-                        target_os_name.to_string()
-                    };
-                    if let Some(spec) = stable_spec.files.get(&target_tag) {
-                        debug!(
-                            "No bottle found for exact platform '{}'. Using compatible older bottle '{}'.",
-                            current_platform, target_tag
-                        );
-                        return Ok((target_tag, spec));
-                    }
-                }
+    let candidates = compatible_bottle_tags(&current_platform);
+    for tag in &candidates {
+        if let Some(spec) = stable_spec.files.get(tag) {
+            if *tag == current_platform {
                 debug!(
-                    "Checked compatible older macOS versions ({:?}), no suitable bottle found.",
-                    &version_list[current_os_index..]
+                    "Found exact bottle match for platform: {}",
+                    current_platform
                 );
             } else {
                 debug!(
-                    "Current OS '{}' not found in known macOS version list.",
-                    current_os_name
+                    "No bottle for platform '{}'; falling back to '{}'",
+                    current_platform, tag
                 );
             }
-        } else {
-            debug!(
-                "Could not extract OS name from platform tag '{}'",
-                current_platform
-            );
+            return Ok((tag.clone(), spec));
         }
     }
-    if current_platform.starts_with("arm64_") {
-        if let Some(spec) = stable_spec.files.get("arm64_big_sur") {
-            debug!(
-                "No specific OS bottle found for {}. Falling back to 'arm64_big_sur' bottle.",
-                current_platform
-            );
-            return Ok(("arm64_big_sur".to_string(), spec));
-        }
-        debug!("No 'arm64_big_sur' fallback bottle tag found.");
-    } else if cfg!(target_os = "macos") {
-        if let Some(spec) = stable_spec.files.get("big_sur") {
-            debug!(
-                "No specific OS bottle found for {}. Falling back to 'big_sur' bottle.",
-                current_platform
-            );
-            return Ok(("big_sur".to_string(), spec));
-        }
-        debug!("No 'big_sur' fallback bottle tag found.");
-    }
-    if let Some(spec) = stable_spec.files.get("all") {
-        debug!(
-            "No platform-specific or OS-specific bottle found for {}. Using 'all' platform bottle.",
-            current_platform
-        );
-        return Ok(("all".to_string(), spec));
-    }
-    debug!("No 'all' platform bottle found.");
+    let mut available: Vec<&str> = stable_spec.files.keys().map(String::as_str).collect();
+    available.sort_unstable();
     Err(SpsError::DownloadError(
         formula.name.clone(),
         "".to_string(),
         format!(
-            "No compatible bottle found for platform '{}'. Available: {:?}",
+            "No compatible bottle found for platform '{}' (tried: {}; available: {})",
             current_platform,
-            stable_spec.files.keys().collect::<Vec<_>>()
+            candidates.join(", "),
+            available.join(", ")
         ),
     ))
 }
 
-pub fn install_bottle(bottle_path: &Path, formula: &Formula, config: &Config) -> Result<PathBuf> {
+/// The bottle tags that can run on `platform`, in order of preference: the platform
+/// itself, the same architecture's tags for each older macOS release, then the
+/// architecture-independent `arm64_all` (on Apple Silicon) and `all`.
+fn compatible_bottle_tags(platform: &str) -> Vec<String> {
+    let (prefix, os_name) = match platform.strip_prefix("arm64_") {
+        Some(os_name) => ("arm64_", os_name),
+        None => ("", platform),
+    };
+    let mut tags = vec![platform.to_string()];
+    if let Some(index) = MACOS_CODENAMES.iter().position(|name| *name == os_name) {
+        tags.extend(
+            MACOS_CODENAMES[index + 1..]
+                .iter()
+                .map(|older| format!("{prefix}{older}")),
+        );
+    }
+    if !prefix.is_empty() {
+        tags.push(format!("{prefix}all"));
+    }
+    tags.push("all".to_string());
+    tags
+}
+
+pub fn install_bottle(
+    bottle_path: &Path,
+    formula: &Formula,
+    config: &Config,
+    installed_on_request: bool,
+    progress: Option<ExtractProgressFn>,
+) -> Result<PathBuf> {
     let install_dir = formula.install_prefix(config.cellar_dir().as_path())?;
     if install_dir.exists() {
         debug!(
@@ -350,16 +455,40 @@ pub fn install_bottle(bottle_path: &Path, formula: &Formula, config: &Config) ->
         install_dir.display(),
         strip_components
     );
-    extract_archive(bottle_path, &install_dir, strip_components, "gz")?;
+    // Kegs may link into the shared prefix, but not beyond it.
+    extract_archive_with_link_root(
+        bottle_path,
+        &install_dir,
+        strip_components,
+        "gz",
+        progress,
+        config.sps_root(),
+    )?;
     debug!(
         "Ensuring write permissions for extracted files in {}",
         install_dir.display()
     );
     ensure_write_permissions(&install_dir)?;
-    debug!("Performing bottle relocation in {}", install_dir.display());
-    perform_bottle_relocation(formula, &install_dir, config)?;
+    if config.skip_relocation {
+        warn!(
+            "Not relocating {} (--skip-relocation); paths compiled for the bottle's build \
+             prefix are left as they are",
+            formula.name()
+        );
+    } else {
+        debug!("Performing bottle relocation in {}", install_dir.display());
+        perform_bottle_relocation(formula, &install_dir, config)?;
+    }
     ensure_llvm_symlinks(&install_dir, formula, config)?;
-    crate::install::bottle::write_receipt(formula, &install_dir, "bottle")?;
+    let wrappers = super::link::generate_libexec_wrappers(&install_dir)?;
+    crate::install::bottle::write_receipt(
+        formula,
+        &install_dir,
+        "bottle",
+        installed_on_request,
+        &wrappers,
+        config,
+    )?;
     debug!(
         "Bottle installation complete for {} at {}",
         formula.name(),
@@ -433,6 +562,11 @@ fn perform_bottle_relocation(formula: &Formula, install_dir: &Path, config: &Con
     );
     repl.insert("@@HOMEBREW_LIBRARY@@".into(), library_path_str.to_string());
 
+    for (built, actual) in built_prefix_relocations(formula, config) {
+        debug!("Adding build prefix relocation: {} -> {}", built, actual);
+        repl.insert(built, actual);
+    }
+
     let formula_opt_path = config.formula_opt_path(formula.name());
     let formula_opt_str = formula_opt_path.to_string_lossy();
     let install_dir_str = install_dir.to_string_lossy();
@@ -536,6 +670,7 @@ fn perform_bottle_relocation(formula: &Formula, install_dir: &Path, config: &Con
                     let stderr = String::from_utf8_lossy(&output.stderr);
                     if !stderr.contains("file not found")
                         && !stderr.contains("no LC_LOAD_DYLIB command specifying file")
+                        && !stderr.contains("no LC_RPATH load command with path")
                         && !stderr.contains("is not a Mach-O file")
                         && !stderr.contains("object file format invalid")
                         && !stderr.trim().is_empty()
@@ -692,6 +827,58 @@ fn perform_bottle_relocation(formula: &Formula, install_dir: &Path, config: &Con
     original_relocation_scan_and_patch(formula, install_dir, config, repl)
 }
 
+/// Replacements for a bottle that has its build cellar and prefix compiled in rather
+/// than placeholders, when those differ from ours. Relocatable bottles (`:any`,
+/// `:any_skip_relocation`) and bottles built for our own cellar need none.
+///
+/// Paths are matched with a trailing `/` so that a prefix like `/usr/local` does not
+/// also rewrite unrelated paths that merely start with the same characters.
+/// Both entries are applied in one pass (see [`macho::replace_placeholders`]), so when
+/// our prefix lies inside the built one (`/usr/local/sps` for `/usr/local`), a path
+/// already moved into our cellar is not rewritten a second time by the prefix entry.
+fn built_prefix_relocations(formula: &Formula, config: &Config) -> Vec<(String, String)> {
+    let recorded = match get_bottle_for_platform(formula, config) {
+        Ok((_, spec)) => spec.cellar.clone(),
+        Err(_) => None,
+    };
+    let Some(built_cellar) = recorded.filter(|c| c.starts_with('/')) else {
+        return Vec::new();
+    };
+    let built_cellar = PathBuf::from(built_cellar.trim_end_matches('/'));
+    let actual_cellar = config.cellar_dir();
+    if built_cellar == actual_cellar {
+        return Vec::new();
+    }
+    warn!(
+        "Bottle for {} was built for {}; relocating its hard-coded paths to {}",
+        formula.name(),
+        built_cellar.display(),
+        actual_cellar.display()
+    );
+    let mut relocations = vec![(
+        format!("{}/", built_cellar.display()),
+        format!("{}/", actual_cellar.display()),
+    )];
+    if let Some(built_prefix) = built_cellar.parent().filter(|p| p != &Path::new("/")) {
+        relocations.push((
+            format!("{}/", built_prefix.display()),
+            format!("{}/", config.sps_root().display()),
+        ));
+    }
+    relocations
+}
+
+/// Rewrites the `replacements` paths in every text file and Mach-O binary in `keg`,
+/// the same way a poured bottle is relocated.
+pub(crate) fn relocate_keg_paths(
+    formula: &Formula,
+    keg: &Path,
+    config: &Config,
+    replacements: HashMap<String, String>,
+) -> Result<()> {
+    original_relocation_scan_and_patch(formula, keg, config, replacements)
+}
+
 fn original_relocation_scan_and_patch(
     _formula: &Formula,
     install_dir: &Path,
@@ -781,7 +968,8 @@ fn original_relocation_scan_and_patch(
                 || is_in_exec_dir
                 || path
                     .extension()
-                    .is_some_and(|e| e == "dylib" || e == "so" || e == "bundle"))
+                    .is_some_and(|e| e == "dylib" || e == "so" || e == "bundle")
+                || macho::has_macho_magic(path))
         {
             match macho::patch_macho_file(path, &replacements) {
                 Ok((true, skipped_paths)) => {
@@ -850,8 +1038,12 @@ fn original_relocation_scan_and_patch(
                     path.display()
                 );
                 for skipped in &skipped_paths_for_file {
-                    match apply_install_name_tool_change(&skipped.old_path, &skipped.new_path, path)
-                    {
+                    match apply_install_name_tool_change(
+                        &skipped.old_path,
+                        &skipped.new_path,
+                        skipped.kind,
+                        path,
+                    ) {
                         Ok(()) => {
                             debug!(
                                 "Successfully applied install_name_tool fallback: '{}' -> '{}' in {}",
@@ -897,16 +1089,10 @@ fn original_relocation_scan_and_patch(
             if is_likely_text {
                 // Read the file content as string
                 if let Ok(content) = fs::read_to_string(path) {
-                    let mut new_content = content.clone();
-                    let mut replacements_made = false;
-                    for (placeholder, replacement) in &replacements {
-                        if new_content.contains(placeholder) {
-                            new_content = new_content.replace(placeholder, replacement);
-                            replacements_made = true;
-                        }
-                    }
+                    let relocated = macho::replace_placeholders(&content, &replacements);
                     // Write back only if changes were made
-                    if replacements_made {
+                    if let Some(new_content) = relocated {
+                        debug!("Relocated paths in {}", path.display());
                         match write_text_file_atomic(path, &new_content) {
                             Ok(_) => {
                                 text_replaced_count += 1;
@@ -1008,30 +1194,14 @@ fn original_relocation_scan_and_patch(
     Ok(())
 }
 
+/// Re-signs `target` ad hoc where macOS requires it, see [`macho::needs_adhoc_signing`].
 fn codesign_path(target: &Path) -> Result<()> {
-    debug!("Re‑signing: {}", target.display());
-    let status = StdCommand::new("codesign")
-        .args([
-            "-s",
-            "-",
-            "--force",
-            "--preserve-metadata=identifier,entitlements",
-            target
-                .to_str()
-                .ok_or_else(|| SpsError::Generic("Non‑UTF8 path for codesign".into()))?,
-        ])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map_err(|e| SpsError::Io(Arc::new(e)))?;
-    if !status.success() {
-        return Err(SpsError::CodesignError(format!(
-            "codesign failed for {}",
-            target.display()
-        )));
+    if macho::needs_adhoc_signing() {
+        macho::codesign_adhoc(target)?;
     }
     Ok(())
 }
+
 fn write_text_file_atomic(original_path: &Path, content: &str) -> Result<()> {
     let dir = original_path.parent().ok_or_else(|| {
         SpsError::Generic(format!(
@@ -1266,8 +1436,15 @@ fn ensure_llvm_symlinks(install_dir: &Path, formula: &Formula, config: &Config)
 }
 
 /// Applies a path change using install_name_tool as a fallback for Mach-O files
-/// where the path replacement is too long for direct binary patching.
-fn apply_install_name_tool_change(old_path: &str, new_path: &str, target: &Path) -> Result<()> {
+/// where the path replacement is too long for direct binary patching. `kind` picks
+/// the option: `-change` for dependencies, `-id` for the install name and `-rpath`
+/// for run-path entries.
+fn apply_install_name_tool_change(
+    old_path: &str,
+    new_path: &str,
+    kind: MachoPathKind,
+    target: &Path,
+) -> Result<()> {
     if !target.exists() {
         debug!(
             "Target {} does not exist, skipping install_name_tool fallback.",
@@ -1276,15 +1453,16 @@ fn apply_install_name_tool_change(old_path: &str, new_path: &str, target: &Path)
         return Ok(());
     }
 
-    debug!(
-        "Running install_name_tool -change {} {} {}",
-        old_path,
-        new_path,
-        target.display()
-    );
+    let target_str = target.to_str().unwrap();
+    let args: Vec<&str> = match kind {
+        MachoPathKind::Load => vec!["-change", old_path, new_path, target_str],
+        MachoPathKind::Id => vec!["-id", new_path, target_str],
+        MachoPathKind::Rpath => vec!["-rpath", old_path, new_path, target_str],
+    };
+    debug!("Running install_name_tool {}", args.join(" "));
 
     let output = StdCommand::new("install_name_tool")
-        .args(["-change", old_path, new_path, target.to_str().unwrap()])
+        .args(&args)
         .output()
         .map_err(|e| SpsError::Io(Arc::new(e)))?;
 
@@ -1318,14 +1496,7 @@ fn apply_install_name_tool_change(old_path: &str, new_path: &str, target: &Path)
     }
 
     // Re-sign the binary after making changes (required on Apple Silicon)
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    {
-        debug!(
-            "Re-signing binary after install_name_tool change: {}",
-            target.display()
-        );
-        codesign_path(target)?;
-    }
+    codesign_path(target)?;
 
     debug!(
         "Successfully applied install_name_tool fallback for {} -> {} in {}",
@@ -1,10 +1,12 @@
 // ===== sps-core/src/build/formula/link.rs =====
+use std::collections::HashSet;
+use std::fmt;
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::os::unix::fs as unix_fs;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use serde_json;
 use sps_common::config::Config; // Import Config
@@ -13,13 +15,69 @@ use sps_common::model::formula::Formula;
 use tracing::{debug, error};
 
 const STANDARD_KEG_DIRS: [&str; 6] = ["bin", "lib", "share", "include", "etc", "Frameworks"];
+/// Keg directories whose entries are symlinked individually into the prefix.
+const STANDARD_ARTIFACT_DIRS: [&str; 3] = ["lib", "include", "share"];
+/// Second line of every bin wrapper; tells wrappers apart from other files in the prefix.
+const WRAPPER_MARKER: &str = "# Wrapper script generated by sp";
+
+/// A link `link_formula_artifacts` would create: a symlink, or a wrapper script for
+/// executables.
+#[derive(Debug, Clone)]
+pub struct PlannedLink {
+    pub link: PathBuf,
+    pub target: PathBuf,
+    pub is_wrapper: bool,
+}
+
+/// An entry in the prefix that linking a formula would replace and that does not belong
+/// to that formula.
+#[derive(Debug, Clone)]
+pub struct LinkConflict {
+    pub path: PathBuf,
+    /// `name version` of the keg the entry belongs to, if it is an sps link or wrapper.
+    pub owner: Option<String>,
+}
+
+impl fmt::Display for LinkConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.owner {
+            Some(owner) => write!(f, "{} (belongs to {})", self.path.display(), owner),
+            None => write!(f, "{} (not created by sps)", self.path.display()),
+        }
+    }
+}
 
-/// Link all artifacts from a formula's installation directory.
+/// Link all artifacts from a formula's installation directory. Keg-only formulae only
+/// get their `opt` link.
+///
+/// Links of another version of the same formula are replaced. Anything else in the way,
+/// another formula's link or a file sps did not create, fails the whole operation
+/// before anything is linked, unless `config.overwrite_links` is set. If linking fails
+/// part-way, the links created so far are removed and the replaced entries are put back.
 // Added Config parameter
 pub fn link_formula_artifacts(
     formula: &Formula,
     installed_keg_path: &Path,
     config: &Config, // Added config
+) -> Result<()> {
+    link_artifacts(formula, installed_keg_path, config, false)
+}
+
+/// Like [`link_formula_artifacts`], but links keg-only formulae into the prefix too and
+/// replaces every conflicting entry; see [`link_conflicts`] to check for those first.
+pub fn force_link_formula_artifacts(
+    formula: &Formula,
+    installed_keg_path: &Path,
+    config: &Config,
+) -> Result<()> {
+    link_artifacts(formula, installed_keg_path, config, true)
+}
+
+fn link_artifacts(
+    formula: &Formula,
+    installed_keg_path: &Path,
+    config: &Config,
+    link_keg_only: bool,
 ) -> Result<()> {
     debug!(
         "Linking artifacts for {} from {}",
@@ -28,209 +86,465 @@ pub fn link_formula_artifacts(
     );
 
     let formula_content_root = determine_content_root(installed_keg_path)?;
-    let mut symlinks_created = Vec::<String>::new();
+    let link_into_prefix = !formula.keg_only || link_keg_only;
+    if !link_into_prefix {
+        debug!(
+            "{} is keg-only; not linking it into {}",
+            formula.name(),
+            config.sps_root().display()
+        );
+    }
+    let planned = plan_links(formula, &formula_content_root, config, link_into_prefix)?;
+
+    // Everything that is in the way is known before the first link is created, so a
+    // conflict never leaves the prefix half-linked. Forced links replace whatever is
+    // there; otherwise only the links of another version of this formula are replaced.
+    let conflicts = link_conflicts(formula, &planned, config);
+    if !conflicts.is_empty() && !link_keg_only && !config.overwrite_links {
+        let listed: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+        return Err(SpsError::InstallError(format!(
+            "Cannot link {}: {} file(s) in the way: {}. The keg stays installed but unlinked; \
+             run `sps link --overwrite {}` to replace them",
+            formula.name(),
+            listed.len(),
+            listed.join(", "),
+            formula.name()
+        )));
+    }
+    for conflict in &conflicts {
+        debug!("  Replacing {}", conflict);
+    }
 
-    // Use config methods for paths
-    let opt_link_path = config.formula_opt_path(formula.name());
-    let target_keg_dir = &formula_content_root;
+    let mut transaction = LinkTransaction::default();
+    let result = planned
+        .iter()
+        .try_for_each(|link| transaction.link(link, &formula_content_root))
+        .and_then(|()| write_install_manifest(installed_keg_path, &transaction.created()));
+    if let Err(e) = result {
+        error!(
+            "Linking {} failed, removing the {} link(s) created so far: {}",
+            formula.name(),
+            transaction.created.len(),
+            e
+        );
+        transaction.rollback();
+        return Err(e);
+    }
+    transaction.commit();
 
-    remove_existing_link_target(&opt_link_path)?;
-    unix_fs::symlink(target_keg_dir, &opt_link_path).map_err(|e| {
-        SpsError::Io(std::sync::Arc::new(std::io::Error::new(
-            e.kind(),
-            format!("Failed to create opt symlink for {}: {}", formula.name(), e),
-        )))
-    })?;
-    symlinks_created.push(opt_link_path.to_string_lossy().to_string());
     debug!(
-        "  Linked opt path: {} -> {}",
-        opt_link_path.display(),
-        target_keg_dir.display()
+        "Successfully completed linking artifacts for {}",
+        formula.name()
     );
+    Ok(())
+}
 
-    if let Some((base, _version)) = formula.name().split_once('@') {
-        let alias_path = config.opt_dir().join(base); // Use config.opt_dir()
-        if !alias_path.exists() {
-            match unix_fs::symlink(target_keg_dir, &alias_path) {
-                Ok(_) => {
-                    debug!(
-                        "  Added un‑versioned opt alias: {} -> {}",
-                        alias_path.display(),
-                        target_keg_dir.display()
-                    );
-                    symlinks_created.push(alias_path.to_string_lossy().to_string());
-                }
-                Err(e) => {
-                    debug!(
-                        "  Could not create opt alias {}: {}",
-                        alias_path.display(),
+/// The links created by one `link_artifacts` call and the entries they replaced. Replaced
+/// entries are moved aside rather than deleted, so that a failure part-way can put the
+/// prefix back the way it was.
+#[derive(Default)]
+struct LinkTransaction {
+    created: Vec<PathBuf>,
+    /// (original path, path it was moved to)
+    replaced: Vec<(PathBuf, PathBuf)>,
+}
+
+impl LinkTransaction {
+    fn link(&mut self, planned: &PlannedLink, formula_content_root: &Path) -> Result<()> {
+        if let Some(parent) = planned.link.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if planned.link.symlink_metadata().is_ok() {
+            let aside = moved_aside_path(&planned.link);
+            remove_existing_link_target(&aside)?;
+            fs::rename(&planned.link, &aside)?;
+            self.replaced.push((planned.link.clone(), aside));
+        }
+        // Recorded first so that a partly written wrapper is removed on rollback too.
+        self.created.push(planned.link.clone());
+        if planned.is_wrapper {
+            create_wrapper_script(&planned.target, &planned.link, formula_content_root)?;
+        } else {
+            unix_fs::symlink(&planned.target, &planned.link).map_err(|e| {
+                SpsError::Io(std::sync::Arc::new(std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Failed to link {} -> {}: {}",
+                        planned.link.display(),
+                        planned.target.display(),
                         e
-                    );
-                }
-            }
+                    ),
+                )))
+            })?;
         }
+        debug!(
+            "  Linked {} -> {}",
+            planned.link.display(),
+            planned.target.display()
+        );
+        Ok(())
     }
 
-    let standard_artifact_dirs = ["lib", "include", "share"];
-    for dir_name in &standard_artifact_dirs {
-        let source_subdir = formula_content_root.join(dir_name);
-        // Use config.prefix() for target base
-        let target_prefix_subdir = config.sps_root().join(dir_name);
-
-        if source_subdir.is_dir() {
-            fs::create_dir_all(&target_prefix_subdir)?;
-            for entry in fs::read_dir(&source_subdir)? {
-                let entry = entry?;
-                let source_item_path = entry.path();
-                let file_name = entry.file_name();
-                if file_name.to_string_lossy().starts_with('.') {
-                    continue;
-                }
+    fn created(&self) -> Vec<String> {
+        self.created
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
 
-                let target_link = target_prefix_subdir.join(&file_name);
-                remove_existing_link_target(&target_link)?;
-                unix_fs::symlink(&source_item_path, &target_link).ok(); // ignore errors for individual links?
-                symlinks_created.push(target_link.to_string_lossy().to_string());
-                debug!(
-                    "  Linked {} -> {}",
-                    target_link.display(),
-                    source_item_path.display()
+    /// Drops the replaced entries for good.
+    fn commit(self) {
+        for (_, aside) in &self.replaced {
+            if let Err(e) = remove_existing_link_target(aside) {
+                debug!("  Could not remove {}: {}", aside.display(), e);
+            }
+        }
+    }
+
+    /// Removes the created links and moves the replaced entries back, newest first.
+    fn rollback(self) {
+        for link in self.created.iter().rev() {
+            if let Err(e) = remove_existing_link_target(link) {
+                error!("Failed to remove {} during rollback: {}", link.display(), e);
+            }
+        }
+        for (original, aside) in self.replaced.iter().rev() {
+            if let Err(e) = fs::rename(aside, original) {
+                error!(
+                    "Failed to restore {} from {} during rollback: {}",
+                    original.display(),
+                    aside.display(),
+                    e
                 );
             }
         }
     }
+}
 
-    // Use config.bin_dir() for target bin
-    let target_bin_dir = config.bin_dir();
-    fs::create_dir_all(&target_bin_dir).ok();
+/// Where a replaced entry is kept while linking is in progress: a hidden sibling, so
+/// the rename stays on one filesystem and the entry is skipped when scanning the prefix.
+fn moved_aside_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.sps-replaced"))
+}
 
-    let source_bin_dir = formula_content_root.join("bin");
-    if source_bin_dir.is_dir() {
-        create_wrappers_in_dir(
-            &source_bin_dir,
-            &target_bin_dir,
-            &formula_content_root,
-            &mut symlinks_created,
-        )?;
+/// The caveat shown after installing a keg-only formula: why it was not linked and
+/// the environment variables that make it visible anyway. `None` for other formulae.
+/// Only the variables relevant to what the keg contains are listed.
+pub fn keg_only_caveats(
+    formula: &Formula,
+    installed_keg_path: &Path,
+    config: &Config,
+) -> Option<String> {
+    if !formula.keg_only {
+        return None;
     }
-    let source_libexec_dir = formula_content_root.join("libexec");
-    if source_libexec_dir.is_dir() {
-        create_wrappers_in_dir(
-            &source_libexec_dir,
-            &target_bin_dir,
-            &formula_content_root,
-            &mut symlinks_created,
-        )?;
+    let name = formula.name();
+    let opt = config.formula_opt_path(name);
+    let mut text = format!(
+        "{} is keg-only, which means it was not symlinked into {}",
+        name,
+        config.sps_root().display()
+    );
+    match &formula.keg_only_reason {
+        Some(reason) => text.push_str(&format!(",\nbecause {reason}.")),
+        None => text.push('.'),
     }
+    let has = |dir: &str| installed_keg_path.join(dir).is_dir();
+    if has("bin") || has("sbin") {
+        let dirs: Vec<String> = ["bin", "sbin"]
+            .into_iter()
+            .filter(|dir| has(dir))
+            .map(|dir| opt.join(dir).display().to_string())
+            .collect();
+        text.push_str(&format!(
+            "\n\nIf you need to have {name} first in your PATH, add this to your shell profile:\n  export PATH=\"{}:$PATH\"",
+            dirs.join(":")
+        ));
+    }
+    if has("lib") || has("include") {
+        text.push_str(&format!(
+            "\n\nFor compilers to find {name} you may need to set:"
+        ));
+        if has("lib") {
+            text.push_str(&format!(
+                "\n  export LDFLAGS=\"-L{}\"",
+                opt.join("lib").display()
+            ));
+        }
+        if has("include") {
+            text.push_str(&format!(
+                "\n  export CPPFLAGS=\"-I{}\"",
+                opt.join("include").display()
+            ));
+        }
+    }
+    if has("lib/pkgconfig") {
+        text.push_str(&format!(
+            "\n\nFor pkg-config to find {name} you may need to set:\n  export PKG_CONFIG_PATH=\"{}\"",
+            opt.join("lib/pkgconfig").display()
+        ));
+    }
+    Some(text)
+}
 
-    write_install_manifest(installed_keg_path, &symlinks_created)?;
+/// Lists the links `force_link_formula_artifacts` would create for a keg, without
+/// touching the filesystem. The un-versioned opt alias is only listed when it is free,
+/// as linking leaves an existing one alone.
+pub fn planned_links(
+    formula: &Formula,
+    installed_keg_path: &Path,
+    config: &Config,
+) -> Result<Vec<PlannedLink>> {
+    let content_root = determine_content_root(installed_keg_path)?;
+    plan_links(formula, &content_root, config, true)
+}
 
-    debug!(
-        "Successfully completed linking artifacts for {}",
-        formula.name()
+/// The opt link (and alias), plus the prefix links and bin wrappers unless
+/// `into_prefix` is false. When two executables share a name, the first one found
+/// gets the wrapper.
+fn plan_links(
+    formula: &Formula,
+    content_root: &Path,
+    config: &Config,
+    into_prefix: bool,
+) -> Result<Vec<PlannedLink>> {
+    let mut links = vec![PlannedLink {
+        link: config.formula_opt_path(formula.name()),
+        target: content_root.to_path_buf(),
+        is_wrapper: false,
+    }];
+    if let Some((base, _version)) = formula.name().split_once('@') {
+        let alias_path = config.opt_dir().join(base);
+        if alias_path.symlink_metadata().is_err() {
+            links.push(PlannedLink {
+                link: alias_path,
+                target: content_root.to_path_buf(),
+                is_wrapper: false,
+            });
+        }
+    }
+    if !into_prefix {
+        return Ok(links);
+    }
+    for dir_name in &STANDARD_ARTIFACT_DIRS {
+        let source_subdir = content_root.join(dir_name);
+        if !source_subdir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&source_subdir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            links.push(PlannedLink {
+                link: config.sps_root().join(dir_name).join(entry.file_name()),
+                target: entry.path(),
+                is_wrapper: false,
+            });
+        }
+    }
+    // Kegs installed before libexec wrappers were generated into `bin` have no `bin`;
+    // their libexec executables are still wrapped directly.
+    let wrapped_dir = if content_root.join("bin").is_dir() {
+        "bin"
+    } else {
+        "libexec"
+    };
+    let mut wrappers = Vec::new();
+    collect_planned_wrappers(
+        &content_root.join(wrapped_dir),
+        &config.bin_dir(),
+        &mut wrappers,
     );
-    Ok(())
+    let mut seen = HashSet::new();
+    links.extend(
+        wrappers
+            .into_iter()
+            .filter(|wrapper| seen.insert(wrapper.link.clone())),
+    );
+    Ok(links)
 }
 
-// remove_existing_link_target, write_install_manifest remain mostly unchanged internally) ...
-fn create_wrappers_in_dir(
+/// Every executable below `source_dir` gets a wrapper named after it directly in
+/// `target_bin_dir`.
+fn collect_planned_wrappers(
     source_dir: &Path,
     target_bin_dir: &Path,
-    formula_content_root: &Path,
-    wrappers_created: &mut Vec<String>,
-) -> Result<()> {
-    debug!(
-        "Scanning for executables in {} to create wrappers in {}",
-        source_dir.display(),
-        target_bin_dir.display()
-    );
-    match fs::read_dir(source_dir) {
-        Ok(entries) => {
-            for entry_result in entries {
-                match entry_result {
-                    Ok(entry) => {
-                        let source_item_path = entry.path();
-                        let file_name = entry.file_name();
-
-                        if file_name.to_string_lossy().starts_with('.') {
-                            continue;
-                        }
-
-                        if source_item_path.is_dir() {
-                            create_wrappers_in_dir(
-                                &source_item_path,
-                                target_bin_dir,
-                                formula_content_root,
-                                wrappers_created,
-                            )?;
-                        } else if source_item_path.is_file() {
-                            match is_executable(&source_item_path) {
-                                Ok(true) => {
-                                    let wrapper_path = target_bin_dir.join(&file_name);
-                                    debug!("Found executable: {}", source_item_path.display());
-                                    if remove_existing_link_target(&wrapper_path).is_ok() {
-                                        debug!(
-                                            "    Creating wrapper script: {} -> {}",
-                                            wrapper_path.display(),
-                                            source_item_path.display()
-                                        );
-                                        match create_wrapper_script(
-                                            &source_item_path,
-                                            &wrapper_path,
-                                            formula_content_root,
-                                        ) {
-                                            Ok(_) => {
-                                                debug!(
-                                                    "  Created wrapper {} -> {}",
-                                                    wrapper_path.display(),
-                                                    source_item_path.display()
-                                                );
-                                                wrappers_created.push(
-                                                    wrapper_path.to_string_lossy().to_string(),
-                                                );
-                                            }
-                                            Err(e) => {
-                                                error!(
-                                                    "Failed to create wrapper script {} -> {}: {}",
-                                                    wrapper_path.display(),
-                                                    source_item_path.display(),
-                                                    e
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                                Ok(false) => { /* Not executable, ignore */ }
-                                Err(e) => {
-                                    debug!(
-                                        "    Could not check executable status for {}: {}",
-                                        source_item_path.display(),
-                                        e
-                                    );
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        debug!(
-                            "  Failed to process directory entry in {}: {}",
-                            source_dir.display(),
-                            e
-                        );
-                    }
+    links: &mut Vec<PlannedLink>,
+) {
+    let Ok(entries) = fs::read_dir(source_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_planned_wrappers(&path, target_bin_dir, links);
+        } else if is_executable(&path).unwrap_or(false) {
+            links.push(PlannedLink {
+                link: target_bin_dir.join(entry.file_name()),
+                target: path,
+                is_wrapper: true,
+            });
+        }
+    }
+}
+
+/// The links and wrappers recorded for a keg when it was last linked. A missing or
+/// unreadable manifest yields an empty list.
+pub fn read_install_manifest(installed_keg_path: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(installed_keg_path.join("INSTALL_MANIFEST.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Vec<String>>(&raw).ok())
+        .map(|links| links.into_iter().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+/// The planned links whose path is already taken by something that does not belong to
+/// `formula`: another keg's link or wrapper, or a file sps did not create.
+pub fn link_conflicts(
+    formula: &Formula,
+    planned: &[PlannedLink],
+    config: &Config,
+) -> Vec<LinkConflict> {
+    planned
+        .iter()
+        .filter(|planned| planned.link.symlink_metadata().is_ok())
+        .filter_map(|planned| {
+            let owner = link_owner(&planned.link, config);
+            match owner {
+                Some((name, _)) if name == formula.name() => None,
+                _ => Some(LinkConflict {
+                    path: planned.link.clone(),
+                    owner: owner.map(|(name, version)| format!("{name} {version}")),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// The formula name and version of the keg a prefix entry belongs to: the keg a symlink
+/// points into, or the keg of the executable a wrapper script runs.
+pub fn link_owner(path: &Path, config: &Config) -> Option<(String, String)> {
+    let target = link_target(path)?;
+    let mut components = target.strip_prefix(config.cellar_dir()).ok()?.components();
+    let name = components.next()?.as_os_str().to_string_lossy().to_string();
+    let version = components.next()?.as_os_str().to_string_lossy().to_string();
+    Some((name, version))
+}
+
+/// What a prefix entry leads to: a symlink's target, or the executable a wrapper script
+/// runs. `None` for anything else. A relative symlink's `..` components are resolved
+/// lexically, e.g. `bin/../Cellar/x` becomes `Cellar/x`.
+pub fn link_target(path: &Path) -> Option<PathBuf> {
+    let metadata = path.symlink_metadata().ok()?;
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path).ok()?;
+        let target = match path.parent() {
+            Some(parent) if target.is_relative() => parent.join(target),
+            _ => target,
+        };
+        let mut normalized = PathBuf::new();
+        for component in target.components() {
+            match component {
+                Component::ParentDir => {
+                    normalized.pop();
                 }
+                Component::CurDir => {}
+                other => normalized.push(other),
             }
         }
-        Err(e) => {
-            debug!(
-                "Failed to read source directory {}: {}",
-                source_dir.display(),
-                e
-            );
-        }
+        Some(normalized)
+    } else if metadata.is_file() {
+        wrapper_target(path)
+    } else {
+        None
     }
-    Ok(())
 }
+
+/// The executable an sps wrapper script runs; `None` for any other file.
+fn wrapper_target(path: &Path) -> Option<PathBuf> {
+    let mut head = [0u8; 64];
+    let read = fs::File::open(path)
+        .and_then(|mut f| f.read(&mut head))
+        .ok()?;
+    if !String::from_utf8_lossy(&head[..read]).contains(WRAPPER_MARKER) {
+        return None;
+    }
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("exec \"")?.strip_suffix("\" \"$@\""))
+        .map(PathBuf::from)
+}
+
+/// Gives a keg that only installed executables under `libexec` a `bin` to link: one
+/// wrapper per executable in `libexec/bin`, or directly in `libexec` if there is no
+/// `libexec/bin`. Formulae with bundled runtimes are laid out like this and expect
+/// such wrappers, which set the runtime's library paths and exec the real executable.
+///
+/// Does nothing if the keg already has a `bin` with entries. Returns the wrappers
+/// created, relative to the keg.
+pub fn generate_libexec_wrappers(installed_keg_path: &Path) -> Result<Vec<PathBuf>> {
+    let content_root = determine_content_root(installed_keg_path)?;
+    let bin_dir = content_root.join("bin");
+    let has_bin_entries = fs::read_dir(&bin_dir)
+        .map(|mut entries| {
+            entries.any(|entry| {
+                entry.is_ok_and(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            })
+        })
+        .unwrap_or(false);
+    if has_bin_entries {
+        return Ok(Vec::new());
+    }
+
+    let libexec = content_root.join("libexec");
+    let source_dir = if libexec.join("bin").is_dir() {
+        libexec.join("bin")
+    } else {
+        libexec
+    };
+    let Ok(entries) = fs::read_dir(&source_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut targets: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_executable(path).unwrap_or(false))
+        .collect();
+    targets.sort();
+
+    let mut generated = Vec::new();
+    for target in targets {
+        let Some(name) = target.file_name() else {
+            continue;
+        };
+        fs::create_dir_all(&bin_dir)?;
+        let wrapper = bin_dir.join(name);
+        create_wrapper_script(&target, &wrapper, &content_root)?;
+        debug!(
+            "Generated wrapper {} -> {}",
+            wrapper.display(),
+            target.display()
+        );
+        generated.push(
+            wrapper
+                .strip_prefix(installed_keg_path)
+                .unwrap_or(&wrapper)
+                .to_path_buf(),
+        );
+    }
+    Ok(generated)
+}
+
 fn create_wrapper_script(
     target_executable: &Path,
     wrapper_path: &Path,
@@ -242,7 +556,8 @@ fn create_wrapper_script(
 
     let mut script_content = String::new();
     script_content.push_str("#!/bin/bash\n");
-    script_content.push_str("# Wrapper script generated by sp\n");
+    script_content.push_str(WRAPPER_MARKER);
+    script_content.push('\n');
     script_content.push_str("set -e\n\n");
 
     if perl_lib_path.exists() && perl_lib_path.is_dir() {
@@ -473,6 +788,11 @@ fn write_install_manifest(installed_keg_path: &Path, symlinks_created: &[String]
     Ok(())
 }
 
+/// Removes the links and wrappers recorded in the keg's install manifest. Only entries
+/// that still lead into this keg are removed: a link that another version or another
+/// formula has since taken over belongs to that keg now and is left alone. Kegs without
+/// a manifest have their links found by scanning the prefix for entries leading into
+/// them.
 pub fn unlink_formula_artifacts(
     formula_name: &str,
     version_str_full: &str, // e.g., "1.2.3_1"
@@ -482,112 +802,91 @@ pub fn unlink_formula_artifacts(
         "Unlinking artifacts for {} version {}",
         formula_name, version_str_full
     );
-    // Use config method to get expected keg path based on name and version string
-    let expected_keg_path = config.formula_keg_path(formula_name, version_str_full);
-    let manifest_path = expected_keg_path.join("INSTALL_MANIFEST.json"); // Manifest *inside* the keg
-
-    if manifest_path.is_file() {
-        debug!("Reading install manifest: {}", manifest_path.display());
-        match fs::read_to_string(&manifest_path) {
-            Ok(manifest_str) => {
-                match serde_json::from_str::<Vec<String>>(&manifest_str) {
-                    Ok(links_to_remove) => {
-                        let mut unlinked_count = 0;
-                        let mut removal_errors = 0;
-                        if links_to_remove.is_empty() {
-                            debug!(
-                                "Install manifest {} is empty. Cannot perform manifest-based unlink.",
-                                manifest_path.display()
-                            );
-                        } else {
-                            // Use Config to get base paths for checking ownership/safety
-                            let opt_base = config.opt_dir();
-                            let bin_base = config.bin_dir();
-                            let lib_base = config.sps_root().join("lib");
-                            let include_base = config.sps_root().join("include");
-                            let share_base = config.sps_root().join("share");
-                            // Add etc, sbin etc. if needed
-
-                            for link_str in links_to_remove {
-                                let link_path = PathBuf::from(link_str);
-                                // Check if it's under a managed directory (safety check)
-                                if link_path.starts_with(&opt_base)
-                                    || link_path.starts_with(&bin_base)
-                                    || link_path.starts_with(&lib_base)
-                                    || link_path.starts_with(&include_base)
-                                    || link_path.starts_with(&share_base)
-                                {
-                                    match remove_existing_link_target(&link_path) {
-                                        // Use helper
-                                        Ok(_) => {
-                                            debug!("Removed link/wrapper: {}", link_path.display());
-                                            unlinked_count += 1;
-                                        }
-                                        Err(e) => {
-                                            // Log error but continue trying to remove others
-                                            debug!(
-                                                "Failed to remove link/wrapper {}: {}",
-                                                link_path.display(),
-                                                e
-                                            );
-                                            removal_errors += 1;
-                                        }
-                                    }
-                                } else {
-                                    // This indicates a potentially corrupted manifest or a link
-                                    // outside expected areas
-                                    error!(
-                                        "Manifest contains unexpected link path, skipping removal: {}",
-                                        link_path.display()
-                                    );
-                                    removal_errors += 1; // Count as an error/problem
-                                }
-                            }
-                        }
-                        debug!(
-                            "Attempted to unlink {} artifacts based on manifest.",
-                            unlinked_count
-                        );
-                        if removal_errors > 0 {
-                            error!(
-                                "Encountered {} errors while removing links listed in manifest.",
-                                removal_errors
-                            );
-                            // Decide if this should be a hard error - perhaps not if keg is being
-                            // removed anyway? For now, just log
-                            // warnings.
-                        }
-                        Ok(()) // Return Ok even if some links failed, keg removal will happen next
-                    }
-                    Err(e) => {
-                        error!(
-                            "Failed to parse formula install manifest {}: {}. Proceeding without detailed unlink.",
-                            manifest_path.display(),
-                            e
-                        );
-                        // Don't error out, allow keg removal to proceed.
-                        Ok(())
-                    }
+    let keg_path = config.formula_keg_path(formula_name, version_str_full);
+    let recorded = keg_path.join("INSTALL_MANIFEST.json").is_file();
+    let links = if recorded {
+        read_install_manifest(&keg_path)
+    } else {
+        debug!(
+            "No install manifest in {}; scanning the prefix for its links",
+            keg_path.display()
+        );
+        prefix_entries(config)
+    };
+
+    let owner = (formula_name.to_string(), version_str_full.to_string());
+    let mut unlinked_count = 0;
+    let mut removal_errors = 0;
+    for link_path in links {
+        if !link_path.starts_with(config.sps_root()) {
+            error!(
+                "Manifest contains unexpected link path, skipping removal: {}",
+                link_path.display()
+            );
+            removal_errors += 1;
+            continue;
+        }
+        match link_owner(&link_path, config) {
+            Some(current) if current == owner => {}
+            Some((name, version)) => {
+                debug!(
+                    "Leaving {}; it now belongs to {} {}",
+                    link_path.display(),
+                    name,
+                    version
+                );
+                continue;
+            }
+            None => {
+                if recorded && link_path.symlink_metadata().is_ok() {
+                    debug!(
+                        "Leaving {}; it no longer leads into a keg",
+                        link_path.display()
+                    );
                 }
+                continue;
+            }
+        }
+        match remove_existing_link_target(&link_path) {
+            Ok(()) => {
+                debug!("Removed link/wrapper: {}", link_path.display());
+                unlinked_count += 1;
             }
             Err(e) => {
-                error!(
-                    "Failed to read formula install manifest {}: {}. Proceeding without detailed unlink.",
-                    manifest_path.display(),
+                // Keep going; the keg itself is usually removed next.
+                debug!(
+                    "Failed to remove link/wrapper {}: {}",
+                    link_path.display(),
                     e
                 );
-                // Don't error out, allow keg removal to proceed.
-                Ok(())
+                removal_errors += 1;
             }
         }
-    } else {
-        debug!(
-            "Warning: No install manifest found at {}. Cannot perform detailed unlink.",
-            manifest_path.display()
+    }
+    debug!(
+        "Unlinked {} artifact(s) of {} {}",
+        unlinked_count, formula_name, version_str_full
+    );
+    if removal_errors > 0 {
+        error!(
+            "Encountered {} errors while removing the links of {} {}.",
+            removal_errors, formula_name, version_str_full
         );
-        // Don't error out, allow keg removal to proceed.
-        Ok(())
     }
+    Ok(())
+}
+
+/// Every entry linking may have created: those in `opt` and `bin`, and in the prefix
+/// directories whose entries are linked individually.
+fn prefix_entries(config: &Config) -> Vec<PathBuf> {
+    let dirs = [config.opt_dir(), config.bin_dir()].into_iter().chain(
+        STANDARD_ARTIFACT_DIRS
+            .iter()
+            .map(|dir| config.sps_root().join(dir)),
+    );
+    dirs.filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .collect()
 }
 
 fn is_executable(path: &Path) -> Result<bool> {
@@ -604,3 +903,61 @@ fn is_executable(path: &Path) -> Result<bool> {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_owner_names_the_keg_a_link_points_into() {
+        let root = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(root.path());
+        let keg_bin = config.cellar_dir().join("jq/1.7/bin");
+        fs::create_dir_all(&keg_bin).unwrap();
+        fs::write(keg_bin.join("jq"), "").unwrap();
+        fs::create_dir_all(config.bin_dir()).unwrap();
+
+        let relative = config.bin_dir().join("jq");
+        unix_fs::symlink("../Cellar/jq/1.7/bin/jq", &relative).unwrap();
+        assert_eq!(
+            link_owner(&relative, &config),
+            Some(("jq".to_string(), "1.7".to_string()))
+        );
+
+        let absolute = config.bin_dir().join("jq-abs");
+        unix_fs::symlink(keg_bin.join("jq"), &absolute).unwrap();
+        assert_eq!(
+            link_owner(&absolute, &config),
+            Some(("jq".to_string(), "1.7".to_string()))
+        );
+
+        let foreign = config.bin_dir().join("sh");
+        unix_fs::symlink("/bin/sh", &foreign).unwrap();
+        assert_eq!(link_owner(&foreign, &config), None);
+        assert_eq!(link_owner(&config.bin_dir().join("missing"), &config), None);
+    }
+
+    #[test]
+    fn link_target_resolves_dot_dot_and_follows_wrappers() {
+        let root = tempfile::tempdir().unwrap();
+        let keg = root.path().join("Cellar/node/22.0");
+        let executable = keg.join("libexec/bin/node");
+        fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        fs::write(&executable, "").unwrap();
+        let bin = root.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+
+        let link = bin.join("node-link");
+        unix_fs::symlink("../Cellar/./node/22.0/libexec/bin/node", &link).unwrap();
+        assert_eq!(link_target(&link), Some(executable.clone()));
+
+        let wrapper = bin.join("node");
+        create_wrapper_script(&executable, &wrapper, &keg).unwrap();
+        assert_eq!(link_target(&wrapper), Some(executable));
+
+        let plain = bin.join("script");
+        fs::write(&plain, "#!/bin/sh\nexec \"/bin/true\" \"$@\"\n").unwrap();
+        assert_eq!(link_target(&plain), None);
+        assert_eq!(link_target(&bin), None);
+    }
+}
@@ -8,9 +8,10 @@ use std::fs;
 use std::io::Write; // Keep for write_patched_buffer
 use std::path::Path;
 use std::process::{Command as StdCommand, Stdio}; // Keep for codesign
+use std::sync::OnceLock;
 
-// --- Imports needed for Mach-O patching (macOS only) ---
-#[cfg(target_os = "macos")]
+// --- Imports needed for Mach-O patching (macOS only, and the tests) ---
+#[cfg(any(target_os = "macos", test))]
 use object::{
     self,
     macho::{MachHeader32, MachHeader64}, // Keep for Mach-O parsing
@@ -31,17 +32,17 @@ use tempfile::NamedTempFile;
 use tracing::{debug, error};
 
 // --- Platform‑specific constants for Mach‑O magic detection ---
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 const MH_MAGIC: u32 = 0xfeedface;
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 const MH_MAGIC_64: u32 = 0xfeedfacf;
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 const MACHO_HEADER32_SIZE: usize = 28;
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 const MACHO_HEADER64_SIZE: usize = 32;
 
 /// Core patch data for **one** string replacement location inside a Mach‑O file.
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 #[derive(Debug, Clone)]
 struct PatchInfo {
     absolute_offset: usize, // Offset in the entire file buffer
@@ -49,12 +50,35 @@ struct PatchInfo {
     new_path: String,       // The new string to write
 }
 
+/// Which kind of load command a path lives in; `install_name_tool` needs a different
+/// option for each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachoPathKind {
+    /// A dependency (`LC_LOAD_DYLIB` and friends), changed with `-change`.
+    Load,
+    /// The library's own install name (`LC_ID_DYLIB`), changed with `-id`.
+    Id,
+    /// A run-path search entry (`LC_RPATH`), changed with `-rpath`.
+    Rpath,
+}
+
+impl std::fmt::Display for MachoPathKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Load => "load path",
+            Self::Id => "install name",
+            Self::Rpath => "rpath",
+        })
+    }
+}
+
 /// Container for paths that couldn't be patched due to length constraints
 #[cfg(target_os = "macos")]
 #[derive(Debug, Clone)]
 pub struct SkippedPath {
     pub old_path: String,
     pub new_path: String,
+    pub kind: MachoPathKind,
 }
 
 /// Main entry point for Mach‑O path patching (macOS only).
@@ -74,6 +98,7 @@ pub fn patch_macho_file(
 pub struct SkippedPath {
     pub old_path: String,
     pub new_path: String,
+    pub kind: MachoPathKind,
 }
 
 /// No‑op stub for non‑macOS platforms.
@@ -148,21 +173,19 @@ fn patch_macho_file_macos(
     debug!("Wrote patched Mach-O: {}", path.display());
 
     // 6) Re‑sign on Apple Silicon
-    #[cfg(target_arch = "aarch64")]
-    {
-        resign_binary(path)?;
-        debug!("Re‑signed patched binary: {}", path.display());
+    if needs_adhoc_signing() {
+        codesign_adhoc(path)?;
     }
 
     Ok((true, skipped_paths))
 }
 
 /// ASCII magic for the start of a static `ar` archive  (`!<arch>\n`)
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
 
 /// Examine a buffer (Mach‑O or FAT) and return every patch we must apply + skipped paths.
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 fn collect_macho_patches(
     buffer: &[u8],
     kind: FileKind,
@@ -284,7 +307,7 @@ fn collect_macho_patches(
 
 /// Iterates through load commands of a parsed MachOFile (slice) and returns
 /// patch details + skipped paths.
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 fn find_patches_in_commands<'data, Mach, R>(
     macho_file: &MachOFile<'data, Mach, R>,
     slice_base_offset: usize,
@@ -320,21 +343,25 @@ where
         };
 
         // — which commands carry path strings we might want? —
-        let path_info: Option<(u32, &[u8])> = match variant {
-            LoadCommandVariant::Dylib(d) | LoadCommandVariant::IdDylib(d) => cmd
+        let path_info: Option<(u32, &[u8], MachoPathKind)> = match variant {
+            LoadCommandVariant::Dylib(d) => cmd
+                .string(endian, d.dylib.name)
+                .ok()
+                .map(|bytes| (d.dylib.name.offset.get(endian), bytes, MachoPathKind::Load)),
+            LoadCommandVariant::IdDylib(d) => cmd
                 .string(endian, d.dylib.name)
                 .ok()
-                .map(|bytes| (d.dylib.name.offset.get(endian), bytes)),
+                .map(|bytes| (d.dylib.name.offset.get(endian), bytes, MachoPathKind::Id)),
             LoadCommandVariant::Rpath(r) => cmd
                 .string(endian, r.path)
                 .ok()
-                .map(|bytes| (r.path.offset.get(endian), bytes)),
+                .map(|bytes| (r.path.offset.get(endian), bytes, MachoPathKind::Rpath)),
             _ => None,
         };
 
-        if let Some((offset_in_cmd, bytes)) = path_info {
+        if let Some((offset_in_cmd, bytes, kind)) = path_info {
             if let Ok(old_path) = std::str::from_utf8(bytes) {
-                if let Some(new_path) = find_and_replace_placeholders(old_path, replacements) {
+                if let Some(new_path) = replace_placeholders(old_path, replacements) {
                     let allocated = cmd_size.saturating_sub(offset_in_cmd as usize);
 
                     if new_path.len() + 1 > allocated {
@@ -349,10 +376,19 @@ where
                         skipped_paths.push(SkippedPath {
                             old_path: old_path.to_string(),
                             new_path: new_path.clone(),
+                            kind,
                         });
                         continue;
                     }
 
+                    debug!(
+                        "Relocating {} '{}' → '{}' in {}",
+                        kind,
+                        old_path,
+                        new_path,
+                        file_path_for_log.display()
+                    );
+
                     patches.push(PatchInfo {
                         absolute_offset: slice_base_offset + cmd_offset + offset_in_cmd as usize,
                         allocated_len: allocated,
@@ -365,38 +401,79 @@ where
     Ok((patches, skipped_paths))
 }
 
-/// Helper to replace placeholders in a string based on the replacements map.
-/// Returns `Some(String)` with replacements if any were made, `None` otherwise.
-fn find_and_replace_placeholders(
-    current_path: &str,
-    replacements: &HashMap<String, String>,
-) -> Option<String> {
-    let mut new_path = current_path.to_string();
-    let mut path_modified = false;
-    // Iterate through all placeholder/replacement pairs
-    for (placeholder, replacement) in replacements {
-        // Check if the current path string contains the placeholder
-        if new_path.contains(placeholder) {
-            // Replace all occurrences of the placeholder
-            new_path = new_path.replace(placeholder, replacement);
-            path_modified = true; // Mark that a change was made
-            debug!(
-                "   Replaced '{}' with '{}' -> '{}'",
-                placeholder, replacement, new_path
-            );
+/// The replacement pairs with the longest placeholder first, so that a placeholder
+/// that extends another (`@@HOMEBREW_OPT_LLVM@@/lib` and `@@HOMEBREW_OPT_LLVM@@`, or
+/// a build cellar inside a build prefix) wins over the shorter one regardless of
+/// `HashMap` order. Empty placeholders are dropped.
+fn ordered_replacements(replacements: &HashMap<String, String>) -> Vec<(&str, &str)> {
+    let mut ordered: Vec<(&str, &str)> = replacements
+        .iter()
+        .filter(|(placeholder, _)| !placeholder.is_empty())
+        .map(|(placeholder, replacement)| (placeholder.as_str(), replacement.as_str()))
+        .collect();
+    ordered.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(b.0)));
+    ordered
+}
+
+/// Replaces every placeholder in `text` in a single left-to-right pass, taking the
+/// longest placeholder where several start at the same position. Replaced text is
+/// never searched again, so a replacement that contains another placeholder (a
+/// relocated cellar inside the old prefix, say) is left as it is. Returns `None` if
+/// nothing matched.
+pub fn replace_placeholders(text: &str, replacements: &HashMap<String, String>) -> Option<String> {
+    let ordered = ordered_replacements(replacements);
+    // Where each placeholder next occurs at or after `pos`.
+    let mut next: Vec<Option<usize>> = ordered
+        .iter()
+        .map(|(placeholder, _)| text.find(placeholder))
+        .collect();
+    let mut relocated = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut replaced = false;
+    // `min_by_key` keeps the first of equal starts, which is the longest placeholder.
+    while let Some((index, start)) = next
+        .iter()
+        .enumerate()
+        .filter_map(|(index, start)| start.map(|start| (index, start)))
+        .min_by_key(|&(_, start)| start)
+    {
+        let (placeholder, replacement) = ordered[index];
+        relocated.push_str(&text[pos..start]);
+        relocated.push_str(replacement);
+        pos = start + placeholder.len();
+        replaced = true;
+        for (slot, (placeholder, _)) in next.iter_mut().zip(&ordered) {
+            if slot.is_some_and(|start| start < pos) {
+                *slot = text[pos..].find(placeholder).map(|start| start + pos);
+            }
         }
     }
-    // Return the modified string only if changes occurred
-    if path_modified {
-        Some(new_path)
-    } else {
-        None
+    if !replaced {
+        return None;
     }
+    relocated.push_str(&text[pos..]);
+    Some(relocated)
+}
+
+/// Whether the file starts with a Mach-O or universal binary magic number. Cheap
+/// enough to run on every file, unlike a full parse.
+pub fn has_macho_magic(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    if std::io::Read::read_exact(&mut file, &mut magic).is_err() {
+        return false;
+    }
+    matches!(
+        u32::from_be_bytes(magic),
+        0xfeedface | 0xfeedfacf | 0xcefaedfe | 0xcffaedfe | 0xcafebabe | 0xcafebabf
+    )
 }
 
 /// Write a new (null‑padded) path into the mutable buffer.  
 /// Assumes the caller already verified the length.
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", test))]
 fn patch_path_in_buffer(
     buf: &mut [u8],
     abs_off: usize,
@@ -468,57 +545,223 @@ fn write_patched_buffer(original_path: &Path, buffer: &[u8]) -> Result<()> {
     Ok(())
 }
 
-/// Re-signs the binary using the `codesign` command-line tool.
-/// This is typically necessary on Apple Silicon (aarch64) after modifying executables.
-#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-fn resign_binary(path: &Path) -> Result<()> {
-    // Suppressed: debug!("Re-signing patched binary: {}", path.display());
-    let status = StdCommand::new("codesign")
+/// Whether modified binaries need a fresh ad-hoc signature. On Apple Silicon the
+/// kernel kills arm64 code whose signature no longer matches, so every binary whose
+/// load commands were rewritten has to be re-signed. Decided at runtime rather than
+/// by the target sps was built for, so that an x86_64 build running under Rosetta
+/// still signs. Never needed on Intel Macs or off macOS.
+pub fn needs_adhoc_signing() -> bool {
+    static ON_APPLE_SILICON: OnceLock<bool> = OnceLock::new();
+    cfg!(target_os = "macos")
+        && *ON_APPLE_SILICON.get_or_init(|| {
+            cfg!(target_arch = "aarch64")
+                || StdCommand::new("sysctl")
+                    .args(["-n", "hw.optional.arm64"])
+                    .stderr(Stdio::null())
+                    .output()
+                    .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).trim() == "1")
+        })
+}
+
+/// Replaces the signature of `path` with an ad-hoc one, keeping its identifier and
+/// entitlements. A failure is a `CodesignError`: the binary would not run.
+pub fn codesign_adhoc(path: &Path) -> Result<()> {
+    debug!("Re-signing {}", path.display());
+    let output = StdCommand::new("codesign")
         .args([
-            "-s",
-            "-",
             "--force",
+            "--sign",
+            "-",
             "--preserve-metadata=identifier,entitlements",
         ])
         .arg(path)
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status() // Execute the command and get its exit status
+        .output()
         .map_err(|e| {
-            error!(
-                "    Failed to execute codesign command for {}: {}",
-                path.display(),
-                e
-            );
-            SpsError::Io(std::sync::Arc::new(e))
+            SpsError::CodesignError(format!(
+                "Failed to run codesign for {}: {e}",
+                path.display()
+            ))
         })?;
-    if status.success() {
-        // Suppressed: debug!("Successfully re-signed {}", path.display());
+    if output.status.success() {
         Ok(())
     } else {
-        error!(
-            "    codesign command failed for {} with status: {}",
-            path.display(),
-            status
-        );
         Err(SpsError::CodesignError(format!(
-            "Failed to re-sign patched binary {}, it may not be executable. Exit status: {}",
+            "codesign failed for {} ({}): {}; the binary will not run until it is signed",
             path.display(),
-            status
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
         )))
     }
 }
 
-// No-op stub for resigning on non-Apple Silicon macOS (e.g., x86_64)
-#[cfg(all(target_os = "macos", not(target_arch = "aarch64")))]
-fn resign_binary(_path: &Path) -> Result<()> {
-    // No re-signing typically needed on Intel Macs after ad-hoc patching
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// No-op stub for resigning Innovations on non-macOS platforms
-#[cfg(not(target_os = "macos"))]
-fn resign_binary(_path: &Path) -> Result<()> {
-    // Resigning is a macOS concept
-    Ok(())
+    const LC_LOAD_DYLIB: u32 = 0xc;
+    const LC_ID_DYLIB: u32 = 0xd;
+    const LC_RPATH: u32 = 0x8000_001c;
+
+    fn replacements(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    /// A load command carrying `path` at `name_offset`, null-padded to 8 bytes with
+    /// `slack` spare bytes for a longer replacement.
+    fn path_command(
+        cmd: u32,
+        name_offset: u32,
+        fields: &[u32],
+        path: &str,
+        slack: usize,
+    ) -> Vec<u8> {
+        let size = (name_offset as usize + path.len() + 1 + slack).next_multiple_of(8);
+        let mut bytes = Vec::with_capacity(size);
+        for word in [cmd, size as u32, name_offset].iter().chain(fields) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(path.as_bytes());
+        bytes.resize(size, 0);
+        bytes
+    }
+
+    /// A minimal arm64 dylib with an install name, one dependency and one rpath.
+    fn fixture_dylib(id: &str, load: &str, rpath: &str) -> Vec<u8> {
+        let commands = [
+            path_command(LC_ID_DYLIB, 24, &[0, 0x1_0000, 0x1_0000], id, 32),
+            path_command(LC_LOAD_DYLIB, 24, &[0, 0x1_0000, 0x1_0000], load, 32),
+            path_command(LC_RPATH, 12, &[], rpath, 32),
+        ];
+        let sizeofcmds: usize = commands.iter().map(Vec::len).sum();
+        let mut bytes = Vec::new();
+        // mach_header_64: magic, cputype (arm64), cpusubtype, filetype (MH_DYLIB),
+        // ncmds, sizeofcmds, flags, reserved.
+        for word in [MH_MAGIC_64, 0x0100_000c, 0, 6, 3, sizeofcmds as u32, 0, 0] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        commands.iter().for_each(|command| bytes.extend(command));
+        bytes
+    }
+
+    fn load_command_paths(buffer: &[u8]) -> Vec<String> {
+        let file = MachOFile::<MachHeader64<Endianness>, _>::parse(buffer).unwrap();
+        let endian = file.endian();
+        let mut commands = file.macho_load_commands().unwrap();
+        let mut paths = Vec::new();
+        while let Some(command) = commands.next().unwrap() {
+            let bytes = match command.variant().unwrap() {
+                LoadCommandVariant::Dylib(d) | LoadCommandVariant::IdDylib(d) => {
+                    command.string(endian, d.dylib.name).unwrap()
+                }
+                LoadCommandVariant::Rpath(r) => command.string(endian, r.path).unwrap(),
+                _ => continue,
+            };
+            paths.push(String::from_utf8(bytes.to_vec()).unwrap());
+        }
+        paths
+    }
+
+    #[test]
+    fn longest_placeholder_comes_first() {
+        let table = replacements(&[
+            ("@@HOMEBREW_OPT_LLVM@@", "/opt/sps/opt/llvm"),
+            ("@@HOMEBREW_OPT_LLVM@@/lib", "/opt/sps/opt/llvm/lib"),
+            ("", "ignored"),
+        ]);
+        let ordered = ordered_replacements(&table);
+        assert_eq!(
+            ordered,
+            [
+                ("@@HOMEBREW_OPT_LLVM@@/lib", "/opt/sps/opt/llvm/lib"),
+                ("@@HOMEBREW_OPT_LLVM@@", "/opt/sps/opt/llvm"),
+            ]
+        );
+    }
+
+    #[test]
+    fn replaced_text_is_not_replaced_again() {
+        // Our prefix lies inside the one the bottle was built for.
+        let table = replacements(&[
+            ("/usr/local/Cellar/", "/usr/local/sps/Cellar/"),
+            ("/usr/local/", "/usr/local/sps/"),
+        ]);
+        assert_eq!(
+            replace_placeholders(
+                "lib=/usr/local/Cellar/foo/1.0/lib inc=/usr/local/include",
+                &table
+            )
+            .as_deref(),
+            Some("lib=/usr/local/sps/Cellar/foo/1.0/lib inc=/usr/local/sps/include")
+        );
+        assert_eq!(replace_placeholders("/opt/other/lib", &table), None);
+    }
+
+    #[test]
+    fn dylib_placeholders_are_rewritten() {
+        let dylib = fixture_dylib(
+            "@@HOMEBREW_PREFIX@@/opt/foo/lib/libfoo.dylib",
+            "@@HOMEBREW_CELLAR@@/bar/2.0/lib/libbar.dylib",
+            "@@HOMEBREW_PREFIX@@/lib",
+        );
+        let table = replacements(&[
+            ("@@HOMEBREW_PREFIX@@", "/opt/sps"),
+            ("@@HOMEBREW_CELLAR@@", "/opt/sps/Cellar"),
+        ]);
+
+        let (patches, skipped) = collect_macho_patches(
+            &dylib,
+            FileKind::parse(&*dylib).unwrap(),
+            &table,
+            Path::new("libfoo.dylib"),
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(patches.len(), 3);
+
+        let mut patched = dylib.clone();
+        for patch in &patches {
+            patch_path_in_buffer(
+                &mut patched,
+                patch.absolute_offset,
+                patch.allocated_len,
+                &patch.new_path,
+                Path::new("libfoo.dylib"),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            load_command_paths(&patched),
+            [
+                "/opt/sps/opt/foo/lib/libfoo.dylib",
+                "/opt/sps/Cellar/bar/2.0/lib/libbar.dylib",
+                "/opt/sps/lib",
+            ]
+        );
+    }
+
+    #[test]
+    fn dylib_path_too_long_for_its_command_is_skipped() {
+        let dylib = fixture_dylib(
+            "@@HOMEBREW_PREFIX@@/lib/libfoo.dylib",
+            "/usr/lib/libSystem.B.dylib",
+            "@loader_path",
+        );
+        let long_prefix = format!("/{}", "x".repeat(200));
+        let table = replacements(&[("@@HOMEBREW_PREFIX@@", long_prefix.as_str())]);
+
+        let (patches, skipped) = collect_macho_patches(
+            &dylib,
+            FileKind::parse(&*dylib).unwrap(),
+            &table,
+            Path::new("libfoo.dylib"),
+        )
+        .unwrap();
+        assert!(patches.is_empty());
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].kind, MachoPathKind::Id);
+    }
 }
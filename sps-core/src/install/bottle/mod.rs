@@ -1,13 +1,14 @@
 // ===== sps-core/src/build/formula/mod.rs =====
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
-use sps_common::config::Config;
+use sps_common::config::{hardware_arch, Config};
+use sps_common::dependency::DependencyExt;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::formula::Formula;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 // Declare submodules
 pub mod exec;
@@ -20,8 +21,8 @@ pub async fn download_formula(
     config: &Config,
     client: &reqwest::Client,
 ) -> Result<PathBuf> {
-    if has_bottle_for_current_platform(formula) {
-        exec::download_bottle(formula, config, client).await
+    if has_bottle_for_current_platform(formula, config) {
+        exec::download_bottle(formula, config, client, None, false).await
     } else {
         Err(SpsError::Generic(format!(
             "No bottle available for {} on this platform",
@@ -30,9 +31,25 @@ pub async fn download_formula(
     }
 }
 
+/// Whether installed bottles will run translated: x86_64 bottles on Apple Silicon.
+pub fn runs_translated(config: &Config) -> bool {
+    cfg!(target_os = "macos") && hardware_arch() == "arm64" && config.bottle_arch() == "x86_64"
+}
+
 /// Checks if a suitable bottle exists for the current platform, considering fallbacks.
-pub fn has_bottle_for_current_platform(formula: &Formula) -> bool {
-    let result = crate::install::bottle::exec::get_bottle_for_platform(formula);
+///
+/// With a bottle tag override, any formula that ships bottles counts as having one:
+/// a tag it lacks should fail with the tags it does have (see
+/// [`exec::get_bottle_for_platform`]) rather than quietly build from source.
+pub fn has_bottle_for_current_platform(formula: &Formula, config: &Config) -> bool {
+    if config.bottle_tag.is_some() {
+        return formula
+            .bottle
+            .stable
+            .as_ref()
+            .is_some_and(|stable| !stable.files.is_empty());
+    }
+    let result = crate::install::bottle::exec::get_bottle_for_platform(formula, config);
     debug!(
         "has_bottle_for_current_platform check for '{}': {:?}",
         formula.name(),
@@ -44,86 +61,27 @@ pub fn has_bottle_for_current_platform(formula: &Formula) -> bool {
     result.is_ok()
 }
 
-// *** Updated get_current_platform function ***
-fn get_current_platform() -> String {
+pub(crate) fn get_current_platform(config: &Config) -> String {
     if cfg!(target_os = "macos") {
-        let arch = if std::env::consts::ARCH == "aarch64" {
-            "arm64"
-        } else if std::env::consts::ARCH == "x86_64" {
-            "x86_64"
-        } else {
-            std::env::consts::ARCH
-        };
-
-        debug!("Attempting to determine macOS version using /usr/bin/sw_vers -productVersion");
-        match Command::new("/usr/bin/sw_vers")
-            .arg("-productVersion")
-            .output()
-        {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                debug!("sw_vers status: {}", output.status);
-                if !output.status.success() || !stderr.trim().is_empty() {
-                    debug!("sw_vers stdout:\n{}", stdout);
-                    if !stderr.trim().is_empty() {
-                        debug!("sw_vers stderr:\n{}", stderr);
-                    }
-                }
+        let arch = config.bottle_arch();
 
-                if output.status.success() {
-                    let version_str = stdout.trim();
-                    if !version_str.is_empty() {
-                        debug!("Extracted version string: {}", version_str);
-                        let os_name = match version_str.split('.').next() {
-                            Some("15") => "sequoia",
-                            Some("14") => "sonoma",
-                            Some("13") => "ventura",
-                            Some("12") => "monterey",
-                            Some("11") => "big_sur",
-                            Some("10") => match version_str.split('.').nth(1) {
-                                Some("15") => "catalina",
-                                Some("14") => "mojave",
-                                _ => {
-                                    debug!(
-                                        "Unrecognized legacy macOS 10.x version: {}",
-                                        version_str
-                                    );
-                                    "unknown_macos"
-                                }
-                            },
-                            _ => {
-                                debug!("Unrecognized macOS major version: {}", version_str);
-                                "unknown_macos"
-                            }
-                        };
-
-                        if os_name != "unknown_macos" {
-                            let platform_tag = if arch == "arm64" {
-                                format!("{arch}_{os_name}")
-                            } else {
-                                os_name.to_string()
-                            };
-                            debug!("Determined platform tag: {}", platform_tag);
-                            return platform_tag;
-                        }
+        match crate::install::devtools::detect_macos_version() {
+            Some(version) => match crate::install::devtools::macos_bottle_codename(&version) {
+                Some(os_name) => {
+                    let platform_tag = if arch == "arm64" {
+                        format!("{arch}_{os_name}")
                     } else {
-                        error!("sw_vers -productVersion output was empty.");
-                    }
-                } else {
-                    error!(
-                        "sw_vers -productVersion command failed with status: {}. Stderr: {}",
-                        output.status,
-                        stderr.trim()
-                    );
+                        os_name.to_string()
+                    };
+                    debug!("Determined platform tag: {}", platform_tag);
+                    return platform_tag;
                 }
-            }
-            Err(e) => {
-                error!("Failed to execute /usr/bin/sw_vers -productVersion: {}", e);
-            }
+                None => error!("Unrecognized macOS version: {}", version),
+            },
+            None => error!("Could not determine the macOS version"),
         }
 
-        error!("!!! FAILED TO DETECT MACOS VERSION VIA SW_VERS !!!");
+        error!("!!! FAILED TO DETECT MACOS VERSION !!!");
         debug!("Using UNRELIABLE fallback platform detection. Bottle selection may be incorrect.");
         if arch == "arm64" {
             debug!("Falling back to platform tag: arm64_monterey");
@@ -161,12 +119,45 @@ pub fn get_formula_cellar_path(formula: &Formula, config: &Config) -> PathBuf {
     config.formula_cellar_dir(formula.name())
 }
 
-// --- write_receipt (unchanged) ---
+/// Writes the keg's INSTALL_RECEIPT.json. `generated_wrappers` are the `bin` wrappers
+/// the install step generated for `libexec` executables (see
+/// [`link::generate_libexec_wrappers`]). `installed_on_request` records whether the
+/// formula was asked for by name or only installed as a dependency. The receipt also
+/// lists the formula's runtime dependencies, which `sps autoremove` trusts over the
+/// current definition, and every file of the keg with its SHA-256, which `sps verify`
+/// checks the keg against later. Hashing is best-effort: if a file cannot be read, the
+/// file list is left out and the install goes ahead.
 pub fn write_receipt(
     formula: &Formula,
     install_dir: &Path,
     installation_type: &str, // "bottle" or "source"
+    installed_on_request: bool,
+    generated_wrappers: &[PathBuf],
+    config: &Config,
 ) -> Result<()> {
+    let generated_wrappers: Vec<String> = generated_wrappers
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    let files: Option<BTreeMap<String, String>> = match crate::check::diff::hash_tree(install_dir) {
+        Ok(files) => Some(
+            files
+                .into_iter()
+                .map(|(path, hash)| (path.to_string_lossy().to_string(), hash))
+                .collect(),
+        ),
+        Err(e) => {
+            warn!(
+                "Could not hash the files of {} for its receipt, so sps verify will not \
+                     check them: {}",
+                install_dir.display(),
+                e
+            );
+            None
+        }
+    };
+
     let receipt_path = install_dir.join("INSTALL_RECEIPT.json");
     let receipt_file = File::create(&receipt_path);
     let mut receipt_file = match receipt_file {
@@ -193,18 +184,31 @@ pub fn write_receipt(
         }
     };
 
+    let runtime_dependencies: Vec<String> = formula
+        .dependencies()?
+        .runtime()
+        .into_iter()
+        .map(|dep| dep.name.clone())
+        .collect();
+
     let timestamp = chrono::Utc::now().to_rfc3339();
 
-    let receipt = serde_json::json!({
+    let mut receipt = serde_json::json!({
         "name": formula.name, "version": formula.version_str_full(), "time": timestamp,
         "source": { "type": "api", "url": formula.url, },
         "built_on": {
             "os": std::env::consts::OS, "arch": std::env::consts::ARCH,
-            "platform_tag": get_current_platform(),
+            "platform_tag": get_current_platform(config),
          },
         "installation_type": installation_type,
+        sps_common::keg::INSTALLED_ON_REQUEST_KEY: installed_on_request,
         "resources_installed": resources_installed,
+        sps_common::keg::RUNTIME_DEPENDENCIES_KEY: runtime_dependencies,
+        "generated_wrappers": generated_wrappers,
     });
+    if let Some(files) = files {
+        receipt[crate::check::verify::RECORDED_FILES_KEY] = serde_json::json!(files);
+    }
 
     let receipt_json = match serde_json::to_string_pretty(&receipt) {
         Ok(json) => json,
@@ -228,3 +232,77 @@ pub fn write_receipt(
 // --- Re-exports (unchanged) ---
 pub use exec::install_bottle;
 pub use link::link_formula_artifacts;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn receipt(keg: &Path) -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(keg.join("INSTALL_RECEIPT.json")).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn receipts_record_wrappers_and_survive_unreadable_files() {
+        let keg = tempfile::tempdir().unwrap();
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "hello",
+            "versions": { "stable": "1.0" },
+        }))
+        .unwrap();
+        let config = Config::for_tests(keg.path());
+        let wrapper = keg.path().join("bin/hello");
+        fs::create_dir_all(keg.path().join("bin")).unwrap();
+        fs::write(&wrapper, "#!/bin/sh\n").unwrap();
+
+        write_receipt(
+            &formula,
+            keg.path(),
+            "source",
+            true,
+            std::slice::from_ref(&wrapper),
+            &config,
+        )
+        .unwrap();
+        let written = receipt(keg.path());
+        assert_eq!(
+            written["generated_wrappers"],
+            serde_json::json!([wrapper.to_string_lossy()])
+        );
+        assert!(written[crate::check::verify::RECORDED_FILES_KEY]["bin/hello"].is_string());
+
+        fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root can read the file regardless, so only check the fallback when it applies.
+        if File::open(&wrapper).is_err() {
+            write_receipt(&formula, keg.path(), "source", true, &[], &config).unwrap();
+            assert!(receipt(keg.path())
+                .get(crate::check::verify::RECORDED_FILES_KEY)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn configured_bottle_tag_selects_only_that_bottle() {
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "hello",
+            "versions": { "stable": "1.0" },
+            "bottle": { "stable": { "rebuild": 0, "files": {
+                "arm64_sonoma": { "url": "https://example.com/sonoma", "sha256": "aa" },
+            } } },
+        }))
+        .unwrap();
+        let mut config = Config::for_tests(Path::new("/opt/sps"));
+
+        config.bottle_tag = Some("arm64_sonoma".to_string());
+        let (tag, spec) = exec::get_bottle_for_platform(&formula, &config).unwrap();
+        assert_eq!(tag, "arm64_sonoma");
+        assert_eq!(spec.url, "https://example.com/sonoma");
+
+        config.bottle_tag = Some("ventura".to_string());
+        assert!(exec::get_bottle_for_platform(&formula, &config).is_err());
+        assert!(has_bottle_for_current_platform(&formula, &config));
+    }
+}
@@ -0,0 +1,215 @@
+// sps-core/src/install/cask/adopt.rs
+//! Brings an app bundle that is already on disk under sps management.
+//!
+//! The bundle is copied into a staging directory and then installed exactly like a
+//! freshly extracted download, so the private store, the `/Applications` symlink and
+//! the Caskroom manifest end up identical to a normal install.
+use std::path::Path;
+use std::process::Command;
+
+use plist::Value as PlistValue;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::cask::Cask;
+use sps_common::pipeline::JobAction;
+use tempfile::TempDir;
+use tracing::debug;
+
+use super::{artifacts, write_cask_manifest};
+
+/// Identity fields read from an app bundle's `Contents/Info.plist`.
+#[derive(Debug, Clone, Default)]
+pub struct AppBundleInfo {
+    pub bundle_identifier: Option<String>,
+    pub short_version: Option<String>,
+    pub bundle_version: Option<String>,
+}
+
+/// Reads the bundle identifier and versions from `app_path`'s Info.plist.
+pub fn read_app_bundle_info(app_path: &Path) -> Result<AppBundleInfo> {
+    let info_plist_path = app_path.join("Contents/Info.plist");
+    let value = PlistValue::from_file(&info_plist_path).map_err(|e| {
+        SpsError::Generic(format!(
+            "Failed to read {}: {}",
+            info_plist_path.display(),
+            e
+        ))
+    })?;
+    let dict = value.as_dictionary().ok_or_else(|| {
+        SpsError::Generic(format!(
+            "Info.plist at {} is not a dictionary",
+            info_plist_path.display()
+        ))
+    })?;
+    let get = |key: &str| {
+        dict.get(key)
+            .and_then(PlistValue::as_string)
+            .map(String::from)
+    };
+    Ok(AppBundleInfo {
+        bundle_identifier: get("CFBundleIdentifier"),
+        short_version: get("CFBundleShortVersionString"),
+        bundle_version: get("CFBundleVersion"),
+    })
+}
+
+/// Adopts the app bundle at `app_path` as an installation of `cask`.
+///
+/// Mismatches between the bundle and the cask definition (version, bundle
+/// identifier, app name) are returned for the caller to warn about; they do not
+/// stop the adoption. The original bundle is replaced by the usual symlink into the
+/// private store.
+pub fn adopt_app(cask: &Cask, app_path: &Path, config: &Config) -> Result<Vec<String>> {
+    if !app_path.is_dir() || app_path.extension().is_none_or(|ext| ext != "app") {
+        return Err(SpsError::InstallError(format!(
+            "{} is not an .app bundle",
+            app_path.display()
+        )));
+    }
+
+    let declared_app = declared_app_names(cask).into_iter().next();
+    let source_name = app_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let app_name = declared_app.clone().unwrap_or_else(|| source_name.clone());
+
+    let mut mismatches = Vec::new();
+    if declared_app
+        .as_deref()
+        .is_some_and(|name| name != source_name)
+    {
+        mismatches.push(format!(
+            "app is named '{source_name}' but cask '{}' installs '{app_name}'",
+            cask.token
+        ));
+    }
+    match read_app_bundle_info(app_path) {
+        Ok(info) => mismatches.extend(check_bundle_info(cask, &info)),
+        Err(e) => mismatches.push(format!("could not verify bundle metadata: {e}")),
+    }
+    if !mismatches.is_empty() {
+        debug!(
+            "[{}] Adopting despite mismatches: {:?}",
+            cask.token, mismatches
+        );
+    }
+
+    let stage_dir = TempDir::new().map_err(|e| {
+        SpsError::Io(std::sync::Arc::new(std::io::Error::new(
+            e.kind(),
+            format!("Failed to create staging directory: {e}"),
+        )))
+    })?;
+    let staged_app_path = stage_dir.path().join(&app_name);
+    debug!(
+        "[{}] Staging {} as {}",
+        cask.token,
+        app_path.display(),
+        staged_app_path.display()
+    );
+    // cp -pR keeps signatures and extended attributes intact, as on upgrade.
+    let cp_status = Command::new("cp")
+        .arg("-pR")
+        .arg(app_path)
+        .arg(&staged_app_path)
+        .status()
+        .map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
+    if !cp_status.success() {
+        return Err(SpsError::InstallError(format!(
+            "Failed to copy {} into staging area",
+            app_path.display()
+        )));
+    }
+
+    let cask_room_version_path = config.cask_room_version_path(
+        &cask.token,
+        &cask.version.clone().unwrap_or_else(|| "latest".to_string()),
+    );
+    let installed = artifacts::app::install_app_from_staged(
+        cask,
+        &staged_app_path,
+        &cask_room_version_path,
+        config,
+        &JobAction::Install,
+    )
+    .inspect_err(|_| {
+        let _ = std::fs::remove_dir_all(&cask_room_version_path);
+    })?;
+    write_cask_manifest(cask, &cask_room_version_path, installed)?;
+    debug!(
+        "[{}] Adopted {} into {}",
+        cask.token,
+        app_path.display(),
+        cask_room_version_path.display()
+    );
+    Ok(mismatches)
+}
+
+/// Names from the cask's `app` artifacts, in declaration order.
+fn declared_app_names(cask: &Cask) -> Vec<String> {
+    cask.artifacts
+        .iter()
+        .flatten()
+        .filter_map(|artifact| artifact.get("app")?.as_array())
+        .flatten()
+        .filter_map(|name| name.as_str().map(String::from))
+        .collect()
+}
+
+/// Bundle identifiers the cask names in `uninstall quit:` directives. Cask JSON has
+/// no dedicated field, so this is the closest declared identity.
+fn declared_bundle_ids(cask: &Cask) -> Vec<String> {
+    let mut ids = Vec::new();
+    for entry in cask.artifacts.iter().flatten() {
+        let Some(steps) = entry.get("uninstall").and_then(|v| v.as_array()) else {
+            continue;
+        };
+        for quit in steps.iter().filter_map(|step| step.get("quit")) {
+            match quit {
+                serde_json::Value::String(id) => ids.push(id.clone()),
+                serde_json::Value::Array(list) => {
+                    ids.extend(list.iter().filter_map(|v| v.as_str().map(String::from)))
+                }
+                _ => {}
+            }
+        }
+    }
+    ids
+}
+
+fn check_bundle_info(cask: &Cask, info: &AppBundleInfo) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    if let Some(cask_version) = cask.version.as_deref().filter(|v| *v != "latest") {
+        // Cask versions are often "<short>,<build>"; accept a match on either part.
+        let matches = cask_version.split(',').any(|part| {
+            info.short_version.as_deref() == Some(part)
+                || info.bundle_version.as_deref() == Some(part)
+        });
+        if !matches {
+            mismatches.push(format!(
+                "app version {} does not match cask version {}",
+                info.short_version
+                    .as_deref()
+                    .or(info.bundle_version.as_deref())
+                    .unwrap_or("<unknown>"),
+                cask_version
+            ));
+        }
+    }
+
+    let expected_ids = declared_bundle_ids(cask);
+    match &info.bundle_identifier {
+        Some(id) if !expected_ids.is_empty() && !expected_ids.contains(id) => {
+            mismatches.push(format!(
+                "bundle identifier {id} is not one declared by the cask ({})",
+                expected_ids.join(", ")
+            ));
+        }
+        None => mismatches.push("app has no CFBundleIdentifier".to_string()),
+        _ => {}
+    }
+
+    mismatches
+}
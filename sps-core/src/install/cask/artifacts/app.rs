@@ -10,6 +10,8 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::{debug, error, warn};
 
+use crate::install::cask::helpers::find_system_tool;
+
 #[cfg(target_os = "macos")]
 /// Finds the primary .app bundle in a directory. Returns an error if none or ambiguous.
 /// If multiple .app bundles are found, returns the first and logs a warning.
@@ -61,6 +63,11 @@ pub fn install_app_from_staged(
         )));
     }
 
+    // Checked before anything is moved into place, so a bad download installs nothing.
+    if config.verify_signature && cfg!(target_os = "macos") {
+        verify_app_signature(staged_app_path)?;
+    }
+
     let app_name = staged_app_path
         .file_name()
         .ok_or_else(|| {
@@ -286,7 +293,7 @@ pub fn install_app_from_staged(
 
     // 5. Set/Verify Quarantine on private store copy (only if not already present)
     #[cfg(target_os = "macos")]
-    {
+    if config.quarantine.quarantine_casks() {
         debug!(
             "Setting/verifying quarantine on private store copy: {}",
             final_private_store_app_path.display()
@@ -546,6 +553,41 @@ pub fn install_app_from_staged(
     Ok(created_artifacts)
 }
 
+/// Runs `codesign --verify --deep --strict` on an app bundle and logs who signed it.
+/// Fails with the reason codesign gives if the signature is missing or invalid.
+pub fn verify_app_signature(app_path: &Path) -> Result<()> {
+    let codesign = find_system_tool("codesign", "verifying app signatures")?;
+    let output = Command::new(&codesign)
+        .args(["--verify", "--deep", "--strict"])
+        .arg(app_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(SpsError::CodesignError(format!(
+            "Code signature of {} is invalid; the download may be corrupt or tampered with: {}",
+            app_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // `codesign -d` writes the signing details to stderr.
+    let details = Command::new(&codesign)
+        .args(["-d", "--verbose=2"])
+        .arg(app_path)
+        .output()?;
+    let details = String::from_utf8_lossy(&details.stderr);
+    let team_id = details
+        .lines()
+        .find_map(|line| line.strip_prefix("TeamIdentifier="))
+        .filter(|team| *team != "not set")
+        .unwrap_or("none (ad-hoc or unsigned team)");
+    debug!(
+        "Code signature of {} is valid (team identifier: {})",
+        app_path.display(),
+        team_id
+    );
+    Ok(())
+}
+
 /// Helper function for robust path removal (internal to app.rs or moved to a common util)
 fn remove_path_robustly(path: &Path, _config: &Config, use_sudo_if_needed: bool) -> bool {
     if !path.exists() && path.symlink_metadata().is_err() {
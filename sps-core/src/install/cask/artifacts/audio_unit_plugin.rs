@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Installs `audio_unit_plugin` bundles from the staging area into
@@ -30,12 +31,7 @@ pub fn install_audio_unit_plugin(
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("audio_unit_plugin").and_then(|v| v.as_array()) {
                     // Target directory for Audio Unit components
-                    let dest_dir = config
-                        .home_dir()
-                        .join("Library")
-                        .join("Audio")
-                        .join("Plug-Ins")
-                        .join("Components");
+                    let dest_dir = library_dir(config, "Audio/Plug-Ins/Components");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Installs any `colorpicker` stanzas from the Cask definition.
@@ -41,12 +42,9 @@ pub fn install_colorpicker(
                                 continue;
                             }
 
-                            // Ensure ~/Library/ColorPickers exists
+                            // Ensure the ColorPickers directory exists
                             // :contentReference[oaicite:4]{index=4}
-                            let dest_dir = config
-                                .home_dir() // e.g. /Users/alxknt
-                                .join("Library")
-                                .join("ColorPickers");
+                            let dest_dir = library_dir(config, "ColorPickers");
                             fs::create_dir_all(&dest_dir)?;
 
                             let dest = dest_dir.join(bundle_name);
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Implements the `dictionary` stanza by moving each declared
@@ -46,12 +47,9 @@ pub fn install_dictionary(
                                 continue;
                             }
 
-                            // Standard user dictionary directory: ~/Library/Dictionaries
+                            // Standard dictionary directory
                             // :contentReference[oaicite:3]{index=3}
-                            let dest_dir = config
-                                .home_dir() // e.g. /Users/alxknt
-                                .join("Library")
-                                .join("Dictionaries");
+                            let dest_dir = library_dir(config, "Dictionaries");
                             fs::create_dir_all(&dest_dir)?;
 
                             let dest = dest_dir.join(bundle_name);
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Implements the `font` stanza by moving each declared
@@ -31,8 +32,8 @@ pub fn install_font(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("font").and_then(|v| v.as_array()) {
-                    // Target directory for user fonts
-                    let dest_dir = config.home_dir().join("Library").join("Fonts");
+                    // Target directory for fonts
+                    let dest_dir = library_dir(config, "Fonts");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
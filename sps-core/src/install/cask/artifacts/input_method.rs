@@ -9,6 +9,7 @@ use sps_common::error::Result;
 use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 use crate::install::cask::write_cask_manifest;
 
@@ -31,9 +32,8 @@ pub fn install_input_method(
                         if let Some(name) = name_val.as_str() {
                             let source = stage_path.join(name);
                             if source.exists() {
-                                // Target directory: ~/Library/Input Methods
-                                let target_dir =
-                                    config.home_dir().join("Library").join("Input Methods");
+                                // Target directory: Input Methods
+                                let target_dir = library_dir(config, "Input Methods");
                                 if !target_dir.exists() {
                                     fs::create_dir_all(&target_dir)?;
                                 }
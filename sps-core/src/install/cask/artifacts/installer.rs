@@ -1,13 +1,14 @@
 // ===== sps-core/src/build/cask/artifacts/installer.rs =====
 
 use std::path::Path;
-use std::process::{Command, Stdio};
 
 use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::cask::Cask;
+use sps_common::pipeline::InstallerOutputFn;
 use tracing::debug;
 
+use crate::install::cask::helpers::run_installer_command;
 use crate::install::cask::InstalledArtifact;
 
 // Helper to validate that the executable is a filename (relative, no '/' or "..")
@@ -41,14 +42,17 @@ fn validate_argument(arg: &str) -> Result<String> {
 
 /// Implements the `installer` stanza:
 /// - `manual`: prints instructions to open the staged path.
-/// - `script`: runs the given executable with args, under sudo if requested.
+/// - `script`: runs the given executable with args, under sudo if requested. Its output
+///   is passed to `output` and it is stopped on Ctrl-C or after the build timeout, as a
+///   pkg installer is.
 ///
 /// Mirrors Homebrew’s `Cask::Artifact::Installer` behavior :contentReference[oaicite:1]{index=1}.
 pub fn run_installer(
     cask: &Cask,
     stage_path: &Path,
     _cask_version_install_path: &Path,
-    _config: &Config,
+    config: &Config,
+    output: Option<&InstallerOutputFn>,
 ) -> Result<Vec<InstalledArtifact>> {
     let mut installed = Vec::new();
 
@@ -67,20 +71,22 @@ pub fn run_installer(
                                 );
                                 continue;
                             }
-                            let exe_key = if inst_obj.contains_key("script") {
-                                "script"
-                            } else {
-                                "executable"
-                            };
+                            // The API gives `script: { executable, args, sudo }`; a bare
+                            // `script: "path"` with the options alongside is accepted too.
+                            let spec = inst_obj
+                                .get("script")
+                                .and_then(|v| v.as_object())
+                                .unwrap_or(inst_obj);
                             let executable = inst_obj
-                                .get(exe_key)
+                                .get("script")
                                 .and_then(|v| v.as_str())
+                                .or_else(|| spec.get("executable").and_then(|v| v.as_str()))
                                 .ok_or_else(|| {
-                                    SpsError::Generic(format!(
-                                        "installer stanza missing '{exe_key}' field"
-                                    ))
+                                    SpsError::Generic(
+                                        "installer stanza missing 'script' executable".to_string(),
+                                    )
                                 })?;
-                            let args: Vec<String> = inst_obj
+                            let args: Vec<String> = spec
                                 .get("args")
                                 .and_then(|v| v.as_array())
                                 .map(|arr| {
@@ -89,10 +95,8 @@ pub fn run_installer(
                                         .collect()
                                 })
                                 .unwrap_or_default();
-                            let use_sudo = inst_obj
-                                .get("sudo")
-                                .and_then(|v| v.as_bool())
-                                .unwrap_or(false);
+                            let use_sudo =
+                                spec.get("sudo").and_then(|v| v.as_bool()).unwrap_or(false);
 
                             let validated_executable =
                                 validate_filename_or_relative_path(executable)?;
@@ -114,24 +118,21 @@ pub fn run_installer(
                                 script_path.display(),
                                 cask.token
                             );
-                            let mut cmd = if use_sudo {
-                                let mut c = Command::new("sudo");
-                                c.arg(script_path.clone());
-                                c
-                            } else {
-                                Command::new(script_path.clone())
-                            };
-                            cmd.args(&validated_args);
-                            cmd.stdin(Stdio::null())
-                                .stdout(Stdio::inherit())
-                                .stderr(Stdio::inherit());
-
-                            let status = cmd.status().map_err(|e| {
-                                SpsError::Generic(format!("Failed to spawn installer script: {e}"))
-                            })?;
-                            if !status.success() {
+                            let context = format!("installer script of {}", cask.token);
+                            let result = run_installer_command(
+                                &script_path,
+                                &validated_args,
+                                use_sudo,
+                                &context,
+                                config,
+                                output,
+                            )?;
+                            if !result.status.success() {
+                                let stderr = String::from_utf8_lossy(&result.stderr);
                                 return Err(SpsError::InstallError(format!(
-                                    "Installer script exited with {status}"
+                                    "Installer script exited with {}: {}",
+                                    result.status,
+                                    stderr.trim()
                                 )));
                             }
 
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Implements the `internet_plugin` stanza by moving each declared
@@ -31,8 +32,8 @@ pub fn install_internet_plugin(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("internet_plugin").and_then(|v| v.as_array()) {
-                    // Target directory for user internet plugins
-                    let dest_dir = config.home_dir().join("Library").join("Internet Plug-Ins");
+                    // Target directory for internet plugins
+                    let dest_dir = library_dir(config, "Internet Plug-Ins");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Mirrors Homebrew’s `KeyboardLayout < Moved` behavior.
@@ -26,8 +27,8 @@ pub fn install_keyboard_layout(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("keyboard_layout").and_then(|v| v.as_array()) {
-                    // Target directory for user keyboard layouts
-                    let dest_dir = config.home_dir().join("Library").join("Keyboard Layouts");
+                    // Target directory for keyboard layouts
+                    let dest_dir = library_dir(config, "Keyboard Layouts");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
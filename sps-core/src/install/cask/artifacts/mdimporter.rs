@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Installs `mdimporter` bundles from the staging area into
@@ -30,8 +31,8 @@ pub fn install_mdimporter(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("mdimporter").and_then(|v| v.as_array()) {
-                    // Target directory for user Spotlight importers
-                    let dest_dir = config.home_dir().join("Library").join("Spotlight");
+                    // Target directory for Spotlight importers
+                    let dest_dir = library_dir(config, "Spotlight");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
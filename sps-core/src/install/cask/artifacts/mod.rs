@@ -22,6 +22,11 @@ pub mod vst3_plugin;
 pub mod vst_plugin;
 pub mod zap;
 
+use std::path::{Path, PathBuf};
+
+use sps_common::config::Config;
+use tracing::debug;
+
 // Re‑export a single enum if you like:
 pub use self::app::install_app_from_staged;
 pub use self::audio_unit_plugin::install_audio_unit_plugin;
@@ -45,3 +50,91 @@ pub use self::suite::install_suite;
 pub use self::uninstall::record_uninstall;
 pub use self::vst3_plugin::install_vst3_plugin;
 pub use self::vst_plugin::install_vst_plugin;
+
+/// The directory under `Library` that a moved artifact (font, plugin, ...) goes to.
+///
+/// Like Homebrew, artifacts go to the per-user `~/Library/<subdir>` by default. With
+/// `cask_user_scope` turned off they go to the system-wide `/Library/<subdir>`
+/// instead, falling back to the per-user directory when the current user cannot write
+/// there (a non-admin account). Installers record the returned path in the cask
+/// manifest, so uninstall removes the artifact from whichever location was chosen.
+pub fn library_dir(config: &Config, subdir: &str) -> PathBuf {
+    select_library_dir(
+        Path::new("/Library"),
+        &config.home_dir().join("Library"),
+        subdir,
+        config.cask_user_scope,
+    )
+}
+
+fn select_library_dir(
+    system_library: &Path,
+    user_library: &Path,
+    subdir: &str,
+    user_scope: bool,
+) -> PathBuf {
+    let user_dir = user_library.join(subdir);
+    if user_scope {
+        return user_dir;
+    }
+    let system_dir = system_library.join(subdir);
+    if is_writable(&system_dir) {
+        return system_dir;
+    }
+    debug!(
+        "{} is not writable; using {}",
+        system_dir.display(),
+        user_dir.display()
+    );
+    user_dir
+}
+
+/// Whether `dir` can be written to, or created inside its nearest existing ancestor.
+fn is_writable(dir: &Path) -> bool {
+    match dir.ancestors().find(|ancestor| ancestor.exists()) {
+        Some(existing) if existing.is_dir() => tempfile::tempfile_in(existing).is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn user_scope_always_uses_the_user_library() {
+        let root = tempfile::tempdir().unwrap();
+        let (system, user) = (root.path().join("system"), root.path().join("user"));
+        fs::create_dir_all(&system).unwrap();
+
+        assert_eq!(
+            select_library_dir(&system, &user, "Fonts", true),
+            user.join("Fonts")
+        );
+    }
+
+    #[test]
+    fn system_scope_falls_back_when_the_system_library_is_read_only() {
+        let root = tempfile::tempdir().unwrap();
+        let (system, user) = (root.path().join("system"), root.path().join("user"));
+        fs::create_dir_all(&system).unwrap();
+
+        assert_eq!(
+            select_library_dir(&system, &user, "Audio/Plug-Ins/VST", false),
+            system.join("Audio/Plug-Ins/VST")
+        );
+
+        fs::set_permissions(&system, fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores directory permissions, so only check the fallback when they apply.
+        if tempfile::tempfile_in(&system).is_err() {
+            assert_eq!(
+                select_library_dir(&system, &user, "Audio/Plug-Ins/VST", false),
+                user.join("Audio/Plug-Ins/VST")
+            );
+        }
+        fs::set_permissions(&system, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
@@ -1,20 +1,31 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::cask::Cask;
+use sps_common::pipeline::InstallerOutputFn;
 use tracing::{debug, error};
 
+use crate::install::cask::helpers::{find_system_tool, run_installer_command};
 use crate::install::cask::InstalledArtifact; // Artifact type alias is just Value
 
-/// Installs a PKG file and returns details of artifacts created/managed.
+/// Held while a pkg installer runs; see [`receipts_written_since`].
+static PKG_INSTALL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Installs a PKG file and returns details of artifacts created/managed. The installer's
+/// progress is passed to `output`, and the package receipts it writes are recorded as
+/// well as those the cask's `uninstall` stanza names.
 pub fn install_pkg_from_path(
     cask: &Cask,
     pkg_path: &Path,
     cask_version_install_path: &Path, // e.g., /opt/homebrew/Caskroom/foo/1.2.3
-    _config: &Config,                 // Keep for potential future use
+    config: &Config,
+    output: Option<&InstallerOutputFn>,
 ) -> Result<Vec<InstalledArtifact>> {
     // <-- Return type changed
     debug!("Installing pkg file: {}", pkg_path.display());
@@ -66,28 +77,39 @@ pub fn install_pkg_from_path(
     }
 
     // --- Run Installer ---
-    debug!("Running installer (this may require sudo)");
     debug!(
-        "Executing: sudo installer -pkg {} -target /",
+        "Executing: sudo installer -pkg {} -target / -verboseR",
         pkg_path.display()
     );
-    let output = Command::new("sudo")
-        .arg("installer")
-        .arg("-pkg")
-        .arg(pkg_path)
-        .arg("-target")
-        .arg("/")
-        .output()
-        .map_err(|e| {
-            SpsError::Io(std::sync::Arc::new(std::io::Error::new(
-                e.kind(),
-                format!("Failed to execute sudo installer: {e}"),
-            )))
-        })?;
+    let installer = find_system_tool("installer", &format!("install {}", pkg_path.display()))?;
+    let progress = output.map(|output| {
+        let output = output.clone();
+        let report: InstallerOutputFn = Arc::new(move |line: &str| {
+            if let Some(message) = installer_progress(line) {
+                output(&message);
+            }
+        });
+        report
+    });
+    let args = [
+        OsStr::new("-pkg"),
+        pkg_path.as_os_str(),
+        OsStr::new("-target"),
+        OsStr::new("/"),
+        OsStr::new("-verboseR"),
+    ];
+    let context = format!("installer for {}", pkg_path.display());
+    // One pkg at a time, so that the receipts written meanwhile are this one's.
+    let _guard = PKG_INSTALL_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let receipts_before = receipt_times();
+    let result =
+        run_installer_command(&installer, &args, true, &context, config, progress.as_ref())?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        error!("sudo installer failed ({}): {}", output.status, stderr);
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        error!("sudo installer failed ({}): {}", result.status, stderr);
         // Don't clean up the reference copy here, let the main process handle directory removal on
         // failure
         return Err(SpsError::InstallError(format!(
@@ -97,9 +119,14 @@ pub fn install_pkg_from_path(
         )));
     }
     debug!("Successfully ran installer command.");
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    if !stdout.trim().is_empty() {
-        debug!("Installer stdout:\n{}", stdout);
+
+    // --- Record the receipts the installer wrote ---
+    for id in receipts_written_since(&receipts_before) {
+        debug!("Installer wrote package receipt: {}", id);
+        let new_artifact = InstalledArtifact::PkgUtilReceipt { id };
+        if !installed_artifacts.contains(&new_artifact) {
+            installed_artifacts.push(new_artifact);
+        }
     }
 
     // --- Record PkgUtil Receipts (based on cask definition) ---
@@ -133,3 +160,55 @@ pub fn install_pkg_from_path(
     debug!("Successfully installed pkg: {}", pkg_path.display());
     Ok(installed_artifacts) // <-- Return collected artifacts
 }
+
+/// Where macOS keeps the receipt of every installed package, as `<id>.plist` and
+/// `<id>.bom`.
+const RECEIPTS_DIR: &str = "/var/db/receipts";
+
+/// The modification time of every package receipt, by package id.
+fn receipt_times() -> HashMap<String, SystemTime> {
+    let Ok(entries) = fs::read_dir(RECEIPTS_DIR) else {
+        debug!("Could not read {}", RECEIPTS_DIR);
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("plist")) {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((id, modified))
+        })
+        .collect()
+}
+
+/// The ids of the receipts that are new or rewritten since `before` was taken, i.e.
+/// written by the installer that ran in between; reinstalling a package rewrites its
+/// receipt too.
+fn receipts_written_since(before: &HashMap<String, SystemTime>) -> Vec<String> {
+    let mut ids: Vec<String> = receipt_times()
+        .into_iter()
+        .filter(|(id, modified)| before.get(id) != Some(modified))
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Turns a line of `installer -verboseR` output into a progress message: the phase it
+/// is in, or how far along it is. Other lines are dropped.
+fn installer_progress(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("installer:")?;
+    if let Some(percent) = rest.strip_prefix('%') {
+        let percent: f64 = percent.trim().parse().ok()?;
+        return Some(format!("{percent:.0}%"));
+    }
+    let phase = rest
+        .strip_prefix("PHASE:")
+        .or_else(|| rest.strip_prefix("STATUS:"))?
+        .trim();
+    (!phase.is_empty()).then(|| phase.to_string())
+}
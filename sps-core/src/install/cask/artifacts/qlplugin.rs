@@ -11,7 +11,8 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
-use crate::install::cask::helpers::remove_path_robustly;
+use super::library_dir;
+use crate::install::cask::helpers::{find_staged_bundle, remove_path_robustly};
 
 /// Installs `qlplugin` bundles from the staging area into
 /// `~/Library/QuickLook`, then symlinks them into the Caskroom.
@@ -31,21 +32,26 @@ pub fn install_qlplugin(
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("qlplugin").and_then(|v| v.as_array()) {
                     // Target directory for QuickLook plugins
-                    let dest_dir = config.home_dir().join("Library").join("QuickLook");
+                    let dest_dir = library_dir(config, "QuickLook");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
                         if let Some(bundle_name) = entry.as_str() {
-                            let src = stage_path.join(bundle_name);
-                            if !src.exists() {
+                            let Some(src) =
+                                find_staged_bundle(stage_path, bundle_name, "qlgenerator")
+                            else {
                                 debug!(
                                     "QuickLook plugin '{}' not found in staging; skipping",
                                     bundle_name
                                 );
                                 continue;
-                            }
+                            };
+                            let installed_name = src
+                                .file_name()
+                                .map(|n| n.to_string_lossy().into_owned())
+                                .unwrap_or_else(|| bundle_name.to_string());
 
-                            let dest = dest_dir.join(bundle_name);
+                            let dest = dest_dir.join(&installed_name);
                             if dest.exists() {
                                 let _ = remove_path_robustly(&dest, config, true);
                             }
@@ -65,7 +71,7 @@ pub fn install_qlplugin(
                             installed.push(InstalledArtifact::MovedResource { path: dest.clone() });
 
                             // Symlink into Caskroom for reference
-                            let link = cask_version_install_path.join(bundle_name);
+                            let link = cask_version_install_path.join(&installed_name);
                             let _ = remove_path_robustly(&link, config, true);
                             symlink(&dest, &link)?;
                             installed.push(InstalledArtifact::CaskroomLink {
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers::remove_path_robustly;
 
 /// Installs `screen_saver` bundles from the staging area into
@@ -29,8 +30,8 @@ pub fn install_screen_saver(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("screen_saver").and_then(|v| v.as_array()) {
-                    // Target directory for user screen savers
-                    let dest_dir = config.home_dir().join("Library").join("Screen Savers");
+                    // Target directory for screen savers
+                    let dest_dir = library_dir(config, "Screen Savers");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
@@ -11,6 +11,7 @@ use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use super::library_dir;
 use crate::install::cask::helpers;
 
 /// Installs `service` artifacts by moving each declared
@@ -30,8 +31,8 @@ pub fn install_service(
         for art in artifacts_def {
             if let Some(obj) = art.as_object() {
                 if let Some(entries) = obj.get("service").and_then(|v| v.as_array()) {
-                    // Target directory for user Services
-                    let dest_dir = config.home_dir().join("Library").join("Services");
+                    // Target directory for Services
+                    let dest_dir = library_dir(config, "Services");
                     fs::create_dir_all(&dest_dir)?;
 
                     for entry in entries {
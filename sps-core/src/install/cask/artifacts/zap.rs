@@ -10,6 +10,7 @@ use sps_common::error::Result;
 use sps_common::model::cask::Cask;
 use tracing::debug;
 
+use crate::install::cask::helpers::find_system_tool;
 use crate::install::cask::InstalledArtifact;
 
 /// Implements the `zap` stanza by performing deep-clean actions
@@ -109,12 +110,20 @@ pub fn install_zap(cask: &Cask, config: &Config) -> Result<Vec<InstalledArtifact
                                                     continue;
                                                 }
                                                 debug!("Forgetting pkgutil receipt {}...", item);
-                                                let _ = Command::new("pkgutil")
-                                                    .arg("--forget")
-                                                    .arg(item)
-                                                    .stdout(Stdio::null())
-                                                    .stderr(Stdio::null())
-                                                    .status();
+                                                match find_system_tool(
+                                                    "pkgutil",
+                                                    &format!("forget package receipt {item}"),
+                                                ) {
+                                                    Ok(pkgutil) => {
+                                                        let _ = Command::new(pkgutil)
+                                                            .arg("--forget")
+                                                            .arg(item)
+                                                            .stdout(Stdio::null())
+                                                            .stderr(Stdio::null())
+                                                            .status();
+                                                    }
+                                                    Err(e) => debug!("{}", e),
+                                                }
                                                 artifacts.push(InstalledArtifact::PkgUtilReceipt {
                                                     id: item.to_string(),
                                                 });
@@ -1,28 +1,79 @@
 // In sps-core/src/build/cask/dmg.rs
 
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
 
 use sps_common::error::{Result, SpsError};
-use tracing::{debug, error}; // Added log imports
+use tempfile::TempDir;
+use tracing::{debug, error, info}; // Added log imports
 
-// --- Keep Existing Helpers ---
-pub fn mount_dmg(dmg_path: &Path) -> Result<PathBuf> {
+use crate::install::cask::helpers::find_system_tool;
+
+/// Written to hdiutil's stdin when an image has a license agreement, answering the
+/// "Agree Y/N?" prompt the way `yes | hdiutil attach` would.
+const LICENSE_ANSWER: &[u8] = b"Y\n";
+/// Upper bound on answers, in case hdiutil keeps reading without exiting.
+const MAX_LICENSE_ANSWERS: usize = 1000;
+
+/// A mounted DMG, detached when dropped so that errors between mounting and
+/// [`MountedDmg::detach`] never leave the volume attached.
+#[derive(Debug)]
+pub struct MountedDmg {
+    mount_point: PathBuf,
+    /// License-free copy of the image that is mounted instead of the original; it has
+    /// to outlive the mount.
+    _converted_image: Option<TempDir>,
+    detached: bool,
+}
+
+impl MountedDmg {
+    pub fn path(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// Detaches the volume, reporting failure instead of only logging it.
+    pub fn detach(mut self) -> Result<()> {
+        self.detached = true;
+        unmount_dmg(&self.mount_point)
+    }
+}
+
+impl Drop for MountedDmg {
+    fn drop(&mut self) {
+        if !self.detached {
+            if let Err(e) = unmount_dmg(&self.mount_point) {
+                error!(
+                    "Could not detach DMG at {}: {}",
+                    self.mount_point.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Mounts a DMG without user interaction. Images with a software license agreement
+/// get it accepted on stdin; if hdiutil still refuses, the image is converted to a
+/// CD/DVD master, which carries no license, and that is mounted instead.
+pub fn mount_dmg(dmg_path: &Path) -> Result<MountedDmg> {
     debug!("Mounting DMG: {}", dmg_path.display());
-    let output = Command::new("hdiutil")
-        .arg("attach")
-        .arg("-plist")
-        .arg("-nobrowse")
-        .arg("-readonly")
-        .arg("-mountrandom")
-        .arg("/tmp") // Consider making mount location configurable or more robust
-        .arg(dmg_path)
-        .output()?;
+    let hdiutil = find_system_tool("hdiutil", &format!("mount {}", dmg_path.display()))?;
+    let has_license = has_license_agreement(&hdiutil, dmg_path);
+    if has_license {
+        info!(
+            "{} has a license agreement; accepting it to mount the image unattended",
+            dmg_path.display()
+        );
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
+    let output = attach(&hdiutil, dmg_path, has_license)?;
+    if output.status.success() {
+        return mounted(&output.stdout, None);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !has_license && !stderr.to_lowercase().contains("licen") {
         error!(
             "hdiutil attach failed for {}: {}",
             dmg_path.display(),
@@ -35,33 +86,141 @@ pub fn mount_dmg(dmg_path: &Path) -> Result<PathBuf> {
         )));
     }
 
-    let mount_point = parse_mount_point(&output.stdout)?;
+    debug!(
+        "Mounting {} with its license accepted failed ({}); converting it to a license-free image",
+        dmg_path.display(),
+        stderr.trim()
+    );
+    let scratch = TempDir::new().map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
+    let converted = scratch.path().join("image.cdr");
+    let convert_output = Command::new(&hdiutil)
+        .arg("convert")
+        .arg("-quiet")
+        .arg("-format")
+        .arg("UDTO")
+        .arg("-o")
+        .arg(&converted)
+        .arg(dmg_path)
+        .stdin(Stdio::null())
+        .output()?;
+    if !convert_output.status.success() {
+        return Err(SpsError::Generic(format!(
+            "Failed to mount DMG '{}' (license agreement could not be accepted): {}",
+            dmg_path.display(),
+            String::from_utf8_lossy(&convert_output.stderr)
+        )));
+    }
+    let output = attach(&hdiutil, &converted, false)?;
+    if !output.status.success() {
+        return Err(SpsError::Generic(format!(
+            "Failed to mount license-free copy of DMG '{}': {}",
+            dmg_path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    mounted(&output.stdout, Some(scratch))
+}
+
+fn mounted(plist: &[u8], converted_image: Option<TempDir>) -> Result<MountedDmg> {
+    let mount_point = parse_mount_point(plist)?;
     debug!("DMG mounted at: {}", mount_point.display());
-    Ok(mount_point)
+    Ok(MountedDmg {
+        mount_point,
+        _converted_image: converted_image,
+        detached: false,
+    })
+}
+
+/// Runs `hdiutil attach`. Without `accept_license` stdin is closed, so an unexpected
+/// license prompt fails instead of waiting for input forever.
+fn attach(hdiutil: &Path, image: &Path, accept_license: bool) -> Result<Output> {
+    let mut cmd = Command::new(hdiutil);
+    cmd.arg("attach")
+        .arg("-plist")
+        .arg("-nobrowse")
+        .arg("-readonly")
+        .arg("-mountrandom")
+        .arg("/tmp") // Consider making mount location configurable or more robust
+        .arg(image)
+        // Print the agreement straight through instead of opening it in a pager.
+        .env("PAGER", "cat")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !accept_license {
+        return Ok(cmd.stdin(Stdio::null()).output()?);
+    }
+
+    let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Stops with a broken pipe once hdiutil has stopped reading.
+        std::thread::spawn(move || {
+            for _ in 0..MAX_LICENSE_ANSWERS {
+                if stdin.write_all(LICENSE_ANSWER).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(child.wait_with_output()?)
 }
 
+/// Whether `hdiutil imageinfo` reports a software license agreement for the image.
+fn has_license_agreement(hdiutil: &Path, dmg_path: &Path) -> bool {
+    let output = match Command::new(hdiutil)
+        .arg("imageinfo")
+        .arg("-plist")
+        .arg(dmg_path)
+        .stdin(Stdio::null())
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!(
+                "hdiutil imageinfo failed for {}: {}",
+                dmg_path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return false;
+        }
+        Err(e) => {
+            debug!("Could not run hdiutil imageinfo: {}", e);
+            return false;
+        }
+    };
+    let plist = String::from_utf8_lossy(&output.stdout);
+    let mut lines = plist.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line == "<key>Software License Agreement</key>" {
+            return lines.next() == Some("<true/>");
+        }
+    }
+    false
+}
+
+/// Detaches a DMG volume: a plain `hdiutil detach` first, then `-force` if something
+/// still holds it open, then `diskutil unmount force` as a last resort.
 pub fn unmount_dmg(mount_point: &Path) -> Result<()> {
     debug!("Unmounting DMG from: {}", mount_point.display());
-    // Add logging for commands
-    debug!("Executing: hdiutil detach -force {}", mount_point.display());
-    let output = Command::new("hdiutil")
-        .arg("detach")
-        .arg("-force")
-        .arg(mount_point)
-        .output()?;
+    let detached = match find_system_tool("hdiutil", &format!("unmount {}", mount_point.display()))
+    {
+        Ok(hdiutil) => {
+            hdiutil_detach(&hdiutil, mount_point, false)?
+                || hdiutil_detach(&hdiutil, mount_point, true)?
+        }
+        Err(e) => {
+            debug!("{}. Trying diskutil", e);
+            false
+        }
+    };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        debug!(
-            "hdiutil detach failed ({}): {}. Trying diskutil",
-            output.status, stderr
-        );
+    if !detached {
         // Add logging for fallback
         debug!(
             "Executing: diskutil unmount force {}",
             mount_point.display()
         );
-        let diskutil_output = Command::new("diskutil")
+        let diskutil = find_system_tool("diskutil", &format!("unmount {}", mount_point.display()))?;
+        let diskutil_output = Command::new(diskutil)
             .arg("unmount")
             .arg("force")
             .arg(mount_point)
@@ -85,6 +244,29 @@ pub fn unmount_dmg(mount_point: &Path) -> Result<()> {
     Ok(())
 }
 
+fn hdiutil_detach(hdiutil: &Path, mount_point: &Path, force: bool) -> Result<bool> {
+    let mut cmd = Command::new(hdiutil);
+    cmd.arg("detach");
+    if force {
+        cmd.arg("-force");
+    }
+    debug!(
+        "Executing: hdiutil detach{} {}",
+        if force { " -force" } else { "" },
+        mount_point.display()
+    );
+    let output = cmd.arg(mount_point).stdin(Stdio::null()).output()?;
+    if !output.status.success() {
+        debug!(
+            "hdiutil detach{} failed ({}): {}",
+            if force { " -force" } else { "" },
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.status.success())
+}
+
 fn parse_mount_point(output: &[u8]) -> Result<PathBuf> {
     // ... (existing implementation) ...
     // Use plist crate for more robust parsing if possible in the future
@@ -148,10 +330,10 @@ fn parse_mount_point(output: &[u8]) -> Result<PathBuf> {
     }
 }
 
-// --- NEW Function ---
 /// Extracts the contents of a mounted DMG to a staging directory using `ditto`.
 pub fn extract_dmg_to_stage(dmg_path: &Path, stage_dir: &Path) -> Result<()> {
-    let mount_point = mount_dmg(dmg_path)?;
+    let mount = mount_dmg(dmg_path)?;
+    let mount_point = mount.path();
 
     // Ensure the stage directory exists (though TempDir should handle it)
     if !stage_dir.exists() {
@@ -171,11 +353,11 @@ pub fn extract_dmg_to_stage(dmg_path: &Path, stage_dir: &Path) -> Result<()> {
         stage_dir.display()
     );
     let ditto_output = Command::new("ditto")
-        .arg(&mount_point) // Source first
+        .arg(mount_point) // Source first
         .arg(stage_dir) // Then destination
         .output()?;
 
-    let unmount_result = unmount_dmg(&mount_point); // Unmount regardless of ditto success
+    let unmount_result = mount.detach(); // Unmount regardless of ditto success
 
     if !ditto_output.status.success() {
         let stderr = String::from_utf8_lossy(&ditto_output.stderr);
@@ -191,20 +373,5 @@ pub fn extract_dmg_to_stage(dmg_path: &Path, stage_dir: &Path) -> Result<()> {
         )));
     }
 
-    // After ditto, quarantine any .app bundles in the stage (macOS only)
-    #[cfg(target_os = "macos")]
-    {
-        if let Err(e) = crate::install::extract::quarantine_extracted_apps_in_stage(
-            stage_dir,
-            "sps-dmg-extractor",
-        ) {
-            tracing::warn!(
-                "Error during post-DMG extraction quarantine scan for {}: {}",
-                dmg_path.display(),
-                e
-            );
-        }
-    }
-
     unmount_result // Return the result of unmounting
 }
@@ -1,9 +1,16 @@
+use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
 
 use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::pipeline::InstallerOutputFn;
 use tracing::debug;
 
+use crate::build::process;
+
 /// Robustly removes a file or directory, handling symlinks and permissions.
 /// If `use_sudo_if_needed` is true, will attempt `sudo rm -rf` on permission errors.
 pub fn remove_path_robustly(path: &Path, _config: &Config, use_sudo_if_needed: bool) -> bool {
@@ -99,3 +106,150 @@ pub fn cleanup_empty_parent_dirs_in_private_store(start_path: &Path, stop_at: &P
         }
     }
 }
+
+/// Locates a bundle named by a cask artifact stanza inside the staging area.
+///
+/// Checks `stage_path/bundle_name` first, then one directory level down (archives often
+/// wrap the bundle in a folder or ship it inside an app). If nothing has that exact name,
+/// falls back to the single bundle with the given `extension` at either level.
+pub fn find_staged_bundle(
+    stage_path: &Path,
+    bundle_name: &str,
+    extension: &str,
+) -> Option<PathBuf> {
+    let direct = stage_path.join(bundle_name);
+    if direct.exists() {
+        return Some(direct);
+    }
+
+    let has_extension = |path: &Path| {
+        path.extension()
+            .is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+    };
+    let subdirs: Vec<PathBuf> = fs::read_dir(stage_path)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+
+    for dir in &subdirs {
+        let nested = dir.join(bundle_name);
+        if nested.exists() {
+            debug!(
+                "Found '{}' nested one level down at {}",
+                bundle_name,
+                nested.display()
+            );
+            return Some(nested);
+        }
+    }
+
+    let mut candidates: Vec<PathBuf> = subdirs
+        .iter()
+        .filter(|p| has_extension(p))
+        .cloned()
+        .collect();
+    for dir in subdirs.iter().filter(|p| !has_extension(p)) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            candidates.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| has_extension(path)),
+            );
+        }
+    }
+    match candidates.len() {
+        1 => {
+            let found = candidates.remove(0);
+            debug!(
+                "'{}' not found by name; using the only .{} bundle in staging: {}",
+                bundle_name,
+                extension,
+                found.display()
+            );
+            Some(found)
+        }
+        0 => None,
+        n => {
+            debug!(
+                "'{}' not found by name and {} .{} bundles are staged; not guessing",
+                bundle_name, n, extension
+            );
+            None
+        }
+    }
+}
+
+/// Directories the macOS system tools live in; checked before PATH so that a binary of
+/// the same name earlier on PATH does not shadow them.
+const SYSTEM_TOOL_DIRS: [&str; 2] = ["/usr/bin", "/usr/sbin"];
+
+/// Resolves a system tool such as `hdiutil`, `installer` or `pkgutil` before a cask
+/// artifact handler runs it. `needed_for` says what the tool was about to do, so that a
+/// missing tool is reported as such instead of as a bare spawn error.
+pub fn find_system_tool(tool: &str, needed_for: &str) -> Result<PathBuf> {
+    if let Some(path) = SYSTEM_TOOL_DIRS
+        .iter()
+        .map(|dir| Path::new(dir).join(tool))
+        .find(|path| path.is_file())
+    {
+        return Ok(path);
+    }
+    which::which(tool).map_err(|_| {
+        SpsError::CommandExecError(format!(
+            "`{}` is needed to {} but was not found in {} or on PATH",
+            tool,
+            needed_for,
+            SYSTEM_TOOL_DIRS.join(", ")
+        ))
+    })
+}
+
+/// Runs a pkg installer or installer script of a cask, as root through sudo when
+/// `privileged`. Like a source build command it runs in its own process group under the
+/// build timeout, so that Ctrl-C stops it through
+/// [`process::cancel_running_builds`]; each line it prints is logged and passed to
+/// `output`.
+///
+/// sudo is asked for its password up front, while it can still read the terminal; the
+/// installer itself runs in the background process group and must not prompt.
+pub fn run_installer_command<S: AsRef<OsStr>>(
+    program: &Path,
+    args: &[S],
+    privileged: bool,
+    context: &str,
+    config: &Config,
+    output: Option<&InstallerOutputFn>,
+) -> Result<Output> {
+    // SAFETY: geteuid cannot fail.
+    let use_sudo = privileged && unsafe { libc::geteuid() } != 0;
+    let mut cmd = if use_sudo {
+        let status = Command::new("sudo").arg("-v").status().map_err(|e| {
+            SpsError::CommandExecError(format!("Failed to run sudo for {context}: {e}"))
+        })?;
+        if !status.success() {
+            return Err(SpsError::InstallError(format!(
+                "{context} needs administrator rights, but sudo failed ({status})"
+            )));
+        }
+        let mut sudo = Command::new("sudo");
+        sudo.arg("-n").arg(program);
+        sudo
+    } else {
+        Command::new(program)
+    };
+    cmd.args(args).stdin(Stdio::null());
+
+    let context_owned = context.to_string();
+    let forward = output.cloned();
+    let on_line: InstallerOutputFn = Arc::new(move |line: &str| {
+        debug!("[{}] {}", context_owned, line);
+        if let Some(forward) = &forward {
+            forward(line);
+        }
+    });
+    debug!("Running {} for {}", program.display(), context);
+    process::output_with_timeout_streaming(&mut cmd, config.build_timeout, context, on_line)
+}
@@ -1,3 +1,4 @@
+pub mod adopt;
 pub mod artifacts;
 pub mod dmg;
 pub mod helpers;
@@ -16,6 +17,8 @@ use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::artifact::InstalledArtifact;
 use sps_common::model::cask::{Cask, Sha256Field, UrlField};
+use sps_common::pipeline::{DownloadProgressFn, ExtractProgressFn, InstallerOutputFn, JobAction};
+use sps_net::validation::{ChecksumAlgo, StreamingChecksum};
 use tempfile::TempDir;
 use tracing::{debug, error};
 
@@ -31,6 +34,10 @@ pub struct CaskInstallManifest {
     pub primary_app_file_name: Option<String>,
     pub is_installed: bool,              // New flag for soft uninstall
     pub cask_store_path: Option<String>, // Path to private store app, if available
+    /// The cask's `depends_on.formula` at install time; `None` in manifests written
+    /// before it was recorded.
+    #[serde(default)]
+    pub formula_dependencies: Option<Vec<String>>,
 }
 
 /// Returns the path to the cask's version directory in the private store.
@@ -70,7 +77,12 @@ pub fn sps_private_cask_app_path(cask: &Cask, config: &Config) -> Option<PathBuf
     None
 }
 
-pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
+pub async fn download_cask(
+    cask: &Cask,
+    config: &Config,
+    cache: &Cache,
+    progress: Option<DownloadProgressFn>,
+) -> Result<PathBuf> {
     let url_field = cask
         .url
         .as_ref()
@@ -108,9 +120,8 @@ pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
         return Ok(cache_path);
     }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(parsed.clone())
+    let client = sps_net::http::shared_client()?;
+    let mut response = sps_net::http::with_github_auth(client.get(parsed.clone()), url_str, config)
         .send()
         .await
         .map_err(|e| SpsError::Http(std::sync::Arc::new(e)))?;
@@ -121,15 +132,37 @@ pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
             format!("HTTP status {}", response.status()),
         ));
     }
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| SpsError::Http(std::sync::Arc::new(e)))?;
     if let Some(parent) = cache_path.parent() {
         fs::create_dir_all(parent)?;
     }
+    let total_bytes = response.content_length();
+    let mut bytes_done: u64 = 0;
+    if let Some(report) = &progress {
+        report(bytes_done, total_bytes);
+    }
     let mut file = fs::File::create(&cache_path)?;
-    file.write_all(&bytes)?;
+    // Hashed as it streams in, so the file is not read back to verify it.
+    let mut checksum = StreamingChecksum::new(ChecksumAlgo::Sha256);
+    let mut watchdog = sps_net::http::StallWatchdog::new();
+    loop {
+        let chunk = match watchdog.next_chunk(response.chunk(), url_str).await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                // Don't leave a truncated file behind; it would be picked up as a cache hit.
+                drop(file);
+                let _ = fs::remove_file(&cache_path);
+                return Err(e);
+            }
+        };
+        file.write_all(&chunk)?;
+        checksum.update(&chunk);
+        bytes_done += chunk.len() as u64;
+        if let Some(report) = &progress {
+            report(bytes_done, total_bytes);
+        }
+    }
+    drop(file);
     match cask.sha256.as_ref() {
         Some(Sha256Field::Hex(s)) => {
             if s.eq_ignore_ascii_case("no_check") {
@@ -138,7 +171,7 @@ pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
                     cache_path.display()
                 );
             } else if !s.is_empty() {
-                match sps_net::validation::verify_checksum(&cache_path, s) {
+                match checksum.verify(&cache_path, s) {
                     Ok(_) => {
                         tracing::debug!(
                             "Cask download checksum verified: {}",
@@ -177,8 +210,8 @@ pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
     debug!("Download completed: {}", cache_path.display());
 
     // --- Set quarantine xattr on the downloaded archive (macOS only) ---
-    #[cfg(target_os = "macos")]
-    {
+    if config.quarantine.quarantine_casks() {
+        #[cfg(target_os = "macos")]
         if let Err(e) = crate::utils::xattr::set_quarantine_attribute(&cache_path, "sps-downloader")
         {
             tracing::warn!(
@@ -192,13 +225,13 @@ pub async fn download_cask(cask: &Cask, cache: &Cache) -> Result<PathBuf> {
     Ok(cache_path)
 }
 
-use sps_common::pipeline::JobAction;
-
 pub fn install_cask(
     cask: &Cask,
     download_path: &Path,
     config: &Config,
     job_action: &JobAction,
+    progress: Option<ExtractProgressFn>,
+    installer_output: Option<InstallerOutputFn>,
 ) -> Result<()> {
     debug!("Installing cask: {}", cask.token);
     // This is the path in the *actual* Caskroom (e.g., /opt/homebrew/Caskroom/token/version)
@@ -273,6 +306,7 @@ pub fn install_cask(
             download_path,
             &actual_cask_room_version_path, // PKG manifest items go into the actual cask_room
             config,
+            installer_output.as_ref(),
         ) {
             Ok(installed_artifacts) => {
                 debug!("Writing PKG install manifest");
@@ -338,16 +372,23 @@ pub fn install_cask(
             dmg::extract_dmg_to_stage(download_path, stage_path)?;
             debug!("Successfully extracted DMG to staging area.");
         }
-        "zip" => {
+        "zip" | "7z" => {
             debug!(
-                "Extracting ZIP {} to stage {}...",
+                "Extracting {} {} to stage {}...",
+                detected_extension.to_uppercase(),
                 download_path.display(),
                 stage_path.display()
             );
-            extract::extract_archive(download_path, stage_path, 0, "zip")?;
-            debug!("Successfully extracted ZIP to staging area.");
+            extract::extract_archive_with_progress(
+                download_path,
+                stage_path,
+                0,
+                detected_extension.as_str(),
+                progress,
+            )?;
+            debug!("Successfully extracted archive to staging area.");
         }
-        "gz" | "bz2" | "xz" | "tar" => {
+        "gz" | "bz2" | "xz" | "lz" | "tar" => {
             let archive_type_for_extraction = detected_extension.as_str();
             debug!(
                 "Extracting TAR archive ({}) {} to stage {}...",
@@ -355,7 +396,13 @@ pub fn install_cask(
                 download_path.display(),
                 stage_path.display()
             );
-            extract::extract_archive(download_path, stage_path, 0, archive_type_for_extraction)?;
+            extract::extract_archive_with_progress(
+                download_path,
+                stage_path,
+                0,
+                archive_type_for_extraction,
+                progress,
+            )?;
             debug!("Successfully extracted TAR archive to staging area.");
         }
         _ => {
@@ -369,8 +416,19 @@ pub fn install_cask(
             )));
         }
     }
+    #[cfg(target_os = "macos")]
+    if config.quarantine.quarantine_casks() {
+        if let Err(e) = extract::quarantine_extracted_apps_in_stage(stage_path, "sps-extractor") {
+            tracing::warn!(
+                "Error during post-extraction quarantine scan for {}: {}",
+                download_path.display(),
+                e
+            );
+        }
+    }
     let mut all_installed_artifacts: Vec<InstalledArtifact> = Vec::new();
     let mut artifact_install_errors = Vec::new();
+    let mut ran_installers = false;
     if let Some(artifacts_def) = &cask.artifacts {
         debug!(
             "Processing {} declared artifacts from staging area...",
@@ -383,6 +441,7 @@ pub fn install_cask(
                     let result: Result<Vec<InstalledArtifact>> = match key.as_str() {
                         "app" => {
                             let mut app_artifacts = vec![];
+                            let mut failure = None;
                             if let Some(app_names) = value.as_array() {
                                 for app_name_val in app_names {
                                     if let Some(app_name) = app_name_val.as_str() {
@@ -402,7 +461,8 @@ pub fn install_cask(
                                                 app_artifacts.append(&mut artifacts)
                                             }
                                             Err(e) => {
-                                                return Err(e);
+                                                failure = Some(e);
+                                                break;
                                             }
                                         }
                                     } else {
@@ -415,10 +475,18 @@ pub fn install_cask(
                             } else {
                                 debug!("'app' artifact value is not an array: {:?}", value);
                             }
-                            Ok(app_artifacts)
+                            match failure {
+                                // Keep what this stanza placed so it is rolled back too.
+                                Some(e) => {
+                                    all_installed_artifacts.append(&mut app_artifacts);
+                                    Err(e)
+                                }
+                                None => Ok(app_artifacts),
+                            }
                         }
                         "pkg" => {
                             let mut installed_pkgs = vec![];
+                            let mut failure = None;
                             if let Some(pkg_names) = value.as_array() {
                                 for pkg_val in pkg_names {
                                     if let Some(pkg_name) = pkg_val.as_str() {
@@ -433,12 +501,14 @@ pub fn install_cask(
                                             &actual_cask_room_version_path, /* Pass actual
                                                                              * cask_room path */
                                             config,
+                                            installer_output.as_ref(),
                                         ) {
                                             Ok(mut artifacts) => {
                                                 installed_pkgs.append(&mut artifacts)
                                             }
                                             Err(e) => {
-                                                return Err(e);
+                                                failure = Some(e);
+                                                break;
                                             }
                                         }
                                     } else {
@@ -451,8 +521,27 @@ pub fn install_cask(
                             } else {
                                 debug!("'pkg' artifact value is not an array: {:?}", value);
                             }
-                            Ok(installed_pkgs)
+                            match failure {
+                                // Keep what this stanza placed so it is rolled back too.
+                                Some(e) => {
+                                    all_installed_artifacts.append(&mut installed_pkgs);
+                                    Err(e)
+                                }
+                                None => Ok(installed_pkgs),
+                            }
                         }
+                        // run_installer handles every `installer` stanza of the cask.
+                        "installer" if !ran_installers => {
+                            ran_installers = true;
+                            artifacts::installer::run_installer(
+                                cask,
+                                stage_path,
+                                &actual_cask_room_version_path,
+                                config,
+                                installer_output.as_ref(),
+                            )
+                        }
+                        "installer" => Ok(vec![]),
                         _ => {
                             debug!("Artifact type '{}' not supported yet — skipping.", key);
                             Ok(vec![])
@@ -477,6 +566,8 @@ pub fn install_cask(
                         Err(e) => {
                             error!("Error processing artifact '{}': {}", key, e);
                             artifact_install_errors.push(e);
+                            // Everything placed so far is rolled back below.
+                            break;
                         }
                     }
                 } else {
@@ -507,9 +598,37 @@ pub fn install_cask(
             artifact_install_errors.len(),
             cask.token
         );
+        let rollback_failures =
+            crate::uninstall::rollback_cask_artifacts(&all_installed_artifacts, config);
         let _ = fs::remove_dir_all(&actual_cask_room_version_path); // Clean up actual cask_room on error
-        return Err(artifact_install_errors.remove(0));
+        let first_error = artifact_install_errors.remove(0);
+        if all_installed_artifacts.is_empty() {
+            return Err(first_error);
+        }
+        let rollback_note = if rollback_failures.is_empty() {
+            format!(
+                "rolled back {} installed artifact(s)",
+                all_installed_artifacts.len()
+            )
+        } else {
+            format!(
+                "rollback could not remove {} of {} installed artifact(s): {}",
+                rollback_failures.len(),
+                all_installed_artifacts.len(),
+                rollback_failures.join("; ")
+            )
+        };
+        return Err(SpsError::InstallError(format!(
+            "Failed to install cask '{}': {}; {}",
+            cask.token, first_error, rollback_note
+        )));
     }
+    // The launchd jobs the app sets up itself; recorded so `sps services` can manage them.
+    all_installed_artifacts.extend(
+        artifacts::record_uninstall(cask)?
+            .into_iter()
+            .filter(|artifact| matches!(artifact, InstalledArtifact::Launchd { .. })),
+    );
     let actual_install_count = all_installed_artifacts
         .iter()
         .filter(|a| {
@@ -615,6 +734,12 @@ pub fn write_cask_manifest(
         primary_app_file_name,
         is_installed: true,
         cask_store_path,
+        formula_dependencies: Some(
+            cask.depends_on
+                .as_ref()
+                .map(|deps| deps.formula.clone())
+                .unwrap_or_default(),
+        ),
     };
     if let Some(parent) = manifest_path.parent() {
         fs::create_dir_all(parent).map_err(|e| {
@@ -120,37 +120,138 @@ pub fn find_sdk_path() -> Result<PathBuf> {
     }
 }
 
-pub fn get_macos_version() -> Result<String> {
-    if cfg!(target_os = "macos") {
-        debug!("Attempting to get macOS version using sw_vers");
-        let output = Command::new("sw_vers")
-            .arg("-productVersion")
-            .stderr(Stdio::piped())
-            .output();
+const SYSTEM_VERSION_PLIST: &str = "/System/Library/CoreServices/SystemVersion.plist";
 
-        match output {
-            Ok(out) if out.status.success() => {
-                let version_full = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                let version_parts: Vec<&str> = version_full.split('.').collect();
-                let version_short = if version_parts.len() >= 2 {
-                    format!("{}.{}", version_parts[0], version_parts[1])
-                } else {
-                    version_full.clone()
-                };
-                debug!("Found macOS version: {version_full} (short: {version_short})");
-                Ok(version_short)
-            }
-            Ok(out) => {
-                let stderr = String::from_utf8_lossy(&out.stderr);
-                Err(SpsError::BuildEnvError(format!(
-                    "sw_vers failed to get product version: {}",
-                    stderr.trim()
-                )))
+/// A way of reading the macOS version, named for logging.
+type VersionSource = (&'static str, fn() -> Option<String>);
+
+/// The full macOS product version, e.g. "14.5", from the first source that answers:
+/// SystemVersion.plist, `sysctl kern.osproductversion`, then `sw_vers`. `None` off
+/// macOS or if all three fail.
+pub fn detect_macos_version() -> Option<String> {
+    if !cfg!(target_os = "macos") {
+        return None;
+    }
+    let sources: [VersionSource; 3] = [
+        (SYSTEM_VERSION_PLIST, version_from_plist),
+        ("sysctl kern.osproductversion", version_from_sysctl),
+        ("sw_vers -productVersion", version_from_sw_vers),
+    ];
+    for (source, read) in sources {
+        match read() {
+            Some(version) => {
+                debug!("Detected macOS {} from {}", version, source);
+                return Some(version);
             }
-            Err(e) => Err(SpsError::BuildEnvError(format!(
-                "Failed to execute 'sw_vers -productVersion': {e}"
-            ))),
+            None => debug!("Could not read the macOS version from {}", source),
         }
+    }
+    None
+}
+
+fn version_from_plist() -> Option<String> {
+    let plist = plist::Value::from_file(SYSTEM_VERSION_PLIST).ok()?;
+    let version = plist
+        .as_dictionary()?
+        .get("ProductVersion")?
+        .as_string()?
+        .trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+fn version_from_sysctl() -> Option<String> {
+    command_stdout(Command::new("/usr/sbin/sysctl").args(["-n", "kern.osproductversion"]))
+}
+
+fn version_from_sw_vers() -> Option<String> {
+    command_stdout(Command::new("/usr/bin/sw_vers").arg("-productVersion"))
+}
+
+fn command_stdout(command: &mut Command) -> Option<String> {
+    let output = command.stderr(Stdio::null()).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty()).then_some(stdout)
+}
+
+/// Homebrew's names for macOS releases in bottle tags, newest first.
+pub const MACOS_CODENAMES: &[&str] = &[
+    "tahoe",
+    "sequoia",
+    "sonoma",
+    "ventura",
+    "monterey",
+    "big_sur",
+    "catalina",
+    "mojave",
+    "high_sierra",
+    "sierra",
+    "el_capitan",
+    "yosemite",
+    "mavericks",
+];
+
+/// The codename bottles are selected by on this macOS version: its own, or for a release
+/// newer than any sps knows, the newest known one, whose bottles it can run.
+pub fn macos_bottle_codename(version: &str) -> Option<&'static str> {
+    if let Some(name) = macos_codename(version) {
+        return Some(name);
+    }
+    let major = version.split('.').next()?.parse::<u32>().ok()?;
+    if major > 26 {
+        debug!(
+            "macOS {} is newer than any known release; using {} bottles",
+            version, MACOS_CODENAMES[0]
+        );
+        return Some(MACOS_CODENAMES[0]);
+    }
+    None
+}
+
+/// Homebrew's bottle tag name for a macOS version, e.g. "sonoma" for "14.5". 10.16 is
+/// what Big Sur reports to programs built against older SDKs.
+pub fn macos_codename(version: &str) -> Option<&'static str> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse::<u32>().ok()?;
+    let minor = parts
+        .next()
+        .and_then(|p| p.parse::<u32>().ok())
+        .unwrap_or(0);
+    let name = match (major, minor) {
+        (26, _) => "tahoe",
+        (15, _) => "sequoia",
+        (14, _) => "sonoma",
+        (13, _) => "ventura",
+        (12, _) => "monterey",
+        (11, _) | (10, 16) => "big_sur",
+        (10, 15) => "catalina",
+        (10, 14) => "mojave",
+        (10, 13) => "high_sierra",
+        (10, 12) => "sierra",
+        (10, 11) => "el_capitan",
+        (10, 10) => "yosemite",
+        (10, 9) => "mavericks",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// The macOS version as "major.minor", e.g. "14.5".
+pub fn get_macos_version() -> Result<String> {
+    if cfg!(target_os = "macos") {
+        let version_full = detect_macos_version().ok_or_else(|| {
+            SpsError::BuildEnvError(
+                "Could not determine the macOS version from SystemVersion.plist, sysctl or sw_vers"
+                    .to_string(),
+            )
+        })?;
+        let version_parts: Vec<&str> = version_full.split('.').collect();
+        let version_short = if version_parts.len() >= 2 {
+            format!("{}.{}", version_parts[0], version_parts[1])
+        } else {
+            version_full.clone()
+        };
+        debug!("Found macOS version: {version_full} (short: {version_short})");
+        Ok(version_short)
     } else {
         debug!("Not on macOS, returning '0.0' as version placeholder");
         Ok(String::from("0.0"))
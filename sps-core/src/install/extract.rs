@@ -3,18 +3,31 @@ use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{self, Read, Seek};
 use std::path::{Component, Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use bzip2::read::BzDecoder;
 use flate2::read::GzDecoder;
 use sps_common::error::{Result, SpsError};
+use sps_common::pipeline::ExtractProgressFn;
 use tar::{Archive, EntryType};
 use tracing::{debug, error, warn};
+use walkdir::WalkDir;
 use zip::ZipArchive;
 
 #[cfg(target_os = "macos")]
 use crate::utils::xattr;
 
+/// Programs that decompress lzip data to stdout, with their arguments, in order of
+/// preference. xz reads lzip since 5.4.
+const LZIP_TOOLS: &[(&str, &[&str])] = &[
+    ("lzip", &["-dc"]),
+    ("plzip", &["-dc"]),
+    ("lunzip", &["-c"]),
+    ("xz", &["--format=lzip", "-dc"]),
+];
+/// 7-Zip executables: `7zz` from 7-Zip itself, `7z`/`7za` from p7zip.
+const SEVEN_ZIP_TOOLS: &[&str] = &["7zz", "7z", "7za"];
+
 pub(crate) fn infer_archive_root_dir(
     archive_path: &Path,
     archive_type: &str,
@@ -44,6 +57,11 @@ pub(crate) fn infer_archive_root_dir(
             // Use external xz command to decompress, then read as tar
             infer_xz_tar_root(archive_path)
         }
+        "lz" => {
+            let decompressed = decompress_with_tool(archive_path, "lzip", LZIP_TOOLS)?;
+            infer_tar_root(decompressed.reopen()?, archive_path)
+        }
+        "7z" => infer_7z_root(archive_path),
         "tar" => infer_tar_root(file, archive_path),
         _ => Err(SpsError::Generic(format!(
             "Cannot infer root dir for unsupported archive type '{}' in {}",
@@ -237,6 +255,50 @@ pub fn extract_archive(
     target_dir: &Path,
     strip_components: usize,
     archive_type: &str,
+) -> Result<()> {
+    extract_archive_with_progress(
+        archive_path,
+        target_dir,
+        strip_components,
+        archive_type,
+        None,
+    )
+}
+
+/// Like [`extract_archive`], but reports `(files_done, total_files)` to `progress` as
+/// entries are unpacked. `total_files` is only known up front for ZIP archives; TAR
+/// archives are streamed and report their total once extraction completes.
+pub fn extract_archive_with_progress(
+    archive_path: &Path,
+    target_dir: &Path,
+    strip_components: usize,
+    archive_type: &str,
+    progress: Option<ExtractProgressFn>,
+) -> Result<()> {
+    extract_archive_with_link_root(
+        archive_path,
+        target_dir,
+        strip_components,
+        archive_type,
+        progress,
+        target_dir,
+    )
+}
+
+/// Like [`extract_archive_with_progress`], but lets symlinks in the archive point
+/// anywhere below `link_root` instead of only inside `target_dir`. Bottles need this:
+/// some kegs link into the shared prefix, e.g. Python's `site-packages`.
+///
+/// Whatever `link_root` is, every entry is written inside `target_dir`: members with
+/// absolute paths or `..` components are rejected, and so is any entry that would be
+/// written through a symlink leading out of `target_dir`.
+pub(crate) fn extract_archive_with_link_root(
+    archive_path: &Path,
+    target_dir: &Path,
+    strip_components: usize,
+    archive_type: &str,
+    progress: Option<ExtractProgressFn>,
+    link_root: &Path,
 ) -> Result<()> {
     debug!(
         "Extracting archive '{}' (type: {}) to '{}' (strip_components={}) using native Rust crates.",
@@ -264,38 +326,75 @@ pub fn extract_archive(
         )))
     })?;
 
-    let result = match archive_type {
-        "zip" => extract_zip_archive(file, target_dir, strip_components, archive_path),
+    match archive_type {
+        "zip" => extract_zip_archive(
+            file,
+            target_dir,
+            strip_components,
+            archive_path,
+            progress.as_ref(),
+        ),
         "gz" | "tgz" => {
             let tar = GzDecoder::new(file);
-            extract_tar_archive(tar, target_dir, strip_components, archive_path)
+            extract_tar_archive(
+                tar,
+                target_dir,
+                strip_components,
+                archive_path,
+                progress.as_ref(),
+                link_root,
+            )
         }
         "bz2" | "tbz" | "tbz2" => {
             let tar = BzDecoder::new(file);
-            extract_tar_archive(tar, target_dir, strip_components, archive_path)
+            extract_tar_archive(
+                tar,
+                target_dir,
+                strip_components,
+                archive_path,
+                progress.as_ref(),
+                link_root,
+            )
         }
-        "xz" | "txz" => extract_xz_tar_archive(archive_path, target_dir, strip_components),
-        "tar" => extract_tar_archive(file, target_dir, strip_components, archive_path),
+        "xz" | "txz" => extract_xz_tar_archive(
+            archive_path,
+            target_dir,
+            strip_components,
+            progress.as_ref(),
+            link_root,
+        ),
+        "lz" => {
+            let decompressed = decompress_with_tool(archive_path, "lzip", LZIP_TOOLS)?;
+            extract_tar_archive(
+                decompressed.reopen()?,
+                target_dir,
+                strip_components,
+                archive_path,
+                progress.as_ref(),
+                link_root,
+            )
+        }
+        "7z" => extract_7z_archive(
+            archive_path,
+            target_dir,
+            strip_components,
+            progress.as_ref(),
+            link_root,
+        ),
+        "tar" => extract_tar_archive(
+            file,
+            target_dir,
+            strip_components,
+            archive_path,
+            progress.as_ref(),
+            link_root,
+        ),
         _ => Err(SpsError::Generic(format!(
             "Unsupported archive type provided for extraction: '{}' for file {}",
             archive_type,
             archive_path.display()
         ))),
-    };
-    #[cfg(target_os = "macos")]
-    {
-        if result.is_ok() {
-            // Only quarantine if main extraction was successful
-            if let Err(e) = quarantine_extracted_apps_in_stage(target_dir, "sps-extractor") {
-                tracing::warn!(
-                    "Error during post-extraction quarantine scan for {}: {}",
-                    archive_path.display(),
-                    e
-                );
-            }
-        }
     }
-    result
 }
 
 /// Represents a hardlink operation that was deferred.
@@ -303,6 +402,8 @@ fn extract_xz_tar_archive(
     archive_path: &Path,
     target_dir: &Path,
     strip_components: usize,
+    progress: Option<&ExtractProgressFn>,
+    link_root: &Path,
 ) -> Result<()> {
     debug!(
         "Extracting XZ+TAR archive using external xz command: {}",
@@ -336,7 +437,363 @@ fn extract_xz_tar_archive(
 
     // Extract as tar
     let file = File::open(temp_file.path()).map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
-    extract_tar_archive(file, target_dir, strip_components, archive_path)
+    extract_tar_archive(
+        file,
+        target_dir,
+        strip_components,
+        archive_path,
+        progress,
+        link_root,
+    )
+}
+
+/// Decompresses `archive_path` into a temporary file with the first of `tools` that is
+/// on PATH. Each tool is given as the program and the arguments that make it write the
+/// decompressed data to stdout.
+fn decompress_with_tool(
+    archive_path: &Path,
+    format: &str,
+    tools: &[(&str, &[&str])],
+) -> Result<tempfile::NamedTempFile> {
+    let Some((program, args)) = tools
+        .iter()
+        .find(|(program, _)| which::which(program).is_ok())
+    else {
+        return Err(missing_tool_error(
+            archive_path,
+            format,
+            &tools
+                .iter()
+                .map(|(program, _)| *program)
+                .collect::<Vec<_>>(),
+        ));
+    };
+    debug!(
+        "Decompressing {} archive {} with {}",
+        format,
+        archive_path.display(),
+        program
+    );
+    let temp_file =
+        tempfile::NamedTempFile::new().map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
+    let output = Command::new(program)
+        .args(*args)
+        .arg(archive_path)
+        .stdin(Stdio::null())
+        .stdout(temp_file.reopen()?)
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| SpsError::Generic(format!("Failed to run {program}: {e}")))?;
+    if !output.status.success() {
+        return Err(SpsError::Generic(format!(
+            "{} decompression of {} failed: {}",
+            program,
+            archive_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(temp_file)
+}
+
+fn find_seven_zip(archive_path: &Path) -> Result<PathBuf> {
+    SEVEN_ZIP_TOOLS
+        .iter()
+        .find_map(|tool| which::which(tool).ok())
+        .ok_or_else(|| missing_tool_error(archive_path, "7z", SEVEN_ZIP_TOOLS))
+}
+
+fn missing_tool_error(archive_path: &Path, format: &str, tools: &[&str]) -> SpsError {
+    SpsError::Generic(format!(
+        "Cannot extract {} archive {}: none of {} was found on PATH. Install {} (e.g. `sps install {}`) and try again.",
+        format,
+        archive_path.display(),
+        tools.join(", "),
+        tools[0],
+        if format == "7z" { "sevenzip" } else { "lzip" }
+    ))
+}
+
+/// Lists a 7z archive and returns its single top-level entry, like [`infer_tar_root`].
+fn infer_7z_root(archive_path: &Path) -> Result<Option<PathBuf>> {
+    let seven_zip = find_seven_zip(archive_path)?;
+    let output = Command::new(&seven_zip)
+        .arg("l")
+        .arg("-slt")
+        .arg(archive_path)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(SpsError::Generic(format!(
+            "Failed to list 7z archive {}: {}",
+            archive_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let listing = String::from_utf8_lossy(&output.stdout);
+    // Entries follow the "----------" separator; the `Path =` lines before it describe
+    // the archive itself.
+    let mut roots = HashSet::new();
+    for line in listing
+        .lines()
+        .skip_while(|line| line.trim() != "----------")
+    {
+        let Some(path) = line.strip_prefix("Path = ") else {
+            continue;
+        };
+        match Path::new(path).components().next() {
+            Some(Component::Normal(name)) => {
+                roots.insert(PathBuf::from(name));
+            }
+            Some(_) => return Ok(None),
+            None => {}
+        }
+        if roots.len() > 1 {
+            return Ok(None);
+        }
+    }
+    Ok(roots.into_iter().next())
+}
+
+/// Extracts a 7z archive with the 7-Zip command line tool. 7-Zip cannot strip leading
+/// path components itself, so the archive is unpacked into a scratch directory and the
+/// entries below `strip_components` levels are moved into place.
+fn extract_7z_archive(
+    archive_path: &Path,
+    target_dir: &Path,
+    strip_components: usize,
+    progress: Option<&ExtractProgressFn>,
+    link_root: &Path,
+) -> Result<()> {
+    let seven_zip = find_seven_zip(archive_path)?;
+    debug!(
+        "Extracting 7z archive {} with {}",
+        archive_path.display(),
+        seven_zip.display()
+    );
+    // Inside the target so the final moves are renames on the same filesystem.
+    let scratch = tempfile::Builder::new()
+        .prefix(".sps-7z-")
+        .tempdir_in(target_dir)
+        .map_err(|e| SpsError::Io(std::sync::Arc::new(e)))?;
+    let output = Command::new(&seven_zip)
+        .arg("x")
+        .arg("-y")
+        .arg("-bd")
+        .arg(format!("-o{}", scratch.path().display()))
+        .arg(archive_path)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(SpsError::Generic(format!(
+            "7z extraction of {} failed: {}",
+            archive_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // Like TAR extraction, entries with no more than `strip_components` components
+    // are dropped.
+    let kept: Vec<PathBuf> = WalkDir::new(scratch.path())
+        .min_depth(strip_components + 1)
+        .max_depth(strip_components + 1)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .collect();
+    let total_files = WalkDir::new(scratch.path())
+        .min_depth(strip_components + 1)
+        .into_iter()
+        .flatten()
+        .filter(|entry| !entry.file_type().is_dir())
+        .count();
+    let mut moved = Vec::new();
+    for source in kept {
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        let dest = target_dir.join(name);
+        match dest.symlink_metadata() {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(&dest)?,
+            Ok(_) => fs::remove_file(&dest)?,
+            Err(_) => {}
+        }
+        fs::rename(&source, &dest)?;
+        moved.push(dest);
+    }
+
+    // 7-Zip refuses `..` and absolute member paths itself, but not symlinks leading
+    // out of the tree; drop them like TAR extraction does.
+    let links: Vec<PathBuf> = moved
+        .iter()
+        .flat_map(|dest| WalkDir::new(dest).into_iter().flatten())
+        .filter(|entry| entry.path_is_symlink())
+        .map(|entry| entry.into_path())
+        .collect();
+    remove_escaping_symlinks(&links, link_root, archive_path)?;
+    if let Some(progress) = progress {
+        progress(total_files, Some(total_files));
+    }
+    debug!(
+        "Extracted {} file(s) from 7z archive {}",
+        total_files,
+        archive_path.display()
+    );
+    Ok(())
+}
+
+fn canonical_dir(dir: &Path) -> Result<PathBuf> {
+    dir.canonicalize().map_err(|e| {
+        SpsError::Io(std::sync::Arc::new(io::Error::new(
+            e.kind(),
+            format!(
+                "Failed to resolve extraction target {}: {}",
+                dir.display(),
+                e
+            ),
+        )))
+    })
+}
+
+/// Fails if a directory on the way from `target_dir` to `dest` is a symlink that leads
+/// outside the target. An archive can create such a link with one entry and then write
+/// through it with the next.
+fn check_no_symlink_escape(
+    target_dir: &Path,
+    target_canonical: &Path,
+    dest: &Path,
+) -> std::result::Result<(), String> {
+    let Ok(relative) = dest.strip_prefix(target_dir) else {
+        return Err(format!(
+            "{} is outside the extraction target {}",
+            dest.display(),
+            target_dir.display()
+        ));
+    };
+    let mut current = target_dir.to_path_buf();
+    let parents = relative
+        .parent()
+        .map(Path::components)
+        .into_iter()
+        .flatten();
+    for comp in parents {
+        current.push(comp);
+        match current.symlink_metadata() {
+            // Missing directories are created by the extraction itself.
+            Err(_) => break,
+            Ok(meta) if meta.file_type().is_symlink() => match current.canonicalize() {
+                Ok(resolved) if resolved.starts_with(target_canonical) => {}
+                _ => {
+                    return Err(format!(
+                        "Refusing to write {} through symlink {} that leads outside {}",
+                        dest.display(),
+                        current.display(),
+                        target_dir.display()
+                    ))
+                }
+            },
+            Ok(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether a symlink at `link_path` with target `link_target` resolves outside
+/// `link_root`. Absolute targets always count as outside. The target is resolved
+/// against what is already on disk, following the symlinks extracted so far, so a
+/// chain such as `a -> .` and `b -> a/..` is judged by where it really leads.
+fn symlink_escapes(link_path: &Path, link_target: &Path, link_root: &Path) -> bool {
+    if link_target.has_root() {
+        return true;
+    }
+    let Ok(root) = link_root.canonicalize() else {
+        return true;
+    };
+    let parent = link_path.parent().unwrap_or(link_root);
+    match resolve_through_symlinks(&parent.join(link_target)) {
+        Some(resolved) => !resolved.starts_with(&root),
+        None => true,
+    }
+}
+
+/// Removes the symlinks among `links` that lead outside `link_root` with everything
+/// extracted. TAR extraction checks each link as it is created, but a later entry can
+/// still change where an earlier link leads, e.g. by replacing a link it goes through.
+/// Removing one link can change where another leads, hence the repeat.
+fn remove_escaping_symlinks(
+    links: &[PathBuf],
+    link_root: &Path,
+    archive_path: &Path,
+) -> Result<()> {
+    loop {
+        let escaping: Vec<&PathBuf> = links
+            .iter()
+            .filter(|link| {
+                fs::read_link(link).is_ok_and(|target| symlink_escapes(link, &target, link_root))
+            })
+            .collect();
+        if escaping.is_empty() {
+            return Ok(());
+        }
+        for link in escaping {
+            warn!(
+                "Removing symlink {} from {}: it points outside {}",
+                link.display(),
+                archive_path.display(),
+                link_root.display()
+            );
+            fs::remove_file(link)?;
+        }
+    }
+}
+
+/// Resolves an absolute `path` one component at a time the way the kernel does: `..`
+/// climbs from where the path has led so far and existing symlinks, dangling or not,
+/// are followed. Components that do not exist yet are taken as they are. `None` for a
+/// relative path, a symlink that cannot be read or a link chain that does not end.
+fn resolve_through_symlinks(path: &Path) -> Option<PathBuf> {
+    const MAX_LINKS: usize = 40;
+    if !path.has_root() {
+        return None;
+    }
+    // Still to be resolved, last component first.
+    let mut pending: Vec<PathBuf> = path
+        .components()
+        .map(|comp| PathBuf::from(comp.as_os_str()))
+        .rev()
+        .collect();
+    let mut resolved = PathBuf::new();
+    let mut links_followed = 0;
+    while let Some(next) = pending.pop() {
+        match next.components().next() {
+            Some(Component::RootDir | Component::Prefix(_)) => resolved = next,
+            Some(Component::ParentDir) => {
+                resolved.pop();
+            }
+            Some(Component::Normal(name)) => {
+                let candidate = resolved.join(name);
+                let is_symlink = candidate
+                    .symlink_metadata()
+                    .is_ok_and(|meta| meta.file_type().is_symlink());
+                if !is_symlink {
+                    resolved = candidate;
+                    continue;
+                }
+                links_followed += 1;
+                if links_followed > MAX_LINKS {
+                    return None;
+                }
+                let target = fs::read_link(&candidate).ok()?;
+                pending.extend(
+                    target
+                        .components()
+                        .map(|comp| PathBuf::from(comp.as_os_str()))
+                        .rev(),
+                );
+            }
+            Some(Component::CurDir) | None => {}
+        }
+    }
+    Some(resolved)
 }
 
 #[cfg(unix)]
@@ -350,7 +807,10 @@ fn extract_tar_archive<R: Read>(
     target_dir: &Path,
     strip_components: usize,
     archive_path_for_log: &Path,
+    progress: Option<&ExtractProgressFn>,
+    link_root: &Path,
 ) -> Result<()> {
+    let target_canonical = canonical_dir(target_dir)?;
     let mut archive = Archive::new(reader);
     archive.set_preserve_permissions(true);
     archive.set_unpack_xattrs(true);
@@ -363,7 +823,9 @@ fn extract_tar_archive<R: Read>(
 
     #[cfg(unix)]
     let mut deferred_hardlinks: Vec<DeferredHardLink> = Vec::new();
+    let mut extracted_symlinks: Vec<PathBuf> = Vec::new();
     let mut errors: Vec<String> = Vec::new();
+    let mut files_done: usize = 0;
 
     for entry_result in archive.entries()? {
         let mut entry = entry_result.map_err(|e| {
@@ -373,6 +835,10 @@ fn extract_tar_archive<R: Read>(
                 e
             ))
         })?;
+        files_done += 1;
+        if let Some(report) = progress {
+            report(files_done, None);
+        }
 
         let original_path_in_archive: PathBuf = entry
             .path()
@@ -398,34 +864,71 @@ fn extract_tar_archive<R: Read>(
             continue;
         }
 
+        // Absolute members and `..` are checked on the whole path: the leading
+        // components are dropped by position, so they must not be used to climb out.
+        let unsafe_component = original_path_in_archive.components().find(|comp| {
+            matches!(
+                comp,
+                Component::ParentDir | Component::Prefix(_) | Component::RootDir
+            )
+        });
+        if let Some(comp) = unsafe_component {
+            let msg = format!(
+                "Refusing TAR entry {} with unsafe component {:?} in {}",
+                original_path_in_archive.display(),
+                comp,
+                archive_path_for_log.display()
+            );
+            error!("{}", msg);
+            errors.push(msg);
+            continue;
+        }
+
         let mut final_target_path_on_disk = target_dir.to_path_buf();
         for comp in stripped_components_iter {
-            match comp {
-                Component::Normal(p) => final_target_path_on_disk.push(p),
-                Component::CurDir => {}
-                Component::ParentDir => {
-                    let msg = format!(
-                        "Unsafe '..' in TAR path {} after stripping in {}",
-                        original_path_in_archive.display(),
-                        archive_path_for_log.display()
-                    );
-                    error!("{}", msg);
-                    errors.push(msg);
-                    continue;
-                }
-                Component::Prefix(_) | Component::RootDir => {
-                    let msg = format!(
-                        "Disallowed component {:?} in TAR path {}",
-                        comp,
-                        original_path_in_archive.display()
-                    );
-                    error!("{}", msg);
-                    errors.push(msg);
-                    continue;
-                }
+            if let Component::Normal(p) = comp {
+                final_target_path_on_disk.push(p);
             }
         }
 
+        if let Err(msg) =
+            check_no_symlink_escape(target_dir, &target_canonical, &final_target_path_on_disk)
+        {
+            error!("{}", msg);
+            errors.push(msg);
+            continue;
+        }
+
+        if entry.header().entry_type() == EntryType::Symlink {
+            let link_target = entry.link_name().ok().flatten().map(|l| l.into_owned());
+            let escapes = link_target.as_deref().is_none_or(|link_target| {
+                symlink_escapes(&final_target_path_on_disk, link_target, link_root)
+            });
+            // Skipped rather than fatal: some source tarballs ship harmless absolute
+            // links such as `INSTALL -> /usr/share/automake/INSTALL`.
+            if escapes {
+                warn!(
+                    "Skipping symlink {} -> {} in {}: it points outside {}",
+                    original_path_in_archive.display(),
+                    link_target
+                        .as_deref()
+                        .map_or_else(|| "<none>".to_string(), |l| l.display().to_string()),
+                    archive_path_for_log.display(),
+                    link_root.display()
+                );
+                continue;
+            }
+        }
+
+        // Replace an existing symlink rather than writing through it.
+        if !entry.header().entry_type().is_dir()
+            && final_target_path_on_disk
+                .symlink_metadata()
+                .is_ok_and(|meta| meta.file_type().is_symlink())
+        {
+            let _ = fs::remove_file(&final_target_path_on_disk);
+        }
+
         if !final_target_path_on_disk.starts_with(target_dir) {
             let msg = format!(
                 "Path traversal {} -> {} detected in {}",
@@ -476,10 +979,15 @@ fn extract_tar_archive<R: Read>(
         }
 
         match entry.unpack(&final_target_path_on_disk) {
-            Ok(_) => debug!(
-                "Unpacked TAR entry to: {}",
-                final_target_path_on_disk.display()
-            ),
+            Ok(_) => {
+                debug!(
+                    "Unpacked TAR entry to: {}",
+                    final_target_path_on_disk.display()
+                );
+                if entry.header().entry_type() == EntryType::Symlink {
+                    extracted_symlinks.push(final_target_path_on_disk);
+                }
+            }
             Err(e) => {
                 if e.kind() != io::ErrorKind::AlreadyExists {
                     let msg = format!(
@@ -525,7 +1033,14 @@ fn extract_tar_archive<R: Read>(
             }
         }
 
-        if !disk_target_path.starts_with(target_dir) || !disk_link_path.starts_with(target_dir) {
+        let target_resolves_outside = disk_target_path
+            .canonicalize()
+            .is_ok_and(|resolved| !resolved.starts_with(&target_canonical));
+        if !disk_target_path.starts_with(target_dir)
+            || !disk_link_path.starts_with(target_dir)
+            || target_resolves_outside
+            || check_no_symlink_escape(target_dir, &target_canonical, &disk_link_path).is_err()
+        {
             let msg = format!("Skipping deferred hardlink due to path traversal attempt: link '{}' -> target '{}'", disk_link_path.display(), disk_target_path.display());
             error!("{}", msg);
             errors.push(msg);
@@ -590,6 +1105,8 @@ fn extract_tar_archive<R: Read>(
         }
     }
 
+    remove_escaping_symlinks(&extracted_symlinks, link_root, archive_path_for_log)?;
+
     if !errors.is_empty() {
         return Err(SpsError::InstallError(format!(
             "Failed during TAR extraction for {} with {} error(s): {}",
@@ -599,20 +1116,13 @@ fn extract_tar_archive<R: Read>(
         )));
     }
 
+    if let Some(report) = progress {
+        report(files_done, Some(files_done));
+    }
     debug!(
         "Finished TAR extraction for {}",
         archive_path_for_log.display()
     );
-    #[cfg(target_os = "macos")]
-    {
-        if let Err(e) = quarantine_extracted_apps_in_stage(target_dir, "sps-tar-extractor") {
-            tracing::warn!(
-                "Error during post-tar extraction quarantine scan for {}: {}",
-                archive_path_for_log.display(),
-                e
-            );
-        }
-    }
     Ok(())
 }
 
@@ -621,7 +1131,9 @@ fn extract_zip_archive<R: Read + Seek>(
     target_dir: &Path,
     strip_components: usize,
     archive_path_for_log: &Path,
+    progress: Option<&ExtractProgressFn>,
 ) -> Result<()> {
+    let target_canonical = canonical_dir(target_dir)?;
     let mut archive = ZipArchive::new(reader).map_err(|e| {
         SpsError::Generic(format!(
             "Failed to open ZIP {}: {}",
@@ -635,7 +1147,11 @@ fn extract_zip_archive<R: Read + Seek>(
         archive_path_for_log.display()
     );
 
-    for i in 0..archive.len() {
+    let total_files = archive.len();
+    for i in 0..total_files {
+        if let Some(report) = progress {
+            report(i, Some(total_files));
+        }
         let mut file = archive
             .by_index(i)
             .map_err(|e| SpsError::Generic(format!("Failed to read ZIP entry {i}: {e}")))?;
@@ -687,6 +1203,13 @@ fn extract_zip_archive<R: Read + Seek>(
             }
         }
 
+        if let Err(msg) =
+            check_no_symlink_escape(target_dir, &target_canonical, &final_target_path_on_disk)
+        {
+            error!("{}", msg);
+            return Err(SpsError::Generic(msg));
+        }
+
         if !final_target_path_on_disk.starts_with(target_dir) {
             error!(
                 "ZIP path traversal detected: {} -> {}",
@@ -775,22 +1298,159 @@ fn extract_zip_archive<R: Read + Seek>(
         }
     }
 
+    if let Some(report) = progress {
+        report(total_files, Some(total_files));
+    }
     debug!(
         "Finished ZIP extraction for {}",
         archive_path_for_log.display()
     );
 
-    // Apply quarantine to extracted apps on macOS
-    #[cfg(target_os = "macos")]
-    {
-        if let Err(e) = quarantine_extracted_apps_in_stage(target_dir, "sps-zip-extractor") {
-            tracing::warn!(
-                "Error during post-zip extraction quarantine scan for {}: {}",
-                archive_path_for_log.display(),
-                e
-            );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tar::{EntryType, Header};
+    use walkdir::WalkDir;
+
+    use super::extract_archive;
+
+    enum Member<'a> {
+        File(&'a str),
+        Dir(&'a str),
+        Symlink(&'a str, &'a str),
+    }
+
+    /// Writes a TAR archive with raw member names, so that `..` and absolute paths the
+    /// `tar` builder would refuse can be crafted.
+    fn write_tar(path: &Path, members: &[Member<'_>]) {
+        let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+        for member in members {
+            let mut header = Header::new_old();
+            let (name, kind, link, data): (&str, EntryType, &str, &[u8]) = match member {
+                Member::File(name) => (name, EntryType::Regular, "", b"payload"),
+                Member::Dir(name) => (name, EntryType::Directory, "", b""),
+                Member::Symlink(name, target) => (name, EntryType::Symlink, target, b""),
+            };
+            let raw = header.as_old_mut();
+            raw.name[..name.len()].copy_from_slice(name.as_bytes());
+            raw.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(kind);
+            header.set_mode(if kind == EntryType::Directory {
+                0o755
+            } else {
+                0o644
+            });
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
         }
+        builder.finish().unwrap();
     }
 
-    Ok(())
+    /// Extracts `members` into `<tmp>/out/target` and returns the temp dir.
+    fn extract(members: &[Member<'_>]) -> (tempfile::TempDir, sps_common::error::Result<()>) {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("crafted.tar");
+        write_tar(&archive, members);
+        let result = extract_archive(&archive, &tmp.path().join("out/target"), 0, "tar");
+        (tmp, result)
+    }
+
+    /// Fails if anything in the target resolves to a place outside it.
+    fn assert_confined(tmp: &Path) {
+        let target = tmp.join("out/target").canonicalize().unwrap();
+        for entry in WalkDir::new(&target).min_depth(1) {
+            let entry = entry.unwrap();
+            if let Ok(resolved) = entry.path().canonicalize() {
+                assert!(
+                    resolved.starts_with(&target),
+                    "{} resolves to {}",
+                    entry.path().display(),
+                    resolved.display()
+                );
+            }
+        }
+        let outside: Vec<_> = fs::read_dir(tmp.join("out"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name != "target")
+            .collect();
+        assert!(outside.is_empty(), "wrote outside the target: {outside:?}");
+    }
+
+    #[test]
+    fn rejects_parent_dir_members() {
+        let (tmp, result) = extract(&[Member::File("../evil"), Member::File("ok")]);
+        assert!(result.is_err());
+        assert!(tmp.path().join("out/target/ok").is_file());
+        assert_confined(tmp.path());
+    }
+
+    #[test]
+    fn rejects_absolute_members() {
+        let tmp = tempfile::tempdir().unwrap();
+        let victim = tmp.path().join("victim");
+        let archive = tmp.path().join("crafted.tar");
+        write_tar(&archive, &[Member::File(victim.to_str().unwrap())]);
+        let result = extract_archive(&archive, &tmp.path().join("out/target"), 0, "tar");
+        assert!(result.is_err());
+        assert!(!victim.exists());
+    }
+
+    #[test]
+    fn skips_symlinks_pointing_outside() {
+        let (tmp, result) = extract(&[
+            Member::Symlink("up", "../.."),
+            Member::Symlink("abs", "/etc/passwd"),
+            Member::Dir("lib/"),
+            Member::File("lib/libfoo.1.dylib"),
+            Member::Symlink("lib/libfoo.dylib", "libfoo.1.dylib"),
+        ]);
+        assert!(result.is_ok());
+        let target = tmp.path().join("out/target");
+        assert!(target.join("up").symlink_metadata().is_err());
+        assert!(target.join("abs").symlink_metadata().is_err());
+        assert!(target.join("lib/libfoo.dylib").is_file());
+        assert_confined(tmp.path());
+    }
+
+    #[test]
+    fn follows_chained_symlinks() {
+        let (tmp, result) = extract(&[Member::Symlink("a", "."), Member::Symlink("b", "a/..")]);
+        assert!(result.is_ok());
+        let target = tmp.path().join("out/target");
+        assert!(target.join("a").symlink_metadata().is_ok());
+        assert!(target.join("b").symlink_metadata().is_err());
+        assert_confined(tmp.path());
+    }
+
+    #[test]
+    fn rechecks_symlinks_after_a_later_member_replaces_one_they_go_through() {
+        let (tmp, result) = extract(&[
+            Member::Dir("sub/"),
+            Member::Symlink("a", "sub"),
+            Member::Symlink("b", "a/.."),
+            Member::Symlink("a", "."),
+        ]);
+        assert!(result.is_ok());
+        assert!(tmp.path().join("out/target/b").symlink_metadata().is_err());
+        assert_confined(tmp.path());
+    }
+
+    #[test]
+    fn refuses_to_write_through_an_escaping_symlink() {
+        let (tmp, result) = extract(&[
+            Member::Symlink("a", "."),
+            Member::Symlink("b", "a/.."),
+            Member::File("b/target/../escaped"),
+            Member::File("b/escaped"),
+        ]);
+        assert!(result.is_err());
+        assert_confined(tmp.path());
+    }
 }
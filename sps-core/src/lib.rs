@@ -3,8 +3,10 @@
 // Declare the top-level modules within the library crate
 pub mod build;
 pub mod check;
+pub mod cleanup;
 pub mod install;
 pub mod pipeline;
+pub mod services;
 pub mod uninstall;
 pub mod upgrade; // New
 #[cfg(target_os = "macos")]
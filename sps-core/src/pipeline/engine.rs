@@ -21,7 +21,7 @@ pub fn start_worker_pool_manager(
     success_count: Arc<AtomicUsize>,
     fail_count: Arc<AtomicUsize>,
 ) -> SpsResult<()> {
-    let num_workers = std::cmp::max(1, num_cpus::get_physical().saturating_sub(1)).min(6);
+    let num_workers = config.limits.max_workers;
     let pool = ThreadPool::new(num_workers);
     debug!(
         "Core worker pool manager started with {} workers.",
@@ -42,13 +42,15 @@ pub fn start_worker_pool_manager(
         let success_count_clone = Arc::clone(&success_count);
         let fail_count_clone = Arc::clone(&fail_count);
 
-        let _ = event_tx_clone.send(PipelineEvent::JobProcessingStarted {
-            target_id: job_id.clone(),
-        });
-
         debug!("[{}] Submitting job to worker pool.", job_id);
 
+        // Jobs only reach this channel once their dependencies have finished, so every
+        // queued job may run as soon as a worker is free. Started is reported from the
+        // worker itself, so the status display shows which jobs are actually running.
         pool.execute(move || {
+            let _ = event_tx_clone.send(PipelineEvent::JobProcessingStarted {
+                target_id: job_id.clone(),
+            });
             let job_result = worker::execute_sync_job(
                 worker_job,
                 &config_clone,
@@ -0,0 +1,364 @@
+// sps-core/src/pipeline/journal.rs
+//! Records the progress of each pipeline job so that an install interrupted by a crash
+//! or a kill can be found and cleaned up later.
+//!
+//! The journal is an append-only JSON-lines file in the state directory. A job writes a
+//! record before it first touches the Cellar or Caskroom, one after each step that
+//! changes the filesystem, and a final `committed` or `failed` record. A job whose
+//! latest record is `failed`, or still `in_progress` while its process is gone, did not
+//! finish: `sps doctor` reports it and `sps doctor --fix` removes what it left behind and
+//! only then records it as `rolled_back`.
+//!
+//! Whether the owning process is gone is decided by the sps process lock, which every
+//! job runs under, rather than by its PID alone: once no other process holds the lock,
+//! a PID that was since reused by an unrelated program does not keep a record open.
+//!
+//! Journal write failures are logged and otherwise ignored; they never fail a job.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::lock::SpsLock;
+use sps_common::model::formula::FormulaDependencies;
+use sps_common::model::InstallTargetIdentifier;
+use sps_common::pipeline::{JobAction, PlannedJob};
+use tracing::{debug, warn};
+
+/// Once the journal grows past this, finished transactions are dropped from it.
+const COMPACT_THRESHOLD_BYTES: u64 = 256 * 1024;
+
+/// Serializes journal writes from the worker threads of this process. Other sps
+/// processes are kept out by the process lock.
+static JOURNAL_WRITE: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    InProgress,
+    Committed,
+    /// The job returned an error; whatever it left behind has not been cleaned up yet.
+    Failed,
+    RolledBack,
+}
+
+/// One line of the journal. Every record repeats the whole transaction, so the latest
+/// record of an id describes it completely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub id: String,
+    pub status: TransactionStatus,
+    pub target: String,
+    pub is_cask: bool,
+    pub version: String,
+    /// `install`, `upgrade` or `reinstall`.
+    pub action: String,
+    /// The keg or Caskroom version directory the job writes.
+    pub path: PathBuf,
+    /// Whether `path` belongs to the job: it did not exist when the job started, or the
+    /// job already removed the installation that was there. Only then may a cleanup
+    /// delete it.
+    #[serde(default)]
+    pub owns_path: bool,
+    /// The installation an upgrade or reinstall replaces. Never removed by a cleanup.
+    #[serde(default)]
+    pub previous_path: Option<PathBuf>,
+    /// The last step that completed.
+    pub step: String,
+    pub pid: u32,
+    pub time: String,
+}
+
+/// An open journal transaction for one pipeline job.
+#[derive(Debug)]
+pub struct Transaction {
+    record: JournalRecord,
+}
+
+impl Transaction {
+    /// Opens a transaction for `job` and records it as in progress.
+    pub fn begin(job: &PlannedJob, config: &Config) -> Self {
+        let (is_cask, version, path) = match &job.target_definition {
+            InstallTargetIdentifier::Formula(formula) => (
+                false,
+                formula.version_str_full(),
+                (**formula)
+                    .install_prefix(config.cellar_dir().as_path())
+                    .unwrap_or_else(|_| config.formula_cellar_dir(formula.name())),
+            ),
+            InstallTargetIdentifier::Cask(cask) => {
+                let version = cask.version.clone().unwrap_or_else(|| "latest".to_string());
+                let path = config.cask_room_version_path(&cask.token, &version);
+                (true, version, path)
+            }
+        };
+        let (action, previous_path) = match &job.action {
+            JobAction::Install => ("install", None),
+            JobAction::Upgrade {
+                old_install_path, ..
+            } => ("upgrade", Some(old_install_path.clone())),
+            JobAction::Reinstall {
+                current_install_path,
+                ..
+            } => ("reinstall", Some(current_install_path.clone())),
+        };
+        let owns_path = path.symlink_metadata().is_err();
+        let transaction = Self {
+            record: JournalRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                status: TransactionStatus::InProgress,
+                target: job.target_id.clone(),
+                is_cask,
+                version,
+                action: action.to_string(),
+                path,
+                owns_path,
+                previous_path,
+                step: "started".to_string(),
+                pid: std::process::id(),
+                time: String::new(),
+            },
+        };
+        compact_if_large(config);
+        transaction.write(config);
+        transaction
+    }
+
+    /// Records that `step` has completed.
+    pub fn step(&mut self, step: &str, config: &Config) {
+        self.record.step = step.to_string();
+        self.write(config);
+    }
+
+    /// Records that the installation at `path` was removed, so that from now on the
+    /// path holds only what this job writes.
+    pub fn removed_previous(&mut self, config: &Config) {
+        if self.record.previous_path.as_deref() == Some(self.record.path.as_path()) {
+            self.record.owns_path = true;
+        }
+        self.step("removed previous version", config);
+    }
+
+    /// Closes the transaction as committed or failed. A failed job is left for
+    /// `sps doctor --fix`, which records the rollback once it has cleaned up.
+    pub fn finish(mut self, succeeded: bool, config: &Config) {
+        self.record.status = if succeeded {
+            TransactionStatus::Committed
+        } else {
+            TransactionStatus::Failed
+        };
+        self.write(config);
+    }
+
+    fn write(&self, config: &Config) {
+        if let Err(e) = append_record(&self.record, config) {
+            warn!(
+                "Could not update the install journal for {}: {}",
+                self.record.target, e
+            );
+        }
+    }
+}
+
+/// Transactions that failed, or whose process ended without committing them, and may
+/// have left something behind.
+pub fn incomplete_transactions(config: &Config) -> Vec<JournalRecord> {
+    let lock_holder = SpsLock::other_holder(config);
+    let mut incomplete: Vec<JournalRecord> = latest_records(config)
+        .into_values()
+        .filter(|record| match record.status {
+            TransactionStatus::InProgress => !owner_running(record, lock_holder),
+            TransactionStatus::Failed => left_changes(record),
+            TransactionStatus::Committed | TransactionStatus::RolledBack => false,
+        })
+        .collect();
+    incomplete.sort_by(|a, b| a.time.cmp(&b.time));
+    incomplete
+}
+
+/// Closes an interrupted transaction after its leftovers were cleaned up.
+pub fn mark_rolled_back(record: &JournalRecord, config: &Config) -> Result<()> {
+    let mut record = record.clone();
+    record.status = TransactionStatus::RolledBack;
+    record.step = "cleaned up".to_string();
+    append_record(&record, config)
+}
+
+fn append_record(record: &JournalRecord, config: &Config) -> Result<()> {
+    let path = config.install_journal_path();
+    let mut record = record.clone();
+    record.time = chrono::Utc::now().to_rfc3339();
+    let mut line = serde_json::to_string(&record).map_err(|e| SpsError::Json(e.into()))?;
+    line.push('\n');
+
+    let _guard = JOURNAL_WRITE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    // The record has to be on disk before the mutation it announces starts.
+    file.sync_data()?;
+    Ok(())
+}
+
+/// The latest record of every transaction in the journal. Unreadable lines, such as a
+/// record cut off by a crash, are skipped.
+fn latest_records(config: &Config) -> HashMap<String, JournalRecord> {
+    let Ok(contents) = fs::read_to_string(config.install_journal_path()) else {
+        return HashMap::new();
+    };
+    let mut latest = HashMap::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<JournalRecord>(line) {
+            Ok(record) => {
+                latest.insert(record.id.clone(), record);
+            }
+            Err(e) => debug!("Skipping unreadable install journal line: {}", e),
+        }
+    }
+    latest
+}
+
+/// Rewrites the journal with only the transactions that are still open.
+fn compact_if_large(config: &Config) {
+    let path = config.install_journal_path();
+    let _guard = JOURNAL_WRITE.lock().unwrap_or_else(|e| e.into_inner());
+    if fs::metadata(&path).map_or(true, |meta| meta.len() < COMPACT_THRESHOLD_BYTES) {
+        return;
+    }
+    let mut open: Vec<JournalRecord> = latest_records(config)
+        .into_values()
+        .filter(|record| match record.status {
+            TransactionStatus::InProgress => true,
+            TransactionStatus::Failed => left_changes(record),
+            TransactionStatus::Committed | TransactionStatus::RolledBack => false,
+        })
+        .collect();
+    open.sort_by(|a, b| a.time.cmp(&b.time));
+    let mut contents = String::new();
+    for record in &open {
+        if let Ok(line) = serde_json::to_string(record) {
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    match fs::write(&tmp, contents).and_then(|_| fs::rename(&tmp, &path)) {
+        Ok(()) => debug!(
+            "Compacted install journal to {} open transaction(s)",
+            open.len()
+        ),
+        Err(e) => warn!("Could not compact install journal: {}", e),
+    }
+}
+
+/// Whether a failed job changed anything: it created its path, or got past its first
+/// step (an upgrade or reinstall may already have removed the previous version).
+fn left_changes(record: &JournalRecord) -> bool {
+    record.step != "started" || (record.owns_path && exists(&record.path))
+}
+
+fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// Whether the process that wrote an in-progress record may still be running it.
+/// `lock_holder` is the process holding the sps lock, if that is not this process.
+fn owner_running(record: &JournalRecord, lock_holder: Option<Option<u32>>) -> bool {
+    if record.pid == std::process::id() {
+        return true;
+    }
+    match lock_holder {
+        // Jobs only run under the lock, so with nobody else holding it the owner is gone.
+        None => false,
+        Some(Some(holder)) => holder == record.pid,
+        // The holder did not record its PID.
+        Some(None) => process_alive(record.pid),
+    }
+}
+
+fn process_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks whether the process exists.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(config: &Config, status: TransactionStatus, pid: u32) -> JournalRecord {
+        JournalRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            status,
+            target: "foo".to_string(),
+            is_cask: false,
+            version: "1.0".to_string(),
+            action: "install".to_string(),
+            path: config.cellar_dir().join("foo/1.0"),
+            owns_path: true,
+            previous_path: None,
+            step: "started".to_string(),
+            pid,
+            time: String::new(),
+        }
+    }
+
+    #[test]
+    fn failed_job_is_not_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        let transaction = Transaction {
+            record: record(&config, TransactionStatus::InProgress, std::process::id()),
+        };
+        let id = transaction.record.id.clone();
+        fs::create_dir_all(&transaction.record.path).unwrap();
+        transaction.finish(false, &config);
+
+        let latest = latest_records(&config);
+        assert_eq!(latest[&id].status, TransactionStatus::Failed);
+        let incomplete = incomplete_transactions(&config);
+        assert_eq!(incomplete.len(), 1);
+
+        mark_rolled_back(&incomplete[0], &config).unwrap();
+        assert!(incomplete_transactions(&config).is_empty());
+    }
+
+    #[test]
+    fn failed_job_that_changed_nothing_is_not_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        append_record(&record(&config, TransactionStatus::Failed, 1), &config).unwrap();
+        assert!(incomplete_transactions(&config).is_empty());
+    }
+
+    #[test]
+    fn reused_pid_does_not_keep_a_transaction_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        // PID 1 is always alive, standing in for a PID reused by another program.
+        append_record(&record(&config, TransactionStatus::InProgress, 1), &config).unwrap();
+        assert_eq!(incomplete_transactions(&config).len(), 1);
+    }
+
+    #[test]
+    fn reinstall_owns_its_path_only_after_removing_the_previous_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        let mut existing = record(&config, TransactionStatus::InProgress, std::process::id());
+        existing.action = "reinstall".to_string();
+        existing.owns_path = false;
+        existing.previous_path = Some(existing.path.clone());
+        let mut transaction = Transaction { record: existing };
+
+        assert!(!transaction.record.owns_path);
+        transaction.removed_previous(&config);
+        assert!(transaction.record.owns_path);
+    }
+}
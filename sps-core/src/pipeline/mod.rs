@@ -1,2 +1,3 @@
 pub mod engine;
+pub mod journal;
 pub mod worker;
@@ -11,10 +11,14 @@ use sps_common::error::{Result as SpsResult, SpsError};
 use sps_common::keg::KegRegistry;
 use sps_common::model::formula::FormulaDependencies;
 use sps_common::model::InstallTargetIdentifier;
-use sps_common::pipeline::{JobAction, PipelineEvent, PipelinePackageType, WorkerJob};
+use sps_common::pipeline::{
+    ExtractProgressFn, GitProgressFn, InstallerOutputFn, JobAction, PipelineEvent,
+    PipelinePackageType, ProgressThrottle, WorkerJob,
+};
 use tokio::sync::broadcast;
 use tracing::{debug, error, instrument, warn};
 
+use super::journal::Transaction;
 use crate::check::installed::{InstalledPackageInfo, PackageType as CorePackageType};
 use crate::{build, install, uninstall, upgrade};
 
@@ -26,7 +30,9 @@ pub(super) fn execute_sync_job(
 ) -> std::result::Result<(JobAction, PipelinePackageType), Box<(JobAction, SpsError)>> {
     let action = worker_job.request.action.clone();
 
-    let result = do_execute_sync_steps(worker_job, config, cache, event_tx);
+    let mut journal = Transaction::begin(&worker_job.request, config);
+    let result = do_execute_sync_steps(worker_job, config, cache, event_tx, &mut journal);
+    journal.finish(result.is_ok(), config);
 
     result
         .map_err(|e| Box::new((action.clone(), e)))
@@ -39,6 +45,7 @@ fn do_execute_sync_steps(
     config: &Config,
     _cache: Arc<Cache>, // Marked as unused if cache is not directly used in this function body
     event_tx: broadcast::Sender<PipelineEvent>,
+    journal: &mut Transaction,
 ) -> SpsResult<PipelinePackageType> {
     let job_request = worker_job.request;
     let download_path = worker_job.download_path;
@@ -129,7 +136,7 @@ fn do_execute_sync_steps(
 
             match &job_request.target_definition {
                 InstallTargetIdentifier::Formula(formula) => {
-                    let http_client_for_bottle_upgrade = Arc::new(reqwest::Client::new());
+                    let http_client_for_bottle_upgrade = Arc::new(sps_net::http::shared_client()?);
                     let installed_path = if job_request.is_source_build {
                         let _ = event_tx.send(PipelineEvent::BuildStarted {
                             target_id: job_request.target_id.clone(),
@@ -141,6 +148,7 @@ fn do_execute_sync_steps(
                             &old_info,
                             config,
                             &all_dep_paths,
+                            job_request.installed_on_request,
                         ))?
                     } else {
                         block_on(upgrade::bottle::upgrade_bottle_formula(
@@ -148,6 +156,7 @@ fn do_execute_sync_steps(
                             &download_path,
                             &old_info,
                             config,
+                            job_request.installed_on_request,
                             http_client_for_bottle_upgrade,
                         ))?
                     };
@@ -159,6 +168,7 @@ fn do_execute_sync_steps(
                         &download_path,
                         &old_info,
                         config,
+                        Some(installer_output_reporter(&job_request.target_id, &event_tx)),
                     ))?;
                 }
             }
@@ -184,7 +194,11 @@ fn do_execute_sync_steps(
                     pkg_type: core_pkg_type.clone(),
                     path: old_install_path.clone(),
                 };
-                let uninstall_opts = uninstall::UninstallOptions { skip_zap: true };
+                let uninstall_opts = uninstall::UninstallOptions {
+                    skip_zap: true,
+                    ignore_dependencies: true,
+                    force: false,
+                };
 
                 match core_pkg_type {
                     CorePackageType::Formula => uninstall::uninstall_formula_artifacts(
@@ -200,6 +214,7 @@ fn do_execute_sync_steps(
                     "[{}] Reinstall: Removed existing version {}.",
                     job_request.target_id, from_version
                 );
+                journal.removed_previous(config);
                 let _ = event_tx.send(PipelineEvent::UninstallFinished {
                     target_id: job_request.target_id.clone(),
                     version: from_version.clone(),
@@ -231,13 +246,19 @@ fn do_execute_sync_steps(
                             formula,
                             config,
                             &build_dep_paths,
+                            job_request.installed_on_request,
                         );
                         let installed_dir = block_on(build_future)?;
                         formula_installed_path = Some(installed_dir);
                     } else {
                         debug!("[{}] Installing bottle...", job_request.target_id);
-                        let installed_dir =
-                            install::bottle::exec::install_bottle(&download_path, formula, config)?;
+                        let installed_dir = install::bottle::exec::install_bottle(
+                            &download_path,
+                            formula,
+                            config,
+                            job_request.installed_on_request,
+                            Some(extract_progress_reporter(&job_request.target_id, &event_tx)),
+                        )?;
                         formula_installed_path = Some(installed_dir);
                     }
                 }
@@ -348,6 +369,8 @@ fn do_execute_sync_steps(
                             &download_path,
                             config,
                             &job_request.action,
+                            Some(extract_progress_reporter(&job_request.target_id, &event_tx)),
+                            Some(installer_output_reporter(&job_request.target_id, &event_tx)),
                         )?;
                     }
                 }
@@ -355,6 +378,8 @@ fn do_execute_sync_steps(
         }
     };
 
+    journal.step("installed", config);
+
     if let Some(ref installed_path) = formula_installed_path {
         debug!(
             "[{}] Formula operation resulted in keg path: {}",
@@ -365,6 +390,19 @@ fn do_execute_sync_steps(
         debug!("[{}] Cask operation completed.", job_request.target_id);
     }
 
+    #[cfg(target_os = "macos")]
+    if let Some(ref keg_path) = formula_installed_path {
+        if config.quarantine.quarantine_formulae() {
+            let marked = crate::utils::xattr::quarantine_keg_executables(keg_path, "sps");
+            debug!(
+                "[{}] Quarantined {} executable(s) in {}",
+                job_request.target_id,
+                marked,
+                keg_path.display()
+            );
+        }
+    }
+
     if let (InstallTargetIdentifier::Formula(formula), Some(keg_path_for_linking)) =
         (&job_request.target_definition, &formula_installed_path)
     {
@@ -378,6 +416,12 @@ fn do_execute_sync_steps(
             pkg_type: pipeline_pkg_type,
         });
         install::bottle::link::link_formula_artifacts(formula, keg_path_for_linking, config)?;
+        journal.step("linked", config);
+        if let Some(caveats) =
+            install::bottle::link::keg_only_caveats(formula, keg_path_for_linking, config)
+        {
+            let _ = event_tx.send(PipelineEvent::LogInfo { message: caveats });
+        }
         debug!(
             "[{}] Linking complete for formula {}.",
             job_request.target_id,
@@ -387,3 +431,64 @@ fn do_execute_sync_steps(
 
     Ok(pipeline_pkg_type)
 }
+
+/// Builds a callback that forwards git clone/fetch progress for `target_id` as
+/// throttled `GitProgress` events.
+pub fn git_progress_reporter(
+    target_id: &str,
+    event_tx: &broadcast::Sender<PipelineEvent>,
+) -> GitProgressFn {
+    let target_id = target_id.to_string();
+    let event_tx = event_tx.clone();
+    let throttle = ProgressThrottle::new();
+    Arc::new(move |objects_received, total_objects, bytes_received| {
+        let is_final = total_objects > 0 && objects_received == total_objects;
+        if throttle.should_send(is_final) {
+            let _ = event_tx.send(PipelineEvent::GitProgress {
+                target_id: target_id.clone(),
+                objects_received,
+                total_objects,
+                bytes_received,
+            });
+        }
+    })
+}
+
+/// Builds a callback that forwards extraction progress for `target_id` as throttled
+/// `ExtractProgress` events.
+fn extract_progress_reporter(
+    target_id: &str,
+    event_tx: &broadcast::Sender<PipelineEvent>,
+) -> ExtractProgressFn {
+    let target_id = target_id.to_string();
+    let event_tx = event_tx.clone();
+    let throttle = ProgressThrottle::new();
+    Arc::new(move |files_done, total_files| {
+        if throttle.should_send(total_files == Some(files_done)) {
+            let _ = event_tx.send(PipelineEvent::ExtractProgress {
+                target_id: target_id.clone(),
+                files_done,
+                total_files,
+            });
+        }
+    })
+}
+
+/// Builds a callback that forwards the output of a cask's installer for `target_id` as
+/// throttled `InstallerOutput` events.
+fn installer_output_reporter(
+    target_id: &str,
+    event_tx: &broadcast::Sender<PipelineEvent>,
+) -> InstallerOutputFn {
+    let target_id = target_id.to_string();
+    let event_tx = event_tx.clone();
+    let throttle = ProgressThrottle::new();
+    Arc::new(move |line| {
+        if throttle.should_send(false) {
+            let _ = event_tx.send(PipelineEvent::InstallerOutput {
+                target_id: target_id.clone(),
+                line: line.to_string(),
+            });
+        }
+    })
+}
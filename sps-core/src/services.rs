@@ -0,0 +1,318 @@
+// sps-core/src/services.rs
+//! launchd services of installed packages: the `homebrew.mxcl.<name>.plist` a formula's
+//! bottle ships in its keg, and the launchd jobs a cask's `uninstall` stanza names, which
+//! are recorded in its install manifest.
+//!
+//! Services run in the user's GUI domain (`gui/<uid>`), or in the system domain through
+//! sudo. Starting a formula's service copies its plist into the domain's LaunchAgents or
+//! LaunchDaemons directory and stopping it removes the copy again, as Homebrew does. A
+//! cask's plist belongs to the app, so it is only loaded and unloaded.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::artifact::InstalledArtifact;
+use tracing::debug;
+
+use crate::check::installed::{self, PackageType};
+use crate::install::cask::helpers::find_system_tool;
+use crate::install::cask::CaskInstallManifest;
+
+/// The launchd domain a service is managed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceDomain {
+    /// The logged-in user's GUI session; the service runs as that user.
+    User,
+    /// The system domain; the service runs as root and starts at boot.
+    System,
+}
+
+impl ServiceDomain {
+    /// The domain target `launchctl` expects, e.g. `gui/501`.
+    fn target(self) -> String {
+        match self {
+            // SAFETY: getuid cannot fail.
+            Self::User => format!("gui/{}", unsafe { libc::getuid() }),
+            Self::System => "system".to_string(),
+        }
+    }
+
+    /// Where this domain's job plists live.
+    fn plist_dir(self, config: &Config) -> PathBuf {
+        match self {
+            Self::User => config.home_dir().join("Library/LaunchAgents"),
+            Self::System => PathBuf::from("/Library/LaunchDaemons"),
+        }
+    }
+}
+
+/// Where a service's plist comes from.
+#[derive(Debug, Clone)]
+pub enum ServiceSource {
+    /// The plist shipped in the formula's keg, copied into place when started.
+    Formula { keg_plist: PathBuf },
+    /// A cask's launchd job; the plist path is known if the manifest recorded it.
+    Cask { plist: Option<PathBuf> },
+}
+
+/// A launchd service belonging to an installed package.
+#[derive(Debug, Clone)]
+pub struct Service {
+    /// The formula or cask the service belongs to.
+    pub name: String,
+    pub label: String,
+    pub source: ServiceSource,
+}
+
+/// What launchd reports for a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    Running {
+        pid: u32,
+    },
+    /// Loaded but no process, e.g. an on-demand job or one that exited.
+    Loaded {
+        last_exit_code: Option<i32>,
+    },
+    Stopped,
+}
+
+/// Every service of the installed formulae and casks, sorted by package name.
+pub async fn installed_services(config: &Config) -> Result<Vec<Service>> {
+    let mut services: Vec<Service> = Vec::new();
+    for package in installed::get_installed_packages(config).await? {
+        match package.pkg_type {
+            PackageType::Formula => {
+                if services.iter().any(|service| service.name == package.name) {
+                    continue;
+                }
+                let label = formula_label(&package.name);
+                let file_name = format!("{label}.plist");
+                // The opt link follows the linked version when several are installed.
+                let keg_plist = [
+                    config.formula_opt_path(&package.name).join(&file_name),
+                    package.path.join(&file_name),
+                ]
+                .into_iter()
+                .find(|path| path.is_file());
+                if let Some(keg_plist) = keg_plist {
+                    services.push(Service {
+                        name: package.name,
+                        label,
+                        source: ServiceSource::Formula { keg_plist },
+                    });
+                }
+            }
+            PackageType::Cask => {
+                let manifest_path = package.path.join("CASK_INSTALL_MANIFEST.json");
+                let Some(manifest) = fs::read_to_string(&manifest_path)
+                    .ok()
+                    .and_then(|raw| serde_json::from_str::<CaskInstallManifest>(&raw).ok())
+                    .filter(|manifest| manifest.is_installed)
+                else {
+                    debug!("No usable manifest at {}", manifest_path.display());
+                    continue;
+                };
+                for artifact in manifest.artifacts {
+                    if let InstalledArtifact::Launchd { label, path } = artifact {
+                        if services.iter().any(|service| service.label == label) {
+                            continue;
+                        }
+                        services.push(Service {
+                            name: package.name.clone(),
+                            label,
+                            source: ServiceSource::Cask { plist: path },
+                        });
+                    }
+                }
+            }
+        }
+    }
+    services.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.label.cmp(&b.label)));
+    Ok(services)
+}
+
+/// The services of the formula or cask `name`, or of the service labelled `name`.
+pub async fn find_services(config: &Config, name: &str) -> Result<Vec<Service>> {
+    let services: Vec<Service> = installed_services(config)
+        .await?
+        .into_iter()
+        .filter(|service| service.name == name || service.label == name)
+        .collect();
+    if services.is_empty() {
+        return Err(SpsError::NotFound(format!(
+            "No installed formula or cask named '{name}' has a launchd service"
+        )));
+    }
+    Ok(services)
+}
+
+/// The label Homebrew formulae use for their service.
+fn formula_label(name: &str) -> String {
+    format!("homebrew.mxcl.{name}")
+}
+
+/// Asks launchd about `service` in `domain`.
+pub fn service_state(service: &Service, domain: ServiceDomain) -> Result<ServiceState> {
+    let output = launchctl(
+        &["print", &format!("{}/{}", domain.target(), service.label)],
+        false,
+    )?;
+    if !output.status.success() {
+        return Ok(ServiceState::Stopped);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value_of = |key: &str| {
+        stdout.lines().find_map(|line| {
+            line.trim()
+                .strip_prefix(key)
+                .and_then(|rest| rest.trim_start().strip_prefix('='))
+                .map(|value| value.trim().to_string())
+        })
+    };
+    if let Some(pid) = value_of("pid").and_then(|pid| pid.parse().ok()) {
+        return Ok(ServiceState::Running { pid });
+    }
+    Ok(ServiceState::Loaded {
+        last_exit_code: value_of("last exit code").and_then(|code| code.parse().ok()),
+    })
+}
+
+/// Loads `service` into `domain`. Returns false if it was already loaded.
+pub fn start_service(service: &Service, domain: ServiceDomain, config: &Config) -> Result<bool> {
+    if service_state(service, domain)? != ServiceState::Stopped {
+        return Ok(false);
+    }
+    let plist = match &service.source {
+        ServiceSource::Formula { keg_plist } => {
+            let installed = domain
+                .plist_dir(config)
+                .join(format!("{}.plist", service.label));
+            copy_plist(keg_plist, &installed, domain)?;
+            installed
+        }
+        ServiceSource::Cask { plist } => cask_plist(service, plist.as_deref(), config)?,
+    };
+    let output = launchctl(
+        &["bootstrap", &domain.target(), &plist.to_string_lossy()],
+        domain == ServiceDomain::System,
+    )?;
+    check_launchctl(&output, "bootstrap", &service.label)?;
+    Ok(true)
+}
+
+/// Unloads `service` from `domain`, removing the plist a formula's start copied into
+/// place. Returns false if it was not loaded.
+pub fn stop_service(service: &Service, domain: ServiceDomain, config: &Config) -> Result<bool> {
+    let was_loaded = service_state(service, domain)? != ServiceState::Stopped;
+    if was_loaded {
+        let output = launchctl(
+            &["bootout", &format!("{}/{}", domain.target(), service.label)],
+            domain == ServiceDomain::System,
+        )?;
+        check_launchctl(&output, "bootout", &service.label)?;
+    }
+    if let ServiceSource::Formula { .. } = service.source {
+        let installed = domain
+            .plist_dir(config)
+            .join(format!("{}.plist", service.label));
+        if installed.exists() {
+            remove_plist(&installed, domain)?;
+        }
+    }
+    Ok(was_loaded)
+}
+
+/// The plist of a cask's job: the recorded one, or `<label>.plist` in the usual
+/// LaunchAgents and LaunchDaemons directories.
+fn cask_plist(service: &Service, recorded: Option<&Path>, config: &Config) -> Result<PathBuf> {
+    let file_name = format!("{}.plist", service.label);
+    recorded
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain([
+            config
+                .home_dir()
+                .join("Library/LaunchAgents")
+                .join(&file_name),
+            Path::new("/Library/LaunchAgents").join(&file_name),
+            Path::new("/Library/LaunchDaemons").join(&file_name),
+        ])
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            SpsError::NotFound(format!(
+                "No plist for {} ({}) in LaunchAgents or LaunchDaemons; start {} once so it \
+                 installs its service",
+                service.label, service.name, service.name
+            ))
+        })
+}
+
+fn copy_plist(from: &Path, to: &Path, domain: ServiceDomain) -> Result<()> {
+    debug!("Installing {} as {}", from.display(), to.display());
+    if domain == ServiceDomain::System && !is_root() {
+        // launchd refuses system daemons whose plist is not owned by root.
+        let output = Command::new("sudo")
+            .args(["install", "-m", "644", "-o", "root", "-g", "wheel"])
+            .arg(from)
+            .arg(to)
+            .output()?;
+        return check_command(&output, &format!("install {}", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from, to)?;
+    Ok(())
+}
+
+fn remove_plist(path: &Path, domain: ServiceDomain) -> Result<()> {
+    debug!("Removing {}", path.display());
+    if domain == ServiceDomain::System && !is_root() {
+        let output = Command::new("sudo")
+            .arg("rm")
+            .arg("-f")
+            .arg(path)
+            .output()?;
+        return check_command(&output, &format!("remove {}", path.display()));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+fn launchctl(args: &[&str], privileged: bool) -> Result<Output> {
+    let tool = find_system_tool("launchctl", "manage launchd services")?;
+    debug!("Running launchctl {}", args.join(" "));
+    let mut command = if privileged && !is_root() {
+        let mut sudo = Command::new("sudo");
+        sudo.arg(tool);
+        sudo
+    } else {
+        Command::new(tool)
+    };
+    Ok(command.args(args).output()?)
+}
+
+fn check_launchctl(output: &Output, subcommand: &str, label: &str) -> Result<()> {
+    check_command(output, &format!("launchctl {subcommand} {label}"))
+}
+
+fn check_command(output: &Output, what: &str) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(SpsError::CommandExecError(format!(
+        "{} failed ({}): {}",
+        what,
+        output.status,
+        stderr.trim()
+    )))
+}
+
+fn is_root() -> bool {
+    // SAFETY: geteuid cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
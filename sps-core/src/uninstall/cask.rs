@@ -14,11 +14,11 @@ use tracing::{debug, error, warn};
 use trash; // This will be used by trash_path
 
 // Import helpers from the common module within the uninstall scope
-use super::common::{expand_tilde, is_safe_path, remove_filesystem_artifact};
+use super::common::{expand_zap_path, is_safe_path, remove_filesystem_artifact};
 use crate::check::installed::InstalledPackageInfo;
 // Corrected import path if install::cask::helpers is where it lives now
 use crate::install::cask::helpers::{
-    cleanup_empty_parent_dirs_in_private_store,
+    cleanup_empty_parent_dirs_in_private_store, find_system_tool,
     remove_path_robustly as remove_path_robustly_from_install_helpers,
 };
 use crate::install::cask::CaskInstallManifest;
@@ -129,15 +129,34 @@ pub fn uninstall_cask_artifacts(info: &InstalledPackageInfo, config: &Config) ->
     }
 }
 
+/// Reverses artifacts placed by an install that failed part-way, newest first, with
+/// the same handlers a regular uninstall uses. Returns a description of every
+/// artifact that could not be removed.
+pub fn rollback_cask_artifacts(artifacts: &[InstalledArtifact], config: &Config) -> Vec<String> {
+    debug!("Rolling back {} installed cask artifacts", artifacts.len());
+    artifacts
+        .iter()
+        .rev()
+        .filter(|artifact| !process_artifact_uninstall_core(artifact, config, false))
+        .map(|artifact| format!("{artifact:?}"))
+        .collect()
+}
+
 /// Performs a "zap" uninstall for a Cask, removing files defined in `zap` stanzas
 /// and cleaning up the private store. Also marks the cask as uninstalled in its manifest.
+///
+/// `~`, `{a,b}` and glob patterns in `trash`/`delete`/`rmdir` paths are expanded, and
+/// each matched path is checked with `is_safe_path` before anything is removed.
+/// `on_removed` is called with every path the stanzas removed or moved to the Trash.
 pub async fn zap_cask_artifacts(
     info: &InstalledPackageInfo,
     cask_def: &Cask,
     config: &Config,
+    on_removed: &mut (dyn FnMut(&Path) + Send),
 ) -> Result<()> {
     debug!("Starting ZAP process for cask: {}", cask_def.token);
     let home = config.home_dir();
+    let cache_dir = config.cache_dir();
     let cask_version_path_in_caskroom = &info.path;
     let mut zap_errors: Vec<String> = Vec::new();
 
@@ -247,11 +266,18 @@ pub async fn zap_cask_artifacts(
             );
             match action_detail {
                 ZapActionDetail::Trash(paths) => {
-                    for path_str in paths {
-                        let target = expand_tilde(path_str, &home);
-                        if is_safe_path(&target, &home, config) {
-                            if !trash_path(&target) {
-                                // Logged within trash_path
+                    for (target, matched_glob) in
+                        paths.iter().flat_map(|p| expand_zap_path(p, &home))
+                    {
+                        if is_safe_path(&target, matched_glob, &home, &cache_dir) {
+                            let exists_before =
+                                target.exists() || target.symlink_metadata().is_ok();
+                            // Failures are logged within trash_path
+                            if trash_path(&target)
+                                && exists_before
+                                && target.symlink_metadata().is_err()
+                            {
+                                on_removed(&target);
                             }
                         } else {
                             zap_errors
@@ -260,9 +286,10 @@ pub async fn zap_cask_artifacts(
                     }
                 }
                 ZapActionDetail::Delete(paths) | ZapActionDetail::Rmdir(paths) => {
-                    for path_str in paths {
-                        let target = expand_tilde(path_str, &home);
-                        if is_safe_path(&target, &home, config) {
+                    for (target, matched_glob) in
+                        paths.iter().flat_map(|p| expand_zap_path(p, &home))
+                    {
+                        if is_safe_path(&target, matched_glob, &home, &cache_dir) {
                             let use_sudo = target.starts_with("/Library")
                                 || target.starts_with("/Applications");
                             let exists_before =
@@ -271,9 +298,9 @@ pub async fn zap_cask_artifacts(
                                 if action_key == "rmdir" && !target.is_dir() {
                                     warn!("Zap rmdir target is not a directory: {}. Attempting as file delete.", target.display());
                                 }
-                                if !remove_filesystem_artifact(&target, use_sudo)
-                                    && (target.exists() || target.symlink_metadata().is_ok())
-                                {
+                                if remove_filesystem_artifact(&target, use_sudo) {
+                                    on_removed(&target);
+                                } else if target.exists() || target.symlink_metadata().is_ok() {
                                     zap_errors.push(format!(
                                         "Failed to {} {}",
                                         action_key,
@@ -579,8 +606,15 @@ fn forget_pkgutil_receipt(id: &str) -> bool {
         return false;
     }
     debug!("Forgetting package receipt (requires sudo): {}", id);
+    let pkgutil = match find_system_tool("pkgutil", &format!("forget package receipt {id}")) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("{}", e);
+            return false;
+        }
+    };
     let output = Command::new("sudo")
-        .arg("pkgutil")
+        .arg(pkgutil)
         .arg("--forget")
         .arg(id)
         .output();
@@ -4,12 +4,15 @@ use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 use std::{fs, io};
 
-use sps_common::config::Config;
 use tracing::{debug, error, warn};
 
 #[derive(Debug, Clone, Default)]
 pub struct UninstallOptions {
     pub skip_zap: bool,
+    /// Remove a formula even if other installed formulae depend on it.
+    pub ignore_dependencies: bool,
+    /// Downgrade the installed-dependents refusal to a warning.
+    pub force: bool,
 }
 
 /// Removes a filesystem artifact (file or directory).
@@ -106,35 +109,138 @@ pub(crate) fn remove_filesystem_artifact(path: &Path, use_sudo: bool) -> bool {
 
 /// Expands a path string that may start with `~` to the user's home directory.
 pub(crate) fn expand_tilde(path_str: &str, home: &Path) -> PathBuf {
-    if let Some(stripped) = path_str.strip_prefix("~/") {
+    if path_str == "~" {
+        home.to_path_buf()
+    } else if let Some(stripped) = path_str.strip_prefix("~/") {
         home.join(stripped)
     } else {
         PathBuf::from(path_str)
     }
 }
 
+/// Expands `~`, `{a,b}` alternatives and glob patterns in a zap path into the
+/// existing paths it matches, each with whether it came from a glob pattern. A path
+/// without patterns is returned as is, whether or not it exists.
+pub(crate) fn expand_zap_path(path_str: &str, home: &Path) -> Vec<(PathBuf, bool)> {
+    let mut paths = Vec::new();
+    for alternative in expand_braces(path_str) {
+        let expanded = expand_tilde(&alternative, home);
+        let pattern = expanded.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push((expanded, false));
+            continue;
+        }
+        match glob::glob(&pattern) {
+            Ok(matches) => paths.extend(matches.flatten().map(|path| (path, true))),
+            Err(e) => warn!("Invalid zap path pattern '{}': {}", path_str, e),
+        }
+    }
+    paths
+}
+
+/// Expands the first `{a,b,...}` group, then recurses for any further groups.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let Some(close) = pattern[open..].find('}').map(|i| open + i) else {
+        return vec![pattern.to_string()];
+    };
+    let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{head}{alternative}{tail}")))
+        .collect()
+}
+
+/// Top-level folders of a Library directory, shared by many apps; zap may remove
+/// entries inside them but never the folders themselves.
+const SHARED_LIBRARY_DIRS: &[&str] = &[
+    "Application Scripts",
+    "Application Support",
+    "Caches",
+    "Containers",
+    "Cookies",
+    "Group Containers",
+    "HTTPStorages",
+    "LaunchAgents",
+    "LaunchDaemons",
+    "Logs",
+    "Preferences",
+    "Saved Application State",
+    "WebKit",
+];
+
+/// Folders that hold credentials; nothing at or below them is ever zapped, whether in
+/// `~/Library` or `/Library`.
+const PROTECTED_LIBRARY_DIRS: &[&str] = &["Keychains"];
+
 /// Checks if a path is safe for zap operations.
-/// Safe paths are typically within user Library, .config, /Applications, /Library,
-/// or the sps cache directory. Root, home, /Applications, /Library themselves are not safe.
-pub(crate) fn is_safe_path(path: &Path, home: &Path, config: &Config) -> bool {
+///
+/// Safe paths are absolute and inside `~/Library`, `~/.config`, /Applications, /Library
+/// or the sps cache directory. Those roots themselves, the shared folders directly
+/// inside the Library directories (e.g. `~/Library/Preferences`) and the keychain
+/// folders are not safe, and neither are dotfiles directly in the home directory (e.g.
+/// `~/.ssh`, `~/.zshrc`). `matched_glob` marks a path that came from expanding a
+/// pattern; such a match directly in the home directory is always refused.
+pub(crate) fn is_safe_path(path: &Path, matched_glob: bool, home: &Path, cache_dir: &Path) -> bool {
     if path.components().any(|c| matches!(c, Component::ParentDir)) {
         warn!("Zap path rejected (contains '..'): {}", path.display());
         return false;
     }
+    if !path.is_absolute() {
+        warn!("Zap path rejected (not absolute): {}", path.display());
+        return false;
+    }
+    let system_library = Path::new("/Library");
+    let user_library = home.join("Library");
+    let user_config = home.join(".config");
     let allowed_roots = [
-        home.join("Library"),
-        home.join(".config"),
-        PathBuf::from("/Applications"),
-        PathBuf::from("/Library"),
-        config.cache_dir().clone(),
-        // Consider adding more specific allowed user dirs if necessary
+        user_library.as_path(),
+        user_config.as_path(),
+        Path::new("/Applications"),
+        system_library,
+        cache_dir,
     ];
 
-    // Check if the path is exactly one of the top-level restricted paths
+    if matched_glob && path.parent() == Some(home) {
+        warn!(
+            "Zap path rejected (pattern match directly in the home directory): {}",
+            path.display()
+        );
+        return false;
+    }
+    let is_home_dotfile = path
+        .strip_prefix(home)
+        .ok()
+        .and_then(|relative| relative.components().next())
+        .is_some_and(|first| {
+            let name = first.as_os_str().to_string_lossy();
+            name.starts_with('.') && first.as_os_str() != ".config"
+        });
+    let is_protected = [user_library.as_path(), system_library]
+        .iter()
+        .any(|library| {
+            PROTECTED_LIBRARY_DIRS
+                .iter()
+                .any(|dir| path.starts_with(library.join(dir)))
+        });
+    if is_home_dotfile || is_protected {
+        warn!("Zap path rejected (protected): {}", path.display());
+        return false;
+    }
+
+    let is_shared_dir = |parent: &Path, names: &[&str]| {
+        path.parent() == Some(parent)
+            && path
+                .file_name()
+                .is_some_and(|name| names.iter().any(|n| name == *n))
+    };
     if path == Path::new("/")
         || path == home
-        || path == Path::new("/Applications")
-        || path == Path::new("/Library")
+        || allowed_roots.contains(&path)
+        || is_shared_dir(&user_library, SHARED_LIBRARY_DIRS)
+        || is_shared_dir(system_library, SHARED_LIBRARY_DIRS)
     {
         warn!("Zap path rejected (too broad): {}", path.display());
         return false;
@@ -150,3 +256,104 @@ pub(crate) fn is_safe_path(path: &Path, home: &Path, config: &Config) -> bool {
     );
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{expand_zap_path, is_safe_path};
+
+    fn home_with(entries: &[&str]) -> tempfile::TempDir {
+        let home = tempfile::tempdir().unwrap();
+        for entry in entries {
+            let path = home.path().join(entry);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+        }
+        home
+    }
+
+    fn safe(path: &Path, matched_glob: bool, home: &Path) -> bool {
+        is_safe_path(path, matched_glob, home, Path::new("/tmp/sps-test-cache"))
+    }
+
+    #[test]
+    fn refuses_dotfiles_in_home() {
+        let home = Path::new("/Users/me");
+        for path in [".ssh", ".ssh/id_ed25519", ".gnupg", ".zshrc"] {
+            assert!(!safe(&home.join(path), false, home), "{path}");
+        }
+    }
+
+    #[test]
+    fn refuses_keychains() {
+        let home = Path::new("/Users/me");
+        for path in [
+            home.join("Library/Keychains"),
+            home.join("Library/Keychains/login.keychain-db"),
+            PathBuf::from("/Library/Keychains/System.keychain"),
+        ] {
+            assert!(!safe(&path, false, home), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn refuses_everything_home_globs_match() {
+        let home = home_with(&[".ssh/id_ed25519", ".zshrc", "Documents/notes.txt"]);
+        for pattern in ["~/*", "~/.*"] {
+            let matches = expand_zap_path(pattern, home.path());
+            assert!(!matches.is_empty(), "{pattern} matched nothing");
+            for (path, matched_glob) in matches {
+                assert!(matched_glob);
+                assert!(
+                    !safe(&path, matched_glob, home.path()),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_roots_shared_dirs_and_other_home_dirs() {
+        let home = Path::new("/Users/me");
+        for path in [
+            PathBuf::from("/"),
+            home.to_path_buf(),
+            home.join("Library"),
+            home.join("Library/Preferences"),
+            home.join(".config"),
+            home.join("Documents/report.pdf"),
+            PathBuf::from("/Library"),
+            PathBuf::from("/Library/Application Support"),
+            PathBuf::from("/usr/local/bin/tool"),
+            PathBuf::from("Library/Preferences/com.example.plist"),
+            home.join("Library/Preferences/../../.ssh"),
+        ] {
+            assert!(!safe(&path, false, home), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn allows_app_files_in_library_and_config() {
+        let home = home_with(&[
+            "Library/Caches/com.example.app/cache.db",
+            "Library/Caches/com.example.helper/cache.db",
+        ]);
+        let home = home.path();
+        for path in [
+            home.join("Library/Preferences/com.example.app.plist"),
+            home.join("Library/Application Support/Example"),
+            home.join(".config/example"),
+            PathBuf::from("/Library/Application Support/Example"),
+        ] {
+            assert!(safe(&path, false, home), "{}", path.display());
+        }
+        let matches = expand_zap_path("~/Library/Caches/com.example.*", home);
+        assert_eq!(matches.len(), 2);
+        for (path, matched_glob) in matches {
+            assert!(safe(&path, matched_glob, home), "{}", path.display());
+        }
+    }
+}
@@ -1,23 +1,229 @@
 // sps-core/src/uninstall/formula.rs
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+
+use sps_common::cache::Cache;
 use sps_common::config::Config;
+use sps_common::dependency::DependencyExt;
 use sps_common::error::{Result, SpsError};
+use sps_common::formulary::Formulary;
+use sps_common::keg::{InstalledKeg, KegRegistry};
+use sps_common::model::cask::Cask;
 use tracing::{debug, error, warn};
 
 use crate::check::installed::InstalledPackageInfo;
+use crate::check::pinned;
 use crate::install; // For install::bottle::link
+use crate::install::cask::CaskInstallManifest;
 use crate::uninstall::common::{remove_filesystem_artifact, UninstallOptions};
 
+const CASK_MANIFEST_FILENAME: &str = "CASK_INSTALL_MANIFEST.json";
+
+/// Maps each of `names` to the installed formulae whose runtime dependencies include
+/// it. Only names with at least one dependent appear in the result. Formulae listed in
+/// `names` are not counted as dependents, so removing a package together with
+/// everything that needs it is allowed.
+///
+/// Dependencies are read as [`find_orphaned_dependencies`] reads them, so a keg still
+/// counts as a dependent after its formula was renamed or dropped upstream. Fails if
+/// what an installed formula depends on cannot be told.
+pub fn find_installed_dependents(
+    names: &[String],
+    config: &Config,
+) -> Result<HashMap<String, Vec<String>>> {
+    let kegs = KegRegistry::new(config.clone()).list_installed_kegs()?;
+    let installed: BTreeSet<&str> = kegs.iter().map(|keg| keg.name.as_str()).collect();
+    let formulary = Formulary::new(config.clone());
+
+    let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+    for &installed_name in installed.iter().filter(|&&n| !names.iter().any(|t| t == n)) {
+        for dep in installed_runtime_dependencies(installed_name, &kegs, &formulary)? {
+            if names.contains(&dep) {
+                dependents
+                    .entry(dep)
+                    .or_default()
+                    .push(installed_name.to_string());
+            }
+        }
+    }
+    Ok(dependents)
+}
+
+/// What installed formula `name` needs at runtime: the dependencies recorded in the
+/// receipt of one of its kegs or, for receipts that predate the record, those of its
+/// current definition. Fails if neither is available.
+fn installed_runtime_dependencies(
+    name: &str,
+    kegs: &[InstalledKeg],
+    formulary: &Formulary,
+) -> Result<Vec<String>> {
+    let recorded = kegs
+        .iter()
+        .filter(|keg| keg.name == name)
+        .find_map(|keg| keg.runtime_dependencies());
+    if let Some(dependencies) = recorded {
+        return Ok(dependencies);
+    }
+    let formula = formulary.load_formula(name).map_err(|e| {
+        SpsError::DependencyError(format!(
+            "Cannot tell what installed formula '{name}' depends on: {e}"
+        ))
+    })?;
+    Ok(formula
+        .dependencies()?
+        .runtime()
+        .into_iter()
+        .map(|dep| dep.name.clone())
+        .collect())
+}
+
+/// Installed formulae that were only installed as dependencies and that nothing kept
+/// still needs. Formulae installed on request, pinned formulae and the formula
+/// dependencies of installed casks are kept, and so is everything they need at runtime,
+/// directly or through other kept formulae; the walk runs until it reaches nothing new,
+/// so a dependency of an orphan is an orphan too.
+///
+/// What a keg needs is taken from its receipt, which records the dependencies it was
+/// installed with; the current definition is only consulted for kegs whose receipt
+/// predates that record. Fails if neither tells what an installed formula or cask
+/// depends on, since its dependencies would otherwise be removed from under it.
+pub fn find_orphaned_dependencies(config: &Config) -> Result<Vec<String>> {
+    let kegs = KegRegistry::new(config.clone()).list_installed_kegs()?;
+    let installed: BTreeSet<&str> = kegs.iter().map(|keg| keg.name.as_str()).collect();
+    let pinned = pinned::get_pinned_packages(config)?;
+    let formulary = Formulary::new(config.clone());
+
+    let mut runtime_dependencies: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut kept: Vec<&str> = Vec::new();
+    for &name in &installed {
+        let dependencies = installed_runtime_dependencies(name, &kegs, &formulary)?;
+        runtime_dependencies.insert(name, dependencies);
+        let on_request = kegs
+            .iter()
+            .filter(|keg| keg.name == name)
+            .any(|keg| keg.installed_on_request().unwrap_or(true));
+        if on_request || pinned.contains(name) {
+            kept.push(name);
+        }
+    }
+    let cask_dependencies = installed_cask_formula_dependencies(config)?;
+    kept.extend(
+        cask_dependencies
+            .iter()
+            .filter_map(|dep| installed.get(dep.as_str()).copied()),
+    );
+
+    let mut required: HashSet<&str> = kept.iter().copied().collect();
+    while let Some(name) = kept.pop() {
+        for dep in runtime_dependencies.get(name).into_iter().flatten() {
+            if let Some(&dep) = installed.get(dep.as_str()) {
+                if required.insert(dep) {
+                    kept.push(dep);
+                }
+            }
+        }
+    }
+    Ok(installed
+        .into_iter()
+        .filter(|name| !required.contains(name))
+        .map(str::to_string)
+        .collect())
+}
+
+/// The `depends_on.formula` entries of every installed cask, as recorded in its install
+/// manifest or, for manifests that predate the record, in the cached cask definitions.
+fn installed_cask_formula_dependencies(config: &Config) -> Result<BTreeSet<String>> {
+    let Ok(tokens) = fs::read_dir(config.cask_room_dir()) else {
+        return Ok(BTreeSet::new());
+    };
+    let mut dependencies = BTreeSet::new();
+    let mut unrecorded = Vec::new();
+    for token_dir in tokens.flatten().map(|entry| entry.path()) {
+        let Ok(versions) = fs::read_dir(&token_dir) else {
+            continue;
+        };
+        for version_dir in versions.flatten().map(|entry| entry.path()) {
+            let Some(manifest) = fs::read_to_string(version_dir.join(CASK_MANIFEST_FILENAME))
+                .ok()
+                .and_then(|raw| serde_json::from_str::<CaskInstallManifest>(&raw).ok())
+            else {
+                continue;
+            };
+            if !manifest.is_installed {
+                continue;
+            }
+            match manifest.formula_dependencies {
+                Some(recorded) => dependencies.extend(recorded),
+                None => unrecorded.push(manifest.token),
+            }
+        }
+    }
+    if unrecorded.is_empty() {
+        return Ok(dependencies);
+    }
+
+    let definitions: Vec<serde_json::Value> = Cache::new(config)
+        .and_then(|cache| cache.load_raw("cask.json"))
+        .and_then(|raw| serde_json::from_str(&raw).map_err(SpsError::from))
+        .map_err(|e| {
+            SpsError::DependencyError(format!(
+                "Cannot tell what installed cask(s) {} depend on: {e}",
+                unrecorded.join(", ")
+            ))
+        })?;
+    for token in &unrecorded {
+        let cask = definitions
+            .iter()
+            .find(|cask| cask.get("token").and_then(|t| t.as_str()) == Some(token))
+            .and_then(|cask| serde_json::from_value::<Cask>(cask.clone()).ok())
+            .ok_or_else(|| {
+                SpsError::DependencyError(format!(
+                    "Cannot tell what installed cask '{token}' depends on: it is not in the \
+                     cached cask definitions"
+                ))
+            })?;
+        if let Some(depends_on) = cask.depends_on {
+            dependencies.extend(depends_on.formula);
+        }
+    }
+    Ok(dependencies)
+}
+
 pub fn uninstall_formula_artifacts(
     info: &InstalledPackageInfo,
     config: &Config,
-    _options: &UninstallOptions, /* options currently unused for formula but kept for signature
-                                  * consistency */
+    options: &UninstallOptions,
 ) -> Result<()> {
     debug!(
         "Uninstalling Formula artifacts for {} version {}",
         info.name, info.version
     );
 
+    // 0. Refuse to break installed formulae that still need this one
+    if !options.ignore_dependencies {
+        let dependents = match find_installed_dependents(std::slice::from_ref(&info.name), config) {
+            Ok(mut dependents) => dependents.remove(&info.name).unwrap_or_default(),
+            Err(e) if options.force => {
+                warn!("{}. Proceeding because --force was given.", e);
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+        if !dependents.is_empty() {
+            let msg = format!(
+                "'{}' is required by installed formulae: {}",
+                info.name,
+                dependents.join(", ")
+            );
+            if !options.force {
+                return Err(SpsError::DependencyError(format!(
+                    "{msg}. Use --ignore-dependencies to remove it anyway."
+                )));
+            }
+            warn!("{}. Proceeding because --force was given.", msg);
+        }
+    }
+
     // 1. Unlink artifacts
     // This function should handle removal of symlinks from /opt/sps/bin, /opt/sps/lib etc.
     // and the /opt/sps/opt/formula_name link.
@@ -56,3 +262,100 @@ pub fn uninstall_formula_artifacts(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn keg(config: &Config, name: &str, on_request: bool, deps: &[&str]) {
+        let path = config.cellar_dir().join(name).join("1.0");
+        fs::create_dir_all(&path).unwrap();
+        let receipt = json!({
+            "name": name,
+            sps_common::keg::INSTALLED_ON_REQUEST_KEY: on_request,
+            sps_common::keg::RUNTIME_DEPENDENCIES_KEY: deps,
+        });
+        fs::write(path.join("INSTALL_RECEIPT.json"), receipt.to_string()).unwrap();
+    }
+
+    fn cask(config: &Config, token: &str, formula_deps: &[&str]) {
+        let path = config.cask_room_dir().join(token).join("1.0");
+        fs::create_dir_all(&path).unwrap();
+        let manifest = CaskInstallManifest {
+            manifest_format_version: "1.0".to_string(),
+            token: token.to_string(),
+            version: "1.0".to_string(),
+            installed_at: 0,
+            artifacts: Vec::new(),
+            primary_app_file_name: None,
+            is_installed: true,
+            cask_store_path: None,
+            formula_dependencies: Some(formula_deps.iter().map(|d| d.to_string()).collect()),
+        };
+        fs::write(
+            path.join(CASK_MANIFEST_FILENAME),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn orphans_of_orphans_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        keg(&config, "app", true, &["lib"]);
+        keg(&config, "lib", false, &["base"]);
+        keg(&config, "base", false, &[]);
+        keg(&config, "old", false, &["stale"]);
+        keg(&config, "stale", false, &["base"]);
+
+        let orphans = find_orphaned_dependencies(&config).unwrap();
+        assert_eq!(orphans, ["old", "stale"]);
+    }
+
+    #[test]
+    fn pinned_kegs_and_their_dependencies_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        keg(&config, "held", false, &["lib"]);
+        keg(&config, "lib", false, &[]);
+        pinned::pin_package("held", &config).unwrap();
+
+        assert!(find_orphaned_dependencies(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cask_formula_dependencies_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        keg(&config, "tool", false, &["lib"]);
+        keg(&config, "lib", false, &[]);
+        keg(&config, "unused", false, &[]);
+        cask(&config, "gui", &["tool"]);
+
+        assert_eq!(find_orphaned_dependencies(&config).unwrap(), ["unused"]);
+    }
+
+    #[test]
+    fn dependents_come_from_receipts() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_tests(dir.path());
+        // Neither formula is in the (empty) API cache, as after an upstream rename.
+        keg(&config, "app", true, &["libold"]);
+        keg(&config, "libold", false, &[]);
+        keg(&config, "other", true, &[]);
+
+        let dependents = find_installed_dependents(&["libold".to_string()], &config).unwrap();
+        assert_eq!(dependents["libold"], ["app"]);
+        assert!(find_installed_dependents(&["app".to_string()], &config)
+            .unwrap()
+            .is_empty());
+
+        let legacy = config.cellar_dir().join("legacy/1.0");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("INSTALL_RECEIPT.json"), "{}").unwrap();
+        assert!(find_installed_dependents(&["libold".to_string()], &config).is_err());
+    }
+}
@@ -5,6 +5,8 @@ pub mod common;
 pub mod formula;
 
 // Re-export key functions and types
-pub use cask::{uninstall_cask_artifacts, zap_cask_artifacts};
+pub use cask::{rollback_cask_artifacts, uninstall_cask_artifacts, zap_cask_artifacts};
 pub use common::UninstallOptions;
-pub use formula::uninstall_formula_artifacts;
+pub use formula::{
+    find_installed_dependents, find_orphaned_dependencies, uninstall_formula_artifacts,
+};
@@ -22,6 +22,7 @@ pub async fn upgrade_bottle_formula(
     new_bottle_download_path: &Path,
     old_install_info: &InstalledPackageInfo,
     config: &Config,
+    installed_on_request: bool,
     http_client: Arc<reqwest::Client>, /* Added for download_bottle if needed, though path is
                                         * pre-downloaded */
 ) -> SpsResult<PathBuf> {
@@ -38,7 +39,12 @@ pub async fn upgrade_bottle_formula(
         old_install_info.version,
         old_install_info.path.display()
     );
-    let uninstall_opts = uninstall::UninstallOptions { skip_zap: true }; // Zap is not relevant for formula upgrades
+    // Zap is not relevant for formula upgrades; dependents move to the new version.
+    let uninstall_opts = uninstall::UninstallOptions {
+        skip_zap: true,
+        ignore_dependencies: true,
+        force: false,
+    };
     uninstall::formula::uninstall_formula_artifacts(old_install_info, config, &uninstall_opts)
         .map_err(|e| {
             error!(
@@ -64,20 +70,24 @@ pub async fn upgrade_bottle_formula(
         formula.name(),
         new_bottle_download_path.display()
     );
-    let installed_keg_path =
-        install::bottle::exec::install_bottle(new_bottle_download_path, formula, config).map_err(
-            |e| {
-                error!(
-                    "Failed to install new bottle for formula {}: {}",
-                    formula.name(),
-                    e
-                );
-                SpsError::InstallError(format!(
-                    "Failed to install new bottle during upgrade of {}: {e}",
-                    formula.name()
-                ))
-            },
-        )?;
+    let installed_keg_path = install::bottle::exec::install_bottle(
+        new_bottle_download_path,
+        formula,
+        config,
+        installed_on_request,
+        None,
+    )
+    .map_err(|e| {
+        error!(
+            "Failed to install new bottle for formula {}: {}",
+            formula.name(),
+            e
+        );
+        SpsError::InstallError(format!(
+            "Failed to install new bottle during upgrade of {}: {e}",
+            formula.name()
+        ))
+    })?;
     debug!(
         "Successfully installed new bottle for {} to {}",
         formula.name(),
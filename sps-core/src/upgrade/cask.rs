@@ -5,18 +5,20 @@ use std::path::Path;
 use sps_common::config::Config;
 use sps_common::error::{Result as SpsResult, SpsError};
 use sps_common::model::cask::Cask;
-use sps_common::pipeline::JobAction; // Required for install_cask
+use sps_common::pipeline::{InstallerOutputFn, JobAction}; // Required for install_cask
 use tracing::{debug, error};
 
 use crate::check::installed::InstalledPackageInfo;
 use crate::{install, uninstall};
 
-/// Upgrades a cask package using Homebrew's proven strategy.
+/// Upgrades a cask package using Homebrew's proven strategy. `installer_output` receives
+/// the output of the new version's pkg installer or installer script, if it has one.
 pub async fn upgrade_cask_package(
     cask: &Cask,
     new_cask_download_path: &Path,
     old_install_info: &InstalledPackageInfo,
     config: &Config,
+    installer_output: Option<InstallerOutputFn>,
 ) -> SpsResult<()> {
     debug!(
         "Upgrading cask {} from {} to {}",
@@ -68,6 +70,8 @@ pub async fn upgrade_cask_package(
         new_cask_download_path,
         config,
         &job_action_for_install,
+        None,
+        installer_output,
     )
     .map_err(|e| {
         error!(
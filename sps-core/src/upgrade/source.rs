@@ -22,6 +22,7 @@ pub async fn upgrade_source_formula(
     old_install_info: &InstalledPackageInfo,
     config: &Config,
     all_installed_dependency_paths: &[PathBuf], // For build environment
+    installed_on_request: bool,
 ) -> SpsResult<PathBuf> {
     debug!(
         "Upgrading source-built formula {} from {} to {}",
@@ -36,7 +37,11 @@ pub async fn upgrade_source_formula(
         old_install_info.version,
         old_install_info.path.display()
     );
-    let uninstall_opts = uninstall::UninstallOptions { skip_zap: true };
+    let uninstall_opts = uninstall::UninstallOptions {
+        skip_zap: true,
+        ignore_dependencies: true,
+        force: false,
+    };
     uninstall::formula::uninstall_formula_artifacts(old_install_info, config, &uninstall_opts)
         .map_err(|e| {
             error!(
@@ -66,6 +71,7 @@ pub async fn upgrade_source_formula(
         formula,
         config,
         all_installed_dependency_paths,
+        installed_on_request,
     )
     .await
     .map_err(|e| {
@@ -1,5 +1,8 @@
+use std::collections::HashSet;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::process::Command;
+use std::sync::{LazyLock, Mutex};
 
 use anyhow::Context;
 use sps_common::error::{Result, SpsError};
@@ -7,6 +10,36 @@ use tracing::{debug, error};
 use uuid::Uuid;
 use xattr;
 
+/// Device IDs of filesystems that reported xattrs as unsupported during this run.
+/// Further quarantine attempts on these devices are skipped quietly.
+static XATTR_UNSUPPORTED_DEVICES: LazyLock<Mutex<HashSet<u64>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn device_id(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|m| m.dev())
+}
+
+fn is_xattr_unsupported_device(dev: u64) -> bool {
+    XATTR_UNSUPPORTED_DEVICES
+        .lock()
+        .map(|devices| devices.contains(&dev))
+        .unwrap_or(false)
+}
+
+/// Records that `dev` does not support xattrs. Returns true the first time a device is seen.
+fn mark_xattr_unsupported_device(dev: u64) -> bool {
+    XATTR_UNSUPPORTED_DEVICES
+        .lock()
+        .map(|mut devices| devices.insert(dev))
+        .unwrap_or(false)
+}
+
+/// Recognizes ENOTSUP/EOPNOTSUPP as reported by the `xattr` tool, e.g.
+/// "[Errno 45] Operation not supported" on network mounts and some external volumes.
+fn is_not_supported_error(stderr: &str) -> bool {
+    stderr.to_ascii_lowercase().contains("not supported")
+}
+
 // Helper to get current timestamp as hex
 fn get_timestamp_hex() -> String {
     let secs = std::time::SystemTime::now()
@@ -47,9 +80,38 @@ pub fn ensure_quarantine_attribute(path: &Path, agent: &str) -> anyhow::Result<(
         .with_context(|| format!("adding quarantine to {}", path.display()))
 }
 
+/// Quarantines the files directly inside a keg's `bin` and `sbin` directories, for
+/// `--force-quarantine`. Symlinks are skipped, as their targets are quarantined
+/// where they live. Failures are logged and skipped; returns how many files were marked.
+pub fn quarantine_keg_executables(keg_path: &Path, agent_name: &str) -> usize {
+    let mut marked = 0;
+    for dir in ["bin", "sbin"].map(|name| keg_path.join(name)) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.symlink_metadata().is_ok_and(|m| m.is_file()) {
+                continue;
+            }
+            match set_quarantine_attribute(&path, agent_name) {
+                Ok(()) => marked += 1,
+                Err(e) => tracing::warn!(
+                    "Failed to set quarantine attribute on {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+    marked
+}
+
 /// Sets the 'com.apple.quarantine' extended attribute on a file or directory.
 /// Uses flags commonly seen for user-initiated downloads (0081).
 /// Logs errors assertively, as failure is critical for correct behavior.
+/// Filesystems without xattr support are not an error: they are noted once at debug level
+/// and skipped for the rest of the run.
 pub fn set_quarantine_attribute(path: &Path, agent_name: &str) -> Result<()> {
     if !cfg!(target_os = "macos") {
         debug!(
@@ -70,6 +132,11 @@ pub fn set_quarantine_attribute(path: &Path, agent_name: &str) -> Result<()> {
         )));
     }
 
+    let dev = device_id(path);
+    if dev.is_some_and(is_xattr_unsupported_device) {
+        return Ok(());
+    }
+
     let timestamp_hex = get_timestamp_hex();
     let uuid_hex = get_uuid_hex();
     // Use "0181" to disable translocation and quarantine mirroring (Homebrew-style).
@@ -99,6 +166,16 @@ pub fn set_quarantine_attribute(path: &Path, agent_name: &str) -> Result<()> {
                 Ok(())
             } else {
                 let stderr = String::from_utf8_lossy(&out.stderr);
+                if is_not_supported_error(&stderr) {
+                    // Not a failure we can fix; note it once and skip this filesystem from now on.
+                    if dev.is_none_or(mark_xattr_unsupported_device) {
+                        debug!(
+                            "Extended attributes are not supported on the filesystem holding {}; skipping quarantine attributes there for the rest of this run.",
+                            path.display()
+                        );
+                    }
+                    return Ok(());
+                }
                 error!( // Changed from warn to error as this is critical for the bug
                     "Failed to set quarantine attribute for {} (status: {}): {}. This may lead to data loss on reinstall or Gatekeeper issues.",
                     path.display(),
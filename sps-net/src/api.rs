@@ -9,7 +9,6 @@ use sps_common::model::cask::{Cask, CaskList};
 use sps_common::model::formula::Formula;
 use tracing::{debug, error};
 
-const FORMULAE_API_BASE_URL: &str = "https://formulae.brew.sh/api";
 const GITHUB_API_BASE_URL: &str = "https://api.github.com";
 const USER_AGENT_STRING: &str = "sps Package Manager (Rust; +https://github.com/your/sp)";
 
@@ -30,19 +29,24 @@ fn build_api_client(config: &Config) -> Result<Client> {
     } else {
         debug!("No GitHub API token found in config.");
     }
-    Ok(Client::builder().default_headers(headers).build()?)
+    Ok(crate::http::client_builder()?
+        .default_headers(headers)
+        .build()?)
 }
 
-pub async fn fetch_raw_formulae_json(endpoint: &str) -> Result<String> {
-    let url = format!("{FORMULAE_API_BASE_URL}/{endpoint}");
+pub async fn fetch_raw_formulae_json(endpoint: &str, config: &Config) -> Result<String> {
+    let url = format!("{}/{endpoint}", config.api_base_url);
     debug!("Fetching data from Homebrew Formulae API: {}", url);
-    let client = reqwest::Client::builder()
-        .user_agent(USER_AGENT_STRING)
-        .build()?;
-    let response = client.get(&url).send().await.map_err(|e| {
-        debug!("HTTP request failed for {}: {}", url, e);
-        SpsError::Http(Arc::new(e))
-    })?;
+    let client = crate::http::shared_client()?;
+    let response = client
+        .get(&url)
+        .timeout(crate::http::api_timeout())
+        .send()
+        .await
+        .map_err(|e| {
+            debug!("HTTP request failed for {}: {}", url, e);
+            SpsError::Http(Arc::new(e))
+        })?;
     if !response.status().is_success() {
         let status = response.status();
         let body = response
@@ -66,16 +70,17 @@ pub async fn fetch_raw_formulae_json(endpoint: &str) -> Result<String> {
     Ok(body)
 }
 
-pub async fn fetch_all_formulas() -> Result<String> {
-    fetch_raw_formulae_json("formula.json").await
+pub async fn fetch_all_formulas(config: &Config) -> Result<String> {
+    fetch_raw_formulae_json("formula.json", config).await
 }
 
-pub async fn fetch_all_casks() -> Result<String> {
-    fetch_raw_formulae_json("cask.json").await
+pub async fn fetch_all_casks(config: &Config) -> Result<String> {
+    fetch_raw_formulae_json("cask.json", config).await
 }
 
-pub async fn fetch_formula(name: &str) -> Result<serde_json::Value> {
-    let direct_fetch_result = fetch_raw_formulae_json(&format!("formula/{name}.json")).await;
+pub async fn fetch_formula(name: &str, config: &Config) -> Result<serde_json::Value> {
+    let direct_fetch_result =
+        fetch_raw_formulae_json(&format!("formula/{name}.json"), config).await;
     if let Ok(body) = direct_fetch_result {
         let formula: serde_json::Value = serde_json::from_str(&body)?;
         Ok(formula)
@@ -85,7 +90,7 @@ pub async fn fetch_formula(name: &str) -> Result<serde_json::Value> {
             name,
             direct_fetch_result.err()
         );
-        let all_formulas_body = fetch_all_formulas().await?;
+        let all_formulas_body = fetch_all_formulas(config).await?;
         let formulas: Vec<serde_json::Value> = serde_json::from_str(&all_formulas_body)?;
         for formula in formulas {
             if formula.get("name").and_then(Value::as_str) == Some(name) {
@@ -101,8 +106,8 @@ pub async fn fetch_formula(name: &str) -> Result<serde_json::Value> {
     }
 }
 
-pub async fn fetch_cask(token: &str) -> Result<serde_json::Value> {
-    let direct_fetch_result = fetch_raw_formulae_json(&format!("cask/{token}.json")).await;
+pub async fn fetch_cask(token: &str, config: &Config) -> Result<serde_json::Value> {
+    let direct_fetch_result = fetch_raw_formulae_json(&format!("cask/{token}.json"), config).await;
     if let Ok(body) = direct_fetch_result {
         let cask: serde_json::Value = serde_json::from_str(&body)?;
         Ok(cask)
@@ -112,7 +117,7 @@ pub async fn fetch_cask(token: &str) -> Result<serde_json::Value> {
             token,
             direct_fetch_result.err()
         );
-        let all_casks_body = fetch_all_casks().await?;
+        let all_casks_body = fetch_all_casks(config).await?;
         let casks: Vec<serde_json::Value> = serde_json::from_str(&all_casks_body)?;
         for cask in casks {
             if cask.get("token").and_then(Value::as_str) == Some(token) {
@@ -129,10 +134,15 @@ async fn fetch_github_api_json(endpoint: &str, config: &Config) -> Result<Value>
     let url = format!("{GITHUB_API_BASE_URL}{endpoint}");
     debug!("Fetching data from GitHub API: {}", url);
     let client = build_api_client(config)?;
-    let response = client.get(&url).send().await.map_err(|e| {
-        error!("GitHub API request failed for {}: {}", url, e);
-        SpsError::Http(Arc::new(e))
-    })?;
+    let response = client
+        .get(&url)
+        .timeout(crate::http::api_timeout())
+        .send()
+        .await
+        .map_err(|e| {
+            error!("GitHub API request failed for {}: {}", url, e);
+            SpsError::Http(Arc::new(e))
+        })?;
     if !response.status().is_success() {
         let status = response.status();
         let body = response
@@ -162,17 +172,22 @@ async fn fetch_github_repo_info(owner: &str, repo: &str, config: &Config) -> Res
     fetch_github_api_json(&endpoint, config).await
 }
 
-pub async fn get_formula(name: &str) -> Result<Formula> {
-    let url = format!("{FORMULAE_API_BASE_URL}/formula/{name}.json");
+pub async fn get_formula(name: &str, config: &Config) -> Result<Formula> {
+    let url = format!("{}/formula/{name}.json", config.api_base_url);
     debug!(
         "Fetching and parsing formula data for '{}' from {}",
         name, url
     );
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await.map_err(|e| {
-        debug!("HTTP request failed when fetching formula {}: {}", name, e);
-        SpsError::Http(Arc::new(e))
-    })?;
+    let client = crate::http::shared_client()?;
+    let response = client
+        .get(&url)
+        .timeout(crate::http::api_timeout())
+        .send()
+        .await
+        .map_err(|e| {
+            debug!("HTTP request failed when fetching formula {}: {}", name, e);
+            SpsError::Http(Arc::new(e))
+        })?;
     let status = response.status();
     let text = response.text().await?;
     if !status.is_success() {
@@ -217,16 +232,16 @@ pub async fn get_formula(name: &str) -> Result<Formula> {
     }
 }
 
-pub async fn get_all_formulas() -> Result<Vec<Formula>> {
-    let raw_data = fetch_all_formulas().await?;
+pub async fn get_all_formulas(config: &Config) -> Result<Vec<Formula>> {
+    let raw_data = fetch_all_formulas(config).await?;
     serde_json::from_str(&raw_data).map_err(|e| {
         error!("Failed to parse all_formulas response: {}", e);
         SpsError::Json(Arc::new(e))
     })
 }
 
-pub async fn get_cask(name: &str) -> Result<Cask> {
-    let raw_json_result = fetch_cask(name).await;
+pub async fn get_cask(name: &str, config: &Config) -> Result<Cask> {
+    let raw_json_result = fetch_cask(name, config).await;
     let raw_json = match raw_json_result {
         Ok(json_val) => json_val,
         Err(e) => {
@@ -256,8 +271,8 @@ pub async fn get_cask(name: &str) -> Result<Cask> {
     }
 }
 
-pub async fn get_all_casks() -> Result<CaskList> {
-    let raw_data = fetch_all_casks().await?;
+pub async fn get_all_casks(config: &Config) -> Result<CaskList> {
+    let raw_data = fetch_all_casks(config).await?;
     let casks: Vec<Cask> = serde_json::from_str(&raw_data).map_err(|e| {
         error!("Failed to parse all_casks response: {}", e);
         SpsError::Json(Arc::new(e))
@@ -1,28 +1,61 @@
-use std::fs;
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use std::{env, fs};
 
-use reqwest::header::{HeaderMap, ACCEPT, USER_AGENT};
-use reqwest::{Client, StatusCode};
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, RequestBuilder, StatusCode};
 use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
 use sps_common::model::formula::ResourceSpec;
+use sps_common::pipeline::DownloadProgressFn;
 use tokio::fs::File as TokioFile;
 use tokio::io::AsyncWriteExt;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
-use crate::validation::{validate_url, verify_checksum};
+use crate::validation::{
+    record_verified_checksum, validate_url, verify_checksum, verify_size, ChecksumAlgo,
+    StreamingChecksum,
+};
 
-const DOWNLOAD_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
 const CONNECT_TIMEOUT_SECS: u64 = 30;
+const HTTP_TIMEOUT_ENV: &str = "SPS_HTTP_TIMEOUT";
+/// How long a download may go without making progress before it is given up on.
+const DEFAULT_DOWNLOAD_TIMEOUT_SECS: u64 = 60;
+const DOWNLOAD_TIMEOUT_ENV: &str = "SPS_DOWNLOAD_TIMEOUT";
+/// A download that receives less than this within the download timeout counts as
+/// stalled, so a connection trickling a few bytes now and then does not hold it open.
+const MIN_PROGRESS_BYTES: u64 = 1024;
+const POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+const TCP_KEEPALIVE_SECS: u64 = 60;
+
+static SHARED_CLIENT: OnceLock<Client> = OnceLock::new();
+/// Attempts per URL before moving on to the next mirror.
+const DEFAULT_FETCH_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled after each further failure.
+pub(crate) const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const FETCH_RETRIES_ENV: &str = "SPS_FETCH_RETRIES";
 const USER_AGENT_STRING: &str = "sps package manager (Rust; +https://github.com/alexykn/sp)";
+/// Hosts that are sent `github_api_token`, so that their downloads count against the
+/// token's rate limit rather than the much lower anonymous one.
+const GITHUB_HOSTS: &[&str] = &[
+    "github.com",
+    "api.github.com",
+    "codeload.github.com",
+    "raw.githubusercontent.com",
+];
 
 pub async fn fetch_formula_source_or_bottle(
     formula_name: &str,
     url: &str,
     sha256_expected: &str,
     mirrors: &[String],
+    expected_size: Option<u64>,
     config: &Config,
+    progress: Option<DownloadProgressFn>,
 ) -> Result<PathBuf> {
     let filename = url
         .split('/')
@@ -41,7 +74,10 @@ pub async fn fetch_formula_source_or_bottle(
 
     if cache_path.is_file() {
         tracing::debug!("File exists in cache: {}", cache_path.display());
-        if !sha256_expected.is_empty() {
+        if let Some(Err(e)) = expected_size.map(|size| verify_size(&cache_path, size)) {
+            debug!("Cached file is the wrong size: {}. Redownloading.", e);
+            let _ = fs::remove_file(&cache_path);
+        } else if !sha256_expected.is_empty() {
             match verify_checksum(&cache_path, sha256_expected) {
                 Ok(_) => {
                     tracing::debug!("Using valid cached file: {}", cache_path.display());
@@ -73,44 +109,22 @@ pub async fn fetch_formula_source_or_bottle(
         tracing::debug!("File not found in cache.");
     }
 
-    fs::create_dir_all(config.cache_dir()).map_err(|e| {
-        SpsError::IoError(format!(
-            "Failed to create cache directory {}: {}",
-            config.cache_dir().display(),
-            e
-        ))
-    })?;
-    // Validate primary URL
-    validate_url(url)?;
-
-    let client = build_http_client()?;
-
-    let urls_to_try = std::iter::once(url).chain(mirrors.iter().map(|s| s.as_str()));
-    let mut last_error: Option<SpsError> = None;
-
-    for current_url in urls_to_try {
-        // Validate mirror URL
-        validate_url(current_url)?;
-        tracing::debug!("Attempting download from: {}", current_url);
-        match download_and_verify(&client, current_url, &cache_path, sha256_expected).await {
-            Ok(path) => {
-                tracing::debug!("Successfully downloaded and verified: {}", path.display());
-                return Ok(path);
-            }
-            Err(e) => {
-                error!("Download attempt failed from {}: {}", current_url, e);
-                last_error = Some(e);
-            }
-        }
-    }
+    config.ensure_cache_dir()?;
 
-    Err(last_error.unwrap_or_else(|| {
-        SpsError::DownloadError(
-            formula_name.to_string(),
-            url.to_string(),
-            "All download attempts failed.".to_string(),
-        )
-    }))
+    // URLs are validated one by one, so a bad primary still falls back to mirrors.
+    let urls: Vec<&str> = std::iter::once(url)
+        .chain(mirrors.iter().map(String::as_str))
+        .collect();
+    download_with_fallback(
+        formula_name,
+        &urls,
+        &cache_path,
+        sha256_expected,
+        expected_size,
+        config,
+        progress.as_ref(),
+    )
+    .await
 }
 
 pub async fn fetch_resource(
@@ -118,7 +132,7 @@ pub async fn fetch_resource(
     resource: &ResourceSpec,
     config: &Config,
 ) -> Result<PathBuf> {
-    let resource_cache_dir = config.cache_dir().join("resources");
+    let resource_cache_dir = config.ensure_cache_dir()?.join("resources");
     fs::create_dir_all(&resource_cache_dir).map_err(|e| {
         SpsError::IoError(format!(
             "Failed to create resource cache directory {}: {}",
@@ -126,8 +140,6 @@ pub async fn fetch_resource(
             e
         ))
     })?;
-    // Validate resource URL
-    validate_url(&resource.url)?;
 
     let url_filename = resource
         .url
@@ -149,7 +161,11 @@ pub async fn fetch_resource(
 
     if cache_path.is_file() {
         tracing::debug!("Resource exists in cache: {}", cache_path.display());
-        match verify_checksum(&cache_path, &resource.sha256) {
+        let cached_ok = resource
+            .size
+            .map_or(Ok(()), |size| verify_size(&cache_path, size))
+            .and_then(|_| verify_checksum(&cache_path, &resource.sha256));
+        match cached_ok {
             Ok(_) => {
                 tracing::debug!("Using cached resource: {}", cache_path.display());
                 return Ok(cache_path);
@@ -173,46 +189,343 @@ pub async fn fetch_resource(
         tracing::debug!("Resource not found in cache.");
     }
 
-    let client = build_http_client()?;
-    match download_and_verify(&client, &resource.url, &cache_path, &resource.sha256).await {
-        Ok(path) => {
-            tracing::debug!(
-                "Successfully downloaded and verified resource: {}",
-                path.display()
-            );
-            Ok(path)
+    let urls: Vec<&str> = std::iter::once(resource.url.as_str())
+        .chain(resource.mirrors.iter().map(String::as_str))
+        .collect();
+    download_with_fallback(
+        &resource.name,
+        &urls,
+        &cache_path,
+        &resource.sha256,
+        resource.size,
+        config,
+        None,
+    )
+    .await
+    .inspect_err(|_| {
+        let _ = fs::remove_file(&cache_path);
+    })
+}
+
+/// Number of attempts per URL, from `SPS_FETCH_RETRIES` or the default.
+pub(crate) fn fetch_attempts() -> u32 {
+    match env::var(FETCH_RETRIES_ENV) {
+        Ok(value) => match value.trim().parse::<u32>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                warn!(
+                    "Ignoring invalid {}='{}'; expected a positive number",
+                    FETCH_RETRIES_ENV, value
+                );
+                DEFAULT_FETCH_ATTEMPTS
+            }
+        },
+        Err(_) => DEFAULT_FETCH_ATTEMPTS,
+    }
+}
+
+/// Downloads from `urls` in order. Each URL is retried with exponential backoff
+/// while its failures look transient; a permanent failure (404, checksum mismatch,
+/// ...) moves straight on to the next URL. If every URL fails, the error lists each
+/// URL with the reason it was given up on.
+async fn download_with_fallback(
+    name: &str,
+    urls: &[&str],
+    final_path: &Path,
+    sha256_expected: &str,
+    expected_size: Option<u64>,
+    config: &Config,
+    progress: Option<&DownloadProgressFn>,
+) -> Result<PathBuf> {
+    let client = shared_client()?;
+    let attempts = fetch_attempts();
+    let mut failures: Vec<String> = Vec::new();
+
+    for &url in urls {
+        if let Err(e) = validate_url(url) {
+            failures.push(format!("{url} ({e})"));
+            continue;
         }
-        Err(e) => {
-            error!("Resource download failed from {}: {}", resource.url, e);
-            let _ = fs::remove_file(&cache_path);
-            Err(SpsError::DownloadError(
-                resource.name.clone(),
-                resource.url.clone(),
-                format!("Download failed: {e}"),
-            ))
+        let mut delay = RETRY_BASE_DELAY;
+        for attempt in 1..=attempts {
+            tracing::debug!("Download attempt {}/{} from {}", attempt, attempts, url);
+            match download_and_verify(
+                &client,
+                url,
+                final_path,
+                sha256_expected,
+                expected_size,
+                config,
+                progress,
+            )
+            .await
+            {
+                Ok(path) => {
+                    tracing::debug!("Successfully downloaded and verified: {}", path.display());
+                    return Ok(path);
+                }
+                Err(failure) if failure.transient && attempt < attempts => {
+                    warn!(
+                        "Download attempt {}/{} from {} failed: {}. Retrying in {:?}.",
+                        attempt, attempts, url, failure.error, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(failure) => {
+                    error!("Download from {} failed: {}", url, failure.error);
+                    failures.push(format!("{url} ({})", failure.error));
+                    break;
+                }
+            }
         }
     }
+
+    Err(SpsError::DownloadError(
+        name.to_string(),
+        urls.first().copied().unwrap_or_default().to_string(),
+        format!("all {} URL(s) failed: {}", urls.len(), failures.join("; ")),
+    ))
 }
 
-fn build_http_client() -> Result<Client> {
-    let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, USER_AGENT_STRING.parse().unwrap());
-    headers.insert(ACCEPT, "*/*".parse().unwrap());
-    Client::builder()
-        .timeout(Duration::from_secs(DOWNLOAD_TIMEOUT_SECS))
-        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
-        .default_headers(headers)
-        .redirect(reqwest::redirect::Policy::limited(10))
+/// A failed download attempt, and whether trying the same URL again might help.
+struct AttemptError {
+    error: SpsError,
+    transient: bool,
+}
+
+impl AttemptError {
+    fn transient(error: SpsError) -> Self {
+        Self {
+            error,
+            transient: true,
+        }
+    }
+}
+
+impl From<SpsError> for AttemptError {
+    fn from(error: SpsError) -> Self {
+        Self {
+            error,
+            transient: false,
+        }
+    }
+}
+
+/// The process-wide client. Cloning a `Client` shares its connection pool, so every
+/// download through this reuses open connections and TLS sessions instead of
+/// handshaking again for each file.
+pub fn shared_client() -> Result<Client> {
+    if let Some(client) = SHARED_CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = build_client()?;
+    // If another thread won the race, use its client and drop ours.
+    Ok(SHARED_CLIENT.get_or_init(|| client).clone())
+}
+
+/// A new client with the settings shared by every sps HTTP client: see
+/// [`client_builder`]. Prefer [`shared_client`] unless the client needs to be separate.
+pub fn build_client() -> Result<Client> {
+    client_builder()?
         .build()
         .map_err(|e| SpsError::HttpError(format!("Failed to build HTTP client: {e}")))
 }
 
+/// Starts a client with the shared settings, for callers that add their own headers
+/// or redirect policy:
+///
+/// - the sps user agent;
+/// - keep-alive, with up to 8 idle connections kept open per host for 90 seconds;
+/// - no limit on the total time of a request, since a large download on a slow link
+///   may take as long as it needs; API requests set [`api_timeout`] themselves;
+/// - a read timeout of `SPS_DOWNLOAD_TIMEOUT` seconds (default 60, `0` disables it),
+///   so a connection that stops sending fails instead of hanging;
+/// - proxies from `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` (or their lowercase
+///   forms), bypassed for the hosts listed in `NO_PROXY`.
+pub fn client_builder() -> Result<ClientBuilder> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT_STRING)
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT_SECS))
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .tcp_keepalive(Duration::from_secs(TCP_KEEPALIVE_SECS));
+    if let Some(timeout) = download_timeout() {
+        builder = builder.read_timeout(timeout);
+    }
+    for var in ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"] {
+        let Some(url) = proxy_env(var) else {
+            continue;
+        };
+        debug!("Using {} proxy {}", var, url);
+        let proxy = match var {
+            "HTTPS_PROXY" => Proxy::https(url.as_str()),
+            "HTTP_PROXY" => Proxy::http(url.as_str()),
+            _ => Proxy::all(url.as_str()),
+        };
+        let proxy = proxy
+            .map_err(|e| SpsError::Config(format!("Invalid {var} '{url}': {e}")))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder)
+}
+
+/// Adds `config.github_api_token` as a bearer token to a request for a GitHub host
+/// and leaves any other request alone. reqwest drops the header when a redirect leaves
+/// the host, so release assets served from elsewhere never see the token.
+pub fn with_github_auth(request: RequestBuilder, url: &str, config: &Config) -> RequestBuilder {
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_ascii_lowercase));
+    let is_github = host
+        .as_deref()
+        .is_some_and(|host| GITHUB_HOSTS.contains(&host));
+    match &config.github_api_token {
+        Some(token) if is_github => {
+            debug!("Requesting {} with the configured GitHub token", url);
+            request.header(AUTHORIZATION, format!("Bearer {token}"))
+        }
+        _ => {
+            if is_github {
+                debug!("Requesting {} without authentication", url);
+            }
+            request
+        }
+    }
+}
+
+/// Reads a proxy variable, falling back to its lowercase spelling.
+fn proxy_env(var: &str) -> Option<String> {
+    env::var(var)
+        .ok()
+        .or_else(|| env::var(var.to_lowercase()).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// The total time an API or metadata request may take: `SPS_HTTP_TIMEOUT` seconds
+/// (default 300). Downloads are only bounded by [`download_timeout`].
+pub fn api_timeout() -> Duration {
+    Duration::from_secs(api_timeout_secs())
+}
+
+fn api_timeout_secs() -> u64 {
+    match env::var(HTTP_TIMEOUT_ENV) {
+        Ok(value) => match value.trim().parse::<u64>() {
+            Ok(secs) if secs > 0 => secs,
+            _ => {
+                warn!(
+                    "Ignoring invalid {}='{}'; expected a number of seconds",
+                    HTTP_TIMEOUT_ENV, value
+                );
+                DEFAULT_TIMEOUT_SECS
+            }
+        },
+        Err(_) => DEFAULT_TIMEOUT_SECS,
+    }
+}
+
+/// `SPS_DOWNLOAD_TIMEOUT` in seconds; `None` if it is `0`. An invalid value keeps the
+/// default.
+pub fn download_timeout() -> Option<Duration> {
+    match env::var(DOWNLOAD_TIMEOUT_ENV) {
+        Ok(value) => match value.trim().parse::<u64>() {
+            Ok(0) => None,
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => {
+                warn!(
+                    "Ignoring invalid {}='{}'; expected a number of seconds",
+                    DOWNLOAD_TIMEOUT_ENV, value
+                );
+                Some(Duration::from_secs(DEFAULT_DOWNLOAD_TIMEOUT_SECS))
+            }
+        },
+        Err(_) => Some(Duration::from_secs(DEFAULT_DOWNLOAD_TIMEOUT_SECS)),
+    }
+}
+
+/// Watches the body of a download as it streams in and aborts it once it stops making
+/// progress: when no chunk arrives, or fewer than 1 KiB arrive, within the download
+/// timeout. The client's read timeout only catches a connection that goes completely
+/// silent; this also catches one that keeps trickling.
+///
+/// A stall surfaces as an [`SpsError::HttpError`], which the retry and mirror logic of
+/// [`fetch_formula_source_or_bottle`] and [`fetch_resource`] treats as transient.
+pub struct StallWatchdog {
+    window: Option<Duration>,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl StallWatchdog {
+    /// A watchdog using `SPS_DOWNLOAD_TIMEOUT`; it never fires if that is `0`.
+    pub fn new() -> Self {
+        Self {
+            window: download_timeout(),
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+
+    /// Awaits the next chunk read by `read` (usually `response.chunk()`), failing if the
+    /// download has stalled. `url` is only used in the error.
+    pub async fn next_chunk<T: AsRef<[u8]>>(
+        &mut self,
+        read: impl Future<Output = reqwest::Result<Option<T>>>,
+        url: &str,
+    ) -> Result<Option<T>> {
+        let Some(window) = self.window else {
+            return Ok(read.await?);
+        };
+        let remaining = window.saturating_sub(self.window_start.elapsed());
+        let chunk = match tokio::time::timeout(remaining, read).await {
+            Ok(Ok(chunk)) => chunk,
+            // The client's read timeout, which can fire just before ours.
+            Ok(Err(e)) if e.is_timeout() => return Err(self.stalled(url, window)),
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(self.stalled(url, window)),
+        };
+        if let Some(bytes) = &chunk {
+            self.window_bytes += bytes.as_ref().len() as u64;
+        }
+        if self.window_start.elapsed() >= window {
+            if self.window_bytes < MIN_PROGRESS_BYTES && chunk.is_some() {
+                return Err(self.stalled(url, window));
+            }
+            self.window_start = Instant::now();
+            self.window_bytes = 0;
+        }
+        Ok(chunk)
+    }
+
+    fn stalled(&self, url: &str, window: Duration) -> SpsError {
+        debug!(
+            "Download from {} received {} byte(s) in {:?}; aborting",
+            url, self.window_bytes, window
+        );
+        SpsError::HttpError(format!(
+            "Download from {url} stalled: no progress in {}s (set {DOWNLOAD_TIMEOUT_ENV} to \
+             change the limit)",
+            window.as_secs()
+        ))
+    }
+}
+
+impl Default for StallWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 async fn download_and_verify(
     client: &Client,
     url: &str,
     final_path: &Path,
     sha256_expected: &str,
-) -> Result<PathBuf> {
+    expected_size: Option<u64>,
+    config: &Config,
+    progress: Option<&DownloadProgressFn>,
+) -> std::result::Result<PathBuf, AttemptError> {
     let temp_filename = format!(
         ".{}.download",
         final_path.file_name().unwrap_or_default().to_string_lossy()
@@ -229,9 +542,12 @@ async fn download_and_verify(
         }
     }
 
-    let response = client.get(url).send().await.map_err(|e| {
+    let request = with_github_auth(client.get(url), url, config);
+    let mut response = request.send().await.map_err(|e| {
         debug!("HTTP request failed for {url}: {e}");
-        SpsError::HttpError(format!("HTTP request failed for {url}: {e}"))
+        AttemptError::transient(SpsError::HttpError(format!(
+            "HTTP request failed for {url}: {e}"
+        )))
     })?;
     let status = response.status();
     tracing::debug!("Received HTTP status: {} for {}", status, url);
@@ -242,6 +558,15 @@ async fn download_and_verify(
             .await
             .unwrap_or_else(|_| "Failed to read response body".to_string());
         tracing::error!("HTTP error {} for URL {}: {}", status, url, body_text);
+        // Server errors, timeouts and rate limiting tend to go away on their own.
+        if status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            return Err(AttemptError::transient(SpsError::HttpError(format!(
+                "HTTP error {status} for URL {url}"
+            ))));
+        }
         return match status {
             StatusCode::NOT_FOUND => Err(SpsError::DownloadError(
                 final_path
@@ -262,7 +587,8 @@ async fn download_and_verify(
             _ => Err(SpsError::HttpError(format!(
                 "HTTP error {status} for URL {url}: {body_text}"
             ))),
-        };
+        }
+        .map_err(AttemptError::from);
     }
 
     let mut temp_file = TokioFile::create(&temp_path).await.map_err(|e| {
@@ -272,13 +598,41 @@ async fn download_and_verify(
             e
         ))
     })?;
-    let content = response
-        .bytes()
-        .await
-        .map_err(|e| SpsError::HttpError(format!("Failed to read response body bytes: {e}")))?;
-    temp_file.write_all(&content).await.map_err(|e| {
+    let total_bytes = response.content_length();
+    let mut bytes_done: u64 = 0;
+    if let Some(report) = progress {
+        report(bytes_done, total_bytes);
+    }
+    // Hashed as it streams in, so the file is not read back to verify it.
+    let mut checksum = StreamingChecksum::new(ChecksumAlgo::Sha256);
+    let mut watchdog = StallWatchdog::new();
+    while let Some(chunk) =
+        watchdog
+            .next_chunk(response.chunk(), url)
+            .await
+            .map_err(|e| match e {
+                SpsError::Http(e) => AttemptError::transient(SpsError::HttpError(format!(
+                    "Failed to read response body bytes: {e}"
+                ))),
+                stalled => AttemptError::transient(stalled),
+            })?
+    {
+        temp_file.write_all(&chunk).await.map_err(|e| {
+            SpsError::IoError(format!(
+                "Failed to write download stream to {}: {}",
+                temp_path.display(),
+                e
+            ))
+        })?;
+        checksum.update(&chunk);
+        bytes_done += chunk.len() as u64;
+        if let Some(report) = progress {
+            report(bytes_done, total_bytes);
+        }
+    }
+    temp_file.flush().await.map_err(|e| {
         SpsError::IoError(format!(
-            "Failed to write download stream to {}: {}",
+            "Failed to flush download stream to {}: {}",
             temp_path.display(),
             e
         ))
@@ -286,8 +640,22 @@ async fn download_and_verify(
     drop(temp_file);
     tracing::debug!("Finished writing download stream to temp file.");
 
+    let size_check = match (expected_size, total_bytes) {
+        (Some(size), _) => Some(size),
+        // Without a published size, Content-Length still catches a cut-off transfer.
+        (None, Some(content_length)) if bytes_done != content_length => Some(content_length),
+        _ => None,
+    };
+    if let Some(size) = size_check {
+        if let Err(e) = verify_size(&temp_path, size) {
+            let _ = fs::remove_file(&temp_path);
+            // Usually a transfer cut off mid-stream.
+            return Err(AttemptError::transient(e));
+        }
+    }
+
     if !sha256_expected.is_empty() {
-        verify_checksum(&temp_path, sha256_expected)?;
+        checksum.verify(&temp_path, sha256_expected)?;
         tracing::debug!(
             "Checksum verified for temporary file: {}",
             temp_path.display()
@@ -311,5 +679,8 @@ async fn download_and_verify(
         "Moved verified file to final location: {}",
         final_path.display()
     );
+    if !sha256_expected.is_empty() {
+        record_verified_checksum(final_path, sha256_expected);
+    }
     Ok(final_path.to_path_buf())
 }
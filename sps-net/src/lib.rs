@@ -11,7 +11,10 @@ pub use api::{
     fetch_all_casks, fetch_all_formulas, fetch_cask, fetch_formula, get_cask, /* ... */
     get_formula,
 };
-pub use http::{fetch_formula_source_or_bottle, fetch_resource /* ... */};
+pub use http::{
+    build_client, fetch_formula_source_or_bottle, fetch_resource, /* ... */
+    shared_client,
+};
 pub use oci::{build_oci_client /* ... */, download_oci_blob, fetch_oci_manifest_index};
 pub use sps_common::{
     model::{
@@ -1,34 +1,98 @@
 use std::collections::HashMap;
 use std::fs::{remove_file, File};
 use std::path::Path;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-use futures::StreamExt;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use reqwest::header::{ACCEPT, AUTHORIZATION};
+use reqwest::header::{ACCEPT, AUTHORIZATION, CACHE_CONTROL};
 use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use sps_common::config::Config;
 use sps_common::error::{Result, SpsError};
-use tracing::{debug, error};
+use sps_common::pipeline::DownloadProgressFn;
+use tracing::{debug, error, warn};
 use url::Url;
 
-use crate::validation::{validate_url, verify_checksum};
+use crate::http::StallWatchdog;
+use crate::validation::{record_verified_checksum, validate_url, ChecksumAlgo, StreamingChecksum};
 
 const OCI_MANIFEST_V1_TYPE: &str = "application/vnd.oci.image.index.v1+json";
 const OCI_LAYER_V1_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
 const DEFAULT_GHCR_TOKEN_ENDPOINT: &str = "https://ghcr.io/token";
 pub const DEFAULT_GHCR_DOMAIN: &str = "ghcr.io";
+/// Where Homebrew's core bottles live; `bottle_domain` mirrors replace this prefix.
+const GHCR_BOTTLE_PREFIX: &str = "https://ghcr.io/v2/homebrew/core";
 
-const CONNECT_TIMEOUT_SECS: u64 = 30;
-const REQUEST_TIMEOUT_SECS: u64 = 300;
-const USER_AGENT_STRING: &str = "sps package manager (Rust; +https://github.com/alexykn/sps)";
+/// Prefix of the error for a request the registry rejected as unauthenticated.
+const AUTH_REQUIRED: &str = "Auth required";
+/// Token lifetime the distribution spec assumes when the exchange does not say.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+/// A cached token is dropped this long before it expires, so it never expires in flight.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(10);
 
 #[derive(Deserialize, Debug)]
 struct OciTokenResponse {
     token: String,
+    /// Seconds the token is valid for.
+    expires_in: Option<u64>,
+}
+
+/// Registry, repository and basic credentials a pull token was issued for.
+type TokenKey = (String, String, Option<String>);
+
+/// Pull tokens issued during this run, so a batch of bottles from one repository
+/// authenticates once instead of once per blob.
+static TOKEN_CACHE: OnceLock<Mutex<HashMap<TokenKey, (String, Instant)>>> = OnceLock::new();
+
+/// Manifest indexes whose response allowed caching, by URL, until their max-age runs out.
+static MANIFEST_CACHE: OnceLock<Mutex<HashMap<String, (OciManifestIndex, Instant)>>> =
+    OnceLock::new();
+
+fn cached_token(key: &TokenKey) -> Option<String> {
+    let mut cache = TOKEN_CACHE.get_or_init(Default::default).lock().ok()?;
+    match cache.get(key) {
+        Some((token, expires_at)) if Instant::now() < *expires_at => Some(token.clone()),
+        Some(_) => {
+            cache.remove(key);
+            None
+        }
+        None => None,
+    }
+}
+
+fn store_token(key: TokenKey, token: &str, lifetime: Duration) {
+    let Some(usable_for) = lifetime.checked_sub(TOKEN_EXPIRY_MARGIN) else {
+        return;
+    };
+    if let Ok(mut cache) = TOKEN_CACHE.get_or_init(Default::default).lock() {
+        cache.insert(key, (token.to_string(), Instant::now() + usable_for));
+    }
+}
+
+fn forget_token(key: &TokenKey) {
+    if let Ok(mut cache) = TOKEN_CACHE.get_or_init(Default::default).lock() {
+        cache.remove(key);
+    }
+}
+
+/// How long a response may be reused according to its `Cache-Control` header; `None`
+/// unless it gives a positive `max-age` and does not forbid storing or reuse.
+fn cache_lifetime(resp: &Response) -> Option<Duration> {
+    let header = resp.headers().get(CACHE_CONTROL)?.to_str().ok()?;
+    let mut max_age = None;
+    for directive in header.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+        match directive.as_str() {
+            "no-store" | "no-cache" => return None,
+            _ => {
+                if let Some(seconds) = directive.strip_prefix("max-age=") {
+                    max_age = seconds.trim_matches('"').parse::<u64>().ok();
+                }
+            }
+        }
+    }
+    max_age.filter(|&s| s > 0).map(Duration::from_secs)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -64,68 +128,217 @@ pub struct OciPlatform {
 #[derive(Debug, Clone)]
 enum OciAuth {
     None,
-    AnonymousBearer { token: String },
-    ExplicitBearer { token: String },
-    Basic { encoded: String },
+    /// Issued by the registry's token endpoint, anonymously or for basic credentials.
+    RegistryBearer {
+        token: String,
+    },
+    ExplicitBearer {
+        token: String,
+    },
+    Basic {
+        encoded: String,
+    },
 }
 
+/// Fetches and parses a JSON resource, returning how long the response may be reused.
 async fn fetch_oci_resource<T: serde::de::DeserializeOwned>(
     resource_url: &str,
     accept_header: &str,
     config: &Config,
     client: &Client,
-) -> Result<T> {
+) -> Result<(T, Option<Duration>)> {
     let url = Url::parse(resource_url)
         .map_err(|e| SpsError::Generic(format!("Invalid URL '{resource_url}': {e}")))?;
     validate_url(url.as_str())?;
-    let registry_domain = url.host_str().unwrap_or(DEFAULT_GHCR_DOMAIN);
-    let repo_path = extract_repo_path_from_url(&url).unwrap_or("");
 
-    let auth = determine_auth(config, client, registry_domain, repo_path).await?;
-    let resp = execute_oci_request(client, resource_url, accept_header, &auth).await?;
-    let txt = resp.text().await.map_err(|e| SpsError::Http(Arc::new(e)))?;
+    let fetch = async {
+        let resp = authorized_request(client, config, &url, accept_header, false).await?;
+        let lifetime = cache_lifetime(&resp);
+        let txt = resp.text().await.map_err(|e| SpsError::Http(Arc::new(e)))?;
+        Ok::<_, SpsError>((txt, lifetime))
+    };
+    let (txt, lifetime) = tokio::time::timeout(crate::http::api_timeout(), fetch)
+        .await
+        .map_err(|_| SpsError::HttpError(format!("Timed out fetching {resource_url}")))??;
 
     debug!("OCI response ({} bytes) from {}", txt.len(), resource_url);
-    serde_json::from_str(&txt).map_err(|e| {
+    let parsed = serde_json::from_str(&txt).map_err(|e| {
         error!("JSON parse error from {}: {}", resource_url, e);
         SpsError::Json(Arc::new(e))
-    })
+    })?;
+    Ok((parsed, lifetime))
 }
 
+/// Sends a GET to a registry with the right authentication. A cached registry token
+/// the registry rejects is dropped and the request retried once with a new one;
+/// `fresh_token` skips the cache from the start.
+async fn authorized_request(
+    client: &Client,
+    config: &Config,
+    url: &Url,
+    accept: &str,
+    fresh_token: bool,
+) -> Result<Response> {
+    let registry_domain = url.host_str().unwrap_or(DEFAULT_GHCR_DOMAIN);
+    let repo_path = extract_repo_path_from_url(url).unwrap_or("");
+
+    let auth = determine_auth(config, client, registry_domain, repo_path, fresh_token).await?;
+    match execute_oci_request(client, url.as_str(), accept, &auth).await {
+        Err(SpsError::Api(msg))
+            if !fresh_token
+                && msg.starts_with(AUTH_REQUIRED)
+                && matches!(auth, OciAuth::RegistryBearer { .. }) =>
+        {
+            debug!(
+                "{} rejected the registry token for {}; requesting a new one",
+                registry_domain, repo_path
+            );
+            let auth = determine_auth(config, client, registry_domain, repo_path, true).await?;
+            execute_oci_request(client, url.as_str(), accept, &auth).await
+        }
+        other => other,
+    }
+}
+
+/// Downloads a blob and verifies it against `expected_digest` (skipped if empty).
+///
+/// A blob that fails verification is deleted and fetched once more with a newly issued
+/// registry token, since a mismatch can come from a stale or truncated response rather
+/// than bad content. Only a second mismatch is reported as `ChecksumMismatch`; if the
+/// retry fails for another reason, the error says the mismatch was likely transient.
+/// Each fetch is also retried while it fails on the connection, see
+/// [`fetch_oci_blob_retrying`].
 pub async fn download_oci_blob(
     blob_url: &str,
     destination_path: &Path,
     config: &Config,
     client: &Client,
     expected_digest: &str,
+    progress: Option<DownloadProgressFn>,
 ) -> Result<()> {
     debug!("Downloading OCI blob: {}", blob_url);
     let url = Url::parse(blob_url)
         .map_err(|e| SpsError::Generic(format!("Invalid URL '{blob_url}': {e}")))?;
     validate_url(url.as_str())?;
-    let registry_domain = url.host_str().unwrap_or(DEFAULT_GHCR_DOMAIN);
-    let repo_path = extract_repo_path_from_url(&url).unwrap_or("");
 
-    let auth = determine_auth(config, client, registry_domain, repo_path).await?;
-    let resp = execute_oci_request(client, blob_url, OCI_LAYER_V1_TYPE, &auth).await?;
+    let first = fetch_oci_blob_retrying(
+        &url,
+        destination_path,
+        config,
+        client,
+        expected_digest,
+        false,
+        progress.clone(),
+    )
+    .await;
+    let first_mismatch = match first {
+        Err(SpsError::ChecksumError(mismatch)) => mismatch,
+        other => return other,
+    };
+
+    warn!(
+        "OCI blob {} failed digest verification ({}). Retrying once with a fresh token.",
+        blob_url, first_mismatch
+    );
+    match fetch_oci_blob_retrying(
+        &url,
+        destination_path,
+        config,
+        client,
+        expected_digest,
+        true,
+        progress,
+    )
+    .await
+    {
+        Ok(()) => {
+            debug!("OCI blob {} verified on retry", blob_url);
+            Ok(())
+        }
+        Err(SpsError::ChecksumError(second_mismatch)) => Err(SpsError::ChecksumMismatch(format!(
+            "{blob_url} failed digest verification twice, the second time with a fresh \
+             registry token, so the content itself is corrupt: {second_mismatch}"
+        ))),
+        Err(e) => Err(SpsError::HttpError(format!(
+            "{blob_url} failed digest verification ({first_mismatch}) and the retry failed: \
+             {e}. This points to a transient registry or token problem; try again."
+        ))),
+    }
+}
+
+/// [`fetch_oci_blob`], tried again with exponential backoff while it fails on the
+/// connection (a dropped or stalled download) rather than on the response, up to
+/// `SPS_FETCH_RETRIES` attempts in all.
+async fn fetch_oci_blob_retrying(
+    url: &Url,
+    destination_path: &Path,
+    config: &Config,
+    client: &Client,
+    expected_digest: &str,
+    fresh_token: bool,
+    progress: Option<DownloadProgressFn>,
+) -> Result<()> {
+    let attempts = crate::http::fetch_attempts();
+    let mut delay = crate::http::RETRY_BASE_DELAY;
+    let mut attempt = 1;
+    loop {
+        match fetch_oci_blob(
+            url,
+            destination_path,
+            config,
+            client,
+            expected_digest,
+            fresh_token,
+            progress.clone(),
+        )
+        .await
+        {
+            Err(e @ (SpsError::Http(_) | SpsError::HttpError(_))) if attempt < attempts => {
+                warn!(
+                    "Download attempt {}/{} of OCI blob {} failed: {}. Retrying in {:?}.",
+                    attempt, attempts, url, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            other => return other,
+        }
+    }
+}
+
+/// One download of a blob into `destination_path`, reusing the run's registry token
+/// unless `fresh_token` asks for a new one. On a digest mismatch the file is deleted and
+/// the `ChecksumError` from verification is returned.
+async fn fetch_oci_blob(
+    url: &Url,
+    destination_path: &Path,
+    config: &Config,
+    client: &Client,
+    expected_digest: &str,
+    fresh_token: bool,
+    progress: Option<DownloadProgressFn>,
+) -> Result<()> {
+    let resp = authorized_request(client, config, url, OCI_LAYER_V1_TYPE, fresh_token).await?;
 
     let tmp = destination_path.with_file_name(format!(
         ".{}.download",
         destination_path.file_name().unwrap().to_string_lossy()
     ));
-    let mut out = File::create(&tmp).map_err(|e| SpsError::Io(Arc::new(e)))?;
-
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let b = chunk.map_err(|e| SpsError::Http(Arc::new(e)))?;
-        std::io::Write::write_all(&mut out, &b).map_err(|e| SpsError::Io(Arc::new(e)))?;
-    }
+    let checksum = match write_response(resp, &tmp, progress).await {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            let _ = remove_file(&tmp);
+            return Err(e);
+        }
+    };
     std::fs::rename(&tmp, destination_path).map_err(|e| SpsError::Io(Arc::new(e)))?;
 
     if !expected_digest.is_empty() {
-        match verify_checksum(destination_path, expected_digest) {
+        match checksum.verify(destination_path, expected_digest) {
             Ok(_) => {
                 tracing::debug!("OCI Blob checksum verified: {}", destination_path.display());
+                record_verified_checksum(destination_path, expected_digest);
             }
             Err(e) => {
                 tracing::error!(
@@ -147,22 +360,87 @@ pub async fn download_oci_blob(
     Ok(())
 }
 
+/// Streams the body into `path`, hashing it on the way, and returns the SHA256 of what
+/// was written.
+async fn write_response(
+    mut resp: Response,
+    path: &Path,
+    progress: Option<DownloadProgressFn>,
+) -> Result<StreamingChecksum> {
+    let mut out = File::create(path).map_err(|e| SpsError::Io(Arc::new(e)))?;
+
+    let total_bytes = resp.content_length();
+    let mut bytes_done: u64 = 0;
+    if let Some(report) = &progress {
+        report(bytes_done, total_bytes);
+    }
+    let mut checksum = StreamingChecksum::new(ChecksumAlgo::Sha256);
+    let url = resp.url().to_string();
+    let mut watchdog = StallWatchdog::new();
+    while let Some(b) = watchdog.next_chunk(resp.chunk(), &url).await? {
+        std::io::Write::write_all(&mut out, &b).map_err(|e| SpsError::Io(Arc::new(e)))?;
+        checksum.update(&b);
+        bytes_done += b.len() as u64;
+        if let Some(report) = &progress {
+            report(bytes_done, total_bytes);
+        }
+    }
+    Ok(checksum)
+}
+
+/// Fetches a manifest index, reusing one fetched earlier in this run while its
+/// `Cache-Control: max-age` allows.
 pub async fn fetch_oci_manifest_index(
     manifest_url: &str,
     config: &Config,
     client: &Client,
 ) -> Result<OciManifestIndex> {
-    fetch_oci_resource(manifest_url, OCI_MANIFEST_V1_TYPE, config, client).await
+    let cache = MANIFEST_CACHE.get_or_init(Default::default);
+    if let Ok(mut cache) = cache.lock() {
+        match cache.get(manifest_url) {
+            Some((index, expires_at)) if Instant::now() < *expires_at => {
+                debug!("Using cached manifest index for {}", manifest_url);
+                return Ok(index.clone());
+            }
+            Some(_) => {
+                cache.remove(manifest_url);
+            }
+            None => {}
+        }
+    }
+    let (index, lifetime): (OciManifestIndex, _) =
+        fetch_oci_resource(manifest_url, OCI_MANIFEST_V1_TYPE, config, client).await?;
+    if let (Some(lifetime), Ok(mut cache)) = (lifetime, cache.lock()) {
+        cache.insert(
+            manifest_url.to_string(),
+            (index.clone(), Instant::now() + lifetime),
+        );
+    }
+    Ok(index)
+}
+
+/// Points an upstream bottle URL at the configured mirror, if any. A `bottle_domain`
+/// replaces the whole Homebrew core repository prefix (like `HOMEBREW_BOTTLE_DOMAIN`);
+/// otherwise an `oci_registry` replaces just the ghcr.io host. Other URLs are returned
+/// unchanged.
+pub fn mirrored_bottle_url(url: &str, config: &Config) -> String {
+    if let Some(domain) = &config.bottle_domain {
+        if let Some(rest) = url.strip_prefix(GHCR_BOTTLE_PREFIX) {
+            return format!("{domain}{rest}");
+        }
+    }
+    if let Some(registry) = &config.oci_registry {
+        if let Some(rest) = url.strip_prefix("https://ghcr.io/") {
+            return format!("https://{registry}/{rest}");
+        }
+    }
+    url.to_string()
 }
 
+/// Registry requests need nothing beyond the shared settings, so this hands out the
+/// shared client and its pooled connections to ghcr.io.
 pub fn build_oci_client() -> Result<Client> {
-    Client::builder()
-        .user_agent(USER_AGENT_STRING)
-        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
-        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-        .redirect(reqwest::redirect::Policy::default())
-        .build()
-        .map_err(|e| SpsError::Http(Arc::new(e)))
+    crate::http::shared_client()
 }
 
 fn extract_repo_path_from_url(url: &Url) -> Option<&str> {
@@ -175,43 +453,91 @@ fn extract_repo_path_from_url(url: &Url) -> Option<&str> {
         .filter(|s| !s.is_empty())
 }
 
+/// Picks how to authenticate to `registry_domain`. An explicit registry token is used
+/// as is. For ghcr.io and the configured `oci_registry`, a pull token is requested from
+/// the registry's token endpoint, in exchange for the basic credentials if there are
+/// any; if that fails, the basic credentials are sent directly. Pull tokens are reused
+/// for the same repository until they expire, unless `fresh_token` is set.
 async fn determine_auth(
     config: &Config,
     client: &Client,
     registry_domain: &str,
     repo_path: &str,
+    fresh_token: bool,
 ) -> Result<OciAuth> {
     if let Some(token) = &config.docker_registry_token {
-        debug!("Using explicit bearer for {}", registry_domain);
+        debug!(
+            "Using the configured registry token for {}",
+            registry_domain
+        );
         return Ok(OciAuth::ExplicitBearer {
             token: token.clone(),
         });
     }
-    if let Some(basic) = &config.docker_registry_basic_auth {
-        debug!("Using explicit basic auth for {}", registry_domain);
-        return Ok(OciAuth::Basic {
-            encoded: basic.clone(),
-        });
-    }
+    let basic = config
+        .docker_registry_basic_auth
+        .as_deref()
+        .filter(|basic| !basic.is_empty());
 
-    if registry_domain.eq_ignore_ascii_case(DEFAULT_GHCR_DOMAIN) && !repo_path.is_empty() {
+    let is_known_registry = registry_domain.eq_ignore_ascii_case(DEFAULT_GHCR_DOMAIN)
+        || config
+            .oci_registry
+            .as_deref()
+            .is_some_and(|registry| registry_domain.eq_ignore_ascii_case(registry));
+    if is_known_registry && !repo_path.is_empty() {
+        let key: TokenKey = (
+            registry_domain.to_ascii_lowercase(),
+            repo_path.to_string(),
+            basic.map(str::to_string),
+        );
+        if fresh_token {
+            forget_token(&key);
+        } else if let Some(token) = cached_token(&key) {
+            debug!(
+                "Reusing registry token for {} {}",
+                registry_domain, repo_path
+            );
+            return Ok(OciAuth::RegistryBearer { token });
+        }
         debug!(
-            "Anonymous token fetch for {} scope={}",
-            registry_domain, repo_path
+            "{} token fetch for {} scope={}",
+            if basic.is_some() {
+                "Basic-auth"
+            } else {
+                "Anonymous"
+            },
+            registry_domain,
+            repo_path
         );
-        match fetch_anonymous_token(client, registry_domain, repo_path).await {
-            Ok(t) => return Ok(OciAuth::AnonymousBearer { token: t }),
-            Err(e) => debug!("Anon token failed, proceeding unauthenticated: {}", e),
+        match fetch_registry_token(client, registry_domain, repo_path, basic).await {
+            Ok(response) => {
+                let lifetime = response
+                    .expires_in
+                    .map_or(DEFAULT_TOKEN_LIFETIME, Duration::from_secs);
+                store_token(key, &response.token, lifetime);
+                return Ok(OciAuth::RegistryBearer {
+                    token: response.token,
+                });
+            }
+            Err(e) => debug!("Token fetch for {} failed: {}", registry_domain, e),
         }
     }
+    if let Some(basic) = basic {
+        debug!("Using the configured basic auth for {}", registry_domain);
+        return Ok(OciAuth::Basic {
+            encoded: basic.to_string(),
+        });
+    }
     Ok(OciAuth::None)
 }
 
-async fn fetch_anonymous_token(
+/// Requests a pull token for `repo_path`, sending `basic` credentials if given.
+async fn fetch_registry_token(
     client: &Client,
     registry_domain: &str,
     repo_path: &str,
-) -> Result<String> {
+    basic: Option<&str>,
+) -> Result<OciTokenResponse> {
     let endpoint = if registry_domain.eq_ignore_ascii_case(DEFAULT_GHCR_DOMAIN) {
         DEFAULT_GHCR_TOKEN_ENDPOINT.to_string()
     } else {
@@ -234,13 +560,17 @@ async fn fetch_anonymous_token(
             token_url
         );
 
-        match client.get(&token_url).send().await {
+        let mut request = client.get(&token_url).timeout(crate::http::api_timeout());
+        if let Some(basic) = basic {
+            request = request.header(AUTHORIZATION, format!("Basic {basic}"));
+        }
+        match request.send().await {
             Ok(resp) if resp.status().is_success() => {
                 let tok: OciTokenResponse = resp
                     .json()
                     .await
                     .map_err(|e| SpsError::ApiRequestError(format!("Parse token response: {e}")))?;
-                return Ok(tok.token);
+                return Ok(tok);
             }
             Ok(resp) => {
                 let code = resp.status();
@@ -275,19 +605,23 @@ async fn execute_oci_request(
     accept: &str,
     auth: &OciAuth,
 ) -> Result<Response> {
-    debug!("OCI request → {} (Accept: {})", url, accept);
     let mut req = client.get(url).header(ACCEPT, accept);
-    match auth {
-        OciAuth::AnonymousBearer { token } | OciAuth::ExplicitBearer { token }
-            if !token.is_empty() =>
-        {
-            req = req.header(AUTHORIZATION, format!("Bearer {token}"))
+    let auth_kind = match auth {
+        OciAuth::RegistryBearer { token } if !token.is_empty() => {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+            "registry token"
+        }
+        OciAuth::ExplicitBearer { token } if !token.is_empty() => {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+            "configured token"
         }
         OciAuth::Basic { encoded } if !encoded.is_empty() => {
-            req = req.header(AUTHORIZATION, format!("Basic {encoded}"))
+            req = req.header(AUTHORIZATION, format!("Basic {encoded}"));
+            "basic auth"
         }
-        _ => {}
-    }
+        _ => "unauthenticated",
+    };
+    debug!("OCI request → {} (Accept: {}, {})", url, accept, auth_kind);
 
     let resp = req.send().await.map_err(|e| SpsError::Http(Arc::new(e)))?;
     let status = resp.status();
@@ -297,7 +631,7 @@ async fn execute_oci_request(
         let body = resp.text().await.unwrap_or_default();
         error!("OCI {} ⇒ {} – {}", url, status, body);
         let err = match status {
-            StatusCode::UNAUTHORIZED => SpsError::Api(format!("Auth required: {status}")),
+            StatusCode::UNAUTHORIZED => SpsError::Api(format!("{AUTH_REQUIRED}: {status}")),
             StatusCode::FORBIDDEN => SpsError::Api(format!("Permission denied: {status}")),
             StatusCode::NOT_FOUND => SpsError::NotFound(format!("Not found: {status}")),
             _ => SpsError::Api(format!("HTTP {status} – {body}")),
@@ -1,11 +1,13 @@
 // sps-io/src/checksum.rs
 //use std::sync::Arc;
 use std::fs::File;
-use std::io;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 use infer;
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
 use sps_common::error::{Result, SpsError};
 use url::Url;
 //use tokio::fs::File;
@@ -63,27 +65,197 @@ use url::Url;
 //    }
 //}
 
-// Keep the synchronous version for now if needed elsewhere or for comparison
+/// Hash algorithms accepted by [`verify_checksum_algo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgo {
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgo {
+    fn name(self) -> &'static str {
+        match self {
+            ChecksumAlgo::Sha256 => "SHA256",
+            ChecksumAlgo::Sha512 => "SHA512",
+        }
+    }
+}
+
+/// Extension of the sidecar file that records the last verified SHA256 of a cached file.
+pub const VERIFIED_SIDECAR_EXTENSION: &str = "sha256";
+
+/// What [`verify_checksum`] stores next to a file it has verified.
+#[derive(Debug, Serialize, Deserialize)]
+struct VerifiedChecksum {
+    sha256: String,
+    size: u64,
+    mtime_ns: u128,
+}
+
+/// Verifies the SHA256 of a file, skipping the hash when the file's sidecar shows it was
+/// already verified against `expected` and its size and mtime have not changed since.
+/// A successful hash refreshes the sidecar; a mismatch removes it.
 pub fn verify_checksum(path: &Path, expected: &str) -> Result<()> {
-    tracing::debug!("Verifying checksum for: {}", path.display());
+    if is_verified(path, expected) {
+        tracing::debug!(
+            "Checksum of {} already verified and unchanged; skipping rehash",
+            path.display()
+        );
+        return Ok(());
+    }
+    match verify_checksum_algo(path, expected, ChecksumAlgo::Sha256) {
+        Ok(()) => {
+            record_verified_checksum(path, expected);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(verified_sidecar_path(path));
+            Err(e)
+        }
+    }
+}
+
+/// The sidecar path [`verify_checksum`] uses for `path`: `<file>.sha256`.
+pub fn verified_sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(VERIFIED_SIDECAR_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Records that `path` currently hashes to `sha256`, for a file verified under another
+/// name before being moved into place. Failures are logged and otherwise ignored; the
+/// next verification just rehashes.
+pub fn record_verified_checksum(path: &Path, sha256: &str) {
+    let Some((size, mtime_ns)) = size_and_mtime(path) else {
+        return;
+    };
+    let record = VerifiedChecksum {
+        sha256: sha256.to_ascii_lowercase(),
+        size,
+        mtime_ns,
+    };
+    let sidecar = verified_sidecar_path(path);
+    let written = serde_json::to_string(&record)
+        .map_err(io::Error::other)
+        .and_then(|json| std::fs::write(&sidecar, json));
+    if let Err(e) = written {
+        tracing::debug!("Could not write {}: {}", sidecar.display(), e);
+    }
+}
+
+fn is_verified(path: &Path, expected: &str) -> bool {
+    let Ok(raw) = std::fs::read_to_string(verified_sidecar_path(path)) else {
+        return false;
+    };
+    let Ok(record) = serde_json::from_str::<VerifiedChecksum>(&raw) else {
+        return false;
+    };
+    record.sha256.eq_ignore_ascii_case(expected)
+        && size_and_mtime(path) == Some((record.size, record.mtime_ns))
+}
+
+fn size_and_mtime(path: &Path) -> Option<(u64, u128)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), mtime.as_nanos()))
+}
+
+/// Files are hashed in reads of this size, so memory use does not grow with file size.
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+/// A digest computed chunk by chunk, e.g. from a download stream as it is written to
+/// disk, so the data never has to be held in memory or read back.
+pub struct StreamingChecksum {
+    algo: ChecksumAlgo,
+    hasher: Hasher,
+    bytes: u64,
+}
+
+impl StreamingChecksum {
+    pub fn new(algo: ChecksumAlgo) -> Self {
+        let hasher = match algo {
+            ChecksumAlgo::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumAlgo::Sha512 => Hasher::Sha512(Sha512::new()),
+        };
+        Self {
+            algo,
+            hasher,
+            bytes: 0,
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        match &mut self.hasher {
+            Hasher::Sha256(hasher) => hasher.update(chunk),
+            Hasher::Sha512(hasher) => hasher.update(chunk),
+        }
+        self.bytes += chunk.len() as u64;
+    }
+
+    /// Compares the digest of everything fed so far with `expected`. `path` is the file
+    /// the data went to, for the error message.
+    pub fn verify(self, path: &Path, expected: &str) -> Result<()> {
+        let algo = self.algo;
+        let bytes = self.bytes;
+        let actual = match self.hasher {
+            Hasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+            Hasher::Sha512(hasher) => hex::encode(hasher.finalize()),
+        };
+        tracing::debug!("Calculated {}: {} ({} bytes)", algo.name(), actual, bytes);
+        tracing::debug!("Expected {}:   {}", algo.name(), expected);
+        if actual.eq_ignore_ascii_case(expected) {
+            Ok(())
+        } else {
+            let note = error_page_note(path)
+                .map(|note| format!(" ({note})"))
+                .unwrap_or_default();
+            Err(SpsError::ChecksumError(format!(
+                "{} checksum mismatch for {}: expected {}, got {}{}",
+                algo.name(),
+                path.display(),
+                expected,
+                actual,
+                note
+            )))
+        }
+    }
+}
+
+/// Verifies the hex-encoded digest of a file using the given algorithm.
+pub fn verify_checksum_algo(path: &Path, expected: &str, algo: ChecksumAlgo) -> Result<()> {
+    tracing::debug!("Verifying {} checksum for: {}", algo.name(), path.display());
     let mut file = File::open(path)?;
-    let mut hasher = Sha256::new();
-    let bytes_copied = io::copy(&mut file, &mut hasher)?;
-    let hash_bytes = hasher.finalize();
-    let actual = hex::encode(hash_bytes);
-    tracing::debug!(
-        "Calculated SHA256: {} ({} bytes read)",
-        actual,
-        bytes_copied
-    );
-    tracing::debug!("Expected SHA256:   {}", expected);
-    if actual.eq_ignore_ascii_case(expected) {
+    let mut checksum = StreamingChecksum::new(algo);
+    let mut buffer = vec![0; HASH_CHUNK_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => checksum.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    checksum.verify(path, expected)
+}
+
+/// Verifies that a file is exactly `expected_bytes` long. Catches truncated downloads
+/// even when no checksum is available.
+pub fn verify_size(path: &Path, expected_bytes: u64) -> Result<()> {
+    let actual = std::fs::metadata(path)?.len();
+    if actual == expected_bytes {
+        tracing::debug!("Size verified for {}: {} bytes", path.display(), actual);
         Ok(())
     } else {
-        Err(SpsError::ChecksumError(format!(
-            "Checksum mismatch for {}: expected {}, got {}",
+        Err(SpsError::ValidationError(format!(
+            "Size mismatch for {}: expected {} bytes, got {}",
             path.display(),
-            expected,
+            expected_bytes,
             actual
         )))
     }
@@ -117,6 +289,14 @@ pub fn verify_content_type(path: &Path, expected_ext: &str) -> Result<()> {
     }
 }
 
+/// Explains a download that is a web page rather than the file asked for, which is
+/// what a proxy or CDN serving an error page with a 200 status leaves behind.
+pub fn error_page_note(path: &Path) -> Option<&'static str> {
+    let kind = infer::get_from_path(path).ok()??;
+    matches!(kind.extension(), "html" | "xml")
+        .then_some("the server sent a web page instead of the file, e.g. an error page")
+}
+
 /// Validates a URL, ensuring it uses the HTTPS scheme.
 pub fn validate_url(url_str: &str) -> Result<()> {
     let url = Url::parse(url_str)
@@ -131,3 +311,28 @@ pub fn validate_url(url_str: &str) -> Result<()> {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn sidecar_is_trusted_only_for_the_same_checksum_and_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bottle.tar.gz");
+        std::fs::write(&path, "hello").unwrap();
+        assert!(!is_verified(&path, HELLO_SHA256));
+
+        verify_checksum(&path, HELLO_SHA256).unwrap();
+        assert!(is_verified(&path, HELLO_SHA256));
+        assert!(is_verified(&path, &HELLO_SHA256.to_ascii_uppercase()));
+        assert!(!is_verified(&path, &"0".repeat(64)));
+
+        std::fs::write(&path, "hello, world").unwrap();
+        assert!(!is_verified(&path, HELLO_SHA256));
+        assert!(verify_checksum(&path, HELLO_SHA256).is_err());
+        assert!(!verified_sidecar_path(&path).exists());
+    }
+}
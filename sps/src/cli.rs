@@ -1,5 +1,6 @@
 // sps/src/cli.rs
 //! Defines the command-line argument structure using clap.
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::{ArgAction, Parser, Subcommand};
@@ -7,35 +8,124 @@ use sps_common::error::Result;
 use sps_common::{Cache, Config};
 
 // Module declarations
+pub mod autoremove;
+pub mod cat;
+pub mod cleanup;
+pub mod completions;
+pub mod config;
+pub mod deps;
+pub mod doctor;
+pub mod fetch;
+pub mod home;
 pub mod info;
 pub mod init;
 pub mod install;
+pub mod leaves;
+pub mod link;
 pub mod list;
+pub mod log;
+pub mod pin;
 pub mod reinstall;
 pub mod search;
+pub mod services;
 pub mod status;
+pub mod tap;
+pub mod ui;
 pub mod uninstall;
 pub mod update;
 pub mod upgrade;
+pub mod uses;
+pub mod verify;
+pub mod which;
 // Re-export InitArgs to make it accessible as cli::InitArgs
 // Import other command Args structs
+use crate::cli::autoremove::Autoremove;
+use crate::cli::cat::Cat;
+use crate::cli::cleanup::Cleanup;
+use crate::cli::completions::Completions;
+use crate::cli::config::ConfigArgs;
+use crate::cli::deps::Deps;
+use crate::cli::doctor::Doctor;
+use crate::cli::fetch::Fetch;
+use crate::cli::home::Home;
 use crate::cli::info::Info;
 pub use crate::cli::init::InitArgs;
 use crate::cli::install::InstallArgs;
+use crate::cli::leaves::Leaves;
+use crate::cli::link::{Link, Unlink};
 use crate::cli::list::List;
+use crate::cli::log::Log;
+use crate::cli::pin::{Pin, Unpin};
 use crate::cli::reinstall::ReinstallArgs;
 use crate::cli::search::Search;
+use crate::cli::services::Services;
+use crate::cli::tap::{Tap, Untap};
+use crate::cli::ui::OutputFormat;
 use crate::cli::uninstall::Uninstall;
 use crate::cli::update::Update;
 use crate::cli::upgrade::UpgradeArgs;
+use crate::cli::uses::Uses;
+use crate::cli::verify::Verify;
+use crate::cli::which::Which;
+
+/// Shown at the end of `--help`; the mapping itself is `exit_code` in main.rs.
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  any other error
+  2  invalid arguments (a usage message is printed)
+  3  network or download failure
+  4  checksum or verification failure
+  5  dependency resolution failure
+  6  package not found";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, name = "sps", bin_name = "sps")]
 #[command(propagate_version = true)]
+#[command(after_help = EXIT_CODES_HELP)]
 pub struct CliArgs {
     #[arg(short, long, action = ArgAction::Count, global = true)]
     pub verbose: u8,
 
+    /// Print only errors: no progress or success output from install, upgrade and
+    /// reinstall, and only error logs (also honored via SPS_QUIET=1). `--verbose` still
+    /// raises the log level; `--output json` still streams every event.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Disable colored output (also honored via the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Use the named config profile (also honored via the SPS_PROFILE environment variable)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Use this prefix instead of the default one; the Cellar, Caskroom, cache and
+    /// state all live under it (also honored via the SPS_PREFIX environment variable)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub prefix: Option<PathBuf>,
+
+    /// Keep downloads and API data in this directory instead of the prefix's cache
+    /// (also honored via the SPS_CACHE_DIR environment variable)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Select bottles for this platform tag, e.g. `arm64_sonoma`, instead of the detected
+    /// one (also honored via the SPS_BOTTLE_TAG environment variable)
+    #[arg(long, global = true, value_name = "TAG")]
+    pub bottle_tag: Option<String>,
+
+    /// Install bottles for this CPU architecture; `x86_64` on Apple Silicon runs them under
+    /// Rosetta 2 (also honored via the SPS_FORCE_ARCH environment variable)
+    #[arg(long, global = true, value_name = "ARCH")]
+    pub arch: Option<String>,
+
+    /// Output format for install, upgrade and reinstall progress; `json` streams each
+    /// pipeline event as a line of JSON on stdout, diagnostics stay on stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -46,26 +136,128 @@ pub enum Command {
     Search(Search),
     List(List),
     Info(Info),
+    Cat(Cat),
+    Home(Home),
     Update(Update),
     Install(InstallArgs),
+    Fetch(Fetch),
+    Deps(Deps),
+    Uses(Uses),
+    Which(Which),
+    Leaves(Leaves),
+    Cleanup(Cleanup),
+    Doctor(Doctor),
+    Verify(Verify),
+    Config(ConfigArgs),
+    Completions(Completions),
     Uninstall(Uninstall),
+    Autoremove(Autoremove),
     Reinstall(ReinstallArgs),
     Upgrade(UpgradeArgs),
+    Link(Link),
+    Unlink(Unlink),
+    Pin(Pin),
+    Unpin(Unpin),
+    Tap(Tap),
+    Untap(Untap),
+    Services(Services),
+    Log(Log),
 }
 
 impl Command {
+    /// Whether the command changes the prefix, cache or state files and therefore has to
+    /// hold the sps process lock while it runs. Dry runs change nothing, and `fetch`
+    /// into a cache outside the prefix leaves the prefix alone.
+    pub fn modifies_prefix(&self, config: &Config) -> bool {
+        if self.is_dry_run() {
+            return false;
+        }
+        match self {
+            Self::Install(_)
+            | Self::Reinstall(_)
+            | Self::Upgrade(_)
+            | Self::Uninstall(_)
+            | Self::Autoremove(_)
+            | Self::Update(_)
+            | Self::Cleanup(_)
+            | Self::Link(_)
+            | Self::Unlink(_)
+            | Self::Pin(_)
+            | Self::Unpin(_)
+            | Self::Untap(_) => true,
+            Self::Fetch(_) => config.cache_dir().starts_with(config.sps_root()),
+            // launchd state and LaunchAgents live outside the prefix.
+            Self::Services(_) => false,
+            Self::Doctor(command) => command.fix,
+            Self::Tap(command) => command.name.is_some(),
+            Self::Init(_)
+            | Self::Search(_)
+            | Self::List(_)
+            | Self::Info(_)
+            | Self::Cat(_)
+            | Self::Home(_)
+            | Self::Deps(_)
+            | Self::Uses(_)
+            | Self::Which(_)
+            | Self::Verify(_)
+            | Self::Leaves(_)
+            | Self::Config(_)
+            | Self::Completions(_)
+            | Self::Log(_) => false,
+        }
+    }
+
+    /// Whether the command was asked to only report what it would do.
+    pub fn is_dry_run(&self) -> bool {
+        match self {
+            Self::Install(command) => command.plans_only(),
+            Self::Reinstall(command) => command.dry_run,
+            Self::Upgrade(command) => command.dry_run,
+            Self::Cleanup(command) => command.dry_run,
+            Self::Autoremove(command) => command.dry_run,
+            Self::Link(command) => command.dry_run,
+            Self::Unlink(command) => command.dry_run,
+            Self::Doctor(command) => command.dry_run,
+            _ => false,
+        }
+    }
+
     pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         match self {
             Self::Init(command) => command.run(config).await,
             Self::Search(command) => command.run(config, cache).await,
             Self::List(command) => command.run(config, cache).await,
             Self::Info(command) => command.run(config, cache).await,
+            Self::Cat(command) => command.run(config, cache).await,
+            Self::Home(command) => command.run(config, cache).await,
             Self::Update(command) => command.run(config, cache).await,
+            Self::Fetch(command) => command.run(config, cache).await,
+            Self::Deps(command) => command.run(config, cache).await,
+            Self::Uses(command) => command.run(config, cache).await,
+            Self::Which(command) => command.run(config, cache).await,
+            Self::Verify(command) => command.run(config, cache).await,
+            Self::Leaves(command) => command.run(config, cache).await,
+            Self::Cleanup(command) => command.run(config, cache).await,
+            Self::Doctor(command) => command.run(config, cache).await,
+            Self::Config(command) => command.run(config, cache).await,
+            Self::Completions(command) => {
+                command.run();
+                Ok(())
+            }
             // Commands that use the pipeline
             Self::Install(command) => command.run(config, cache).await,
             Self::Reinstall(command) => command.run(config, cache).await,
             Self::Upgrade(command) => command.run(config, cache).await,
             Self::Uninstall(command) => command.run(config, cache).await,
+            Self::Autoremove(command) => command.run(config, cache).await,
+            Self::Link(command) => command.run(config, cache).await,
+            Self::Unlink(command) => command.run(config, cache).await,
+            Self::Pin(command) => command.run(config, cache).await,
+            Self::Unpin(command) => command.run(config, cache).await,
+            Self::Tap(command) => command.run(config, cache).await,
+            Self::Untap(command) => command.run(config, cache).await,
+            Self::Services(command) => command.run(config, cache).await,
+            Self::Log(command) => command.run(config, cache).await,
         }
     }
 }
@@ -77,3 +269,44 @@ impl Command {
 // ...
 // pipeline_runner::run_pipeline(&initial_targets, CommandType::Install, config, cache,
 // &flags).await
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn command(args: &[&str]) -> Command {
+        CliArgs::try_parse_from(std::iter::once("sps").chain(args.iter().copied()))
+            .expect("arguments parse")
+            .command
+    }
+
+    fn config_with_cache(cache_dir: Option<&str>) -> Config {
+        let mut config = Config::for_tests(Path::new("/opt/sps"));
+        config.cache_dir_override = cache_dir.map(PathBuf::from);
+        config
+    }
+
+    #[test]
+    fn dry_runs_do_not_modify_the_prefix() {
+        let config = config_with_cache(None);
+        assert!(command(&["install", "jq"]).modifies_prefix(&config));
+        for args in [
+            &["install", "--dry-run", "jq"][..],
+            &["install", "--json-plan", "jq"],
+            &["upgrade", "--dry-run"],
+            &["cleanup", "--dry-run"],
+            &["doctor", "--fix", "--dry-run"],
+        ] {
+            assert!(!command(args).modifies_prefix(&config), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn fetch_into_an_outside_cache_leaves_the_prefix_alone() {
+        let fetch = command(&["fetch", "jq"]);
+        assert!(fetch.modifies_prefix(&config_with_cache(None)));
+        assert!(!fetch.modifies_prefix(&config_with_cache(Some("/tmp/sps-cache"))));
+    }
+}
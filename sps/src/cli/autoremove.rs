@@ -0,0 +1,90 @@
+// sps/src/cli/autoremove.rs
+//! Contains the logic for the `autoremove` command, which uninstalls formulae that were
+//! only installed as dependencies and that nothing installed on request still needs.
+//!
+//! Formulae installed on request and pinned formulae are never removed; see
+//! [`sps_core::uninstall::find_orphaned_dependencies`].
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::keg::KegRegistry;
+use sps_core::check::{InstalledPackageInfo, PackageType};
+use sps_core::{uninstall as core_uninstall, UninstallOptions};
+
+use crate::cli::uninstall::{count_files_and_size, format_size};
+
+#[derive(Args, Debug)]
+pub struct Autoremove {
+    /// List what would be removed without uninstalling anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+impl Autoremove {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let orphans = core_uninstall::find_orphaned_dependencies(config)?;
+        if orphans.is_empty() {
+            println!("No orphaned dependencies to remove.");
+            return Ok(());
+        }
+
+        let kegs = KegRegistry::new(config.clone()).list_installed_kegs()?;
+        // Orphans are needed by nothing that stays, so the dependents check is moot.
+        let uninstall_opts = UninstallOptions {
+            skip_zap: true,
+            ignore_dependencies: true,
+            force: false,
+        };
+        let mut reclaimed = 0;
+        let mut removed = 0;
+        let mut failed = 0;
+        for keg in kegs.iter().filter(|keg| orphans.contains(&keg.name)) {
+            let label = format!("{} {}", keg.name, keg.version_str);
+            let (_, size) = count_files_and_size(&keg.path).unwrap_or((0, 0));
+            if self.dry_run {
+                println!("Would remove: {} ({})", label, format_size(size));
+                reclaimed += size;
+                removed += 1;
+                continue;
+            }
+            let info = InstalledPackageInfo {
+                name: keg.name.clone(),
+                version: keg.version_str.clone(),
+                pkg_type: PackageType::Formula,
+                path: keg.path.clone(),
+            };
+            match core_uninstall::uninstall_formula_artifacts(&info, config, &uninstall_opts) {
+                Ok(()) => {
+                    println!("Removing: {} ({})", label, format_size(size));
+                    reclaimed += size;
+                    removed += 1;
+                }
+                Err(e) => {
+                    eprintln!("✖ {}: {}", label.cyan(), e);
+                    failed += 1;
+                }
+            }
+        }
+
+        let verb = if self.dry_run { "would free" } else { "freed" };
+        println!(
+            "{} {} orphaned keg(s), {} {}",
+            if self.dry_run { "Found" } else { "Removed" },
+            removed,
+            verb,
+            format_size(reclaimed).green()
+        );
+
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(SpsError::Generic(format!(
+                "Failed to remove {failed} keg(s)."
+            )))
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// sps/src/cli/cat.rs
+//! Contains the logic for the `cat` command, which prints the definition sps uses for a
+//! formula or cask exactly as it came from the cache or the API.
+use std::sync::Arc;
+
+use clap::Args;
+use serde_json::Value;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+
+use crate::cli::info::{get_cask_info, get_formula_info_raw};
+
+#[derive(Args, Debug)]
+pub struct Cat {
+    /// Name of the formula or cask
+    pub name: String,
+
+    /// Only look for a formula
+    #[arg(long, conflicts_with = "cask")]
+    pub formula: bool,
+
+    /// Only look for a cask
+    #[arg(long)]
+    pub cask: bool,
+}
+
+impl Cat {
+    /// Prints the pretty-printed JSON definition of the formula, or of the cask if there
+    /// is no formula of that name.
+    pub async fn run(&self, _config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let definition = load_definition(cache, &self.name, self.formula, self.cask).await?;
+        println!("{}", serde_json::to_string_pretty(&definition)?);
+        Ok(())
+    }
+}
+
+/// Looks up the raw definition of `name`: only as a formula or only as a cask if asked
+/// to, otherwise as a formula first and then as a cask.
+pub(crate) async fn load_definition(
+    cache: Arc<Cache>,
+    name: &str,
+    formula_only: bool,
+    cask_only: bool,
+) -> Result<Value> {
+    if cask_only {
+        return get_cask_info(cache, name).await;
+    }
+    if formula_only {
+        return get_formula_info_raw(cache, name).await;
+    }
+    match get_formula_info_raw(Arc::clone(&cache), name).await {
+        Ok(definition) => Ok(definition),
+        Err(SpsError::NotFound(_)) | Err(SpsError::Generic(_)) => {
+            tracing::debug!("No formula '{}', trying cask.", name);
+            get_cask_info(cache, name).await.map_err(|e| match e {
+                SpsError::NotFound(_) => {
+                    SpsError::NotFound(format!("No formula or cask named '{name}'"))
+                }
+                e => e,
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,106 @@
+// sps/src/cli/cleanup.rs
+//! Contains the logic for the `cleanup` command.
+//!
+//! Removes old cached downloads and keg versions superseded by a newer install.
+//! The version linked under `opt` is always kept.
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_core::cleanup::{self, CleanupKind, CleanupOptions};
+
+use crate::cli::uninstall::format_size;
+
+const DEFAULT_MAX_AGE_DAYS: u64 = 120;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Args, Debug)]
+pub struct Cleanup {
+    /// Remove cached downloads older than this many days
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_AGE_DAYS)]
+    pub days: u64,
+
+    /// Prune cached downloads older than N days, or `all` to remove every cached
+    /// download and every keg that is not currently linked
+    #[arg(long, value_name = "N|all")]
+    pub prune: Option<String>,
+
+    /// List what would be removed without deleting anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+impl Cleanup {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let options = self.options()?;
+        let items = cleanup::plan_cleanup(config, &options)?;
+        if items.is_empty() {
+            println!("Nothing to clean up.");
+            return Ok(());
+        }
+
+        let mut reclaimed = 0;
+        let mut failed = 0;
+        for item in &items {
+            let label = match &item.kind {
+                CleanupKind::CacheFile => item.path.display().to_string(),
+                CleanupKind::Keg { name, version } => format!("{name} {version}"),
+            };
+            if self.dry_run {
+                println!("Would remove: {} ({})", label, format_size(item.size));
+                reclaimed += item.size;
+                continue;
+            }
+            match cleanup::remove_item(item) {
+                Ok(()) => {
+                    println!("Removing: {} ({})", label, format_size(item.size));
+                    reclaimed += item.size;
+                }
+                Err(e) => {
+                    eprintln!("✖ {}: {}", label.cyan(), e);
+                    failed += 1;
+                }
+            }
+        }
+
+        let verb = if self.dry_run { "would free" } else { "freed" };
+        println!(
+            "{} {} item(s), {} {}",
+            if self.dry_run { "Found" } else { "Removed" },
+            items.len() - failed,
+            verb,
+            format_size(reclaimed).green()
+        );
+
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(SpsError::Generic(format!(
+                "Failed to remove {failed} item(s)."
+            )))
+        }
+    }
+
+    fn options(&self) -> Result<CleanupOptions> {
+        let (days, prune_all) = match self.prune.as_deref() {
+            None => (self.days, false),
+            Some("all") => (0, true),
+            Some(value) => (
+                value.parse::<u64>().map_err(|_| {
+                    SpsError::Generic(format!(
+                        "Invalid --prune value '{value}': expected a number of days or 'all'"
+                    ))
+                })?,
+                false,
+            ),
+        };
+        Ok(CleanupOptions {
+            max_age: Duration::from_secs(days * SECONDS_PER_DAY),
+            prune_all,
+        })
+    }
+}
@@ -0,0 +1,25 @@
+// sps/src/cli/completions.rs
+//! Contains the logic for the `completions` command, which prints a shell completion
+//! script generated from the clap definition of the CLI.
+use std::io;
+
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+
+use crate::cli::CliArgs;
+
+#[derive(Args, Debug)]
+pub struct Completions {
+    /// The shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+impl Completions {
+    /// Writes the completion script to stdout. Needs no config, so it also works before
+    /// `sps init`.
+    pub fn run(&self) {
+        let mut command = CliArgs::command();
+        clap_complete::generate(self.shell, &mut command, "sps", &mut io::stdout());
+    }
+}
@@ -0,0 +1,251 @@
+// sps/src/cli/config.rs
+//! Contains the logic for the `config` command.
+//!
+//! Reads and edits the active config file: the selected profile's if `--profile` or
+//! `SPS_PROFILE` is set, otherwise the base config. `show` prints the configuration
+//! sps actually resolved from that file, the environment and the defaults.
+use std::fs;
+use std::sync::Arc;
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use serde_json::{json, Map, Value};
+use sps_common::cache::Cache;
+use sps_common::config::{Config, ConfigFile};
+use sps_common::error::{Result, SpsError};
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Show the resolved configuration and paths sps is using, with credentials redacted
+    Show {
+        /// Print a JSON object instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the settings stored in the active config file
+    List,
+    /// Print one setting from the active config file
+    Get { key: String },
+    /// Store a setting in the active config file
+    Set { key: String, value: String },
+    /// Remove a setting from the active config file
+    Unset { key: String },
+    /// Print the path of the active config file
+    Path,
+    /// List the available profiles
+    Profiles,
+    /// Create an empty profile
+    NewProfile { name: String },
+    /// Delete a profile
+    RemoveProfile { name: String },
+}
+
+impl ConfigArgs {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let path = config.active_config_path()?;
+        match &self.action {
+            ConfigAction::Show { json } => {
+                let resolved = resolved_settings(config)?;
+                if *json {
+                    let object: Map<String, Value> = resolved
+                        .into_iter()
+                        .map(|(key, value)| (key.to_string(), value))
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&object)?);
+                } else {
+                    let width = resolved.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+                    for (key, value) in resolved {
+                        let shown = match value {
+                            Value::Null => "(not set)".dimmed().to_string(),
+                            Value::String(s) => s,
+                            other => other.to_string(),
+                        };
+                        println!("{}  {}", format!("{key:width$}").cyan(), shown);
+                    }
+                }
+            }
+            ConfigAction::List => {
+                let file = ConfigFile::load(&path)?;
+                println!(
+                    "{} ({})",
+                    active_label(config).bold(),
+                    path.display().to_string().dimmed()
+                );
+                let mut any = false;
+                for key in ConfigFile::KEYS {
+                    if let Some(value) = file.get(key)? {
+                        println!("{} = {}", key.cyan(), display_value(key, &value));
+                        any = true;
+                    }
+                }
+                if !any {
+                    println!("No settings stored.");
+                }
+            }
+            ConfigAction::Get { key } => match ConfigFile::load(&path)?.get(key)? {
+                Some(value) => println!("{value}"),
+                None => {
+                    return Err(SpsError::NotFound(format!(
+                        "'{}' is not set in {}",
+                        key,
+                        active_label(config)
+                    )))
+                }
+            },
+            ConfigAction::Set { key, value } => {
+                let mut file = ConfigFile::load(&path)?;
+                file.set(key, value)?;
+                file.save(&path)?;
+                println!("✓ Set {} in {}", key.green(), active_label(config));
+            }
+            ConfigAction::Unset { key } => {
+                let mut file = ConfigFile::load(&path)?;
+                file.unset(key)?;
+                file.save(&path)?;
+                println!("✓ Unset {} in {}", key.green(), active_label(config));
+            }
+            ConfigAction::Path => println!("{}", path.display()),
+            ConfigAction::Profiles => {
+                let mut names: Vec<String> = fs::read_dir(config.profiles_dir())
+                    .map(|entries| {
+                        entries
+                            .flatten()
+                            .filter_map(|entry| {
+                                let path = entry.path();
+                                if path.extension()? != "json" {
+                                    return None;
+                                }
+                                path.file_stem()?.to_str().map(String::from)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                names.sort();
+                if names.is_empty() {
+                    println!("No profiles. Create one with `sps config new-profile <name>`.");
+                }
+                for name in names {
+                    if config.profile.as_deref() == Some(name.as_str()) {
+                        println!("* {}", name.green());
+                    } else {
+                        println!("  {name}");
+                    }
+                }
+            }
+            ConfigAction::NewProfile { name } => {
+                let profile_path = config.profile_config_path(name)?;
+                if profile_path.exists() {
+                    return Err(SpsError::Generic(format!(
+                        "Profile '{name}' already exists"
+                    )));
+                }
+                ConfigFile::default().save(&profile_path)?;
+                println!(
+                    "✓ Created profile {} ({})",
+                    name.green(),
+                    profile_path.display()
+                );
+            }
+            ConfigAction::RemoveProfile { name } => {
+                let profile_path = config.profile_config_path(name)?;
+                if !profile_path.exists() {
+                    return Err(SpsError::NotFound(format!(
+                        "Profile '{name}' does not exist"
+                    )));
+                }
+                fs::remove_file(&profile_path)?;
+                println!("✓ Removed profile {}", name.green());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every resolved setting and derived path, in display order. Credentials only show
+/// whether they are set.
+fn resolved_settings(config: &Config) -> Result<Vec<(&'static str, Value)>> {
+    let path = |p: std::path::PathBuf| json!(p.display().to_string());
+    let secret = |value: &Option<String>| value.as_ref().map(|_| json!("set (redacted)"));
+    let lowercase = |value: &dyn std::fmt::Debug| json!(format!("{value:?}").to_lowercase());
+    Ok(vec![
+        ("sps_root", path(config.sps_root().to_path_buf())),
+        ("profile", json!(config.profile)),
+        ("config_file", path(config.active_config_path()?)),
+        ("cellar", path(config.cellar_dir())),
+        ("caskroom", path(config.cask_room_dir())),
+        ("opt", path(config.opt_dir())),
+        ("bin", path(config.bin_dir())),
+        ("cache", path(config.cache_dir())),
+        ("logs", path(config.logs_dir())),
+        ("state", path(config.state_dir())),
+        ("tmp", path(config.tmp_dir())),
+        ("api_base_url", json!(config.api_base_url)),
+        ("artifact_domain", json!(config.artifact_domain)),
+        ("bottle_domain", json!(config.bottle_domain)),
+        ("oci_registry", json!(config.oci_registry)),
+        (
+            "github_api_token",
+            secret(&config.github_api_token).unwrap_or(Value::Null),
+        ),
+        (
+            "docker_registry_token",
+            secret(&config.docker_registry_token).unwrap_or(Value::Null),
+        ),
+        (
+            "docker_registry_basic_auth",
+            secret(&config.docker_registry_basic_auth).unwrap_or(Value::Null),
+        ),
+        ("max_downloads", json!(config.limits.max_downloads)),
+        ("max_workers", json!(config.limits.max_workers)),
+        ("build_jobs", json!(config.limits.build_jobs)),
+        ("worker_queue_size", json!(config.limits.worker_queue_size)),
+        ("cask_user_scope", json!(config.cask_user_scope)),
+        ("verify_signature", json!(config.verify_signature)),
+        ("quarantine", lowercase(&config.quarantine)),
+        (
+            "build_generator",
+            config
+                .build_generator
+                .map_or(json!("auto"), |generator| lowercase(&generator)),
+        ),
+        (
+            "build_timeout_secs",
+            json!(config.build_timeout.map(|t| t.as_secs())),
+        ),
+        ("offline_build", json!(config.offline_build)),
+        ("skip_relocation", json!(config.skip_relocation)),
+        ("go_proxy", json!(config.go_proxy)),
+        ("go_sumdb", json!(config.go_sumdb)),
+        ("lock_timeout_secs", json!(config.lock_timeout.as_secs())),
+    ])
+}
+
+fn active_label(config: &Config) -> String {
+    match &config.profile {
+        Some(name) => format!("profile '{name}'"),
+        None => "base config".to_string(),
+    }
+}
+
+/// Hides all but the last few characters of credentials.
+fn display_value(key: &str, value: &str) -> String {
+    let is_secret = key.ends_with("_token") || key.ends_with("_auth");
+    if !is_secret {
+        return value.to_string();
+    }
+    let visible: String = value
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{visible}")
+}
@@ -0,0 +1,247 @@
+// sps/src/cli/deps.rs
+//! Contains the logic for the `deps` command.
+//!
+//! Runs the same dependency resolution the install planner uses and prints the
+//! result, without downloading or installing anything.
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::dependency::resolver::{
+    DependencyResolver, PerTargetInstallPreferences, ResolutionContext, ResolutionStatus,
+    ResolvedGraph,
+};
+use sps_common::dependency::DependencyTag;
+use sps_common::error::{Result, SpsError};
+use sps_common::formulary::Formulary;
+use sps_common::keg::KegRegistry;
+use sps_common::pipeline::JobAction;
+
+#[derive(Args, Debug)]
+pub struct Deps {
+    /// The formulae whose dependencies should be listed
+    #[arg(required = true)]
+    pub names: Vec<String>,
+
+    /// Print dependencies as a tree instead of a flat install-order list
+    #[arg(long)]
+    pub tree: bool,
+
+    /// Only show dependencies that are already installed
+    #[arg(long)]
+    pub installed: bool,
+
+    /// Include optional dependencies
+    #[arg(long)]
+    pub include_optional: bool,
+
+    /// Skip recommended dependencies
+    #[arg(long)]
+    pub skip_recommended: bool,
+
+    /// Include build dependencies, as for a source build
+    #[arg(long)]
+    pub include_build: bool,
+
+    /// Include test dependencies
+    #[arg(long)]
+    pub include_test: bool,
+}
+
+impl Deps {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let formulary = Formulary::new(config.clone());
+        let keg_registry = KegRegistry::new(config.clone());
+        let per_target_prefs = PerTargetInstallPreferences::default();
+        let initial_target_actions: HashMap<String, JobAction> = HashMap::new();
+
+        let ctx = ResolutionContext {
+            formulary: &formulary,
+            keg_registry: &keg_registry,
+            sps_prefix: config.sps_root(),
+            include_optional: self.include_optional,
+            include_test: self.include_test,
+            skip_recommended: self.skip_recommended,
+            initial_target_preferences: &per_target_prefs,
+            build_all_from_source: self.include_build,
+            cascade_source_preference_to_dependencies: true,
+            has_bottle_for_current_platform:
+                sps_core::install::bottle::has_bottle_for_current_platform,
+            initial_target_actions: &initial_target_actions,
+        };
+
+        let mut resolver = DependencyResolver::new(ctx);
+        let graph = resolver.resolve_targets(&self.names).map_err(|e| match e {
+            SpsError::DependencyError(msg) => SpsError::DependencyError(format!(
+                "Cannot resolve dependencies of {}: {msg}",
+                self.names.join(", ")
+            )),
+            other => other,
+        })?;
+
+        let missing: Vec<&String> = self
+            .names
+            .iter()
+            .filter(|name| {
+                graph
+                    .resolution_details
+                    .get(name.as_str())
+                    .is_none_or(|dep| dep.status == ResolutionStatus::NotFound)
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(SpsError::NotFound(format!(
+                "No formula definition for: {}",
+                missing
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let edges = DependencyEdges::from_graph(&graph, resolver.context());
+        if self.tree {
+            for name in &self.names {
+                println!("{}", name.cyan());
+                let mut path = vec![name.clone()];
+                self.print_subtree(name, &edges, &graph, "", &mut path);
+            }
+        } else {
+            let targets: HashSet<&str> = self.names.iter().map(|s| s.as_str()).collect();
+            let mut visited = HashSet::new();
+            let mut order = Vec::new();
+            for name in &self.names {
+                edges.post_order(name, &mut visited, &mut order);
+            }
+            for name in order {
+                if targets.contains(name.as_str()) || !self.should_show(&name, &graph) {
+                    continue;
+                }
+                println!("{name}");
+            }
+        }
+        Ok(())
+    }
+
+    fn should_show(&self, name: &str, graph: &ResolvedGraph) -> bool {
+        !self.installed
+            || graph
+                .resolution_details
+                .get(name)
+                .is_some_and(|dep| dep.status == ResolutionStatus::Installed)
+    }
+
+    fn print_subtree(
+        &self,
+        name: &str,
+        edges: &DependencyEdges,
+        graph: &ResolvedGraph,
+        prefix: &str,
+        path: &mut Vec<String>,
+    ) {
+        let children: Vec<&(String, DependencyTag)> = edges
+            .children(name)
+            .iter()
+            .filter(|(child, _)| self.should_show(child, graph))
+            .collect();
+
+        for (i, (child, tags)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let branch = if last { "└── " } else { "├── " };
+            let mut label = child.clone();
+            let tag_text = tag_label(*tags);
+            if !tag_text.is_empty() {
+                label.push_str(&format!(" ({tag_text})").dimmed().to_string());
+            }
+            if graph
+                .resolution_details
+                .get(child)
+                .is_some_and(|dep| dep.status == ResolutionStatus::NotFound)
+            {
+                label.push_str(&" [not found]".red().to_string());
+            }
+
+            // Cycles are rejected by the resolver; this only guards the printer.
+            if path.contains(child) {
+                println!("{prefix}{branch}{label} {}", "[cycle]".red());
+                continue;
+            }
+            println!("{prefix}{branch}{label}");
+
+            let child_prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            path.push(child.clone());
+            self.print_subtree(child, edges, graph, &child_prefix, path);
+            path.pop();
+        }
+    }
+}
+
+/// The edges the resolver actually followed, keyed by parent name.
+struct DependencyEdges {
+    children: HashMap<String, Vec<(String, DependencyTag)>>,
+}
+
+impl DependencyEdges {
+    fn from_graph(graph: &ResolvedGraph, ctx: &ResolutionContext) -> Self {
+        let mut children: HashMap<String, Vec<(String, DependencyTag)>> = HashMap::new();
+        for (name, resolved) in &graph.resolution_details {
+            let Ok(dependencies) = resolved.formula.dependencies() else {
+                continue;
+            };
+            let entry = children.entry(name.clone()).or_default();
+            for dep in dependencies {
+                let followed = graph
+                    .resolution_details
+                    .get(&dep.name)
+                    .is_some_and(|d| d.status != ResolutionStatus::SkippedOptional)
+                    && ctx.should_process_dependency_edge(
+                        &resolved.formula,
+                        dep.tags,
+                        resolved.determined_install_strategy,
+                    );
+                if followed {
+                    entry.push((dep.name.clone(), dep.tags));
+                }
+            }
+            entry.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        Self { children }
+    }
+
+    fn children(&self, name: &str) -> &[(String, DependencyTag)] {
+        self.children.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Dependencies before dependents, each name once.
+    fn post_order(&self, name: &str, visited: &mut HashSet<String>, out: &mut Vec<String>) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        for (child, _) in self.children(name) {
+            self.post_order(child, visited, out);
+        }
+        out.push(name.to_string());
+    }
+}
+
+/// Human-readable list of the non-runtime tags on a dependency edge, e.g. "build, optional".
+pub(crate) fn tag_label(tags: DependencyTag) -> String {
+    let mut parts = Vec::new();
+    if tags.contains(DependencyTag::BUILD) {
+        parts.push("build");
+    }
+    if tags.contains(DependencyTag::OPTIONAL) {
+        parts.push("optional");
+    }
+    if tags.contains(DependencyTag::RECOMMENDED) {
+        parts.push("recommended");
+    }
+    if tags.contains(DependencyTag::TEST) {
+        parts.push("test");
+    }
+    parts.join(", ")
+}
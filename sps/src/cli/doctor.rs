@@ -0,0 +1,97 @@
+// sps/src/cli/doctor.rs
+//! Contains the logic for the `doctor` command.
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_core::check::doctor::{self, CheckStatus};
+
+#[derive(Args, Debug)]
+pub struct Doctor {
+    /// Repair the issues that can be fixed automatically
+    #[arg(long)]
+    pub fix: bool,
+
+    /// With --fix, show what would be repaired without changing anything
+    #[arg(long, requires = "fix")]
+    pub dry_run: bool,
+}
+
+impl Doctor {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let failed_checks = if self.fix { 0 } else { print_checks(config) };
+
+        let issues = doctor::diagnose(config)?;
+        if issues.is_empty() {
+            if failed_checks > 0 {
+                return Err(SpsError::Generic(format!(
+                    "{failed_checks} check(s) failed."
+                )));
+            }
+            println!("{} No problems found.", "✓".green());
+            return Ok(());
+        }
+
+        if !self.fix {
+            for issue in &issues {
+                println!("{} {}", "Warning:".yellow().bold(), issue);
+            }
+            return Err(SpsError::Generic(format!(
+                "{} problem(s) found. Run `sps doctor --fix` to repair them.",
+                issues.len()
+            )));
+        }
+
+        let mut failed = 0;
+        for issue in &issues {
+            if self.dry_run {
+                println!("Would {}", issue.fix_description());
+                continue;
+            }
+            match doctor::fix(issue, config) {
+                Ok(()) => println!("{} {}", "✓".green(), issue.fix_description()),
+                Err(e) => {
+                    eprintln!("✖ Could not fix {}: {}", issue, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        if !self.dry_run {
+            println!(
+                "Changes were logged to {}",
+                config.logs_dir().join("doctor.log").display()
+            );
+        }
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(SpsError::Generic(format!(
+                "{failed} problem(s) could not be fixed."
+            )))
+        }
+    }
+}
+
+/// Prints each environment check and returns how many failed.
+fn print_checks(config: &Config) -> usize {
+    let results = doctor::run_checks(config);
+    for result in &results {
+        let marker = match result.status {
+            CheckStatus::Pass => "✓".green(),
+            CheckStatus::Warn => "!".yellow(),
+            CheckStatus::Fail => "✖".red(),
+        };
+        println!("{} {}: {}", marker, result.name.bold(), result.message);
+        if let Some(hint) = &result.hint {
+            println!("    {}", hint.dimmed());
+        }
+    }
+    results
+        .iter()
+        .filter(|result| result.status == CheckStatus::Fail)
+        .count()
+}
@@ -0,0 +1,331 @@
+// sps/src/cli/fetch.rs
+//! Contains the logic for the `fetch` command.
+//!
+//! Downloads bottles, source archives, or cask artifacts into the cache without
+//! planning an install or touching install state. Useful for warming CI caches.
+//!
+//! Every artifact that has been downloaded and verified is recorded in a manifest
+//! directory, one file per artifact and version, so concurrent fetches never overwrite
+//! each other's records. `--continue` skips what an interrupted run already recorded.
+//! Once a run fetches everything it removes the records of its own artifacts, and
+//! nothing else; other files in the directory, such as the records of a concurrent run,
+//! are left alone.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sps_common::cache::{Cache, FETCH_MANIFEST_DIR};
+use sps_common::config::Config;
+use sps_common::dependency::DependencyExt;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::{Formula, InstallTargetIdentifier};
+use sps_core::{build, install};
+use tracing::debug;
+
+use crate::pipeline::planner::fetch_target_definitions;
+
+#[derive(Args, Debug)]
+pub struct Fetch {
+    /// The formulae or casks to download into the cache
+    #[arg(required = true)]
+    pub names: Vec<String>,
+
+    /// Also fetch the runtime dependencies of the named formulae
+    #[arg(long)]
+    pub deps: bool,
+
+    /// Fetch source archives instead of bottles
+    #[arg(long)]
+    pub build_from_source: bool,
+
+    /// Skip artifacts an interrupted fetch already downloaded and verified
+    #[arg(long = "continue")]
+    pub resume: bool,
+
+    /// Where completed fetches are recorded (default: `fetch-manifest` in the cache)
+    #[arg(long, value_name = "DIR")]
+    pub manifest: Option<PathBuf>,
+}
+
+impl Fetch {
+    pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let targets = self.collect_targets(Arc::clone(&cache)).await?;
+        let http_client = sps_net::http::shared_client()?;
+
+        let manifest = match &self.manifest {
+            Some(dir) => FetchManifest::new(dir.clone(), false),
+            None => FetchManifest::new(cache.get_dir().join(FETCH_MANIFEST_DIR), true),
+        };
+
+        let keys: Vec<String> = targets
+            .iter()
+            .map(|(name, target)| self.artifact_key(name, target, config))
+            .collect();
+        let mut failed = Vec::new();
+        for ((name, target), key) in targets.iter().zip(&keys) {
+            if self.resume {
+                if let Some(path) = manifest.completed(key) {
+                    println!("✓ {} {} (already fetched)", name.green(), path.display());
+                    continue;
+                }
+            }
+            match self
+                .fetch_one(target, config, cache.as_ref(), &http_client)
+                .await
+            {
+                Ok(path) => {
+                    if let Err(e) = manifest.record(key, &path) {
+                        tracing::warn!("Failed to record fetch of {} in manifest: {}", name, e);
+                    }
+                    println!("✓ {} {}", name.green(), path.display());
+                }
+                Err(e) => {
+                    eprintln!("✖ Failed to fetch '{}': {}", name.cyan(), e);
+                    failed.push(name.clone());
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            manifest.remove(&keys)?;
+            Ok(())
+        } else {
+            eprintln!("Run again with --continue to skip what was already fetched.");
+            Err(SpsError::DownloadError(
+                failed.join(", "),
+                String::new(),
+                format!("{} of {} fetches failed", failed.len(), targets.len()),
+            ))
+        }
+    }
+
+    /// Resolves definitions for the named targets and, with `--deps`, walks formula
+    /// runtime dependencies breadth-first. Order follows discovery; duplicates are dropped.
+    async fn collect_targets(
+        &self,
+        cache: Arc<Cache>,
+    ) -> Result<Vec<(String, InstallTargetIdentifier)>> {
+        let mut targets = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut missing = Vec::new();
+        let mut pending: Vec<String> = self.names.clone();
+
+        while !pending.is_empty() {
+            pending.retain(|name| seen.insert(name.clone()));
+            if pending.is_empty() {
+                break;
+            }
+            let mut definitions = fetch_target_definitions(&pending, Arc::clone(&cache)).await;
+            let mut next = Vec::new();
+            for name in pending.drain(..) {
+                match definitions.remove(&name) {
+                    Some(Ok(target)) => {
+                        if self.deps {
+                            if let InstallTargetIdentifier::Formula(formula) = &target {
+                                next.extend(runtime_dependency_names(formula)?);
+                            }
+                        }
+                        targets.push((name, target));
+                    }
+                    Some(Err(e)) => {
+                        eprintln!("✖ Cannot fetch '{}': {}", name.cyan(), e);
+                        missing.push(name);
+                    }
+                    None => {
+                        eprintln!("✖ Cannot fetch '{}': no definition found", name.cyan());
+                        missing.push(name);
+                    }
+                }
+            }
+            pending = next;
+        }
+
+        if !missing.is_empty() {
+            return Err(SpsError::NotFound(format!(
+                "No formula or cask definition for: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(targets)
+    }
+
+    /// Identifies an artifact in the manifest; a formula's bottle and source archive are
+    /// different artifacts, and so are two versions of the same package.
+    fn artifact_key(
+        &self,
+        name: &str,
+        target: &InstallTargetIdentifier,
+        config: &Config,
+    ) -> String {
+        match target {
+            InstallTargetIdentifier::Formula(formula)
+                if !self.build_from_source
+                    && install::bottle::has_bottle_for_current_platform(formula, config) =>
+            {
+                artifact_key("bottle", name, &formula.version_str_full())
+            }
+            InstallTargetIdentifier::Formula(formula) => {
+                artifact_key("source", name, &formula.version_str_full())
+            }
+            InstallTargetIdentifier::Cask(cask) => {
+                artifact_key("cask", name, cask.version.as_deref().unwrap_or("latest"))
+            }
+        }
+    }
+
+    async fn fetch_one(
+        &self,
+        target: &InstallTargetIdentifier,
+        config: &Config,
+        cache: &Cache,
+        http_client: &reqwest::Client,
+    ) -> Result<PathBuf> {
+        match target {
+            InstallTargetIdentifier::Formula(formula) => {
+                let use_bottle = !self.build_from_source
+                    && install::bottle::has_bottle_for_current_platform(formula, config);
+                if use_bottle {
+                    install::bottle::exec::download_bottle(
+                        formula,
+                        config,
+                        http_client,
+                        None,
+                        false,
+                    )
+                    .await
+                } else {
+                    debug!("Fetching source archive for {}", formula.name());
+                    build::compile::download_source(formula, config, None).await
+                }
+            }
+            InstallTargetIdentifier::Cask(cask) => {
+                install::cask::download_cask(cask, config, cache, None).await
+            }
+        }
+    }
+}
+
+fn artifact_key(kind: &str, name: &str, version: &str) -> String {
+    format!("{kind}-{name}-{version}").replace(['/', '\\'], "_")
+}
+
+fn runtime_dependency_names(formula: &Formula) -> Result<Vec<String>> {
+    Ok(formula
+        .dependencies()?
+        .runtime()
+        .into_iter()
+        .map(|dep| dep.name.clone())
+        .collect())
+}
+
+#[derive(Serialize, Deserialize)]
+struct FetchedArtifact {
+    path: PathBuf,
+}
+
+/// A directory with one JSON file per fetched artifact.
+struct FetchManifest {
+    dir: PathBuf,
+    /// Whether the directory is sps's own, so that it may be removed once empty.
+    owned: bool,
+}
+
+impl FetchManifest {
+    fn new(dir: PathBuf, owned: bool) -> Self {
+        Self { dir, owned }
+    }
+
+    /// The recorded download of `key`, if it is still in the cache.
+    fn completed(&self, key: &str) -> Option<PathBuf> {
+        let raw = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: FetchedArtifact = serde_json::from_str(&raw).ok()?;
+        entry.path.is_file().then_some(entry.path)
+    }
+
+    /// Written through a temporary file so that an interrupted write leaves no entry.
+    fn record(&self, key: &str, path: &Path) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = serde_json::to_vec(&FetchedArtifact {
+            path: path.to_path_buf(),
+        })?;
+        let tmp = self.dir.join(format!(".{key}.{}.tmp", std::process::id()));
+        fs::write(&tmp, entry)?;
+        fs::rename(&tmp, self.entry_path(key))?;
+        Ok(())
+    }
+
+    /// Removes the records of `keys`, and the directory too if it is sps's own and
+    /// nothing else is left in it.
+    fn remove(&self, keys: &[String]) -> Result<()> {
+        for key in keys {
+            match fs::remove_file(self.entry_path(key)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        if self.owned {
+            // Fails, harmlessly, while a concurrent run still has records in it.
+            let _ = fs::remove_dir(&self.dir);
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continue_skips_recorded_artifacts_only() {
+        let cache = tempfile::tempdir().unwrap();
+        let manifest = FetchManifest::new(cache.path().join(FETCH_MANIFEST_DIR), true);
+        let done = cache.path().join("foo--1.0.bottle.tar.gz");
+        fs::write(&done, b"verified").unwrap();
+        // An interrupted download leaves only its partial file and no record, so it is
+        // fetched again and resumed from that file.
+        let partial = cache.path().join(".bar--2.0.bottle.tar.gz.download");
+        fs::write(&partial, b"partial").unwrap();
+
+        let foo = artifact_key("bottle", "foo", "1.0");
+        let bar = artifact_key("bottle", "bar", "2.0");
+        manifest.record(&foo, &done).unwrap();
+
+        assert_eq!(manifest.completed(&foo), Some(done.clone()));
+        assert_eq!(manifest.completed(&bar), None);
+        // A new version is a different artifact.
+        assert_eq!(
+            manifest.completed(&artifact_key("bottle", "foo", "1.1")),
+            None
+        );
+
+        fs::remove_file(&done).unwrap();
+        assert_eq!(manifest.completed(&foo), None);
+    }
+
+    #[test]
+    fn remove_keeps_other_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = FetchManifest::new(dir.path().to_path_buf(), false);
+        let artifact = dir.path().join("artifact");
+        fs::write(&artifact, b"data").unwrap();
+        let ours = artifact_key("cask", "ours", "1.0");
+        let theirs = artifact_key("cask", "theirs", "1.0");
+        manifest.record(&ours, &artifact).unwrap();
+        manifest.record(&theirs, &artifact).unwrap();
+        fs::write(dir.path().join("notes.txt"), b"user file").unwrap();
+
+        manifest.remove(std::slice::from_ref(&ours)).unwrap();
+
+        assert_eq!(manifest.completed(&ours), None);
+        assert_eq!(manifest.completed(&theirs), Some(artifact));
+        assert!(dir.path().join("notes.txt").is_file());
+    }
+}
@@ -0,0 +1,66 @@
+// sps/src/cli/home.rs
+//! Contains the logic for the `home` command, which opens a package's homepage.
+use std::process::Command;
+use std::sync::Arc;
+
+use clap::Args;
+use serde_json::Value;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+
+use crate::cli::cat::load_definition;
+
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const OPENER: &str = "xdg-open";
+
+#[derive(Args, Debug)]
+pub struct Home {
+    /// Name of the formula or cask
+    pub name: String,
+
+    /// Only look for a formula
+    #[arg(long, conflicts_with = "cask")]
+    pub formula: bool,
+
+    /// Only look for a cask
+    #[arg(long)]
+    pub cask: bool,
+
+    /// Print the homepage URL instead of opening it
+    #[arg(long)]
+    pub print: bool,
+}
+
+impl Home {
+    pub async fn run(&self, _config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let definition = load_definition(cache, &self.name, self.formula, self.cask).await?;
+        let homepage = definition
+            .get("homepage")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| {
+                SpsError::NotFound(format!("'{}' does not define a homepage", self.name))
+            })?;
+
+        if self.print {
+            println!("{homepage}");
+            return Ok(());
+        }
+        tracing::debug!("Opening {} with {}", homepage, OPENER);
+        let status = Command::new(OPENER).arg(homepage).status().map_err(|e| {
+            SpsError::CommandExecError(format!(
+                "Failed to run {OPENER} for {homepage}: {e} (use --print to show the URL)"
+            ))
+        })?;
+        if !status.success() {
+            return Err(SpsError::CommandExecError(format!(
+                "{OPENER} {homepage} exited with {status}"
+            )));
+        }
+        Ok(())
+    }
+}
@@ -4,12 +4,19 @@ use std::sync::Arc;
 
 use clap::Args;
 use colored::Colorize;
-use serde_json::Value;
+use serde_json::{json, Value};
 use sps_common::cache::Cache;
 use sps_common::config::Config;
+use sps_common::dependency::{Dependency, DependencyExt, DependencyTag};
 use sps_common::error::{Result, SpsError};
+use sps_common::model::tap::{self, DefinitionKind};
+use sps_common::model::{Cask, Formula};
+use sps_core::check::{installed, PackageType};
+use sps_core::install::bottle::has_bottle_for_current_platform;
 use sps_net::api;
 
+use crate::cli::ui::print_table;
+
 #[derive(Args, Debug)]
 pub struct Info {
     /// Name of the formula or cask
@@ -18,24 +25,27 @@ pub struct Info {
     /// Show information for a cask, not a formula
     #[arg(long)]
     pub cask: bool,
+
+    /// Print the package as a single JSON object instead of tables
+    #[arg(long)]
+    pub json: bool,
 }
 
 impl Info {
     /// Displays detailed information about a formula or cask.
-    pub async fn run(&self, _config: &Config, cache: Arc<Cache>) -> Result<()> {
+    pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         let name = &self.name;
         let is_cask = self.cask;
         tracing::debug!("Getting info for package: {name}, is_cask: {is_cask}",);
 
-        // Print loading message instead of spinner
-        println!("Loading info for {name}");
+        // Print loading message instead of spinner; keep stdout clean for --json
+        if !self.json {
+            println!("Loading info for {name}");
+        }
 
         if self.cask {
             match get_cask_info(Arc::clone(&cache), name).await {
-                Ok(info) => {
-                    print_cask_info(name, &info);
-                    Ok(())
-                }
+                Ok(info) => self.show_cask(name, &info, config).await,
                 Err(e) => Err(e),
             }
         } else {
@@ -43,8 +53,7 @@ impl Info {
                 Ok(info) => {
                     // Removed bottle check logic here as it was complex and potentially racy.
                     // We'll try formula first, then cask if formula fails.
-                    print_formula_info(name, &info);
-                    return Ok(());
+                    return self.show_formula(name, &info, config).await;
                 }
                 Err(SpsError::NotFound(_)) | Err(SpsError::Generic(_)) => {
                     // If formula lookup failed (not found or generic error), try cask.
@@ -56,20 +65,161 @@ impl Info {
             }
             // --- Cask Fallback ---
             match get_cask_info(Arc::clone(&cache), name).await {
-                Ok(info) => {
-                    print_cask_info(name, &info);
-                    Ok(())
-                }
+                Ok(info) => self.show_cask(name, &info, config).await,
                 Err(e) => {
                     Err(e) // Return the cask error if both formula and cask fail
                 }
             }
         }
     }
+
+    async fn show_formula(&self, name: &str, info: &Value, config: &Config) -> Result<()> {
+        if !self.json {
+            print_formula_info(name, info);
+            return Ok(());
+        }
+        let formula: Formula = serde_json::from_value(info.clone())?;
+        let installed = installed_json(formula.name(), PackageType::Formula, config).await?;
+        print_json(&formula_json(&formula, info, installed, config)?)
+    }
+
+    async fn show_cask(&self, name: &str, info: &Value, config: &Config) -> Result<()> {
+        if !self.json {
+            print_cask_info(name, info);
+            return Ok(());
+        }
+        let cask: Cask = serde_json::from_value(info.clone())?;
+        let installed = installed_json(&cask.token, PackageType::Cask, config).await?;
+        print_json(&cask_json(&cask, info, installed))
+    }
+}
+
+/// Builds the `--json` object for a formula from the parsed model, taking fields the
+/// model does not carry (license, caveats) from the raw API entry.
+fn formula_json(
+    formula: &Formula,
+    raw: &Value,
+    installed: Value,
+    config: &Config,
+) -> Result<Value> {
+    let dependencies = formula.dependencies()?;
+    let names = |deps: Vec<&Dependency>| -> Vec<String> {
+        deps.into_iter().map(|d| d.name.clone()).collect()
+    };
+    let tagged = |tag: DependencyTag| -> Vec<String> {
+        dependencies
+            .iter()
+            .filter(|d| d.tags.contains(tag))
+            .map(|d| d.name.clone())
+            .collect()
+    };
+    let mut bottle_platforms: Vec<&String> = formula
+        .bottle
+        .stable
+        .as_ref()
+        .map(|stable| stable.files.keys().collect())
+        .unwrap_or_default();
+    bottle_platforms.sort();
+
+    Ok(json!({
+        "type": "formula",
+        "name": formula.name(),
+        "full_name": raw.get("full_name").cloned().unwrap_or(Value::Null),
+        "version": formula.version_str_full(),
+        "versions": {
+            "stable": formula.stable_version_str,
+            "revision": formula.revision,
+        },
+        "desc": formula.desc,
+        "homepage": formula.homepage,
+        "license": raw.get("license").cloned().unwrap_or(Value::Null),
+        "caveats": raw.get("caveats").cloned().unwrap_or(Value::Null),
+        "dependencies": names(dependencies.runtime()),
+        "build_dependencies": names(dependencies.build_time()),
+        "optional_dependencies": tagged(DependencyTag::OPTIONAL),
+        "recommended_dependencies": tagged(DependencyTag::RECOMMENDED),
+        "bottle": {
+            "available_for_current_platform": has_bottle_for_current_platform(formula, config),
+            "platforms": bottle_platforms,
+        },
+        "installed": installed,
+    }))
+}
+
+fn cask_json(cask: &Cask, raw: &Value, installed: Value) -> Value {
+    json!({
+        "type": "cask",
+        "token": cask.token,
+        "name": cask.name,
+        "version": cask.version,
+        "desc": cask.desc,
+        "homepage": cask.homepage,
+        "caveats": cask.caveats,
+        "auto_updates": cask.auto_updates,
+        "depends_on": raw.get("depends_on").cloned().unwrap_or(Value::Null),
+        "installed": installed,
+    })
+}
+
+/// The installed version and path of `name`, or `null` when it is not installed as
+/// the given package type.
+async fn installed_json(name: &str, pkg_type: PackageType, config: &Config) -> Result<Value> {
+    Ok(
+        match installed::get_installed_package(name, config).await? {
+            Some(info) if info.pkg_type == pkg_type => json!({
+                "version": info.version,
+                "path": info.path,
+            }),
+            _ => Value::Null,
+        },
+    )
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Retrieves formula information from the cache or API as raw JSON, or from an
+/// installed tap if core has no formula of that name
+pub(crate) async fn get_formula_info_raw(cache: Arc<Cache>, name: &str) -> Result<Value> {
+    let config = cache.config().clone();
+    or_tapped(
+        get_core_formula_info(cache, name).await,
+        &config,
+        DefinitionKind::Formula,
+        name,
+    )
+}
+
+/// Retrieves cask information from the cache or API, or from an installed tap if core
+/// has no cask of that name
+pub(crate) async fn get_cask_info(cache: Arc<Cache>, name: &str) -> Result<Value> {
+    let config = cache.config().clone();
+    or_tapped(
+        get_core_cask_info(cache, name).await,
+        &config,
+        DefinitionKind::Cask,
+        name,
+    )
+}
+
+/// Falls back to a tapped definition when the core lookup found nothing.
+fn or_tapped(
+    core: Result<Value>,
+    config: &Config,
+    kind: DefinitionKind,
+    name: &str,
+) -> Result<Value> {
+    match core {
+        Err(SpsError::NotFound(message)) => tap::find_tapped_definition(config, kind, name)
+            .map(|tapped| tapped.value)
+            .ok_or(SpsError::NotFound(message)),
+        result => result,
+    }
 }
 
-/// Retrieves formula information from the cache or API as raw JSON
-async fn get_formula_info_raw(cache: Arc<Cache>, name: &str) -> Result<Value> {
+async fn get_core_formula_info(cache: Arc<Cache>, name: &str) -> Result<Value> {
     match cache.load_raw("formula.json") {
         Ok(formula_data) => {
             let formulas: Vec<Value> =
@@ -100,7 +250,7 @@ async fn get_formula_info_raw(cache: Arc<Cache>, name: &str) -> Result<Value> {
     }
     tracing::debug!("Fetching formula '{}' directly from API", name);
     // api::fetch_formula returns Value directly now
-    let value = api::fetch_formula(name).await?;
+    let value = api::fetch_formula(name, cache.config()).await?;
     // Store in cache if fetched successfully
     // Note: This might overwrite the full list cache, consider storing individual files or a map
     // cache.store_raw(&format!("formula/{}.json", name), &value.to_string())?; // Example of
@@ -108,8 +258,7 @@ async fn get_formula_info_raw(cache: Arc<Cache>, name: &str) -> Result<Value> {
     Ok(value)
 }
 
-/// Retrieves cask information from the cache or API
-async fn get_cask_info(cache: Arc<Cache>, name: &str) -> Result<Value> {
+async fn get_core_cask_info(cache: Arc<Cache>, name: &str) -> Result<Value> {
     match cache.load_raw("cask.json") {
         Ok(cask_data) => {
             let casks: Vec<Value> = serde_json::from_str(&cask_data).map_err(SpsError::from)?;
@@ -139,7 +288,7 @@ async fn get_cask_info(cache: Arc<Cache>, name: &str) -> Result<Value> {
     }
     tracing::debug!("Fetching cask '{}' directly from API", name);
     // api::fetch_cask returns Value directly now
-    let value = api::fetch_cask(name).await?;
+    let value = api::fetch_cask(name, cache.config()).await?;
     // Store in cache if fetched successfully
     // cache.store_raw(&format!("cask/{}.json", name), &value.to_string())?; // Example of storing
     // individually
@@ -185,7 +334,7 @@ fn print_formula_info(_name: &str, formula: &Value) {
     table.add_row(prettytable::row!["Version", version_str]);
     table.add_row(prettytable::row!["License", license]);
     table.add_row(prettytable::row!["Homepage", homepage]);
-    table.printstd();
+    print_table(&table);
 
     // Detailed sections
     if let Some(desc) = formula.get("desc").and_then(|d| d.as_str()) {
@@ -236,7 +385,7 @@ fn print_formula_info(_name: &str, formula: &Value) {
 
     if has_deps {
         println!("\n{}", "Dependencies".blue().bold());
-        dep_table.printstd();
+        print_table(&dep_table);
     }
 
     // Installation hint
@@ -282,7 +431,7 @@ fn print_cask_info(name: &str, cask: &Value) {
             table.add_row(prettytable::row!["SHA256", sha]);
         }
     }
-    table.printstd();
+    print_table(&table);
 
     // Dependencies Section
     if let Some(deps) = cask.get("depends_on").and_then(|d| d.as_object()) {
@@ -332,7 +481,7 @@ fn print_cask_info(name: &str, cask: &Value) {
 
         if has_deps {
             println!("\n{}", "Dependencies".blue().bold());
-            dep_table.printstd();
+            print_table(&dep_table);
         }
     }
 
@@ -1,14 +1,27 @@
 // sps-cli/src/cli/install.rs
 
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use clap::Args;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
 use sps_common::cache::Cache;
-use sps_common::config::Config;
-use sps_common::error::Result;
+use sps_common::config::{BuildGenerator, Config};
+use sps_common::error::{Result, SpsError};
+use sps_common::model::formula::Formula;
+use sps_common::model::InstallTargetIdentifier;
+use sps_common::pipeline::PlannedJob;
+use sps_core::build::bottle;
+use sps_core::install::cask;
+use sps_core::{build, install};
 use tracing::instrument;
 
+use crate::cli::uninstall::format_size;
 // Import pipeline components from the new module
+use crate::pipeline::plan_json::JsonPlan;
+use crate::pipeline::planner::fetch_target_definitions;
 use crate::pipeline::runner::{self, CommandType, PipelineFlags};
 
 // Keep the Args struct specific to 'install' if needed, or reuse a common one
@@ -28,34 +41,230 @@ pub struct InstallArgs {
     include_optional: bool,
     #[arg(long)]
     skip_recommended: bool,
+    #[arg(long, help = "Also install the test dependencies of the formulae")]
+    include_test: bool,
     #[arg(
         long,
-        help = "Force building the formula from source, even if a bottle is available"
+        conflicts_with_all = ["from_app", "build_bottle"],
+        help = "Install only the dependencies of the named packages, not the packages themselves"
     )]
-    build_from_source: bool,
-    // Worker/Queue size flags might belong here or be global CLI flags
-    // #[arg(long, value_name = "sps_WORKERS")]
-    // max_workers: Option<usize>,
-    // #[arg(long, value_name = "sps_QUEUE")]
-    // queue_size: Option<usize>,
+    only_dependencies: bool,
+    #[arg(
+        long,
+        value_name = "FORMULA",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true,
+        help = "Build from source even if a bottle is available: everything, or with \
+                `=name,...` only the listed formulae, targets or dependencies"
+    )]
+    build_from_source: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Replace files in the prefix that belong to another formula or were not created by sps instead of failing to link"
+    )]
+    overwrite: bool,
+    #[arg(
+        long,
+        value_name = "ARCHIVE",
+        conflicts_with = "cask",
+        help = "Build the formula from a local source archive instead of downloading its source"
+    )]
+    build_from_local: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Reinstall targets that are already installed, e.g. to repair a damaged keg or \
+                app, and install casks even if their declared macOS version range excludes \
+                this system"
+    )]
+    force: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "cask",
+        help = "Adopt an existing .app bundle as the install of the named cask instead of downloading"
+    )]
+    from_app: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with = "from_app",
+        help = "Print the install plan as JSON and exit without downloading or installing"
+    )]
+    json_plan: bool,
+    #[arg(
+        short = 'n',
+        long,
+        conflicts_with_all = ["from_app", "json_plan"],
+        help = "Print the jobs that would run and exit without downloading or installing"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["from_app", "json_plan", "dry_run", "build_bottle"],
+        help = "Plan the install and download everything it needs into the cache, but install \
+                nothing; packages already installed here are not fetched"
+    )]
+    download_only: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["cask", "from_app", "json_plan", "dry_run"],
+        help = "Build each named formula from source in a staging prefix and package it as a \
+                bottle; its dependencies are installed, the formula itself is not"
+    )]
+    build_bottle: bool,
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "build_bottle",
+        help = "Directory to write bottles to (default: current directory)"
+    )]
+    bottle_dir: Option<PathBuf>,
+    #[command(flatten)]
+    options: InstallOptionArgs,
+    #[command(flatten)]
+    limits: ConcurrencyArgs,
+}
+
+/// Build, pour and cask options shared by `install`, `reinstall` and `upgrade`. Unset
+/// flags leave the corresponding [`Config`] settings as they are.
+#[derive(Debug, Args)]
+#[command(about = None, long_about = None)]
+pub struct InstallOptionArgs {
+    #[arg(
+        long,
+        value_name = "GENERATOR",
+        help = "Build tool for CMake and Meson source builds: ninja or make \
+                (default: ninja if installed, or SPS_BUILD_GENERATOR)"
+    )]
+    pub generator: Option<BuildGenerator>,
+    #[arg(
+        long,
+        help = "Build Go formulae from their vendor/ directory without network access \
+                (or SPS_OFFLINE=1); other build systems still download what they need"
+    )]
+    pub offline: bool,
+    #[arg(
+        long,
+        help = "Pour bottles without rewriting the paths compiled into them (or SPS_SKIP_RELOCATION=1)"
+    )]
+    pub skip_relocation: bool,
+    #[arg(
+        long,
+        help = "Skip bottle checksum verification (debugging only; unsafe)"
+    )]
+    pub no_verify: bool,
+    #[arg(
+        long,
+        help = "Check the code signature of cask apps before installing them (or SPS_VERIFY_SIGNATURE=1)"
+    )]
+    pub verify_signature: bool,
+    #[arg(
+        long,
+        conflicts_with = "force_quarantine",
+        help = "Do not mark cask downloads and apps as quarantined (or SPS_NO_QUARANTINE=1). \
+                Gatekeeper then never checks them on first launch; only use this for software you trust"
+    )]
+    pub no_quarantine: bool,
+    #[arg(
+        long,
+        help = "Also quarantine the executables of installed formulae so Gatekeeper checks them \
+                (or SPS_FORCE_QUARANTINE=1); unsigned binaries may then refuse to run"
+    )]
+    pub force_quarantine: bool,
+}
+
+impl InstallOptionArgs {
+    /// Applies these flags to `config`. `--no-verify` is carried in the pipeline flags
+    /// instead.
+    pub fn apply(&self, config: &mut Config) {
+        config.verify_signature |= self.verify_signature;
+        config.quarantine = config
+            .quarantine
+            .with_flags(self.no_quarantine, self.force_quarantine);
+        config.build_generator = self.generator.or(config.build_generator);
+        config.offline_build |= self.offline;
+        config.skip_relocation |= self.skip_relocation;
+    }
+}
+
+/// Concurrency overrides shared by the pipeline commands. Unset flags fall back to
+/// the limits in [`Config`], which already account for environment variables.
+#[derive(Debug, Args)]
+#[command(about = None, long_about = None)]
+pub struct ConcurrencyArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Maximum number of concurrent downloads (default: 4, or SPS_MAX_DOWNLOADS)"
+    )]
+    pub max_downloads: Option<usize>,
+    #[arg(
+        long,
+        visible_alias = "max-workers",
+        value_name = "N",
+        help = "Packages to install or build at once (default: CPU count, or SPS_JOBS)"
+    )]
+    pub jobs: Option<usize>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Parallel make jobs within a source build (default: CPU count, or SPS_BUILD_JOBS)"
+    )]
+    pub build_jobs: Option<usize>,
+}
+
+impl ConcurrencyArgs {
+    /// Returns a copy of `config` with these flags applied on top of its limits.
+    pub fn apply(&self, config: &Config) -> Config {
+        let limits = config
+            .limits
+            .with_overrides(self.max_downloads, self.jobs, self.build_jobs);
+        Config {
+            limits,
+            ..config.clone()
+        }
+    }
 }
 
 impl InstallArgs {
+    /// Whether the command only prints a plan (`--dry-run`, `--json-plan`).
+    pub fn plans_only(&self) -> bool {
+        self.dry_run || self.json_plan
+    }
+
     #[instrument(skip(self, config, cache), fields(targets = ?self.names))]
     pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         // --- Argument Validation (moved from old run) ---
         if self.formula && self.cask {
-            return Err(sps_common::error::SpsError::Generic(
+            return Err(SpsError::Generic(
                 "Cannot use --formula and --cask together.".to_string(),
             ));
         }
         // Add validation for skip_deps if needed
+        let build_from_local = match &self.build_from_local {
+            Some(archive) => Some((self.names[0].clone(), self.validate_local_archive(archive)?)),
+            None => None,
+        };
+
+        if let Some(app_path) = &self.from_app {
+            return self.adopt_app(app_path, config, cache).await;
+        }
 
         // --- Prepare Pipeline Flags ---
         let flags = PipelineFlags {
-            build_from_source: self.build_from_source,
+            build_from_source: self
+                .build_from_source
+                .as_ref()
+                .is_some_and(|names| names.is_empty()),
+            build_from_source_formulae: self.build_from_source.iter().flatten().cloned().collect(),
             include_optional: self.include_optional,
             skip_recommended: self.skip_recommended,
+            include_test: self.include_test,
+            only_dependencies: self.only_dependencies || self.build_bottle,
+            force: self.force,
+            no_verify: self.options.no_verify,
+            build_from_local,
+            only_type: None,
             // Add other flags...
         };
 
@@ -63,14 +272,324 @@ impl InstallArgs {
         // (This logic might be better inside plan_package_operations based on CommandType)
         let initial_targets = self.names.clone(); // For install, all names are initial targets
 
+        let mut config = self.limits.apply(config);
+        self.options.apply(&mut config);
+        config.overwrite_links |= self.overwrite;
+        if self.json_plan {
+            return print_json_plan(&initial_targets, &config, cache, &flags).await;
+        }
+        if self.dry_run {
+            return print_dry_run(
+                &initial_targets,
+                CommandType::Install,
+                &config,
+                cache,
+                &flags,
+            )
+            .await;
+        }
+
+        if self.download_only {
+            return download_plan(&initial_targets, &config, cache, &flags).await;
+        }
+
+        let bottle_targets = if self.build_bottle {
+            self.bottle_targets(cache.clone()).await?
+        } else {
+            Vec::new()
+        };
+
         // --- Execute the Pipeline ---
         runner::run_pipeline(
             &initial_targets,
             CommandType::Install, // Specify the command type
-            config,
+            &config,
             cache,
             &flags, // Pass the flags struct
         )
-        .await
+        .await?;
+
+        if self.build_bottle {
+            let output_dir = match &self.bottle_dir {
+                Some(dir) => dir.clone(),
+                None => std::env::current_dir()?,
+            };
+            for formula in &bottle_targets {
+                let local_source = flags
+                    .build_from_local
+                    .as_ref()
+                    .filter(|(name, _)| name == formula.name())
+                    .map(|(_, archive)| archive.as_path());
+                let bottle =
+                    bottle::build_bottle(formula, local_source, &config, &output_dir).await?;
+                println!(
+                    "✓ Bottled {} {} ({}, cellar {})",
+                    bottle.name.green(),
+                    bottle.version,
+                    bottle.tag,
+                    bottle.cellar
+                );
+                println!("  {}", bottle.path.display());
+                println!("  sha256 {}", bottle.sha256);
+            }
+        }
+        Ok(())
+    }
+
+    /// The formulae `--build-bottle` builds and packages once their dependencies are
+    /// installed.
+    async fn bottle_targets(&self, cache: Arc<Cache>) -> Result<Vec<Arc<Formula>>> {
+        let mut definitions = fetch_target_definitions(&self.names, cache).await;
+        let mut formulae = Vec::new();
+        for name in &self.names {
+            let formula = match definitions.remove(name) {
+                Some(Ok(InstallTargetIdentifier::Formula(formula))) => formula,
+                Some(Ok(InstallTargetIdentifier::Cask(_))) => {
+                    return Err(SpsError::Generic(format!(
+                        "'{name}' is a cask; only formulae can be bottled."
+                    )));
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Err(SpsError::NotFound(format!("No formula named '{name}'"))),
+            };
+            formulae.push(formula);
+        }
+        Ok(formulae)
+    }
+
+    /// `--build-from-local` supplies the source for exactly one formula. Returns the
+    /// archive as an absolute path, since the build runs in another directory.
+    fn validate_local_archive(&self, archive: &Path) -> Result<PathBuf> {
+        if self.names.len() != 1 {
+            return Err(SpsError::Generic(
+                "--build-from-local takes exactly one formula name or formula file.".to_string(),
+            ));
+        }
+        if !archive.is_file() {
+            return Err(SpsError::NotFound(format!(
+                "Source archive {} does not exist",
+                archive.display()
+            )));
+        }
+        Ok(std::fs::canonicalize(archive)?)
+    }
+
+    /// Handles `--cask <token> --from-app <path>`: registers an app that is already
+    /// on disk as the cask's installation without downloading anything.
+    async fn adopt_app(&self, app_path: &Path, config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let [token] = self.names.as_slice() else {
+            return Err(SpsError::Generic(
+                "--from-app takes exactly one cask token.".to_string(),
+            ));
+        };
+        let cask = match fetch_target_definitions(std::slice::from_ref(token), cache)
+            .await
+            .remove(token)
+        {
+            Some(Ok(InstallTargetIdentifier::Cask(cask))) => cask,
+            Some(Ok(InstallTargetIdentifier::Formula(_))) => {
+                return Err(SpsError::Generic(format!(
+                    "'{token}' is a formula; --from-app only works for casks."
+                )));
+            }
+            Some(Err(e)) => return Err(e),
+            None => return Err(SpsError::NotFound(format!("No cask named '{token}'"))),
+        };
+        if cask.is_installed(config) {
+            return Err(SpsError::Generic(format!(
+                "Cask '{token}' is already installed; uninstall it before adopting {}.",
+                app_path.display()
+            )));
+        }
+
+        let mismatches = cask::adopt::adopt_app(&cask, app_path, config)?;
+        for mismatch in &mismatches {
+            eprintln!("{} {}", "Warning:".yellow().bold(), mismatch);
+        }
+        println!("✓ Adopted {} as {}", app_path.display(), token.green());
+        Ok(())
+    }
+}
+
+/// Prints the plan for `--json-plan`. Planning errors are part of the JSON; they also
+/// make the command fail so scripts can rely on the exit status.
+async fn print_json_plan(
+    targets: &[String],
+    config: &Config,
+    cache: Arc<Cache>,
+    flags: &PipelineFlags,
+) -> Result<()> {
+    let ops = runner::plan_pipeline(targets, CommandType::Install, config, cache, flags).await?;
+    let plan = JsonPlan::from_operations(&ops, config);
+    let json =
+        serde_json::to_string_pretty(&plan).map_err(|e| SpsError::Json(std::sync::Arc::new(e)))?;
+    println!("{json}");
+    if plan.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(SpsError::Generic(format!(
+            "Planning failed for {} target(s).",
+            plan.errors.len()
+        )))
+    }
+}
+
+/// Downloads and verifies every bottle, source archive, resource and cask the plan for
+/// `targets` would use, leaving them in the cache for a later (possibly offline) install.
+async fn download_plan(
+    targets: &[String],
+    config: &Config,
+    cache: Arc<Cache>,
+    flags: &PipelineFlags,
+) -> Result<()> {
+    let ops =
+        runner::plan_pipeline(targets, CommandType::Install, config, cache.clone(), flags).await?;
+    for (target, error) in &ops.errors {
+        eprintln!("✖ {}: {}", target.red(), error);
+    }
+    let jobs: Vec<&PlannedJob> = ops
+        .jobs
+        .iter()
+        .filter(|job| !ops.already_installed_or_up_to_date.contains(&job.target_id))
+        .filter(|job| job.use_private_store_source.is_none())
+        .collect();
+    let http_client = sps_net::http::shared_client()?;
+
+    let results: Vec<(&PlannedJob, Result<Vec<PathBuf>>)> = stream::iter(jobs)
+        .map(|job| {
+            let (cache, http_client) = (cache.as_ref(), &http_client);
+            async move {
+                let paths = download_job(job, config, cache, http_client, flags.no_verify).await;
+                (job, paths)
+            }
+        })
+        .buffer_unordered(config.limits.max_downloads.max(1))
+        .collect()
+        .await;
+
+    let mut total_bytes = 0;
+    let mut file_count = 0;
+    let mut failed = Vec::new();
+    for (job, result) in results {
+        match result {
+            Ok(paths) => {
+                for path in paths {
+                    total_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    file_count += 1;
+                    println!("✓ {} {}", job.target_id.green(), path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("✖ Failed to fetch '{}': {}", job.target_id.cyan(), e);
+                failed.push(job.target_id.clone());
+            }
+        }
+    }
+    println!(
+        "Fetched {} file(s), {} in the cache",
+        file_count,
+        format_size(total_bytes).green()
+    );
+
+    if !failed.is_empty() {
+        return Err(SpsError::DownloadError(
+            failed.join(", "),
+            String::new(),
+            format!("{} download(s) failed", failed.len()),
+        ));
+    }
+    match ops.errors.into_iter().next() {
+        Some((_, error)) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// The files one planned job needs: its bottle, its source archive and resources, or
+/// its cask artifact. Each is verified against its checksum when downloaded.
+async fn download_job(
+    job: &PlannedJob,
+    config: &Config,
+    cache: &Cache,
+    http_client: &reqwest::Client,
+    skip_verify: bool,
+) -> Result<Vec<PathBuf>> {
+    match &job.target_definition {
+        InstallTargetIdentifier::Formula(formula) if job.is_source_build => {
+            let mut paths = vec![build::compile::download_source(formula, config, None).await?];
+            paths.extend(
+                build::compile::download_resources(formula, config)
+                    .await?
+                    .into_iter()
+                    .map(|(_, path)| path),
+            );
+            Ok(paths)
+        }
+        InstallTargetIdentifier::Formula(formula) => {
+            let path = install::bottle::exec::download_bottle(
+                formula,
+                config,
+                http_client,
+                None,
+                skip_verify,
+            )
+            .await?;
+            Ok(vec![path])
+        }
+        InstallTargetIdentifier::Cask(cask) => Ok(vec![
+            install::cask::download_cask(cask, config, cache, None).await?,
+        ]),
+    }
+}
+
+/// Prints the plan for `--dry-run`, one job per line in the order they would run, with
+/// the action, version and whether a bottle or the source would be used.
+pub(crate) async fn print_dry_run(
+    targets: &[String],
+    command_type: CommandType,
+    config: &Config,
+    cache: Arc<Cache>,
+    flags: &PipelineFlags,
+) -> Result<()> {
+    let ops = runner::plan_pipeline(targets, command_type, config, cache, flags).await?;
+    let plan = JsonPlan::from_operations(&ops, config);
+    let (up_to_date, jobs): (Vec<_>, Vec<_>) = plan.jobs.iter().partition(|job| job.up_to_date);
+    if jobs.is_empty() {
+        println!("Nothing to do.");
+    } else {
+        println!("{} {} job(s):", "Would run".bold(), jobs.len());
+    }
+    for job in &jobs {
+        let version = match (&job.from_version, &job.version) {
+            (Some(from), Some(to)) if job.action == "upgrade" => format!("{from} -> {to}"),
+            (_, Some(version)) => version.clone(),
+            (_, None) => String::new(),
+        };
+        let method = match job.kind {
+            "cask" => "cask",
+            _ if job.local_source.is_some() => "local source",
+            _ if job.source_build => "source",
+            _ => "bottle",
+        };
+        println!(
+            "  {:<10} {} {} ({})",
+            job.action,
+            job.target.cyan(),
+            version,
+            method.dimmed()
+        );
+    }
+    for job in &up_to_date {
+        println!("  {:<10} {}", "up to date", job.target.dimmed());
+    }
+    for error in &plan.errors {
+        eprintln!("✖ {}: {}", error.target.red(), error.message);
+    }
+    if plan.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(SpsError::Generic(format!(
+            "Planning failed for {} target(s).",
+            plan.errors.len()
+        )))
     }
 }
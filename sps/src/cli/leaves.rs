@@ -0,0 +1,75 @@
+// sps/src/cli/leaves.rs
+//! Contains the logic for the `leaves` command, which lists installed formulae that no
+//! other installed formula needs at runtime.
+//!
+//! A dependency only needed to build or test something does not keep a formula off the
+//! list, since nothing breaks when it is removed.
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+use clap::Args;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::dependency::DependencyTag;
+use sps_common::error::Result;
+use sps_common::formulary::load_tapped_formula;
+use sps_common::keg::{InstalledKeg, KegRegistry};
+
+use crate::pipeline::planner::load_or_fetch_formulae_map;
+
+#[derive(Args, Debug)]
+pub struct Leaves {
+    /// Only list formulae that were installed by name, not pulled in as dependencies
+    #[arg(long)]
+    pub installed_on_request: bool,
+}
+
+impl Leaves {
+    pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let kegs = KegRegistry::new(config.clone()).list_installed_kegs()?;
+        let installed: BTreeSet<&str> = kegs.iter().map(|keg| keg.name.as_str()).collect();
+        if installed.is_empty() {
+            return Ok(());
+        }
+
+        let formulae = load_or_fetch_formulae_map(cache).await?;
+        let mut needed: HashSet<String> = HashSet::new();
+        for &name in &installed {
+            let dependencies = match formulae.get(name) {
+                Some(formula) => formula.dependencies()?,
+                None => match load_tapped_formula(config, name)? {
+                    Some(formula) => formula.dependencies()?,
+                    None => {
+                        tracing::debug!("No definition for installed formula '{}'", name);
+                        continue;
+                    }
+                },
+            };
+            needed.extend(
+                dependencies
+                    .into_iter()
+                    .filter(|dep| {
+                        !dep.tags
+                            .intersects(DependencyTag::BUILD | DependencyTag::TEST)
+                    })
+                    .map(|dep| dep.name),
+            );
+        }
+
+        for name in installed.into_iter().filter(|name| !needed.contains(*name)) {
+            if self.installed_on_request && !on_request(name, &kegs) {
+                continue;
+            }
+            println!("{name}");
+        }
+        Ok(())
+    }
+}
+
+/// Whether any keg of `name` was installed by name. Kegs installed before sps recorded
+/// this count as installed by name, so they are never mistaken for leftovers.
+fn on_request(name: &str, kegs: &[InstalledKeg]) -> bool {
+    kegs.iter()
+        .filter(|keg| keg.name == name)
+        .any(|keg| keg.installed_on_request().unwrap_or(true))
+}
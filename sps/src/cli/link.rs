@@ -0,0 +1,203 @@
+// sps/src/cli/link.rs
+//! Contains the logic for the `link` and `unlink` commands.
+//!
+//! Both act on kegs that are already in the Cellar: `link` creates a keg's `opt` link,
+//! prefix symlinks and bin wrappers, replacing those of any other linked version of
+//! the formula; `unlink` removes what the keg's install manifest records.
+use std::fs;
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::formulary::Formulary;
+use sps_common::keg::{InstalledKeg, KegRegistry};
+use sps_core::install::bottle::link;
+
+#[derive(Args, Debug)]
+// `--version` selects a keg here, so the global version flag is turned off.
+#[command(disable_version_flag = true)]
+pub struct Link {
+    /// The installed formula to link
+    pub name: String,
+
+    /// Link this installed version instead of the newest one
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
+
+    /// Replace files in the prefix that belong to something else
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Link a keg-only formula into the prefix anyway
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print the links that would be created without changing anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct Unlink {
+    /// The linked formula to unlink
+    pub name: String,
+
+    /// Print the links that would be removed without changing anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+impl Link {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let kegs = installed_kegs(&self.name, config)?;
+        let keg = match &self.version {
+            Some(version) => kegs
+                .iter()
+                .find(|keg| &keg.version_str == version)
+                .cloned()
+                .ok_or_else(|| {
+                    SpsError::NotFound(format!(
+                        "{} {} is not installed (installed: {})",
+                        self.name,
+                        version,
+                        versions(&kegs)
+                    ))
+                })?,
+            None => KegRegistry::new(config.clone())
+                .get_installed_keg(&self.name)?
+                .ok_or_else(|| {
+                    SpsError::NotFound(format!("Formula '{}' is not installed", self.name))
+                })?,
+        };
+
+        let formula = Formulary::new(config.clone()).load_formula(&self.name)?;
+        if let Some(caveats) = link::keg_only_caveats(&formula, &keg.path, config) {
+            if !self.force {
+                println!("{caveats}");
+                println!("\nUse --force to link it into the prefix anyway.");
+                return Ok(());
+            }
+        }
+
+        let planned = link::planned_links(&formula, &keg.path, config)?;
+        let conflicts = link::link_conflicts(&formula, &planned, config);
+        let previous = linked_keg(&kegs, config).filter(|linked| linked.path != keg.path);
+
+        if self.dry_run {
+            if let Some(previous) = previous {
+                println!("Would unlink {} {}", self.name, previous.version_str);
+            }
+            for planned in &planned {
+                let kind = if planned.is_wrapper {
+                    "wrapper"
+                } else {
+                    "link"
+                };
+                println!(
+                    "Would {} {} -> {}",
+                    kind,
+                    planned.link.display(),
+                    planned.target.display()
+                );
+            }
+            for conflict in &conflicts {
+                let action = if self.overwrite {
+                    "overwrite"
+                } else {
+                    "conflict with"
+                };
+                println!("Would {action} {conflict}");
+            }
+            return Ok(());
+        }
+
+        if !conflicts.is_empty() && !self.overwrite {
+            for conflict in &conflicts {
+                eprintln!("✖ {conflict} already exists");
+            }
+            return Err(SpsError::Generic(format!(
+                "Cannot link {}: {} file(s) in the way. Use --overwrite to replace them.",
+                self.name,
+                conflicts.len()
+            )));
+        }
+
+        if let Some(previous) = previous {
+            link::unlink_formula_artifacts(&self.name, &previous.version_str, config)?;
+            println!("✓ Unlinked {} {}", self.name, previous.version_str);
+        }
+        if let Err(e) = link::force_link_formula_artifacts(&formula, &keg.path, config) {
+            // Linking rolled itself back; put the previously linked version back too.
+            if let Some(previous) = previous {
+                if link::force_link_formula_artifacts(&formula, &previous.path, config).is_ok() {
+                    eprintln!("Relinked {} {}", self.name, previous.version_str);
+                }
+            }
+            return Err(e);
+        }
+        println!(
+            "✓ Linked {} {} ({} links)",
+            self.name.green(),
+            keg.version_str,
+            planned.len()
+        );
+        Ok(())
+    }
+}
+
+impl Unlink {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let kegs = installed_kegs(&self.name, config)?;
+        let Some(keg) = linked_keg(&kegs, config) else {
+            println!("{} is not linked", self.name.cyan());
+            return Ok(());
+        };
+
+        if self.dry_run {
+            for path in link::read_install_manifest(&keg.path) {
+                if path.symlink_metadata().is_ok() {
+                    println!("Would remove {}", path.display());
+                }
+            }
+            return Ok(());
+        }
+
+        link::unlink_formula_artifacts(&self.name, &keg.version_str, config)?;
+        println!("✓ Unlinked {} {}", self.name.green(), keg.version_str);
+        Ok(())
+    }
+}
+
+/// The installed kegs of a formula; an error if there are none.
+fn installed_kegs(name: &str, config: &Config) -> Result<Vec<InstalledKeg>> {
+    let mut kegs: Vec<InstalledKeg> = KegRegistry::new(config.clone())
+        .list_installed_kegs()?
+        .into_iter()
+        .filter(|keg| keg.name == name)
+        .collect();
+    if kegs.is_empty() {
+        return Err(SpsError::NotFound(format!(
+            "Formula '{name}' is not installed"
+        )));
+    }
+    kegs.sort_by(|a, b| a.version_str.cmp(&b.version_str));
+    Ok(kegs)
+}
+
+fn versions(kegs: &[InstalledKeg]) -> String {
+    kegs.iter()
+        .map(|keg| keg.version_str.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The keg the formula's `opt` link currently points into, if any.
+fn linked_keg<'a>(kegs: &'a [InstalledKeg], config: &Config) -> Option<&'a InstalledKeg> {
+    let name = &kegs.first()?.name;
+    let target = fs::canonicalize(config.formula_opt_path(name)).ok()?;
+    kegs.iter()
+        .find(|keg| fs::canonicalize(&keg.path).is_ok_and(|keg_path| target.starts_with(keg_path)))
+}
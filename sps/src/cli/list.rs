@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 use clap::Args;
@@ -9,9 +10,12 @@ use sps_common::config::Config;
 use sps_common::error::Result;
 use sps_common::formulary::Formulary;
 use sps_core::check::installed::{get_installed_packages, PackageType};
+use sps_core::check::pinned::get_pinned_packages;
 use sps_core::check::update::check_for_updates;
 use sps_core::check::InstalledPackageInfo;
 
+use crate::cli::ui::print_table;
+
 #[derive(Args, Debug)]
 pub struct List {
     /// Show only formulas
@@ -28,6 +32,7 @@ pub struct List {
 impl List {
     pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         let installed = get_installed_packages(config).await?;
+        let pinned = get_pinned_packages(config)?;
         // Only show the latest version for each name
         use std::collections::HashMap;
         let mut formula_map: HashMap<&str, &sps_core::check::installed::InstalledPackageInfo> =
@@ -63,27 +68,27 @@ impl List {
         // If user wants to show installed formulas only.
         if self.formula_only {
             if self.outdated_only {
-                self.print_outdated_formulas_table(&formulas, config)
+                self.print_outdated_formulas_table(&formulas, &pinned, config)
                     .await?;
             } else {
-                self.print_formulas_table(formulas, config);
+                self.print_formulas_table(formulas, &pinned, config);
             }
             return Ok(());
         }
         // If user wants to show installed casks only.
         if self.cask_only {
             if self.outdated_only {
-                self.print_outdated_casks_table(&casks, cache.clone())
+                self.print_outdated_casks_table(&casks, &pinned, cache.clone())
                     .await?;
             } else {
-                self.print_casks_table(casks, cache);
+                self.print_casks_table(casks, &pinned, cache);
             }
             return Ok(());
         }
 
         // If user wants to show only outdated packages
         if self.outdated_only {
-            self.print_outdated_all_table(&formulas, &casks, config, cache)
+            self.print_outdated_all_table(&formulas, &casks, &pinned, config, cache)
                 .await?;
             return Ok(());
         }
@@ -97,6 +102,7 @@ impl List {
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("New Version?").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
         let mut formula_count = 0;
         let mut cask_count = 0;
@@ -116,6 +122,7 @@ impl List {
                 // TODO: update to display the latest version string.
                 // TODO: Not showing when the using --all flag.
                 Cell::new(if has_new { "✔" } else { "" }),
+                pinned_cell(&pinned, &pkg.name),
             ]));
             formula_count += 1;
         }
@@ -139,10 +146,11 @@ impl List {
                 Cell::new(&pkg.name).style_spec("Fb"),
                 Cell::new(&pkg.version),
                 Cell::new(if has_new { "✔" } else { "" }),
+                pinned_cell(&pinned, &pkg.name),
             ]));
             cask_count += 1;
         }
-        table.printstd();
+        print_table(&table);
         if formula_count > 0 && cask_count > 0 {
             println!(
                 "{}",
@@ -159,6 +167,7 @@ impl List {
     fn print_formulas_table(
         &self,
         formulas: Vec<&sps_core::check::installed::InstalledPackageInfo>,
+        pinned: &BTreeSet<String>,
         config: &Config,
     ) {
         if formulas.is_empty() {
@@ -174,11 +183,12 @@ impl List {
             format::Alignment::CENTER,
         )
         .style_spec("bFg")
-        .with_hspan(3)]));
+        .with_hspan(4)]));
         table.add_row(Row::new(vec![
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("New Version?").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
         let mut formula_count = 0;
         for pkg in formulas {
@@ -194,16 +204,18 @@ impl List {
                 Cell::new(&pkg.name).style_spec("Fb"),
                 Cell::new(&pkg.version),
                 Cell::new(if has_new { "✔" } else { "" }),
+                pinned_cell(pinned, &pkg.name),
             ]));
             formula_count += 1;
         }
-        table.printstd();
+        print_table(&table);
         println!("{}", format!("{formula_count} formulas installed").bold());
     }
 
     fn print_casks_table(
         &self,
         casks: Vec<&sps_core::check::installed::InstalledPackageInfo>,
+        pinned: &BTreeSet<String>,
         cache: Arc<Cache>,
     ) {
         if casks.is_empty() {
@@ -218,11 +230,12 @@ impl List {
             format::Alignment::CENTER,
         )
         .style_spec("bFg")
-        .with_hspan(3)]));
+        .with_hspan(4)]));
         table.add_row(Row::new(vec![
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("New Version?").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
         let mut cask_count = 0;
         for pkg in casks {
@@ -244,16 +257,18 @@ impl List {
                 Cell::new(&pkg.name).style_spec("Fb"),
                 Cell::new(&pkg.version),
                 Cell::new(if has_new { "✔" } else { "" }),
+                pinned_cell(pinned, &pkg.name),
             ]));
             cask_count += 1;
         }
-        table.printstd();
+        print_table(&table);
         println!("{}", format!("{cask_count} casks installed").bold());
     }
 
     async fn print_outdated_formulas_table(
         &self,
         formulas: &[&InstalledPackageInfo],
+        pinned: &BTreeSet<String>,
         config: &Config,
     ) -> Result<()> {
         if formulas.is_empty() {
@@ -279,11 +294,12 @@ impl List {
             format::Alignment::CENTER,
         )
         .style_spec("bFg")
-        .with_hspan(3)]));
+        .with_hspan(4)]));
         table.add_row(Row::new(vec![
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("Available").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
 
         let mut count = 0;
@@ -292,11 +308,12 @@ impl List {
                 Cell::new(&update.name).style_spec("Fb"),
                 Cell::new(&update.installed_version),
                 Cell::new(&update.available_version).style_spec("Fg"),
+                pinned_cell(pinned, &update.name),
             ]));
             count += 1;
         }
 
-        table.printstd();
+        print_table(&table);
         println!("{}", format!("{count} outdated formulas").bold());
         Ok(())
     }
@@ -304,6 +321,7 @@ impl List {
     async fn print_outdated_casks_table(
         &self,
         casks: &[&InstalledPackageInfo],
+        pinned: &BTreeSet<String>,
         cache: Arc<Cache>,
     ) -> Result<()> {
         if casks.is_empty() {
@@ -328,11 +346,12 @@ impl List {
             format::Alignment::CENTER,
         )
         .style_spec("bFg")
-        .with_hspan(3)]));
+        .with_hspan(4)]));
         table.add_row(Row::new(vec![
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("Available").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
 
         let mut count = 0;
@@ -341,11 +360,12 @@ impl List {
                 Cell::new(&update.name).style_spec("Fb"),
                 Cell::new(&update.installed_version),
                 Cell::new(&update.available_version).style_spec("Fy"),
+                pinned_cell(pinned, &update.name),
             ]));
             count += 1;
         }
 
-        table.printstd();
+        print_table(&table);
         println!("{}", format!("{count} outdated casks").bold());
         Ok(())
     }
@@ -354,6 +374,7 @@ impl List {
         &self,
         formulas: &[&InstalledPackageInfo],
         casks: &[&InstalledPackageInfo],
+        pinned: &BTreeSet<String>,
         config: &Config,
         cache: Arc<Cache>,
     ) -> Result<()> {
@@ -381,6 +402,7 @@ impl List {
             Cell::new("Name").style_spec("b"),
             Cell::new("Installed").style_spec("b"),
             Cell::new("Available").style_spec("b"),
+            Cell::new("Pinned").style_spec("b"),
         ]));
 
         let mut formula_count = 0;
@@ -403,10 +425,11 @@ impl List {
                 Cell::new(&update.name).style_spec("Fb"),
                 Cell::new(&update.installed_version),
                 Cell::new(&update.available_version).style_spec("Fg"),
+                pinned_cell(pinned, &update.name),
             ]));
         }
 
-        table.printstd();
+        print_table(&table);
         if formula_count > 0 && cask_count > 0 {
             println!(
                 "{}",
@@ -420,3 +443,11 @@ impl List {
         Ok(())
     }
 }
+
+fn pinned_cell(pinned: &BTreeSet<String>, name: &str) -> Cell {
+    if pinned.contains(name) {
+        Cell::new("pinned").style_spec("Fm")
+    } else {
+        Cell::new("")
+    }
+}
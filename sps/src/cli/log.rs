@@ -0,0 +1,47 @@
+// sps/src/cli/log.rs
+//! Contains the logic for the `log` command.
+//!
+//! Source builds write every stage's command and output to
+//! `<logs_dir>/<formula>-build.log`; each build of a formula replaces its log.
+use std::fs;
+use std::sync::Arc;
+
+use clap::Args;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+
+#[derive(Args, Debug)]
+pub struct Log {
+    /// The formula whose latest source build log to show
+    pub name: String,
+
+    /// Print the path of the log instead of its contents
+    #[arg(long)]
+    pub path: bool,
+}
+
+impl Log {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        if self.name.contains('/') || self.name.contains("..") {
+            return Err(SpsError::Generic(format!(
+                "Invalid formula name '{}'",
+                self.name
+            )));
+        }
+        let log_path = config.build_log_path(&self.name);
+        if !log_path.is_file() {
+            return Err(SpsError::NotFound(format!(
+                "No build log for '{}' (expected {}); logs are written by source builds",
+                self.name,
+                log_path.display()
+            )));
+        }
+        if self.path {
+            println!("{}", log_path.display());
+        } else {
+            print!("{}", String::from_utf8_lossy(&fs::read(&log_path)?));
+        }
+        Ok(())
+    }
+}
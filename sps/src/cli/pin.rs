@@ -0,0 +1,66 @@
+// sps/src/cli/pin.rs
+//! Contains the logic for the `pin` and `unpin` commands.
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_core::check::{installed, pinned};
+
+#[derive(Args, Debug)]
+pub struct Pin {
+    /// The names of the installed packages to pin at their current version
+    #[arg(required = true)]
+    pub names: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Unpin {
+    /// The names of the pinned packages to release
+    #[arg(required = true)]
+    pub names: Vec<String>,
+}
+
+impl Pin {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let mut failed = Vec::new();
+        for name in &self.names {
+            match installed::get_installed_package(name, config).await? {
+                Some(info) => {
+                    if pinned::pin_package(name, config)? {
+                        println!("✓ Pinned {} at {}", name.green(), info.version);
+                    } else {
+                        println!("{} is already pinned", name.cyan());
+                    }
+                }
+                None => {
+                    eprintln!("✖ Cannot pin '{}': not installed.", name.cyan());
+                    failed.push(name.clone());
+                }
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(SpsError::NotFound(format!(
+                "Cannot pin packages that are not installed: {}",
+                failed.join(", ")
+            )))
+        }
+    }
+}
+
+impl Unpin {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        for name in &self.names {
+            if pinned::unpin_package(name, config)? {
+                println!("✓ Unpinned {}", name.green());
+            } else {
+                println!("{} is not pinned", name.cyan());
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,18 @@
 // sps-cli/src/cli/reinstall.rs
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::Args;
+use colored::Colorize;
 use sps_common::cache::Cache;
 use sps_common::config::Config;
 use sps_common::error::Result;
+use sps_core::check::diff::{self, InstallSnapshot};
+use sps_core::check::installed;
+use tracing::warn;
 
+use crate::cli::install::{print_dry_run, ConcurrencyArgs, InstallOptionArgs};
 use crate::pipeline::runner::{self, CommandType, PipelineFlags};
 
 #[derive(Args, Debug)]
@@ -18,6 +25,25 @@ pub struct ReinstallArgs {
         help = "Force building the formula from source, even if a bottle is available"
     )]
     pub build_from_source: bool,
+
+    #[arg(
+        long,
+        help = "Report files added, removed or changed by the reinstall (hashes every file)"
+    )]
+    pub verbose_diff: bool,
+
+    #[arg(
+        short = 'n',
+        long,
+        help = "Print the jobs that would run and exit without downloading or installing"
+    )]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub options: InstallOptionArgs,
+
+    #[command(flatten)]
+    pub limits: ConcurrencyArgs,
 }
 
 impl ReinstallArgs {
@@ -25,10 +51,108 @@ impl ReinstallArgs {
         let flags = PipelineFlags {
             // Populate flags from args
             build_from_source: self.build_from_source,
+            build_from_source_formulae: HashSet::new(),
             include_optional: false, // Reinstall usually doesn't change optional deps
             skip_recommended: true,  /* Reinstall usually doesn't change recommended deps
                                       * ... add other common flags if needed ... */
+            include_test: false,
+            only_dependencies: false,
+            force: false,
+            no_verify: self.options.no_verify,
+            build_from_local: None,
+            only_type: None,
+        };
+        let mut config = self.limits.apply(config);
+        self.options.apply(&mut config);
+        if self.dry_run {
+            return print_dry_run(&self.names, CommandType::Reinstall, &config, cache, &flags)
+                .await;
+        }
+        let before = if self.verbose_diff {
+            snapshot_installed(&self.names, &config).await
+        } else {
+            HashMap::new()
+        };
+        let result =
+            runner::run_pipeline(&self.names, CommandType::Reinstall, &config, cache, &flags).await;
+        if self.verbose_diff {
+            print_install_diffs(&before, &config).await;
+        }
+        result
+    }
+}
+
+/// Snapshots the installed files of each of `names` that is installed, for
+/// `--verbose-diff`. Packages that cannot be read are left out with a warning.
+pub(crate) async fn snapshot_installed(
+    names: &[String],
+    config: &Config,
+) -> HashMap<String, InstallSnapshot> {
+    let mut snapshots = HashMap::new();
+    for name in names {
+        let info = match installed::get_installed_package(name, config).await {
+            Ok(Some(info)) => info,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Cannot check install state of {}: {}", name, e);
+                continue;
+            }
+        };
+        match diff::snapshot(&info, config) {
+            Ok(snapshot) => {
+                snapshots.insert(name.clone(), snapshot);
+            }
+            Err(e) => warn!("Cannot snapshot {} before the operation: {}", name, e),
+        }
+    }
+    snapshots
+}
+
+/// Compares each snapshot in `before` with the package as installed now and prints
+/// the difference. Packages that are no longer installed (e.g. a failed job) are
+/// skipped.
+pub(crate) async fn print_install_diffs(
+    before: &HashMap<String, InstallSnapshot>,
+    config: &Config,
+) {
+    let mut names: Vec<&String> = before.keys().collect();
+    names.sort();
+    for name in names {
+        let old = &before[name];
+        let new = match installed::get_installed_package(name, config).await {
+            Ok(Some(info)) => match diff::snapshot(&info, config) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    warn!("Cannot snapshot {} after the operation: {}", name, e);
+                    continue;
+                }
+            },
+            _ => continue,
+        };
+        let changes = diff::diff_snapshots(old, &new);
+        let header = if old.version == new.version {
+            format!("{} {}", name, new.version)
+        } else {
+            format!("{} {} -> {}", name, old.version, new.version)
+        };
+        if changes.is_empty() {
+            println!("{}: no file changes", header.bold());
+            continue;
+        }
+        println!(
+            "{}: {} added, {} removed, {} changed",
+            header.bold(),
+            changes.added.len(),
+            changes.removed.len(),
+            changes.changed.len()
+        );
+        let print_paths = |marker: colored::ColoredString, paths: &[PathBuf]| {
+            for path in paths {
+                println!("  {} {}", marker, path.display());
+            }
         };
-        runner::run_pipeline(&self.names, CommandType::Reinstall, config, cache, &flags).await
+        print_paths("+".green(), &changes.added);
+        print_paths("-".red(), &changes.removed);
+        print_paths("~".yellow(), &changes.changed);
     }
 }
@@ -1,16 +1,23 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use clap::Args;
 use colored::Colorize;
 use prettytable::{format, Cell, Row, Table};
+use regex::Regex;
 use serde_json::Value;
 use sps_common::cache::Cache;
 use sps_common::config::Config;
-use sps_common::error::Result;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::tap::{self, DefinitionKind};
+use sps_core::check::installed::{get_installed_packages, PackageType};
+use sps_core::check::update::check_for_updates;
 use sps_net::api;
 use terminal_size::{terminal_size, Width};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+use crate::cli::ui::print_table;
+
 #[derive(Args, Debug)]
 pub struct Search {
     pub query: String,
@@ -18,6 +25,18 @@ pub struct Search {
     pub formula: bool,
     #[arg(long, conflicts_with = "formula")]
     pub cask: bool,
+    /// Only match against descriptions, not names
+    #[arg(long)]
+    pub desc: bool,
+    /// Treat the query as a regular expression instead of a case-insensitive substring
+    #[arg(long)]
+    pub regex: bool,
+    /// Only show packages that are installed
+    #[arg(long)]
+    pub installed: bool,
+    /// Only show installed packages that have a newer version available
+    #[arg(long, conflicts_with = "installed")]
+    pub outdated: bool,
 }
 
 pub enum SearchType {
@@ -26,6 +45,94 @@ pub enum SearchType {
     Cask,
 }
 
+/// How the query is compared against names and descriptions.
+pub enum Matcher {
+    /// Case-insensitive substring; holds the lowercased query.
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str, regex: bool) -> Result<Self> {
+        if regex {
+            Regex::new(query).map(Self::Regex).map_err(|e| {
+                SpsError::ValidationError(format!("Invalid search pattern '{query}': {e}"))
+            })
+        } else {
+            Ok(Self::Substring(query.to_lowercase()))
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(query) => text.to_lowercase().contains(query.as_str()),
+            Self::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+/// Which field of a package matched the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchSource {
+    Name,
+    Description,
+}
+
+impl MatchSource {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Description => "desc",
+        }
+    }
+}
+
+pub type SearchMatch = (Value, MatchSource);
+
+/// The installed packages that results are restricted to by `--installed` or
+/// `--outdated`.
+#[derive(Debug, Default)]
+pub struct LocalPackages {
+    pub formulae: HashSet<String>,
+    pub casks: HashSet<String>,
+}
+
+impl LocalPackages {
+    /// All installed packages, or with `outdated_only` those that have an update.
+    pub async fn load(outdated_only: bool, config: &Config, cache: &Cache) -> Result<Self> {
+        let installed = get_installed_packages(config).await?;
+        let packages: Vec<(PackageType, String)> = if outdated_only {
+            check_for_updates(&installed, cache, config)
+                .await?
+                .into_iter()
+                .map(|update| (update.pkg_type, update.name))
+                .collect()
+        } else {
+            installed
+                .into_iter()
+                .map(|pkg| (pkg.pkg_type, pkg.name))
+                .collect()
+        };
+        let mut local = Self::default();
+        for (pkg_type, name) in packages {
+            match pkg_type {
+                PackageType::Formula => local.formulae.insert(name),
+                PackageType::Cask => local.casks.insert(name),
+            };
+        }
+        Ok(local)
+    }
+
+    fn retain(names: &HashSet<String>, matches: &mut Vec<SearchMatch>, key: &str) {
+        matches.retain(|(package, _)| {
+            package
+                .get(key)
+                .and_then(|name| name.as_str())
+                .is_some_and(|name| names.contains(name))
+        });
+    }
+}
+
 impl Search {
     pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         let search_type = if self.formula {
@@ -35,14 +142,35 @@ impl Search {
         } else {
             SearchType::All
         };
-        run_search(&self.query, search_type, config, cache).await
+        let matcher = Matcher::new(&self.query, self.regex)?;
+        let local = if self.installed || self.outdated {
+            Some(LocalPackages::load(self.outdated, config, &cache).await?)
+        } else {
+            None
+        };
+        run_search(
+            &self.query,
+            &matcher,
+            self.desc,
+            local.as_ref(),
+            search_type,
+            config,
+            cache,
+        )
+        .await
     }
 }
 
+/// Matches names and descriptions, or only descriptions with `desc_only`. A package
+/// whose name matches is reported as a name match even if its description does too.
+/// With `local`, only packages in it are shown.
 pub async fn run_search(
     query: &str,
+    matcher: &Matcher,
+    desc_only: bool,
+    local: Option<&LocalPackages>,
     search_type: SearchType,
-    _config: &Config,
+    config: &Config,
     cache: Arc<Cache>,
 ) -> Result<()> {
     tracing::debug!("Searching for packages matching: {}", query);
@@ -55,8 +183,17 @@ pub async fn run_search(
     let mut cask_err = None;
 
     if matches!(search_type, SearchType::All | SearchType::Formula) {
-        match search_formulas(Arc::clone(&cache), query).await {
-            Ok(matches) => formula_matches = matches,
+        match search_formulas(Arc::clone(&cache), matcher, desc_only).await {
+            Ok(mut matches) => {
+                add_tapped_matches(
+                    &mut matches,
+                    config,
+                    DefinitionKind::Formula,
+                    matcher,
+                    desc_only,
+                );
+                formula_matches = matches;
+            }
             Err(e) => {
                 tracing::error!("Error searching formulas: {}", e);
                 formula_err = Some(e);
@@ -65,8 +202,17 @@ pub async fn run_search(
     }
 
     if matches!(search_type, SearchType::All | SearchType::Cask) {
-        match search_casks(Arc::clone(&cache), query).await {
-            Ok(matches) => cask_matches = matches,
+        match search_casks(Arc::clone(&cache), matcher, desc_only).await {
+            Ok(mut matches) => {
+                add_tapped_matches(
+                    &mut matches,
+                    config,
+                    DefinitionKind::Cask,
+                    matcher,
+                    desc_only,
+                );
+                cask_matches = matches;
+            }
             Err(e) => {
                 tracing::error!("Error searching casks: {}", e);
                 cask_err = Some(e);
@@ -74,6 +220,11 @@ pub async fn run_search(
         }
     }
 
+    if let Some(local) = local {
+        LocalPackages::retain(&local.formulae, &mut formula_matches, "name");
+        LocalPackages::retain(&local.casks, &mut cask_matches, "token");
+    }
+
     if formula_matches.is_empty() && cask_matches.is_empty() {
         if let Some(e) = formula_err.or(cask_err) {
             return Err(e);
@@ -85,8 +236,11 @@ pub async fn run_search(
     Ok(())
 }
 
-async fn search_formulas(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
-    let query_lower = query.to_lowercase();
+async fn search_formulas(
+    cache: Arc<Cache>,
+    matcher: &Matcher,
+    desc_only: bool,
+) -> Result<Vec<SearchMatch>> {
     let mut matches = Vec::new();
     let mut data_source_name = "cache";
 
@@ -97,7 +251,7 @@ async fn search_formulas(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
         Err(e) => {
             tracing::debug!("Formula cache load failed ({}), fetching from API", e);
             data_source_name = "API";
-            let all_formulas = api::fetch_all_formulas().await?;
+            let all_formulas = api::fetch_all_formulas(cache.config()).await?;
 
             if let Err(cache_err) = cache.store_raw("formula.json", &all_formulas) {
                 tracing::warn!("Failed to cache formula data after fetching: {}", cache_err);
@@ -107,8 +261,8 @@ async fn search_formulas(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
     };
 
     for formula in formulas {
-        if is_formula_match(&formula, &query_lower) {
-            matches.push(formula);
+        if let Some(source) = formula_match(&formula, matcher, desc_only) {
+            matches.push((formula, source));
         }
     }
 
@@ -125,8 +279,11 @@ async fn search_formulas(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
     Ok(matches)
 }
 
-async fn search_casks(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
-    let query_lower = query.to_lowercase();
+async fn search_casks(
+    cache: Arc<Cache>,
+    matcher: &Matcher,
+    desc_only: bool,
+) -> Result<Vec<SearchMatch>> {
     let mut matches = Vec::new();
     let mut data_source_name = "cache";
 
@@ -137,7 +294,7 @@ async fn search_casks(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
         Err(e) => {
             tracing::debug!("Cask cache load failed ({}), fetching from API", e);
             data_source_name = "API";
-            let all_casks = api::fetch_all_casks().await?;
+            let all_casks = api::fetch_all_casks(cache.config()).await?;
 
             if let Err(cache_err) = cache.store_raw("cask.json", &all_casks) {
                 tracing::warn!("Failed to cache cask data after fetching: {}", cache_err);
@@ -147,8 +304,8 @@ async fn search_casks(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
     };
 
     for cask in casks {
-        if is_cask_match(&cask, &query_lower) {
-            matches.push(cask);
+        if let Some(source) = cask_match(&cask, matcher, desc_only) {
+            matches.push((cask, source));
         }
     }
     tracing::debug!(
@@ -159,62 +316,81 @@ async fn search_casks(cache: Arc<Cache>, query: &str) -> Result<Vec<Value>> {
     Ok(matches)
 }
 
-fn is_formula_match(formula: &Value, query: &str) -> bool {
-    if let Some(name) = formula.get("name").and_then(|n| n.as_str()) {
-        if name.to_lowercase().contains(query) {
-            return true;
-        }
-    }
-
-    if let Some(full_name) = formula.get("full_name").and_then(|n| n.as_str()) {
-        if full_name.to_lowercase().contains(query) {
-            return true;
-        }
-    }
-
-    if let Some(desc) = formula.get("desc").and_then(|d| d.as_str()) {
-        if desc.to_lowercase().contains(query) {
-            return true;
+/// Adds the matching definitions from installed taps. Core definitions win, so a tapped
+/// definition whose name core already uses is left out.
+fn add_tapped_matches(
+    matches: &mut Vec<SearchMatch>,
+    config: &Config,
+    kind: DefinitionKind,
+    matcher: &Matcher,
+    desc_only: bool,
+) {
+    let key = match kind {
+        DefinitionKind::Formula => "name",
+        DefinitionKind::Cask => "token",
+    };
+    let core_names: HashSet<String> = matches
+        .iter()
+        .filter_map(|(package, _)| package.get(key)?.as_str().map(str::to_string))
+        .collect();
+    for tapped in tap::tapped_definitions(config, kind) {
+        if core_names.contains(&tapped.name) {
+            continue;
         }
-    }
-
-    if let Some(aliases) = formula.get("aliases").and_then(|a| a.as_array()) {
-        for alias in aliases {
-            if let Some(alias_str) = alias.as_str() {
-                if alias_str.to_lowercase().contains(query) {
-                    return true;
-                }
-            }
+        let source = match kind {
+            DefinitionKind::Formula => formula_match(&tapped.value, matcher, desc_only),
+            DefinitionKind::Cask => cask_match(&tapped.value, matcher, desc_only),
+        };
+        if let Some(source) = source {
+            matches.push((tapped.value, source));
         }
     }
-
-    false
 }
 
-fn is_cask_match(cask: &Value, query: &str) -> bool {
-    if let Some(token) = cask.get("token").and_then(|t| t.as_str()) {
-        if token.to_lowercase().contains(query) {
-            return true;
-        }
-    }
-
-    if let Some(names) = cask.get("name").and_then(|n| n.as_array()) {
-        for name in names {
-            if let Some(name_str) = name.as_str() {
-                if name_str.to_lowercase().contains(query) {
-                    return true;
-                }
-            }
+fn formula_match(formula: &Value, matcher: &Matcher, desc_only: bool) -> Option<MatchSource> {
+    if !desc_only {
+        let aliases = formula
+            .get("aliases")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten();
+        let mut names = ["name", "full_name"]
+            .iter()
+            .filter_map(|key| formula.get(*key))
+            .chain(aliases)
+            .filter_map(|n| n.as_str());
+        if names.any(|name| matcher.is_match(name)) {
+            return Some(MatchSource::Name);
         }
     }
+    desc_match(formula, matcher)
+}
 
-    if let Some(desc) = cask.get("desc").and_then(|d| d.as_str()) {
-        if desc.to_lowercase().contains(query) {
-            return true;
+fn cask_match(cask: &Value, matcher: &Matcher, desc_only: bool) -> Option<MatchSource> {
+    if !desc_only {
+        let names = cask
+            .get("name")
+            .and_then(|n| n.as_array())
+            .into_iter()
+            .flatten();
+        let mut names = cask
+            .get("token")
+            .into_iter()
+            .chain(names)
+            .filter_map(|n| n.as_str());
+        if names.any(|name| matcher.is_match(name)) {
+            return Some(MatchSource::Name);
         }
     }
+    desc_match(cask, matcher)
+}
 
-    false
+fn desc_match(package: &Value, matcher: &Matcher) -> Option<MatchSource> {
+    package
+        .get("desc")
+        .and_then(|d| d.as_str())
+        .filter(|desc| matcher.is_match(desc))
+        .map(|_| MatchSource::Description)
 }
 
 fn truncate_vis(s: &str, max: usize) -> String {
@@ -237,7 +413,11 @@ fn truncate_vis(s: &str, max: usize) -> String {
     out
 }
 
-pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches: &[Value]) {
+pub fn print_search_results(
+    query: &str,
+    formula_matches: &[SearchMatch],
+    cask_matches: &[SearchMatch],
+) {
     let total = formula_matches.len() + cask_matches.len();
     if total == 0 {
         println!("{}", format!("No matches found for '{query}'").yellow());
@@ -253,9 +433,10 @@ pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches
         .unwrap_or(120);
 
     let type_col = 7;
+    let source_col = 4;
     let version_col = 10;
-    let sep_width = 3 * 3;
-    let total_fixed = type_col + version_col + sep_width;
+    let sep_width = 4 * 3;
+    let total_fixed = type_col + source_col + version_col + sep_width;
 
     let name_min_width = 10;
     let desc_min_width = 20;
@@ -273,7 +454,7 @@ pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches
     let mut tbl = Table::new();
     tbl.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
 
-    for formula in formula_matches {
+    for (formula, source) in formula_matches {
         let raw_name = formula
             .get("name")
             .and_then(|n| n.as_str())
@@ -286,6 +467,7 @@ pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches
 
         tbl.add_row(Row::new(vec![
             Cell::new("Formula").style_spec("Fg"),
+            Cell::new(source.label()),
             Cell::new(&_name).style_spec("Fb"),
             Cell::new(version),
             Cell::new(&desc),
@@ -293,10 +475,10 @@ pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches
     }
 
     if !formula_matches.is_empty() && !cask_matches.is_empty() {
-        tbl.add_row(Row::new(vec![Cell::new(" ").with_hspan(4)]));
+        tbl.add_row(Row::new(vec![Cell::new(" ").with_hspan(5)]));
     }
 
-    for cask in cask_matches {
+    for (cask, source) in cask_matches {
         let raw_name = cask
             .get("token")
             .and_then(|t| t.as_str())
@@ -308,13 +490,14 @@ pub fn print_search_results(query: &str, formula_matches: &[Value], cask_matches
 
         tbl.add_row(Row::new(vec![
             Cell::new("Cask").style_spec("Fy"),
+            Cell::new(source.label()),
             Cell::new(raw_name).style_spec("Fb"),
             Cell::new(version),
             Cell::new(&desc),
         ]));
     }
 
-    tbl.printstd();
+    print_table(&tbl);
 }
 
 fn get_version(formula: &Value) -> &str {
@@ -0,0 +1,121 @@
+// sps/src/cli/services.rs
+//! Contains the logic for the `services` command, which lists, starts and stops the
+//! launchd services of installed formulae and casks.
+//!
+//! A name may be a formula, a cask, or a service label; a cask can have several
+//! services, and each is acted on. See [`sps_core::services`] for where services are
+//! found and how they are loaded.
+use std::sync::Arc;
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_core::services::{self, Service, ServiceDomain, ServiceSource, ServiceState};
+
+#[derive(Args, Debug)]
+pub struct Services {
+    #[command(subcommand)]
+    pub action: ServicesAction,
+
+    /// Manage services in the system domain as root (through sudo) instead of the
+    /// user's session; system services start at boot
+    #[arg(long, global = true)]
+    pub system: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServicesAction {
+    /// List the services of installed packages and whether they are running
+    List,
+    /// Start a service now and whenever the domain starts (login, or boot with --system)
+    Start { name: String },
+    /// Stop a service and keep it from starting again
+    Stop { name: String },
+    /// Stop a service if it is running, then start it
+    Restart { name: String },
+}
+
+impl Services {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        if !cfg!(target_os = "macos") {
+            return Err(SpsError::Generic(
+                "sps services manages launchd services and is only supported on macOS.".to_string(),
+            ));
+        }
+        let domain = if self.system {
+            ServiceDomain::System
+        } else {
+            ServiceDomain::User
+        };
+        match &self.action {
+            ServicesAction::List => list(config, domain).await,
+            ServicesAction::Start { name } => {
+                for service in services::find_services(config, name).await? {
+                    if services::start_service(&service, domain, config)? {
+                        println!("✓ Started {}", describe(&service).green());
+                    } else {
+                        println!("{} is already started", describe(&service).cyan());
+                    }
+                }
+                Ok(())
+            }
+            ServicesAction::Stop { name } => {
+                for service in services::find_services(config, name).await? {
+                    if services::stop_service(&service, domain, config)? {
+                        println!("✓ Stopped {}", describe(&service).green());
+                    } else {
+                        println!("{} is not started", describe(&service).cyan());
+                    }
+                }
+                Ok(())
+            }
+            ServicesAction::Restart { name } => {
+                for service in services::find_services(config, name).await? {
+                    services::stop_service(&service, domain, config)?;
+                    services::start_service(&service, domain, config)?;
+                    println!("✓ Restarted {}", describe(&service).green());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn list(config: &Config, domain: ServiceDomain) -> Result<()> {
+    let services = services::installed_services(config).await?;
+    if services.is_empty() {
+        println!("No installed formula or cask has a launchd service.");
+        return Ok(());
+    }
+    let name_width = services.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let label_width = services.iter().map(|s| s.label.len()).max().unwrap_or(0);
+    for service in &services {
+        let state = match services::service_state(service, domain)? {
+            ServiceState::Running { pid } => format!("running (pid {pid})").green(),
+            ServiceState::Loaded {
+                last_exit_code: Some(code),
+            } if code != 0 => format!("error (exit {code})").red(),
+            ServiceState::Loaded { .. } => "loaded".yellow(),
+            ServiceState::Stopped => "stopped".dimmed(),
+        };
+        let kind = match service.source {
+            ServiceSource::Formula { .. } => "formula",
+            ServiceSource::Cask { .. } => "cask",
+        };
+        println!(
+            "{:name_width$}  {:label_width$}  {:7}  {}",
+            service.name, service.label, kind, state
+        );
+    }
+    Ok(())
+}
+
+/// `name` for a formula's single service, `name (label)` for a cask's.
+fn describe(service: &Service) -> String {
+    match service.source {
+        ServiceSource::Formula { .. } => service.name.clone(),
+        ServiceSource::Cask { .. } => format!("{} ({})", service.name, service.label),
+    }
+}
@@ -13,6 +13,8 @@ enum JobStatus {
     Waiting,
     Downloading,
     Downloaded,
+    /// Handed to the worker pool, waiting for a free worker.
+    Queued,
     Processing,
     Installing,
     Linking,
@@ -26,6 +28,7 @@ impl JobStatus {
             JobStatus::Waiting => "waiting",
             JobStatus::Downloading => "downloading",
             JobStatus::Downloaded => "downloaded",
+            JobStatus::Queued => "queued",
             JobStatus::Processing => "processing",
             JobStatus::Installing => "installing",
             JobStatus::Linking => "linking",
@@ -39,6 +42,7 @@ impl JobStatus {
             JobStatus::Waiting => " ·".dimmed().to_string(),
             JobStatus::Downloading => " ↓".yellow().to_string(),
             JobStatus::Downloaded => " ✓".green().to_string(),
+            JobStatus::Queued => " …".blue().dimmed().to_string(),
             JobStatus::Processing => " ⚙".blue().to_string(),
             JobStatus::Installing => " ⚙".magenta().to_string(),
             JobStatus::Linking => " →".cyan().to_string(),
@@ -52,6 +56,7 @@ impl JobStatus {
             JobStatus::Waiting => self.display_state().dimmed(),
             JobStatus::Downloading => self.display_state().yellow(),
             JobStatus::Downloaded => self.display_state().green(),
+            JobStatus::Queued => self.display_state().blue().dimmed(),
             JobStatus::Processing => self.display_state().blue(),
             JobStatus::Installing => self.display_state().magenta(),
             JobStatus::Linking => self.display_state().cyan(),
@@ -61,12 +66,41 @@ impl JobStatus {
     }
 }
 
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const PROGRESS_BAR_WIDTH: usize = 10;
+
+/// Latest progress reported for the job's current step.
+#[derive(Debug, Clone)]
+enum JobProgress {
+    Download {
+        done: u64,
+        total: Option<u64>,
+    },
+    Extract {
+        done: usize,
+        total: Option<usize>,
+    },
+    Git {
+        done: usize,
+        total: Option<usize>,
+    },
+    /// The last line a cask's installer printed.
+    Installer {
+        line: String,
+    },
+}
+
+/// Width the last installer line is cut to in the progress column.
+const INSTALLER_LINE_WIDTH: usize = 32;
+
 struct JobInfo {
     name: String,
     status: JobStatus,
     size_bytes: Option<u64>,
     start_time: Option<Instant>,
     pool_id: usize,
+    progress: Option<JobProgress>,
+    streamed_bytes: u64,
 }
 
 impl JobInfo {
@@ -83,6 +117,52 @@ impl JobInfo {
             None => "–".to_string(),
         }
     }
+
+    /// Renders a progress bar when the total is known, or a spinner with a running
+    /// count when it is not.
+    fn progress_str(&self) -> String {
+        let (done, total, unit) = match &self.progress {
+            Some(JobProgress::Download { done, total }) => (*done, *total, None),
+            Some(JobProgress::Extract { done, total }) => {
+                (*done as u64, total.map(|t| t as u64), Some("files"))
+            }
+            Some(JobProgress::Git { done, total }) => {
+                (*done as u64, total.map(|t| t as u64), Some("objects"))
+            }
+            Some(JobProgress::Installer { line }) => {
+                let line: String = line.chars().take(INSTALLER_LINE_WIDTH).collect();
+                return format!("{} {}", self.spinner_frame().yellow(), line.dimmed());
+            }
+            None => return String::new(),
+        };
+        match total {
+            Some(total) if total > 0 => {
+                let done = done.min(total);
+                let filled = (done * PROGRESS_BAR_WIDTH as u64 / total) as usize;
+                let percent = done * 100 / total;
+                format!(
+                    "{}{} {:>3}%",
+                    "▍".repeat(filled).green(),
+                    "·".repeat(PROGRESS_BAR_WIDTH - filled).dimmed(),
+                    percent
+                )
+            }
+            _ => {
+                let count = match unit {
+                    Some(unit) => format!("{done} {unit}"),
+                    None => format_bytes(done),
+                };
+                format!("{} {}", self.spinner_frame().yellow(), count)
+            }
+        }
+    }
+
+    fn spinner_frame(&self) -> &'static str {
+        let tick = self
+            .start_time
+            .map_or(0, |start| start.elapsed().as_millis() / 100) as usize;
+        SPINNER_FRAMES[tick % SPINNER_FRAMES.len()]
+    }
 }
 
 struct StatusDisplay {
@@ -132,6 +212,8 @@ impl StatusDisplay {
                     None
                 },
                 pool_id: self.next_pool_id,
+                progress: None,
+                streamed_bytes: 0,
             };
 
             if let Some(bytes) = size_bytes {
@@ -149,9 +231,13 @@ impl StatusDisplay {
             let was_downloading = job.status == JobStatus::Downloading;
             let is_downloading = status == JobStatus::Downloading;
 
+            if job.status != status {
+                job.progress = None;
+            }
             job.status = status;
 
-            if job.start_time.is_none() && status != JobStatus::Waiting {
+            if job.start_time.is_none() && !matches!(status, JobStatus::Waiting | JobStatus::Queued)
+            {
                 job.start_time = Some(Instant::now());
             }
 
@@ -166,7 +252,8 @@ impl StatusDisplay {
             if was_downloading && !is_downloading {
                 self.active_downloads = self.active_downloads.saturating_sub(1);
                 if let Some(bytes) = job.size_bytes {
-                    self.downloaded_bytes += bytes;
+                    // Bytes already counted from progress events must not be added twice.
+                    self.downloaded_bytes += bytes.saturating_sub(job.streamed_bytes);
                 }
             } else if !was_downloading && is_downloading {
                 self.active_downloads += 1;
@@ -174,6 +261,47 @@ impl StatusDisplay {
         }
     }
 
+    /// The runner reports a dispatch after handing the job over, so a worker may already
+    /// have started it; only jobs that have not got that far are shown as queued.
+    fn mark_queued(&mut self, target_id: &str) {
+        let not_started = self.jobs.get(target_id).is_some_and(|job| {
+            matches!(
+                job.status,
+                JobStatus::Waiting | JobStatus::Downloading | JobStatus::Downloaded
+            )
+        });
+        if not_started {
+            self.update_job_status(target_id, JobStatus::Queued, None);
+        }
+    }
+
+    fn update_download_progress(&mut self, target_id: &str, done: u64, total: Option<u64>) {
+        if let Some(job) = self.jobs.get_mut(target_id) {
+            self.downloaded_bytes += done.saturating_sub(job.streamed_bytes);
+            job.streamed_bytes = job.streamed_bytes.max(done);
+            job.progress = Some(JobProgress::Download { done, total });
+        }
+    }
+
+    fn update_extract_progress(&mut self, target_id: &str, done: usize, total: Option<usize>) {
+        if let Some(job) = self.jobs.get_mut(target_id) {
+            job.progress = Some(JobProgress::Extract { done, total });
+        }
+    }
+
+    fn update_installer_output(&mut self, target_id: &str, line: String) {
+        if let Some(job) = self.jobs.get_mut(target_id) {
+            job.progress = Some(JobProgress::Installer { line });
+        }
+    }
+
+    fn update_git_progress(&mut self, target_id: &str, done: usize, total: usize) {
+        if let Some(job) = self.jobs.get_mut(target_id) {
+            let total = (total > 0).then_some(total);
+            job.progress = Some(JobProgress::Git { done, total });
+        }
+    }
+
     fn update_speed(&mut self) {
         let now = Instant::now();
         let time_diff = now.duration_since(self.last_speed_update).as_secs_f64();
@@ -226,22 +354,38 @@ impl StatusDisplay {
             .values()
             .filter(|j| matches!(j.status, JobStatus::Failed))
             .count();
+        let running = self
+            .jobs
+            .values()
+            .filter(|j| {
+                matches!(
+                    j.status,
+                    JobStatus::Processing | JobStatus::Installing | JobStatus::Linking
+                )
+            })
+            .count();
         let progress_chars = self.generate_progress_bar(completed, failed);
         let speed_str = format_speed(self.current_speed_bps);
 
-        println!("{} net {}", progress_chars, speed_str.blue());
+        println!(
+            "{} net {} {}",
+            progress_chars,
+            speed_str.blue(),
+            format!("{running} running").dimmed()
+        );
 
         io::stdout().flush().unwrap();
     }
 
     fn print_header(&self) {
         println!(
-            "{:<6} {:<12} {:<15} {:>8} {}",
+            "{:<6} {:<12} {:<15} {:>8} {} {}",
             "IID".bold().dimmed(),
             "STATE".bold().dimmed(),
             "PKG".bold().dimmed(),
             "SIZE".bold().dimmed(),
-            "SLOT".bold().dimmed()
+            "SLOT".bold().dimmed(),
+            "PROGRESS".bold().dimmed()
         );
     }
 
@@ -252,12 +396,13 @@ impl StatusDisplay {
         for target_id in &self.job_order {
             if let Some(job) = self.jobs.get(target_id) {
                 output.push_str(&format!(
-                    "{:<6} {:<12} {:<15} {:>8} {}\n",
+                    "{:<6} {:<12} {:<15} {:>8} {}   {}\n",
                     format!("#{:02}", job.pool_id).cyan(),
                     job.status.colored_state(),
                     job.name.cyan(),
                     job.size_str(),
-                    job.status.slot_indicator()
+                    job.status.slot_indicator(),
+                    job.progress_str()
                 ));
             }
         }
@@ -324,7 +469,13 @@ fn format_speed(bytes_per_sec: f64) -> String {
     format!("{:.1} {}", value, UNITS[unit_idx])
 }
 
-pub async fn handle_events(_config: Config, mut event_rx: broadcast::Receiver<PipelineEvent>) {
+pub async fn handle_events(config: Config, mut event_rx: broadcast::Receiver<PipelineEvent>) {
+    if config.json_output {
+        return stream_json_events(event_rx).await;
+    }
+    if config.quiet {
+        return report_failures(event_rx).await;
+    }
     let mut display = StatusDisplay::new();
     let mut logs_buffer = Vec::new();
     let mut pipeline_active = false;
@@ -356,6 +507,43 @@ pub async fn handle_events(_config: Config, mut event_rx: broadcast::Receiver<Pi
                         display.render();
                     }
                 }
+                PipelineEvent::DownloadProgress {
+                    target_id,
+                    bytes_done,
+                    total_bytes,
+                } => {
+                    display.update_download_progress(&target_id, bytes_done, total_bytes);
+                    if pipeline_active {
+                        display.render();
+                    }
+                }
+                PipelineEvent::ExtractProgress {
+                    target_id,
+                    files_done,
+                    total_files,
+                } => {
+                    display.update_extract_progress(&target_id, files_done, total_files);
+                    if pipeline_active {
+                        display.render();
+                    }
+                }
+                PipelineEvent::GitProgress {
+                    target_id,
+                    objects_received,
+                    total_objects,
+                    ..
+                } => {
+                    display.update_git_progress(&target_id, objects_received, total_objects);
+                    if pipeline_active {
+                        display.render();
+                    }
+                }
+                PipelineEvent::InstallerOutput { target_id, line } => {
+                    display.update_installer_output(&target_id, line);
+                    if pipeline_active {
+                        display.render();
+                    }
+                }
                 PipelineEvent::DownloadFinished {
                     target_id,
                     size_bytes,
@@ -380,6 +568,12 @@ pub async fn handle_events(_config: Config, mut event_rx: broadcast::Receiver<Pi
                         display.render();
                     }
                 }
+                PipelineEvent::JobDispatchedToCore { target_id } => {
+                    display.mark_queued(&target_id);
+                    if pipeline_active {
+                        display.render();
+                    }
+                }
                 PipelineEvent::JobProcessingStarted { target_id } => {
                     display.update_job_status(&target_id, JobStatus::Processing, None);
                     if pipeline_active {
@@ -507,3 +701,58 @@ pub async fn handle_events(_config: Config, mut event_rx: broadcast::Receiver<Pi
         }
     }
 }
+
+/// Quiet mode: prints nothing but failures, to stderr, until the pipeline finishes.
+async fn report_failures(mut event_rx: broadcast::Receiver<PipelineEvent>) {
+    loop {
+        match event_rx.recv().await {
+            Ok(PipelineEvent::DownloadFailed {
+                target_id, error, ..
+            }) => eprintln!(
+                "{} {}: {}",
+                "Download failed:".red(),
+                target_id.cyan(),
+                error.red()
+            ),
+            Ok(PipelineEvent::JobFailed {
+                target_id, error, ..
+            }) => eprintln!("{} {}: {}", "✗".red().bold(), target_id.cyan(), error.red()),
+            Ok(PipelineEvent::LogError { message }) => eprintln!("{}", message.red()),
+            Ok(PipelineEvent::PipelineFinished { .. })
+            | Err(broadcast::error::RecvError::Closed) => break,
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+        }
+    }
+}
+
+/// Writes each event to stdout as one line of JSON until the pipeline finishes. Events
+/// dropped because this task fell behind are reported as a `lagged` line with the
+/// number skipped, so consumers know progress is incomplete.
+async fn stream_json_events(mut event_rx: broadcast::Receiver<PipelineEvent>) {
+    let mut stdout = io::stdout();
+    loop {
+        match event_rx.recv().await {
+            Ok(event) => {
+                write_json_line(&mut stdout, &event);
+                if matches!(event, PipelineEvent::PipelineFinished { .. }) {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => write_json_line(
+                &mut stdout,
+                &serde_json::json!({ "event": "lagged", "skipped": skipped }),
+            ),
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+fn write_json_line(out: &mut impl Write, value: &impl serde::Serialize) {
+    match serde_json::to_string(value) {
+        Ok(line) => {
+            let _ = writeln!(out, "{line}");
+            let _ = out.flush();
+        }
+        Err(e) => tracing::error!("Failed to serialize pipeline event: {}", e),
+    }
+}
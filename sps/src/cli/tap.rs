@@ -0,0 +1,211 @@
+// sps/src/cli/tap.rs
+//! Contains the logic for the `tap` and `untap` commands, which add and remove
+//! third-party taps.
+//!
+//! A tap is cloned from `https://github.com/<user>/homebrew-<repo>`. Only definitions in
+//! the API's JSON format are read from it; see [`sps_common::model::tap`].
+//!
+//! The clone reports its transfer progress as it goes and stops on Ctrl-C, removing
+//! what it had cloned so far.
+use std::collections::HashSet;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::tap::{self, DefinitionKind, Tap as TapRepo};
+use sps_common::model::Cask;
+use sps_common::pipeline::PipelineEvent;
+use sps_core::pipeline::worker::git_progress_reporter;
+use tokio::sync::broadcast;
+
+use crate::cli::uninstall::format_size;
+
+#[derive(Args, Debug)]
+pub struct Tap {
+    /// The tap to add, as `user/repo`; lists the installed taps if omitted
+    pub name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Untap {
+    /// The tap to remove, as `user/repo`
+    pub name: String,
+}
+
+impl Tap {
+    pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let Some(name) = &self.name else {
+            list_taps(config);
+            return Ok(());
+        };
+        let tap = TapRepo::new(name, config)?;
+        if tap.is_installed() {
+            println!("{} is already tapped", tap.full_name().cyan());
+            return Ok(());
+        }
+
+        println!("Tapping {}", tap.full_name());
+        let (event_tx, event_rx) = broadcast::channel(64);
+        let progress = git_progress_reporter(&tap.full_name(), &event_tx);
+        drop(event_tx);
+        let printer = tokio::spawn(print_git_progress(event_rx, config.quiet));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let interrupt = tokio::spawn({
+            let cancel = Arc::clone(&cancel);
+            async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+        let result = tokio::task::spawn_blocking(move || {
+            tap.install(Some(&progress), Some(&cancel)).map(|_| tap)
+        })
+        .await;
+        interrupt.abort();
+        let _ = printer.await;
+        let tap = result.map_err(|e| SpsError::Generic(format!("Tap task failed: {e}")))??;
+
+        let formulae = tap.definitions(DefinitionKind::Formula);
+        let casks = tap.definitions(DefinitionKind::Cask);
+        println!(
+            "✓ Tapped {} ({} formulae, {} casks)",
+            tap.full_name().green(),
+            formulae.len(),
+            casks.len()
+        );
+        if formulae.is_empty() && casks.is_empty() {
+            println!(
+                "{}",
+                "The tap has no JSON definitions in Formula/ or Casks/, so sps cannot use \
+                 anything from it."
+                    .yellow()
+            );
+        }
+        report_conflicts(
+            &tap,
+            DefinitionKind::Formula,
+            &core_formula_names(&cache),
+            config,
+        );
+        report_conflicts(&tap, DefinitionKind::Cask, &core_cask_names(&cache), config);
+        Ok(())
+    }
+}
+
+/// Prints clone progress on one line of a terminal's stderr until the clone drops its
+/// sender.
+async fn print_git_progress(mut event_rx: broadcast::Receiver<PipelineEvent>, quiet: bool) {
+    let show = !quiet && std::io::stderr().is_terminal();
+    let mut printed = false;
+    loop {
+        match event_rx.recv().await {
+            Ok(PipelineEvent::GitProgress {
+                objects_received,
+                total_objects,
+                bytes_received,
+                ..
+            }) if show => {
+                let percent = (objects_received * 100)
+                    .checked_div(total_objects)
+                    .unwrap_or(0);
+                eprint!(
+                    "\rReceiving objects: {percent:>3}% ({objects_received}/{total_objects}), {}",
+                    format_size(bytes_received as u64)
+                );
+                let _ = std::io::stderr().flush();
+                printed = true;
+            }
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+    if printed {
+        eprintln!();
+    }
+}
+
+impl Untap {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let tap = TapRepo::new(&self.name, config)?;
+        tap.remove()?;
+        println!("✓ Untapped {}", tap.full_name().green());
+        Ok(())
+    }
+}
+
+fn list_taps(config: &Config) {
+    let taps = tap::installed_taps(config);
+    if taps.is_empty() {
+        println!("No taps installed.");
+        return;
+    }
+    for tap in taps {
+        println!(
+            "{} ({} formulae, {} casks)",
+            tap.full_name(),
+            tap.definitions(DefinitionKind::Formula).len(),
+            tap.definitions(DefinitionKind::Cask).len()
+        );
+    }
+}
+
+/// Warns about definitions in `tap` that are not used because core, or a tap earlier
+/// in name order, defines the same name.
+fn report_conflicts(
+    tap: &TapRepo,
+    kind: DefinitionKind,
+    core_names: &HashSet<String>,
+    config: &Config,
+) {
+    let label = match kind {
+        DefinitionKind::Formula => "formula",
+        DefinitionKind::Cask => "cask",
+    };
+    for definition in tap.definitions(kind) {
+        if core_names.contains(&definition.name) {
+            println!(
+                "{} {} '{}' is shadowed by the core {}",
+                "Warning:".yellow(),
+                label,
+                definition.name,
+                label
+            );
+        } else if let Some(used) = tap::find_tapped_definition(config, kind, &definition.name)
+            .filter(|used| used.tap != definition.tap)
+        {
+            println!(
+                "{} {} '{}' is shadowed by the one in {}",
+                "Warning:".yellow(),
+                label,
+                definition.name,
+                used.tap
+            );
+        }
+    }
+}
+
+fn core_formula_names(cache: &Cache) -> HashSet<String> {
+    match sps_common::formulary::load_formula_index(cache) {
+        Ok(formulae) => formulae.into_iter().map(|f| f.name).collect(),
+        Err(e) => {
+            tracing::debug!("Cannot check tapped formulae against core: {}", e);
+            HashSet::new()
+        }
+    }
+}
+
+fn core_cask_names(cache: &Cache) -> HashSet<String> {
+    match cache.load_parsed::<Vec<Cask>>("cask.json") {
+        Ok(casks) => casks.into_iter().map(|c| c.token).collect(),
+        Err(e) => {
+            tracing::debug!("Cannot check tapped casks against core: {}", e);
+            HashSet::new()
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// sps/src/cli/ui.rs
+//! Shared terminal output helpers.
+use std::env;
+use std::io::{self, IsTerminal};
+
+use clap::ValueEnum;
+use prettytable::Table;
+
+/// What pipeline commands write to stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The live status table and summary
+    #[default]
+    Human,
+    /// One JSON object per pipeline event, newline-delimited
+    Json,
+}
+
+/// Decides whether colored output is allowed and applies it globally.
+///
+/// Color is disabled by `--no-color`, a non-empty `NO_COLOR` environment variable
+/// (see <https://no-color.org>), or when stdout is not a terminal. Returns whether
+/// color is enabled so callers can configure other writers (e.g. tracing) to match.
+pub fn init_color(no_color_flag: bool) -> bool {
+    let no_color_env = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let enabled = !no_color_flag && !no_color_env && io::stdout().is_terminal();
+    if !enabled {
+        colored::control::set_override(false);
+    }
+    enabled
+}
+
+/// Prints a table to stdout, dropping cell styles when colored output is disabled.
+pub fn print_table(table: &Table) {
+    if colored::control::SHOULD_COLORIZE.should_colorize() {
+        table.printstd();
+    } else {
+        let _ = table.print(&mut io::stdout());
+    }
+}
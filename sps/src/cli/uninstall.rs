@@ -25,6 +25,14 @@ pub struct Uninstall {
         help = "Perform a deep clean for casks, removing associated user data, caches, and configuration files. Use with caution!"
     )]
     pub zap: bool,
+
+    /// Remove formulae even if other installed formulae depend on them
+    #[arg(long)]
+    pub ignore_dependencies: bool,
+
+    /// Warn instead of refusing when installed formulae depend on a target
+    #[arg(long)]
+    pub force: bool,
 }
 
 impl Uninstall {
@@ -32,6 +40,10 @@ impl Uninstall {
         let names = &self.names;
         let mut errors: Vec<(String, SpsError)> = Vec::new();
 
+        if !self.ignore_dependencies {
+            self.check_installed_dependents(config).await?;
+        }
+
         for name in names {
             // Basic name validation to prevent path traversal
             if name.contains('/') || name.contains("..") {
@@ -47,7 +59,12 @@ impl Uninstall {
                 Ok(Some(installed_info)) => {
                     let (file_count, size_bytes) =
                         count_files_and_size(&installed_info.path).unwrap_or((0, 0));
-                    let uninstall_opts = UninstallOptions { skip_zap: false };
+                    // Dependents were checked for the whole batch up front.
+                    let uninstall_opts = UninstallOptions {
+                        skip_zap: false,
+                        ignore_dependencies: true,
+                        force: self.force,
+                    };
                     debug!(
                         "Attempting uninstall for {} ({:?})",
                         name, installed_info.pkg_type
@@ -92,7 +109,7 @@ impl Uninstall {
 
                         // Fetch the Cask definition (needed for the zap stanza)
                         let cask_def_result: Result<Cask> = async {
-                            match api::get_cask(name).await {
+                            match api::get_cask(name, config).await {
                                 Ok(cask) => Ok(cask),
                                 Err(e) => {
                                     warn!("Failed API fetch for zap definition for '{}' ({}), trying cache...", name, e);
@@ -116,6 +133,7 @@ impl Uninstall {
                                     &installed_info,
                                     &cask_def,
                                     config,
+                                    &mut |path| println!("  Removed {}", path.display()),
                                 )
                                 .await
                                 {
@@ -175,10 +193,62 @@ impl Uninstall {
             ))
         }
     }
+
+    /// Reports every target formula that other installed formulae still depend on,
+    /// before anything is removed. Fails unless `--force` was given.
+    async fn check_installed_dependents(&self, config: &Config) -> Result<()> {
+        let mut formula_names = Vec::new();
+        for name in &self.names {
+            if let Ok(Some(info)) = installed::get_installed_package(name, config).await {
+                if info.pkg_type == PackageType::Formula {
+                    formula_names.push(name.clone());
+                }
+            }
+        }
+        if formula_names.is_empty() {
+            return Ok(());
+        }
+
+        let dependents = match core_uninstall::find_installed_dependents(&formula_names, config) {
+            Ok(dependents) => dependents,
+            Err(e) if self.force => {
+                eprintln!("{} {}", "Warning:".yellow(), e);
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(SpsError::DependencyError(format!(
+                    "{e}. Use --ignore-dependencies to uninstall without checking dependents."
+                )))
+            }
+        };
+        if dependents.is_empty() {
+            return Ok(());
+        }
+        for name in formula_names.iter().filter(|n| dependents.contains_key(*n)) {
+            eprintln!(
+                "{} {} is required by: {}",
+                if self.force {
+                    "Warning:".yellow()
+                } else {
+                    "✖".red()
+                },
+                name.cyan(),
+                dependents[name].join(", ")
+            );
+        }
+        if self.force {
+            return Ok(());
+        }
+        Err(SpsError::DependencyError(
+            "Refusing to uninstall packages that installed formulae depend on. Use \
+             --ignore-dependencies to remove them anyway."
+                .to_string(),
+        ))
+    }
 }
 
 // --- Unchanged Helper Functions ---
-fn count_files_and_size(path: &std::path::Path) -> Result<(usize, u64)> {
+pub(crate) fn count_files_and_size(path: &std::path::Path) -> Result<(usize, u64)> {
     let mut file_count = 0;
     let mut total_size = 0;
     for entry in walkdir::WalkDir::new(path) {
@@ -210,7 +280,7 @@ fn count_files_and_size(path: &std::path::Path) -> Result<(usize, u64)> {
     Ok((file_count, total_size))
 }
 
-fn format_size(size: u64) -> String {
+pub(crate) fn format_size(size: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
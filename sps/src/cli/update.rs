@@ -14,32 +14,35 @@ impl Update {
     pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
         tracing::debug!("Running manual update..."); // Log clearly it's the manual one
 
-        // Use the ui utility function to create the spinner
-        println!("Updating package lists"); // <-- CHANGED
+        let say = |message: &str| {
+            if !config.quiet {
+                println!("{message}");
+            }
+        };
+        say("Updating package lists");
 
         tracing::debug!("Using cache directory: {:?}", config.cache_dir());
 
         // Fetch and store raw formula data
-        match api::fetch_all_formulas().await {
+        match api::fetch_all_formulas(config).await {
             Ok(raw_data) => {
                 cache.store_raw("formula.json", &raw_data)?;
                 tracing::debug!("✓ Successfully cached formulas data");
-                println!("Cached formulas data");
+                say("Cached formulas data");
             }
             Err(e) => {
                 let err_msg = format!("Failed to fetch/store formulas from API: {e}");
                 tracing::error!("{}", err_msg);
-                println!(); // Clear spinner on error
                 return Err(e);
             }
         }
 
         // Fetch and store raw cask data
-        match api::fetch_all_casks().await {
+        match api::fetch_all_casks(config).await {
             Ok(raw_data) => {
                 cache.store_raw("cask.json", &raw_data)?;
                 tracing::debug!("✓ Successfully cached casks data");
-                println!("Cached casks data");
+                say("Cached casks data");
             }
             Err(e) => {
                 let err_msg = format!("Failed to fetch/store casks from API: {e}");
@@ -67,7 +70,7 @@ impl Update {
             }
         }
 
-        println!("Update completed successfully!");
+        say("Update completed successfully!");
         Ok(())
     }
 }
@@ -1,11 +1,14 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use clap::Args;
 use sps_common::cache::Cache;
 use sps_common::config::Config;
 use sps_common::error::Result;
-use sps_core::check::installed;
+use sps_core::check::installed::{self, PackageType};
 
+use crate::cli::install::{print_dry_run, ConcurrencyArgs, InstallOptionArgs};
+use crate::cli::reinstall::{print_install_diffs, snapshot_installed};
 use crate::pipeline::runner::{self, CommandType, PipelineFlags};
 
 #[derive(Args, Debug)]
@@ -16,8 +19,39 @@ pub struct UpgradeArgs {
     #[arg(long, conflicts_with = "names")]
     pub all: bool,
 
+    #[arg(long, conflicts_with = "cask", help = "Only upgrade formulae")]
+    pub formula: bool,
+
+    #[arg(long, help = "Only upgrade casks")]
+    pub cask: bool,
+
     #[arg(long)]
     pub build_from_source: bool,
+
+    #[arg(
+        long,
+        help = "Upgrade explicitly named packages even if they are pinned or outside their macOS range"
+    )]
+    pub force: bool,
+
+    #[arg(
+        long,
+        help = "Report files added, removed or changed by the upgrade (hashes every file)"
+    )]
+    pub verbose_diff: bool,
+
+    #[arg(
+        short = 'n',
+        long,
+        help = "Print the jobs that would run and exit without downloading or installing"
+    )]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub options: InstallOptionArgs,
+
+    #[command(flatten)]
+    pub limits: ConcurrencyArgs,
 }
 
 impl UpgradeArgs {
@@ -25,7 +59,12 @@ impl UpgradeArgs {
         let targets = if self.all {
             // Get all installed package names
             let installed = installed::get_installed_packages(config).await?;
-            installed.into_iter().map(|p| p.name).collect()
+            let only_type = self.only_type();
+            installed
+                .into_iter()
+                .filter(|p| only_type.as_ref().is_none_or(|t| &p.pkg_type == t))
+                .map(|p| p.name)
+                .collect()
         } else {
             self.names.clone()
         };
@@ -37,21 +76,46 @@ impl UpgradeArgs {
         let flags = PipelineFlags {
             // Populate flags from args
             build_from_source: self.build_from_source,
+            build_from_source_formulae: HashSet::new(),
             // Upgrade should respect original install options ideally,
             // but for now let's default them. This could be enhanced later
             // by reading install receipts.
             include_optional: false,
             skip_recommended: false,
+            include_test: false,
+            only_dependencies: false,
+            force: self.force,
+            no_verify: self.options.no_verify,
             // ... add other common flags if needed ...
+            build_from_local: None,
+            only_type: self.only_type(),
         };
 
-        runner::run_pipeline(
-            &targets,
-            CommandType::Upgrade { all: self.all },
-            config,
-            cache,
-            &flags,
-        )
-        .await
+        let mut config = self.limits.apply(config);
+        self.options.apply(&mut config);
+        let command_type = CommandType::Upgrade { all: self.all };
+        if self.dry_run {
+            return print_dry_run(&targets, command_type, &config, cache, &flags).await;
+        }
+        let before = if self.verbose_diff {
+            snapshot_installed(&targets, &config).await
+        } else {
+            HashMap::new()
+        };
+        let result = runner::run_pipeline(&targets, command_type, &config, cache, &flags).await;
+        if self.verbose_diff {
+            print_install_diffs(&before, &config).await;
+        }
+        result
+    }
+
+    fn only_type(&self) -> Option<PackageType> {
+        if self.formula {
+            Some(PackageType::Formula)
+        } else if self.cask {
+            Some(PackageType::Cask)
+        } else {
+            None
+        }
     }
 }
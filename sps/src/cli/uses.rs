@@ -0,0 +1,118 @@
+// sps/src/cli/uses.rs
+//! Contains the logic for the `uses` command.
+//!
+//! The inverse of `deps`: lists formulae that declare a dependency on the given
+//! formula, so it is clear what would break before uninstalling it.
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::dependency::DependencyTag;
+use sps_common::error::{Result, SpsError};
+use sps_common::keg::KegRegistry;
+
+use crate::cli::deps::tag_label;
+use crate::pipeline::planner::load_or_fetch_formulae_map;
+
+#[derive(Args, Debug)]
+pub struct Uses {
+    /// The formula to find dependents of
+    pub name: String,
+
+    /// Only consider formulae that are currently installed
+    #[arg(long)]
+    pub installed: bool,
+
+    /// Also list formulae that depend on the target indirectly
+    #[arg(long)]
+    pub recursive: bool,
+}
+
+impl Uses {
+    pub async fn run(&self, config: &Config, cache: Arc<Cache>) -> Result<()> {
+        let formulae = load_or_fetch_formulae_map(cache).await?;
+
+        let installed: Option<HashSet<String>> = if self.installed {
+            let registry = KegRegistry::new(config.clone());
+            Some(
+                registry
+                    .list_installed_kegs()?
+                    .into_iter()
+                    .map(|keg| keg.name)
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        if !formulae.contains_key(&self.name)
+            && !installed
+                .as_ref()
+                .is_some_and(|names| names.contains(&self.name))
+        {
+            return Err(SpsError::NotFound(format!(
+                "No formula named '{}'",
+                self.name
+            )));
+        }
+
+        // dependency name -> [(dependent, edge tags)]
+        let mut dependents: HashMap<String, Vec<(&str, DependencyTag)>> = HashMap::new();
+        for (name, formula) in &formulae {
+            if installed
+                .as_ref()
+                .is_some_and(|names| !names.contains(name))
+            {
+                continue;
+            }
+            for dep in formula.dependencies()? {
+                if !dep.tags.contains(DependencyTag::TEST) {
+                    dependents
+                        .entry(dep.name)
+                        .or_default()
+                        .push((name.as_str(), dep.tags));
+                }
+            }
+        }
+
+        // dependent -> tags of its direct edge to the target (None when only indirect)
+        let mut found: BTreeMap<&str, Option<DependencyTag>> = BTreeMap::new();
+        let mut queue = VecDeque::from([self.name.as_str()]);
+        while let Some(current) = queue.pop_front() {
+            let Some(users) = dependents.get(current) else {
+                continue;
+            };
+            for &(user, tags) in users {
+                if user == self.name {
+                    continue;
+                }
+                let direct = current == self.name;
+                let is_new = !found.contains_key(user);
+                let entry = found.entry(user).or_insert(None);
+                if direct {
+                    *entry = Some(entry.map_or(tags, |t| t | tags));
+                }
+                if self.recursive && is_new {
+                    queue.push_back(user);
+                }
+            }
+        }
+
+        if found.is_empty() {
+            let scope = if self.installed { "installed " } else { "" };
+            println!("No {scope}formulae depend on {}", self.name.cyan());
+            return Ok(());
+        }
+
+        for (user, tags) in found {
+            match tags.map(tag_label).filter(|label| !label.is_empty()) {
+                Some(label) => println!("{user} {}", format!("({label})").dimmed()),
+                None => println!("{user}"),
+            }
+        }
+        Ok(())
+    }
+}
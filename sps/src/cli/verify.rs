@@ -0,0 +1,88 @@
+// sps/src/cli/verify.rs
+//! Contains the logic for the `verify` command, which checks installed formulae and
+//! casks against what was recorded when they were installed.
+//!
+//! Kegs are checked for missing files and broken prefix links, and with `--checksums`
+//! for files whose contents changed; casks for missing app bundles and links. See
+//! [`sps_core::check::verify`].
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_core::check::installed;
+use sps_core::check::verify;
+
+#[derive(Args, Debug)]
+pub struct Verify {
+    /// The installed formulae or casks to verify; every installed package if none given
+    pub names: Vec<String>,
+
+    /// Also re-hash the keg files recorded at install time to find modified ones
+    #[arg(long)]
+    pub checksums: bool,
+}
+
+impl Verify {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let packages: Vec<_> = installed::get_installed_packages(config)
+            .await?
+            .into_iter()
+            .filter(|package| self.names.is_empty() || self.names.contains(&package.name))
+            .collect();
+        if let Some(missing) = self
+            .names
+            .iter()
+            .find(|name| !packages.iter().any(|package| &package.name == *name))
+        {
+            return Err(SpsError::NotFound(format!(
+                "No installed formula or cask named '{missing}'"
+            )));
+        }
+
+        let mut broken: Vec<String> = Vec::new();
+        for package in &packages {
+            let label = format!("{} {}", package.name, package.version);
+            let report = verify::verify_package(package, config, self.checksums)?;
+            if report.is_ok() {
+                println!("✓ {} ({} recorded entries)", label.green(), report.checked);
+            } else {
+                println!(
+                    "✖ {}: {} problem(s)",
+                    label.red(),
+                    report.discrepancies.len()
+                );
+                for discrepancy in &report.discrepancies {
+                    println!("    {discrepancy}");
+                }
+                if !broken.contains(&package.name) {
+                    broken.push(package.name.clone());
+                }
+            }
+            if !report.files_recorded {
+                println!(
+                    "    {}",
+                    "its receipt does not list the keg's files; reinstall it to record them"
+                        .dimmed()
+                );
+            }
+            if !report.linked && !self.names.is_empty() {
+                println!("    {}", "not linked; links were not checked".dimmed());
+            }
+        }
+
+        if broken.is_empty() {
+            return Ok(());
+        }
+        println!(
+            "\nRun `sps reinstall {}` to restore them.",
+            broken.join(" ")
+        );
+        Err(SpsError::ValidationError(format!(
+            "{} package(s) do not match what was installed",
+            broken.len()
+        )))
+    }
+}
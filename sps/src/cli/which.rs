@@ -0,0 +1,119 @@
+// sps/src/cli/which.rs
+//! Contains the logic for the `which` command, which reports the formula or cask that
+//! put a command into the prefix.
+//!
+//! Formula commands are symlinks or wrapper scripts leading into a keg; cask commands
+//! are the binary links recorded in the cask's install manifest.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use sps_common::cache::Cache;
+use sps_common::config::Config;
+use sps_common::error::{Result, SpsError};
+use sps_common::model::artifact::InstalledArtifact;
+use sps_core::check::installed::{self, PackageType};
+use sps_core::install::bottle::link;
+use sps_core::install::cask::CaskInstallManifest;
+
+#[derive(Args, Debug)]
+pub struct Which {
+    /// The command to look up in the prefix's bin, or a path to an entry in the prefix
+    pub name: String,
+
+    /// Print the absolute path the command resolves to instead of its owner
+    #[arg(long)]
+    pub path: bool,
+}
+
+impl Which {
+    pub async fn run(&self, config: &Config, _cache: Arc<Cache>) -> Result<()> {
+        let entry = self.prefix_entry(config)?;
+        let resolved = link::link_target(&entry)
+            .and_then(|target| fs::canonicalize(target).ok())
+            .unwrap_or_else(|| entry.clone());
+        if self.path {
+            println!("{}", resolved.display());
+            return Ok(());
+        }
+
+        let owner = match link::link_owner(&entry, config) {
+            Some((name, version)) => Some(("formula", name, version)),
+            None => cask_owner(&entry, config)
+                .await?
+                .map(|(token, version)| ("cask", token, version)),
+        };
+        let Some((kind, name, version)) = owner else {
+            return Err(SpsError::NotFound(format!(
+                "{} was not put there by an installed formula or cask",
+                entry.display()
+            )));
+        };
+        println!(
+            "{} is provided by {} {} {}",
+            entry.display(),
+            kind,
+            name.green(),
+            version
+        );
+        if resolved != entry {
+            println!("  -> {}", resolved.display().to_string().dimmed());
+        }
+        Ok(())
+    }
+
+    /// `name` itself if it is a path, otherwise the entry of that name in the prefix's
+    /// `bin` or `sbin`.
+    fn prefix_entry(&self, config: &Config) -> Result<PathBuf> {
+        let exists = |path: &Path| path.symlink_metadata().is_ok();
+        if self.name.contains('/') {
+            let path = std::path::absolute(&self.name)?;
+            return if exists(&path) {
+                Ok(path)
+            } else {
+                Err(SpsError::NotFound(format!(
+                    "{} does not exist",
+                    path.display()
+                )))
+            };
+        }
+        let dirs = [config.bin_dir(), config.sps_root().join("sbin")];
+        dirs.iter()
+            .map(|dir| dir.join(&self.name))
+            .find(|path| exists(path))
+            .ok_or_else(|| {
+                SpsError::NotFound(format!(
+                    "No command named '{}' in {}",
+                    self.name,
+                    config.bin_dir().display()
+                ))
+            })
+    }
+}
+
+/// The token and version of the installed cask whose manifest records a link at `entry`.
+async fn cask_owner(entry: &Path, config: &Config) -> Result<Option<(String, String)>> {
+    for package in installed::get_installed_packages(config).await? {
+        if package.pkg_type != PackageType::Cask {
+            continue;
+        }
+        let Some(manifest) = fs::read_to_string(package.path.join("CASK_INSTALL_MANIFEST.json"))
+            .ok()
+            .and_then(|raw| serde_json::from_str::<CaskInstallManifest>(&raw).ok())
+            .filter(|manifest| manifest.is_installed)
+        else {
+            continue;
+        };
+        let links_entry = manifest.artifacts.iter().any(|artifact| match artifact {
+            InstalledArtifact::BinaryLink { link_path, .. }
+            | InstalledArtifact::ManpageLink { link_path, .. } => link_path == entry,
+            _ => false,
+        });
+        if links_entry {
+            return Ok(Some((manifest.token, manifest.version)));
+        }
+    }
+    Ok(None)
+}
@@ -1,4 +1,5 @@
 // sps/src/main.rs
+use std::path::PathBuf;
 use std::process::{self}; // StdCommand is used
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -7,8 +8,9 @@ use std::{env, fs};
 use clap::Parser;
 use colored::Colorize;
 use sps_common::cache::Cache;
-use sps_common::config::Config;
+use sps_common::config::{parse_bottle_arch, Config};
 use sps_common::error::{Result as spResult, SpsError};
+use sps_common::lock::SpsLock;
 use tracing::level_filters::LevelFilter;
 use tracing::{debug, error, warn}; // Import all necessary tracing macros
 use tracing_subscriber::fmt::writer::MakeWriterExt;
@@ -20,8 +22,23 @@ mod pipeline;
 use cli::{CliArgs, Command, InitArgs};
 
 // Standalone function to handle the init command logic
-async fn run_init_command(init_args: &InitArgs, verbose_level: u8) -> spResult<()> {
+async fn run_init_command(
+    init_args: &InitArgs,
+    prefix: Option<PathBuf>,
+    quiet_flag: bool,
+    verbose_level: u8,
+    use_ansi: bool,
+) -> spResult<()> {
+    let initial_config_for_path = Config::load_with_prefix(prefix, None).map_err(|e| {
+        // Handle error if even basic config loading fails for path determination
+        SpsError::Config(format!(
+            "Could not determine sps_root for init (config load failed): {e}"
+        ))
+    })?;
+    let quiet = quiet_flag || initial_config_for_path.quiet;
+
     let init_level_filter = match verbose_level {
+        0 if quiet => LevelFilter::ERROR,
         0 => LevelFilter::INFO,
         1 => LevelFilter::DEBUG,
         _ => LevelFilter::TRACE,
@@ -29,25 +46,38 @@ async fn run_init_command(init_args: &InitArgs, verbose_level: u8) -> spResult<(
     let _ = tracing_subscriber::fmt()
         .with_max_level(init_level_filter)
         .with_writer(std::io::stderr)
-        .with_ansi(true)
+        .with_ansi(use_ansi)
         .without_time()
         .try_init();
 
-    let initial_config_for_path = Config::load().map_err(|e| {
-        // Handle error if even basic config loading fails for path determination
-        SpsError::Config(format!(
-            "Could not determine sps_root for init (config load failed): {e}"
-        ))
-    })?;
-
     // Create a minimal Config struct, primarily for sps_root() and derived paths.
     let temp_config_for_init = Config {
         sps_root: initial_config_for_path.sps_root().to_path_buf(),
-        api_base_url: "https://formulae.brew.sh/api".to_string(),
+        api_base_url: initial_config_for_path.api_base_url.clone(),
         artifact_domain: None,
+        bottle_domain: initial_config_for_path.bottle_domain.clone(),
+        oci_registry: initial_config_for_path.oci_registry.clone(),
         docker_registry_token: None,
         docker_registry_basic_auth: None,
         github_api_token: None,
+        limits: initial_config_for_path.limits,
+        profile: None,
+        cask_user_scope: true,
+        verify_signature: false,
+        quarantine: Default::default(),
+        build_generator: None,
+        build_timeout: None,
+        offline_build: false,
+        skip_relocation: false,
+        overwrite_links: false,
+        go_proxy: None,
+        go_sumdb: None,
+        lock_timeout: Duration::ZERO,
+        cache_dir_override: None,
+        bottle_tag: None,
+        arch_override: None,
+        json_output: false,
+        quiet,
     };
 
     init_args.run(&temp_config_for_init).await
@@ -56,9 +86,26 @@ async fn run_init_command(init_args: &InitArgs, verbose_level: u8) -> spResult<(
 #[tokio::main]
 async fn main() -> spResult<()> {
     let cli_args = CliArgs::parse();
+    let json_output = cli_args.output == cli::ui::OutputFormat::Json;
+    let arch_override = match cli_args.arch.as_deref().map(parse_bottle_arch).transpose() {
+        Ok(arch) => arch.flatten(),
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            process::exit(1);
+        }
+    };
+    let use_ansi = cli::ui::init_color(cli_args.no_color || json_output);
 
     if let Command::Init(ref init_args_ref) = cli_args.command {
-        match run_init_command(init_args_ref, cli_args.verbose).await {
+        match run_init_command(
+            init_args_ref,
+            cli_args.prefix.clone(),
+            cli_args.quiet,
+            cli_args.verbose,
+            use_ansi,
+        )
+        .await
+        {
             Ok(_) => {
                 return Ok(());
             }
@@ -69,13 +116,39 @@ async fn main() -> spResult<()> {
         }
     }
 
-    let config = Config::load().map_err(|e| {
-        SpsError::Config(format!(
-            "Could not load config (have you run 'sps init'?): {e}"
-        ))
-    })?;
+    if let Command::Completions(ref completions) = cli_args.command {
+        completions.run();
+        return Ok(());
+    }
+
+    let mut config = Config::load_with_prefix(cli_args.prefix.clone(), cli_args.profile.as_deref())
+        .map_err(|e| {
+            SpsError::Config(format!(
+                "Could not load config (have you run 'sps init'?): {e}"
+            ))
+        })?;
+    if let Some(cache_dir) = cli_args.cache_dir.clone() {
+        config.set_cache_dir_override(cache_dir);
+    }
+    if let Some(tag) = cli_args.bottle_tag.clone().filter(|t| !t.is_empty()) {
+        config.bottle_tag = Some(tag);
+    }
+    if arch_override.is_some() {
+        config.arch_override = arch_override;
+    }
+    config.json_output = json_output;
+    config.quiet |= cli_args.quiet;
+
+    // Checked before anything below creates directories under the prefix.
+    if cli_args.command.modifies_prefix(&config) {
+        if let Err(e) = config.check_prefix_writable() {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            process::exit(1);
+        }
+    }
 
     let level_filter = match cli_args.verbose {
+        0 if config.quiet => LevelFilter::ERROR,
         0 => LevelFilter::INFO,
         1 => LevelFilter::DEBUG,
         _ => LevelFilter::TRACE,
@@ -98,7 +171,7 @@ async fn main() -> spResult<()> {
         let _ = tracing_subscriber::fmt() // Use `let _ =`
             .with_env_filter(env_filter)
             .with_writer(std::io::stderr)
-            .with_ansi(true)
+            .with_ansi(use_ansi)
             .without_time()
             .try_init(); // Use try_init
     } else if cli_args.verbose > 0 {
@@ -112,7 +185,7 @@ async fn main() -> spResult<()> {
         let _ = tracing_subscriber::fmt() // Use `let _ =`
             .with_env_filter(env_filter)
             .with_writer(stderr_writer.and(file_writer))
-            .with_ansi(true)
+            .with_ansi(use_ansi)
             .without_time()
             .try_init(); // Use try_init
 
@@ -127,26 +200,81 @@ async fn main() -> spResult<()> {
         let _ = tracing_subscriber::fmt() // Use `let _ =`
             .with_env_filter(env_filter)
             .with_writer(std::io::stderr)
-            .with_ansi(true)
+            .with_ansi(use_ansi)
             .without_time()
             .try_init(); // Use try_init
     }
 
+    if let Some(mismatch) = sps_core::check::prefix::prefix_arch_mismatch(&config) {
+        warn!("{}", mismatch);
+    }
+
     let cache = Arc::new(Cache::new(&config).map_err(|e| {
         SpsError::Cache(format!(
             "Could not initialize cache (ensure 'sps init' was successful): {e}"
         ))
     })?);
 
+    // Held until main returns; the OS releases it if the process exits any other way.
+    let process_lock = if cli_args.command.modifies_prefix(&config) {
+        match SpsLock::acquire(&config) {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    if matches!(
+        cli_args.command,
+        Command::Install(_) | Command::Reinstall(_) | Command::Upgrade(_)
+    ) {
+        let interrupted = sps_core::pipeline::journal::incomplete_transactions(&config);
+        if !interrupted.is_empty() {
+            warn!(
+                "{} earlier install(s) were interrupted ({}); run `sps doctor --fix` to clean up",
+                interrupted.len(),
+                interrupted
+                    .iter()
+                    .map(|record| record.target.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    // A dry run reports against the index as it is rather than refreshing it.
     let needs_update_check = matches!(
         cli_args.command,
         Command::Install(_) | Command::Search { .. } | Command::Info { .. } | Command::Upgrade(_)
-    );
+    ) && !cli_args.command.is_dry_run();
 
     if needs_update_check {
-        if let Err(e) = check_and_run_auto_update(&config, Arc::clone(&cache)).await {
+        // Read-only commands only update the index when no other sps process is busy.
+        let mut update_lock = None;
+        let busy = process_lock.is_none()
+            && match SpsLock::try_acquire(&config) {
+                Ok(lock) => {
+                    update_lock = lock;
+                    update_lock.is_none()
+                }
+                Err(e) => {
+                    debug!(
+                        "Could not take the sps lock for the auto-update check: {}",
+                        e
+                    );
+                    false
+                }
+            };
+        if busy {
+            debug!("Another sps process is running; skipping the auto-update check.");
+        } else if let Err(e) = check_and_run_auto_update(&config, Arc::clone(&cache)).await {
             error!("Error during auto-update check: {}", e); // Use `error!` macro
         }
+        drop(update_lock);
     } else {
         debug!(
             // Use `debug!` macro
@@ -155,6 +283,23 @@ async fn main() -> spResult<()> {
         );
     }
 
+    // Source builds and cask installers run in their own process groups, out of reach
+    // of the terminal's Ctrl-C, so forward it to them before exiting.
+    if matches!(
+        cli_args.command,
+        Command::Install(_) | Command::Reinstall(_) | Command::Upgrade(_)
+    ) {
+        tokio::spawn(async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("\nInterrupted, stopping running builds and installers...");
+                let _ =
+                    tokio::task::spawn_blocking(sps_core::build::process::cancel_running_builds)
+                        .await;
+                process::exit(130);
+            }
+        });
+    }
+
     // Pass config and cache to the command's run method
     let command_execution_result = match &cli_args.command {
         Command::Init(_) => {
@@ -165,6 +310,7 @@ async fn main() -> spResult<()> {
     };
 
     if let Err(e) = command_execution_result {
+        let code = exit_code(&e);
         // For pipeline commands (Install, Reinstall, Upgrade), errors are already
         // displayed via the status system, so only log in verbose mode
         let is_pipeline_command = matches!(
@@ -183,13 +329,31 @@ async fn main() -> spResult<()> {
             error!("Command failed: {:#}", e);
             eprintln!("{}: {:#}", "Error".red().bold(), e);
         }
-        process::exit(1);
+        process::exit(code);
     }
 
     debug!("Command completed successfully."); // Use `debug!` macro
     Ok(())
 }
 
+/// The process exit code for a failed command, by error category; listed in `--help`.
+fn exit_code(error: &SpsError) -> i32 {
+    match error {
+        SpsError::Http(_)
+        | SpsError::HttpError(_)
+        | SpsError::DownloadError(..)
+        | SpsError::Api(_)
+        | SpsError::ApiRequestError(_) => 3,
+        SpsError::ChecksumMismatch(_)
+        | SpsError::ChecksumError(_)
+        | SpsError::ValidationError(_) => 4,
+        SpsError::DependencyError(_) => 5,
+        SpsError::NotFound(_) => 6,
+        SpsError::Aggregated { category, .. } => exit_code(category),
+        _ => 1,
+    }
+}
+
 async fn check_and_run_auto_update(config: &Config, cache: Arc<Cache>) -> spResult<()> {
     if env::var("SPS_NO_AUTO_UPDATE").is_ok_and(|v| v == "1") {
         debug!("Auto-update disabled via SPS_NO_AUTO_UPDATE=1.");
@@ -256,18 +420,23 @@ async fn check_and_run_auto_update(config: &Config, cache: Arc<Cache>) -> spResu
     }
 
     if needs_update {
-        println!(
-            "{}{}",
-            "==> ".bold().blue(),
-            "Running auto-update...".bold()
-        );
+        let quiet = config.quiet;
+        if !quiet {
+            println!(
+                "{}{}",
+                "==> ".bold().blue(),
+                "Running auto-update...".bold()
+            );
+        }
         match cli::update::Update.run(config, cache).await {
             Ok(_) => {
-                println!(
-                    "{}{}",
-                    "==> ".bold().blue(),
-                    "Auto-update successful.".bold()
-                );
+                if !quiet {
+                    println!(
+                        "{}{}",
+                        "==> ".bold().blue(),
+                        "Auto-update successful.".bold()
+                    );
+                }
                 match fs::File::create(&timestamp_file) {
                     Ok(_) => {
                         debug!("Updated timestamp file: {}", timestamp_file.display());
@@ -283,7 +452,9 @@ async fn check_and_run_auto_update(config: &Config, cache: Arc<Cache>) -> spResu
             }
             Err(e) => {
                 error!("Auto-update failed: {}", e);
-                eprintln!("{} Auto-update failed: {}", "Warning:".yellow(), e);
+                if !quiet {
+                    eprintln!("{} Auto-update failed: {}", "Warning:".yellow(), e);
+                }
             }
         }
     } else {
@@ -292,3 +463,25 @@ async fn check_and_run_auto_update(config: &Config, cache: Arc<Cache>) -> spResu
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregated_failures_keep_their_category_exit_code() {
+        assert_eq!(exit_code(&SpsError::NotFound("foo".into())), 6);
+        assert_eq!(exit_code(&SpsError::DependencyError("cycle".into())), 5);
+        assert_eq!(exit_code(&SpsError::InstallError("mixed".into())), 1);
+
+        let aggregated = SpsError::Aggregated {
+            message: "Operation failed with 2 total failure(s)".into(),
+            category: Box::new(SpsError::ChecksumMismatch("foo".into())),
+        };
+        assert_eq!(exit_code(&aggregated), 4);
+        assert_eq!(
+            aggregated.to_string(),
+            "Operation failed with 2 total failure(s)"
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod downloader;
+pub mod plan_json;
 pub mod planner;
 pub mod runner;
@@ -7,13 +7,15 @@ use reqwest::Client as HttpClient;
 use sps_common::cache::Cache;
 use sps_common::config::Config;
 use sps_common::model::InstallTargetIdentifier;
-use sps_common::pipeline::{DownloadOutcome, PipelineEvent, PlannedJob};
+use sps_common::pipeline::{
+    DownloadOutcome, DownloadProgressFn, PipelineEvent, PlannedJob, ProgressThrottle,
+};
 use sps_common::SpsError;
 use sps_core::{build, install};
 use sps_net::UrlField;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, Semaphore};
 use tokio::task::JoinSet;
-use tracing::{error, warn};
+use tracing::{debug, error, warn};
 
 use super::runner::get_panic_message;
 
@@ -22,6 +24,10 @@ pub(crate) struct DownloadCoordinator {
     cache: Arc<Cache>,
     http_client: Arc<HttpClient>,
     event_tx: Option<broadcast::Sender<PipelineEvent>>,
+    /// Bounds the number of downloads in flight at once.
+    download_slots: Arc<Semaphore>,
+    /// Skips bottle checksum verification (`--no-verify`).
+    skip_verify: bool,
 }
 
 impl DownloadCoordinator {
@@ -30,12 +36,16 @@ impl DownloadCoordinator {
         cache: Arc<Cache>,
         http_client: Arc<HttpClient>,
         event_tx: broadcast::Sender<PipelineEvent>,
+        max_downloads: usize,
+        skip_verify: bool,
     ) -> Self {
         Self {
             config,
             cache,
             http_client,
             event_tx: Some(event_tx),
+            download_slots: Arc::new(Semaphore::new(max_downloads.max(1))),
+            skip_verify,
         }
     }
 
@@ -55,6 +65,8 @@ impl DownloadCoordinator {
             let task_http_client = Arc::clone(&self.http_client);
             let task_event_tx = self.event_tx.as_ref().cloned();
             let outcome_tx_clone = download_outcome_tx.clone();
+            let task_download_slots = Arc::clone(&self.download_slots);
+            let task_skip_verify = self.skip_verify;
             let current_planned_job_for_task = planned_job.clone();
 
             download_tasks.spawn(async move {
@@ -67,7 +79,7 @@ impl DownloadCoordinator {
                     let display_url_for_event = match &current_planned_job_for_task.target_definition {
                         InstallTargetIdentifier::Formula(f) => {
                             if !current_planned_job_for_task.is_source_build {
-                                sps_core::install::bottle::exec::get_bottle_for_platform(f)
+                                sps_core::install::bottle::exec::get_bottle_for_platform(f, &task_config)
                                     .map_or_else(|_| f.url.clone(), |(_, spec)| spec.url.clone())
                             } else {
                                 f.url.clone()
@@ -96,6 +108,22 @@ impl DownloadCoordinator {
                         }
                         download_path_result = Err(sps_err);
                     } else {
+                        // Held until this download finishes; the outcome is still sent right
+                        // away so the job can be dispatched without waiting on the batch.
+                        let _download_slot = match task_download_slots.acquire_owned().await {
+                            Ok(permit) => Some(permit),
+                            Err(e) => {
+                                warn!(
+                                    "[DownloaderTask:{}] Download limiter closed ({}), continuing unbounded.",
+                                    job_id_in_task, e
+                                );
+                                None
+                            }
+                        };
+                        debug!(
+                            "[DownloaderTask:{}] Acquired download slot.",
+                            job_id_in_task
+                        );
                         if let Some(ref tx) = task_event_tx {
                             tx.send(PipelineEvent::DownloadStarted {
                                 target_id: job_id_in_task.clone(),
@@ -103,22 +131,34 @@ impl DownloadCoordinator {
                             }).ok();
                         }
 
+                        let progress = task_event_tx
+                            .as_ref()
+                            .map(|tx| download_progress_reporter(&job_id_in_task, tx));
                         let actual_download_result: Result<PathBuf, SpsError> =
                             match &current_planned_job_for_task.target_definition {
                                 InstallTargetIdentifier::Formula(f) => {
                                     if current_planned_job_for_task.is_source_build {
-                                        build::compile::download_source(f, &task_config).await
+                                        build::compile::download_source(f, &task_config, progress)
+                                            .await
                                     } else {
                                         install::bottle::exec::download_bottle(
                                             f,
                                             &task_config,
                                             &task_http_client,
+                                            progress,
+                                            task_skip_verify,
                                         )
                                         .await
                                     }
                                 }
                                 InstallTargetIdentifier::Cask(c) => {
-                                    install::cask::download_cask(c, task_cache.as_ref()).await
+                                    install::cask::download_cask(
+                                        c,
+                                        &task_config,
+                                        task_cache.as_ref(),
+                                        progress,
+                                    )
+                                        .await
                                 }
                             };
 
@@ -183,3 +223,23 @@ impl DownloadCoordinator {
         critical_spawn_errors
     }
 }
+
+/// Builds a callback that forwards download progress for `target_id` as throttled
+/// `DownloadProgress` events.
+fn download_progress_reporter(
+    target_id: &str,
+    event_tx: &broadcast::Sender<PipelineEvent>,
+) -> DownloadProgressFn {
+    let target_id = target_id.to_string();
+    let event_tx = event_tx.clone();
+    let throttle = ProgressThrottle::new();
+    Arc::new(move |bytes_done, total_bytes| {
+        if throttle.should_send(total_bytes == Some(bytes_done)) {
+            let _ = event_tx.send(PipelineEvent::DownloadProgress {
+                target_id: target_id.clone(),
+                bytes_done,
+                total_bytes,
+            });
+        }
+    })
+}
@@ -0,0 +1,163 @@
+// sps/src/pipeline/plan_json.rs
+//! Serializes a finished plan for `sps install --json-plan`.
+//!
+//! The output is a contract for external tools, so its shape only changes together
+//! with `PLAN_SCHEMA_VERSION`. Fields may be added within a version; renaming or
+//! removing one, or changing its meaning, bumps the version.
+//!
+//! Schema version 1:
+//!
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "jobs": [                      // in install order: dependencies come first
+//!     {
+//!       "target": "wget",
+//!       "kind": "formula" | "cask",
+//!       "version": "1.24.5",       // null for casks without a version
+//!       "action": "install" | "upgrade" | "reinstall",
+//!       "from_version": "1.24.4",  // upgrade/reinstall only, otherwise null
+//!       "source_build": false,
+//!       "up_to_date": false,       // true if nothing needs to be done for it
+//!       "url": "https://...",      // bottle, source or cask artifact; null if none
+//!       "sha256": "...",           // null if the artifact is not checksummed
+//!       "local_source": null,      // path used instead of downloading `url`
+//!       "depends_on": ["openssl@3"] // targets of other jobs in this plan
+//!     }
+//!   ],
+//!   "errors": [{ "target": "foo", "message": "..." }]
+//! }
+//! ```
+use std::collections::HashSet;
+
+use serde::Serialize;
+use sps_common::config::Config;
+use sps_common::dependency::DependencyExt;
+use sps_common::model::cask::{Sha256Field, UrlField};
+use sps_common::model::InstallTargetIdentifier;
+use sps_common::pipeline::{JobAction, PlannedJob, PlannedOperations};
+use sps_core::install::bottle::exec::get_bottle_for_platform;
+
+pub const PLAN_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct JsonPlan {
+    pub schema_version: u32,
+    pub jobs: Vec<JsonPlannedJob>,
+    pub errors: Vec<JsonPlanError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonPlannedJob {
+    pub target: String,
+    pub kind: &'static str,
+    pub version: Option<String>,
+    pub action: &'static str,
+    pub from_version: Option<String>,
+    pub source_build: bool,
+    pub up_to_date: bool,
+    pub url: Option<String>,
+    pub sha256: Option<String>,
+    pub local_source: Option<String>,
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonPlanError {
+    pub target: String,
+    pub message: String,
+}
+
+impl JsonPlan {
+    pub fn from_operations(ops: &PlannedOperations, config: &Config) -> Self {
+        let planned: HashSet<&str> = ops.jobs.iter().map(|j| j.target_id.as_str()).collect();
+        Self {
+            schema_version: PLAN_SCHEMA_VERSION,
+            jobs: ops
+                .jobs
+                .iter()
+                .map(|job| {
+                    let up_to_date = ops.already_installed_or_up_to_date.contains(&job.target_id);
+                    json_job(job, up_to_date, &planned, config)
+                })
+                .collect(),
+            errors: ops
+                .errors
+                .iter()
+                .map(|(target, error)| JsonPlanError {
+                    target: target.clone(),
+                    message: error.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn json_job(
+    job: &PlannedJob,
+    up_to_date: bool,
+    planned: &HashSet<&str>,
+    config: &Config,
+) -> JsonPlannedJob {
+    let (action, from_version) = match &job.action {
+        JobAction::Install => ("install", None),
+        JobAction::Upgrade { from_version, .. } => ("upgrade", Some(from_version.clone())),
+        JobAction::Reinstall { version, .. } => ("reinstall", Some(version.clone())),
+    };
+    let (kind, version, url, sha256, depends_on) = match &job.target_definition {
+        InstallTargetIdentifier::Formula(formula) => {
+            let (url, sha256) = if job.is_source_build {
+                (formula.url.clone(), formula.sha256.clone())
+            } else {
+                match get_bottle_for_platform(formula, config) {
+                    Ok((_, spec)) => (spec.url.clone(), spec.sha256.clone()),
+                    Err(_) => (String::new(), String::new()),
+                }
+            };
+            let deps = formula.dependencies().unwrap_or_default();
+            let needed = if job.is_source_build {
+                deps.iter().collect::<Vec<_>>()
+            } else {
+                deps.runtime()
+            };
+            let depends_on = needed
+                .into_iter()
+                .map(|dep| dep.name.clone())
+                .filter(|name| planned.contains(name.as_str()))
+                .collect();
+            (
+                "formula",
+                Some(formula.version_str_full()),
+                Some(url).filter(|u| !u.is_empty()),
+                Some(sha256).filter(|s| !s.is_empty()),
+                depends_on,
+            )
+        }
+        InstallTargetIdentifier::Cask(cask) => {
+            let url = cask.url.as_ref().map(|field| match field {
+                UrlField::Simple(url) | UrlField::WithSpec { url, .. } => url.clone(),
+            });
+            let sha256 = match &cask.sha256 {
+                Some(Sha256Field::Hex(hex)) => Some(hex.clone()),
+                _ => None,
+            };
+            ("cask", cask.version.clone(), url, sha256, Vec::new())
+        }
+    };
+    JsonPlannedJob {
+        target: job.target_id.clone(),
+        kind,
+        version,
+        action,
+        from_version,
+        source_build: job.is_source_build,
+        up_to_date,
+        url,
+        sha256,
+        local_source: job
+            .use_private_store_source
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        depends_on,
+    }
+}
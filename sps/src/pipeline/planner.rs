@@ -11,11 +11,14 @@ use sps_common::dependency::resolver::{
 };
 use sps_common::error::{Result as SpsResult, SpsError};
 use sps_common::formulary::Formulary;
-use sps_common::keg::KegRegistry;
+use sps_common::keg::{installed_on_request, KegRegistry};
+use sps_common::model::tap::{self, DefinitionKind};
 use sps_common::model::{Cask, Formula, InstallTargetIdentifier};
 use sps_common::pipeline::{JobAction, PipelineEvent, PlannedJob, PlannedOperations};
 use sps_core::check::installed::{self, InstalledPackageInfo, PackageType as CorePackageType};
+use sps_core::check::pinned;
 use sps_core::check::update::{self, UpdateInfo};
+use sps_core::install::devtools;
 use tokio::sync::broadcast;
 use tokio::task::JoinSet;
 use tracing::{debug, error as trace_error, instrument, warn};
@@ -31,6 +34,10 @@ struct IntermediatePlan {
     already_satisfied: HashSet<String>,
     processed_globally: HashSet<String>,
     private_store_sources: HashMap<String, PathBuf>,
+    /// Formulae loaded from local JSON files, keyed by formula name.
+    local_formulae: HashMap<String, Arc<Formula>>,
+    /// The formula `--build-from-local` builds from its archive.
+    local_archive_target: Option<String>,
 }
 
 #[instrument(skip(cache))]
@@ -80,6 +87,7 @@ pub(crate) async fn fetch_target_definitions(
         let name_owned = name_str.to_string();
         let local_formulae_map = formulae_map.clone();
         let local_casks_map = casks_map.clone();
+        let config = cache.config().clone();
 
         futures.spawn(async move {
             if let Some(ref map) = local_formulae_map {
@@ -93,14 +101,17 @@ pub(crate) async fn fetch_target_definitions(
                 }
             }
             debug!("[FetchDefs] Definition for '{}' not found in cached lists, fetching directly from API...", name_owned);
-            match sps_net::api::get_formula(&name_owned).await {
+            match sps_net::api::get_formula(&name_owned, &config).await {
                 Ok(formula_obj) => return (name_owned, Ok(InstallTargetIdentifier::Formula(Arc::new(formula_obj)))),
                 Err(SpsError::NotFound(_)) => {}
                 Err(e) => return (name_owned, Err(e)),
             }
-            match sps_net::api::get_cask(&name_owned).await {
+            match sps_net::api::get_cask(&name_owned, &config).await {
                 Ok(cask_obj) => (name_owned, Ok(InstallTargetIdentifier::Cask(Arc::new(cask_obj)))),
-                Err(SpsError::NotFound(_)) => (name_owned.clone(), Err(SpsError::NotFound(format!("Formula or Cask '{name_owned}' not found")))),
+                Err(SpsError::NotFound(_)) => match load_tapped_definition(&config, &name_owned) {
+                    Some(result) => (name_owned, result),
+                    None => (name_owned.clone(), Err(SpsError::NotFound(format!("Formula or Cask '{name_owned}' not found")))),
+                },
                 Err(e) => (name_owned, Err(e)),
             }
         });
@@ -129,19 +140,37 @@ pub(crate) async fn fetch_target_definitions(
     results
 }
 
-async fn load_or_fetch_formulae_map(cache: Arc<Cache>) -> SpsResult<HashMap<String, Arc<Formula>>> {
-    match cache.load_raw("formula.json") {
-        Ok(data) => {
-            let formulas: Vec<Formula> = serde_json::from_str(&data)
-                .map_err(|e| SpsError::Cache(format!("Parse cached formula.json failed: {e}")))?;
-            Ok(formulas
-                .into_iter()
-                .map(|f| (f.name.clone(), Arc::new(f)))
-                .collect())
-        }
+/// The formula or cask `name` from an installed tap, for names core does not know.
+fn load_tapped_definition(
+    config: &Config,
+    name: &str,
+) -> Option<SpsResult<InstallTargetIdentifier>> {
+    match sps_common::formulary::load_tapped_formula(config, name) {
+        Ok(Some(formula)) => return Some(Ok(InstallTargetIdentifier::Formula(Arc::new(formula)))),
+        Ok(None) => {}
+        Err(e) => return Some(Err(e)),
+    }
+    let tapped = tap::find_tapped_definition(config, DefinitionKind::Cask, name)?;
+    Some(
+        serde_json::from_value::<Cask>(tapped.value)
+            .map(|cask| InstallTargetIdentifier::Cask(Arc::new(cask)))
+            .map_err(|e| {
+                SpsError::Generic(format!("Invalid cask '{name}' in tap {}: {e}", tapped.tap))
+            }),
+    )
+}
+
+pub(crate) async fn load_or_fetch_formulae_map(
+    cache: Arc<Cache>,
+) -> SpsResult<HashMap<String, Arc<Formula>>> {
+    match sps_common::formulary::load_formula_index(&cache) {
+        Ok(formulas) => Ok(formulas
+            .into_iter()
+            .map(|f| (f.name.clone(), Arc::new(f)))
+            .collect()),
         Err(_) => {
             debug!("[FetchDefs] Cache miss for formula.json, fetching from API...");
-            let raw_data = sps_net::api::fetch_all_formulas().await?;
+            let raw_data = sps_net::api::fetch_all_formulas(cache.config()).await?;
             if let Err(e) = cache.store_raw("formula.json", &raw_data) {
                 warn!("Failed to store formula.json in cache: {}", e);
             }
@@ -156,18 +185,14 @@ async fn load_or_fetch_formulae_map(cache: Arc<Cache>) -> SpsResult<HashMap<Stri
 }
 
 async fn load_or_fetch_casks_map(cache: Arc<Cache>) -> SpsResult<HashMap<String, Arc<Cask>>> {
-    match cache.load_raw("cask.json") {
-        Ok(data) => {
-            let casks: Vec<Cask> = serde_json::from_str(&data)
-                .map_err(|e| SpsError::Cache(format!("Parse cached cask.json failed: {e}")))?;
-            Ok(casks
-                .into_iter()
-                .map(|c| (c.token.clone(), Arc::new(c)))
-                .collect())
-        }
+    match cache.load_parsed::<Vec<Cask>>("cask.json") {
+        Ok(casks) => Ok(casks
+            .into_iter()
+            .map(|c| (c.token.clone(), Arc::new(c)))
+            .collect()),
         Err(_) => {
             debug!("[FetchDefs] Cache miss for cask.json, fetching from API...");
-            let raw_data = sps_net::api::fetch_all_casks().await?;
+            let raw_data = sps_net::api::fetch_all_casks(cache.config()).await?;
             if let Err(e) = cache.store_raw("cask.json", &raw_data) {
                 warn!("Failed to store cask.json in cache: {}", e);
             }
@@ -279,7 +304,42 @@ impl<'a> OperationPlanner<'a> {
 
     async fn plan_for_install(&self, targets: &[String]) -> PlanResult<IntermediatePlan> {
         let mut plan = IntermediatePlan::default();
-        for name in targets {
+        let home = self.config.home_dir();
+        let formulae_map = if targets
+            .iter()
+            .any(|target| target.contains('@') && local_formula_path(target, &home).is_none())
+        {
+            load_or_fetch_formulae_map(self.cache.clone()).await.ok()
+        } else {
+            None
+        };
+        for target in targets {
+            let local_formula = match local_formula_path(target, &home)
+                .as_deref()
+                .map(load_local_formula)
+            {
+                Some(Ok(formula)) => Some(Arc::new(formula)),
+                Some(Err(e)) => {
+                    plan.errors.push((target.clone(), e));
+                    plan.processed_globally.insert(target.clone());
+                    continue;
+                }
+                None => None,
+            };
+            let name = &match (&local_formula, &formulae_map) {
+                (Some(formula), _) => formula.name().to_string(),
+                (None, Some(formulae)) => {
+                    match self.resolve_versioned_target(target, formulae).await {
+                        Ok(name) => name,
+                        Err(e) => {
+                            plan.errors.push((target.clone(), e));
+                            plan.processed_globally.insert(target.clone());
+                            continue;
+                        }
+                    }
+                }
+                (None, None) => target.clone(),
+            };
             if plan.processed_globally.contains(name) {
                 continue;
             }
@@ -311,7 +371,30 @@ impl<'a> OperationPlanner<'a> {
                             proceed_with_install = true;
                         }
                     }
-                    if proceed_with_install {
+                    // With --only-dependencies the target is planned anyway so that its
+                    // dependencies get resolved; its own job is dropped later.
+                    if self.flags.force && !self.flags.only_dependencies {
+                        debug!("Target '{}' is installed; reinstalling it (--force).", name);
+                        if installed_info.pkg_type == CorePackageType::Cask {
+                            if let Some(private_path) = self
+                                .determine_cask_private_store_source(name, &installed_info.version)
+                                .await
+                            {
+                                plan.private_store_sources
+                                    .insert(name.clone(), private_path);
+                            }
+                        }
+                        plan.initial_ops.insert(
+                            name.clone(),
+                            (
+                                JobAction::Reinstall {
+                                    version: installed_info.version.clone(),
+                                    current_install_path: installed_info.path.clone(),
+                                },
+                                None,
+                            ),
+                        );
+                    } else if proceed_with_install || self.flags.only_dependencies {
                         if let Some(private_path) = self
                             .determine_cask_private_store_source(name, &installed_info.version)
                             .await
@@ -348,10 +431,49 @@ impl<'a> OperationPlanner<'a> {
                     plan.processed_globally.insert(name.clone());
                 }
             }
+
+            if let Some((_, opt_def)) = plan.initial_ops.get_mut(name) {
+                if let Some(formula) = local_formula {
+                    *opt_def = Some(InstallTargetIdentifier::Formula(Arc::clone(&formula)));
+                    plan.local_formulae.insert(name.clone(), formula);
+                }
+                if let Some((_, archive)) = self
+                    .flags
+                    .build_from_local
+                    .as_ref()
+                    .filter(|(local_target, _)| local_target == target)
+                {
+                    plan.private_store_sources
+                        .insert(name.clone(), archive.clone());
+                    plan.local_archive_target = Some(name.clone());
+                }
+            }
         }
         Ok(plan)
     }
 
+    /// Resolves a `name@version` install target to the formula to install. A cask or
+    /// tapped definition of exactly that name is left alone.
+    async fn resolve_versioned_target(
+        &self,
+        target: &str,
+        formulae: &HashMap<String, Arc<Formula>>,
+    ) -> PlanResult<String> {
+        match sps_common::formulary::resolve_versioned_name(target, formulae) {
+            Ok(name) => Ok(name),
+            Err(e) => {
+                let is_cask = load_or_fetch_casks_map(self.cache.clone())
+                    .await
+                    .is_ok_and(|casks| casks.contains_key(target));
+                if is_cask || load_tapped_definition(self.config, target).is_some() {
+                    Ok(target.to_string())
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
     async fn plan_for_reinstall(&self, targets: &[String]) -> PlanResult<IntermediatePlan> {
         let mut plan = IntermediatePlan::default();
         for name in targets {
@@ -399,14 +521,51 @@ impl<'a> OperationPlanner<'a> {
         Ok(plan)
     }
 
+    /// Refuses casks whose `depends_on.macos` range excludes the running macOS,
+    /// unless `--force` was given.
+    fn check_macos_requirement(&self, cask: &Cask) -> SpsResult<()> {
+        if !cfg!(target_os = "macos") {
+            return Ok(());
+        }
+        let Some(req) = cask.depends_on.as_ref().and_then(|d| d.macos.as_ref()) else {
+            return Ok(());
+        };
+        let bounds = req.bounds()?;
+        if bounds.is_unbounded() {
+            return Ok(());
+        }
+        let host = devtools::get_macos_version()?;
+        if bounds.contains(&host)? {
+            return Ok(());
+        }
+        if self.flags.force {
+            warn!(
+                "Installing '{}' on macOS {} although it requires {} (--force)",
+                cask.token, host, bounds
+            );
+            return Ok(());
+        }
+        Err(SpsError::Generic(format!(
+            "Cask '{}' requires {}, but this system runs macOS {}. Pass --force to install anyway.",
+            cask.token, bounds, host
+        )))
+    }
+
     async fn plan_for_upgrade(
         &self,
         targets: &[String],
         all: bool,
     ) -> PlanResult<IntermediatePlan> {
         let mut plan = IntermediatePlan::default();
+        let pinned_packages = pinned::get_pinned_packages(self.config).unwrap_or_else(|e| {
+            warn!(
+                "[Planner] Failed to read pinned packages, assuming none: {}",
+                e
+            );
+            Default::default()
+        });
         let packages_to_check = if all {
-            installed::get_installed_packages(self.config)
+            let installed_packages = installed::get_installed_packages(self.config)
                 .await
                 .map_err(|e| {
                     plan.errors.push((
@@ -414,10 +573,39 @@ impl<'a> OperationPlanner<'a> {
                         SpsError::Generic(format!("Failed to get installed packages: {e}")),
                     ));
                     e
-                })?
+                })?;
+            let mut unpinned = Vec::with_capacity(installed_packages.len());
+            for info in installed_packages {
+                if pinned_packages.contains(&info.name) {
+                    debug!("[Planner] Skipping pinned package '{}'", info.name);
+                    self.event_tx
+                        .send(PipelineEvent::LogInfo {
+                            message: format!(
+                                "Skipping pinned package '{}' ({})",
+                                info.name, info.version
+                            ),
+                        })
+                        .ok();
+                    plan.already_satisfied.insert(info.name.clone());
+                    plan.processed_globally.insert(info.name.clone());
+                } else {
+                    unpinned.push(info);
+                }
+            }
+            unpinned
         } else {
             let mut specific = Vec::new();
             for name in targets {
+                if pinned_packages.contains(name) && !self.flags.force {
+                    plan.errors.push((
+                        name.to_string(),
+                        SpsError::Generic(format!(
+                            "Cannot upgrade '{name}': package is pinned. Run 'sps unpin {name}' or pass --force."
+                        )),
+                    ));
+                    plan.processed_globally.insert(name.clone());
+                    continue;
+                }
                 match self.check_installed_status(name).await {
                     Ok(Some(info)) => {
                         if info.pkg_type == CorePackageType::Cask {
@@ -461,6 +649,35 @@ impl<'a> OperationPlanner<'a> {
             specific
         };
 
+        let packages_to_check: Vec<InstalledPackageInfo> = match &self.flags.only_type {
+            Some(only_type) => packages_to_check
+                .into_iter()
+                .filter(|info| {
+                    if &info.pkg_type == only_type {
+                        return true;
+                    }
+                    debug!("[Planner] Skipping '{}': not a {:?}", info.name, only_type);
+                    if !all {
+                        self.event_tx
+                            .send(PipelineEvent::LogInfo {
+                                message: format!(
+                                    "Skipping '{}': only upgrading {}",
+                                    info.name,
+                                    match only_type {
+                                        CorePackageType::Formula => "formulae",
+                                        CorePackageType::Cask => "casks",
+                                    }
+                                ),
+                            })
+                            .ok();
+                    }
+                    plan.processed_globally.insert(info.name.clone());
+                    false
+                })
+                .collect(),
+            None => packages_to_check,
+        };
+
         if packages_to_check.is_empty() {
             return Ok(plan);
         }
@@ -572,10 +789,14 @@ impl<'a> OperationPlanner<'a> {
                         }
                     }
                     Err(e) => {
-                        intermediate_plan.errors.push((
-                            name.clone(),
-                            SpsError::Generic(format!("Failed to get definition for target: {e}")),
-                        ));
+                        // Not-found and network errors keep their kind for the exit code.
+                        let e = match e {
+                            SpsError::Generic(msg) => SpsError::Generic(format!(
+                                "Failed to get definition for target: {msg}"
+                            )),
+                            e => e,
+                        };
+                        intermediate_plan.errors.push((name.clone(), e));
                         intermediate_plan.processed_globally.insert(name);
                     }
                 }
@@ -672,6 +893,14 @@ impl<'a> OperationPlanner<'a> {
                 }
             };
 
+            if let Err(e) = self.check_macos_requirement(&cask_arc) {
+                intermediate_plan.errors.push((cask_token.clone(), e));
+                intermediate_plan
+                    .processed_globally
+                    .insert(cask_token.clone());
+                continue;
+            }
+
             if let Some(deps) = &cask_arc.depends_on {
                 for formula_dep_name in &deps.formula {
                     if formulae_for_resolution.contains_key(formula_dep_name)
@@ -744,13 +973,21 @@ impl<'a> OperationPlanner<'a> {
         if !formulae_for_resolution.is_empty() {
             let targets_for_resolver: Vec<_> = formulae_for_resolution.keys().cloned().collect();
             let formulary = Formulary::new(self.config.clone());
+            for formula in intermediate_plan.local_formulae.values() {
+                formulary.register(formula.as_ref().clone());
+            }
             let keg_registry = KegRegistry::new(self.config.clone());
 
             let per_target_prefs = PerTargetInstallPreferences {
                 force_source_build_targets: if self.flags.build_from_source {
                     targets_for_resolver.iter().cloned().collect()
+                } else if let Some(local_target) = &intermediate_plan.local_archive_target {
+                    // The local archive is a source tree; never fall back to a bottle.
+                    std::iter::once(local_target.clone())
+                        .chain(self.flags.build_from_source_formulae.iter().cloned())
+                        .collect()
                 } else {
-                    HashSet::new()
+                    self.flags.build_from_source_formulae.clone()
                 },
                 force_bottle_only_targets: HashSet::new(),
             };
@@ -781,11 +1018,15 @@ impl<'a> OperationPlanner<'a> {
                 keg_registry: &keg_registry,
                 sps_prefix: self.config.sps_root(),
                 include_optional: self.flags.include_optional,
-                include_test: false,
+                include_test: self.flags.include_test,
                 skip_recommended: self.flags.skip_recommended,
                 initial_target_preferences: &per_target_prefs,
                 build_all_from_source: self.flags.build_from_source,
-                cascade_source_preference_to_dependencies: true,
+                // Naming formulae to build means only those; their dependencies still pour.
+                cascade_source_preference_to_dependencies: self
+                    .flags
+                    .build_from_source_formulae
+                    .is_empty(),
                 has_bottle_for_current_platform:
                     sps_core::install::bottle::has_bottle_for_current_platform,
                 initial_target_actions: &initial_target_actions,
@@ -799,11 +1040,27 @@ impl<'a> OperationPlanner<'a> {
                         "[Planner] Dependency resolution succeeded! Install plan has {} items",
                         g.install_plan.len()
                     );
+                    let mut unused: Vec<&String> = self
+                        .flags
+                        .build_from_source_formulae
+                        .iter()
+                        .filter(|name| !g.resolution_details.contains_key(name.as_str()))
+                        .collect();
+                    if !unused.is_empty() {
+                        unused.sort();
+                        warn!(
+                            "--build-from-source names formulae that are not part of this install: {}",
+                            unused.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")
+                        );
+                    }
                     resolved_formula_graph_opt = Some(Arc::new(g));
                 }
                 Err(e) => {
                     debug!("[Planner] Dependency resolution failed: {}", e);
-                    let resolver_error_msg = e.to_string(); // Capture full error
+                    let resolver_error_msg = match e {
+                        SpsError::DependencyError(msg) => msg,
+                        e => e.to_string(),
+                    };
                     for n in targets_for_resolver {
                         if !intermediate_plan
                             .errors
@@ -864,7 +1121,27 @@ impl<'a> OperationPlanner<'a> {
                 continue;
             }
 
+            if self.flags.only_dependencies {
+                debug!(
+                    "[Planner] Not planning '{}' itself (--only-dependencies)",
+                    name
+                );
+                names_processed_from_initial_ops.insert(name.clone());
+                continue;
+            }
+
             match opt_def {
+                Some(InstallTargetIdentifier::Cask(_))
+                    if intermediate_plan.local_archive_target.as_ref() == Some(name) =>
+                {
+                    intermediate_plan.errors.push((
+                        name.clone(),
+                        SpsError::Generic(format!(
+                            "'{name}' is a cask; --build-from-local only works for formulae."
+                        )),
+                    ));
+                    names_processed_from_initial_ops.insert(name.clone());
+                }
                 Some(target_def) => {
                     let is_source_build = determine_build_strategy_for_job(
                         target_def,
@@ -883,6 +1160,23 @@ impl<'a> OperationPlanner<'a> {
                             .private_store_sources
                             .get(name)
                             .cloned(),
+                        installed_on_request: match action {
+                            JobAction::Install => true,
+                            // `install --force` of an installed formula names it.
+                            JobAction::Reinstall { .. }
+                                if matches!(command_type, CommandType::Install) =>
+                            {
+                                true
+                            }
+                            JobAction::Upgrade {
+                                old_install_path: path,
+                                ..
+                            }
+                            | JobAction::Reinstall {
+                                current_install_path: path,
+                                ..
+                            } => installed_on_request(path).unwrap_or(true),
+                        },
                     });
                     names_processed_from_initial_ops.insert(name.clone());
                 }
@@ -970,6 +1264,7 @@ impl<'a> OperationPlanner<'a> {
                         action: JobAction::Install,
                         is_source_build: is_source_build_for_dep,
                         use_private_store_source: None,
+                        installed_on_request: false,
                     });
                 } else if dep_detail.status == ResolutionStatus::Installed {
                     intermediate_plan
@@ -998,6 +1293,7 @@ impl<'a> OperationPlanner<'a> {
                             .private_store_sources
                             .get(&cask_token)
                             .cloned(),
+                        installed_on_request: false,
                     });
                 }
                 Ok(Some(_installed_info)) => {
@@ -1047,6 +1343,53 @@ impl<'a> OperationPlanner<'a> {
     }
 }
 
+/// Install targets written as a path (`./foo.json`, `/abs/foo.json`, `~/foo.json`)
+/// name a local formula file rather than a formula from the API. A bare `foo.json`
+/// only does if that file exists, so that it can still name a formula otherwise.
+fn local_formula_path(target: &str, home: &Path) -> Option<PathBuf> {
+    if let Some(relative) = target.strip_prefix("~/") {
+        return Some(home.join(relative));
+    }
+    if ["./", "../", "/"]
+        .iter()
+        .any(|prefix| target.starts_with(prefix))
+    {
+        return Some(PathBuf::from(target));
+    }
+    let path = Path::new(target);
+    (target.ends_with(".json") && path.is_file()).then(|| path.to_path_buf())
+}
+
+/// Reads a formula definition in the API's JSON format from `path`. Parse errors
+/// name the file, line and column, and quote the offending line.
+fn load_local_formula(path: &Path) -> SpsResult<Formula> {
+    let raw = std::fs::read_to_string(path).map_err(|e| {
+        SpsError::Generic(format!(
+            "Failed to read formula file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    serde_json::from_str::<Formula>(&raw).map_err(|e| {
+        let location = format!(" at line {} column {}", e.line(), e.column());
+        let message = e.to_string();
+        let message = message.strip_suffix(&location).unwrap_or(&message);
+        let excerpt = raw
+            .lines()
+            .nth(e.line().saturating_sub(1))
+            .map(str::trim)
+            .unwrap_or_default();
+        SpsError::Generic(format!(
+            "Invalid formula file {}:{}:{}: {}\n    {}",
+            path.display(),
+            e.line(),
+            e.column(),
+            message,
+            excerpt
+        ))
+    })
+}
+
 fn determine_build_strategy_for_job(
     target_def: &InstallTargetIdentifier,
     action: &JobAction,
@@ -1080,7 +1423,7 @@ fn determine_build_strategy_for_job(
                     return true;
                 }
             }
-            !sps_core::install::bottle::has_bottle_for_current_platform(formula_arc)
+            !sps_core::install::bottle::has_bottle_for_current_platform(formula_arc, planner.config)
         }
         InstallTargetIdentifier::Cask(_) => false,
     }
@@ -1102,3 +1445,27 @@ fn sort_planned_jobs(jobs: &mut [PlannedJob], formula_graph: &ResolvedGraph) {
         InstallTargetIdentifier::Cask(_) => usize::MAX - 1,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_formula_paths_need_a_path_or_an_existing_file() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            local_formula_path("~/formulae/foo.json", home),
+            Some(PathBuf::from("/home/me/formulae/foo.json"))
+        );
+        assert_eq!(
+            local_formula_path("./foo.json", home),
+            Some(PathBuf::from("./foo.json"))
+        );
+        assert_eq!(
+            local_formula_path("/tmp/foo.json", home),
+            Some(PathBuf::from("/tmp/foo.json"))
+        );
+        assert_eq!(local_formula_path("foo", home), None);
+        assert_eq!(local_formula_path("no-such-file.json", home), None);
+    }
+}
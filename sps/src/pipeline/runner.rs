@@ -1,21 +1,23 @@
 // sps/src/pipeline/runner.rs
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use colored::Colorize;
 use crossbeam_channel::bounded as crossbeam_bounded;
-use reqwest::Client as HttpClient;
 use sps_common::cache::Cache;
 use sps_common::config::Config;
 use sps_common::dependency::resolver::{ResolutionStatus, ResolvedGraph};
 use sps_common::error::{Result as SpsResult, SpsError};
+use sps_common::keg::KegRegistry;
 use sps_common::model::InstallTargetIdentifier;
 use sps_common::pipeline::{
     DownloadOutcome, JobProcessingState, PipelineEvent, PlannedJob,
     PlannedOperations as PlannerOutputCommon, WorkerJob,
 };
+use sps_core::check::installed::PackageType;
 use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, instrument, warn};
@@ -23,9 +25,10 @@ use tracing::{debug, error, instrument, warn};
 use super::downloader::DownloadCoordinator;
 use super::planner::OperationPlanner;
 
-const WORKER_JOB_CHANNEL_SIZE: usize = 100;
 const EVENT_CHANNEL_SIZE: usize = 100;
 const DOWNLOAD_OUTCOME_CHANNEL_SIZE: usize = 100;
+/// Present once Rosetta 2 has been installed.
+const ROSETTA_RUNTIME: &str = "/Library/Apple/usr/libexec/oah/libRosettaRuntime";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CommandType {
@@ -37,8 +40,22 @@ pub enum CommandType {
 #[derive(Debug, Clone)]
 pub struct PipelineFlags {
     pub build_from_source: bool,
+    /// Formulae to build from source, whether targets or dependencies; everything else
+    /// still pours bottles where it can.
+    pub build_from_source_formulae: HashSet<String>,
     pub include_optional: bool,
     pub skip_recommended: bool,
+    /// Also resolve and install test dependencies.
+    pub include_test: bool,
+    /// Install the dependencies of the targets but not the targets themselves.
+    pub only_dependencies: bool,
+    pub force: bool,
+    pub no_verify: bool,
+    /// The install target to build from a local source archive, and the archive. No
+    /// other formula, target or dependency, is affected.
+    pub build_from_local: Option<(String, PathBuf)>,
+    /// Limits an upgrade to installed packages of this type.
+    pub only_type: Option<PackageType>,
 }
 
 struct PropagationContext {
@@ -49,6 +66,23 @@ struct PropagationContext {
     final_fail_count: Arc<AtomicUsize>,
 }
 
+/// Records that installed formulae named by `install` were requested, so that a formula
+/// first pulled in as a dependency is no longer an autoremove candidate once the user
+/// asks for it by name.
+fn mark_requested(names: &HashSet<String>, config: &Config) {
+    let registry = KegRegistry::new(config.clone());
+    for name in names {
+        let Ok(Some(keg)) = registry.get_installed_keg(name) else {
+            continue;
+        };
+        match sps_common::keg::mark_installed_on_request(&keg.path) {
+            Ok(true) => debug!("[{}] Now recorded as installed on request.", name),
+            Ok(false) => {}
+            Err(e) => warn!("Could not record {} as installed on request: {}", name, e),
+        }
+    }
+}
+
 fn err_to_string(e: &SpsError) -> String {
     e.to_string()
 }
@@ -63,6 +97,21 @@ pub(crate) fn get_panic_message(e: Box<dyn std::any::Any + Send>) -> String {
     }
 }
 
+/// Runs only the planning phase, for callers that act on the plan themselves. Planner
+/// events are discarded; nothing is downloaded or installed.
+pub async fn plan_pipeline(
+    initial_targets: &[String],
+    command_type: CommandType,
+    config: &Config,
+    cache: Arc<Cache>,
+    flags: &PipelineFlags,
+) -> SpsResult<PlannerOutputCommon> {
+    let (event_tx, _) = broadcast::channel::<PipelineEvent>(EVENT_CHANNEL_SIZE);
+    OperationPlanner::new(config, cache, flags, event_tx)
+        .plan_operations(initial_targets, command_type)
+        .await
+}
+
 #[instrument(skip_all, fields(cmd = ?command_type, targets = ?initial_targets))]
 pub async fn run_pipeline(
     initial_targets: &[String],
@@ -75,6 +124,32 @@ pub async fn run_pipeline(
         "Pipeline run initiated for targets: {:?}, command: {:?}",
         initial_targets, command_type
     );
+    if flags.no_verify {
+        eprintln!(
+            "{}",
+            "WARNING: --no-verify disables bottle checksum verification. Corrupted or tampered \
+             bottles will be installed without complaint. Use only for debugging."
+                .yellow()
+                .bold()
+        );
+    }
+    if sps_core::install::bottle::runs_translated(config) {
+        eprintln!(
+            "{}",
+            "WARNING: installing x86_64 bottles on Apple Silicon. These packages will run \
+             translated under Rosetta 2, and source builds are still built for arm64."
+                .yellow()
+                .bold()
+        );
+        if !Path::new(ROSETTA_RUNTIME).exists() {
+            eprintln!(
+                "{}",
+                "Rosetta 2 does not appear to be installed; install it with \
+                 `softwareupdate --install-rosetta`."
+                    .yellow()
+            );
+        }
+    }
     let start_time = Instant::now();
     let final_success_count = Arc::new(AtomicUsize::new(0));
     let final_fail_count = Arc::new(AtomicUsize::new(0));
@@ -90,11 +165,11 @@ pub async fn run_pipeline(
     let runner_event_tx_clone = event_tx.clone();
 
     debug!(
-        "Creating crossbeam worker job channel (WORKER_JOB_CHANNEL_SIZE={})",
-        WORKER_JOB_CHANNEL_SIZE
+        "Creating crossbeam worker job channel (worker_queue_size={})",
+        config.limits.worker_queue_size
     );
     let (worker_job_tx, worker_job_rx_for_core) =
-        crossbeam_bounded::<WorkerJob>(WORKER_JOB_CHANNEL_SIZE);
+        crossbeam_bounded::<WorkerJob>(config.limits.worker_queue_size);
 
     debug!("Cloning event_tx for core_event_tx_for_worker_manager");
     let core_config = config.clone();
@@ -152,6 +227,9 @@ pub async fn run_pipeline(
         {
             Ok(ops) => {
                 debug!("plan_operations returned Ok.");
+                if matches!(command_type, CommandType::Install) {
+                    mark_requested(&ops.already_installed_or_up_to_date, config);
+                }
                 planner_output = ops;
             }
             Err(e) => {
@@ -309,7 +387,7 @@ pub async fn run_pipeline(
     if !downloads_to_initiate.is_empty() {
         debug!("Cloning runner_event_tx_clone for download_coordinator_event_tx_clone");
         let download_coordinator_event_tx_clone = runner_event_tx_clone.clone();
-        let http_client = Arc::new(HttpClient::new());
+        let http_client = Arc::new(sps_net::http::shared_client()?);
         let config_for_downloader_owned = config.clone();
 
         let mut download_coordinator = DownloadCoordinator::new(
@@ -317,10 +395,13 @@ pub async fn run_pipeline(
             cache.clone(),
             http_client,
             download_coordinator_event_tx_clone,
+            config.limits.max_downloads,
+            flags.no_verify,
         );
         debug!(
-            "Starting download coordination for {} jobs...",
-            downloads_to_initiate.len()
+            "Starting download coordination for {} jobs (max {} concurrent)...",
+            downloads_to_initiate.len(),
+            config.limits.max_downloads
         );
         debug!("Cloning download_outcome_tx for tx_for_download_task");
         let tx_for_download_task = download_outcome_tx.clone();
@@ -571,12 +652,15 @@ pub async fn run_pipeline(
         Ok(())
     } else {
         let mut accumulated_errors = Vec::new();
+        let mut failures: Vec<SpsError> = Vec::new();
         for (name, err_obj) in planner_output.errors {
             accumulated_errors.push(format!("Planning for '{name}': {err_obj}"));
+            failures.push(err_obj);
         }
         let states_guard = job_processing_states.lock().unwrap();
         for job in planned_jobs.iter() {
             if let Some(JobProcessingState::Failed(err_arc)) = states_guard.get(&job.target_id) {
+                failures.push((**err_arc).clone());
                 let err_str = err_to_string(err_arc);
                 let job_err_msg = format!("Processing '{}': {}", job.target_id, err_str);
                 if !accumulated_errors.contains(&job_err_msg) {
@@ -593,10 +677,29 @@ pub async fn run_pipeline(
         };
 
         // Error details are already sent via PipelineEvent::JobFailed events
-        // and will be displayed in status.rs
-        Err(SpsError::InstallError(format!(
+        // and will be displayed in status.rs.
+        let message = format!(
             "Operation failed with {fail_total} total failure(s). Details: [{specific_error_msg}] (Worker errors are included in total)"
-        )))
+        );
+        Err(aggregate_failures(message, &failures))
+    }
+}
+
+/// Combines the pipeline's failures into one error. If they all map to the same exit
+/// code, the first is kept alongside the message so the exit code tells what went wrong.
+fn aggregate_failures(message: String, failures: &[SpsError]) -> SpsError {
+    match failures.first() {
+        Some(first)
+            if failures
+                .iter()
+                .all(|e| crate::exit_code(e) == crate::exit_code(first)) =>
+        {
+            SpsError::Aggregated {
+                message,
+                category: Box::new(first.clone()),
+            }
+        }
+        _ => SpsError::InstallError(message),
     }
 }
 
@@ -878,7 +981,7 @@ fn are_dependencies_succeeded(
                     keg_registry: &sps_common::keg::KegRegistry::new(config.clone()),
                     sps_prefix: config.sps_root(),
                     include_optional: flags.include_optional,
-                    include_test: false,
+                    include_test: flags.include_test,
                     skip_recommended: flags.skip_recommended,
                     initial_target_preferences: &Default::default(),
                     build_all_from_source: flags.build_from_source,
@@ -1093,3 +1196,30 @@ fn propagate_failure(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_grouped_by_exit_code() {
+        let aggregated = aggregate_failures(
+            "2 failures".into(),
+            &[
+                SpsError::ChecksumMismatch("foo".into()),
+                SpsError::ChecksumError("bar".into()),
+            ],
+        );
+        assert_eq!(crate::exit_code(&aggregated), 4);
+        assert_eq!(aggregated.to_string(), "2 failures");
+
+        let aggregated = aggregate_failures(
+            "2 failures".into(),
+            &[
+                SpsError::NotFound("foo".into()),
+                SpsError::ChecksumError("bar".into()),
+            ],
+        );
+        assert!(matches!(aggregated, SpsError::InstallError(_)));
+    }
+}